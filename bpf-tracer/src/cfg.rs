@@ -0,0 +1,221 @@
+//! Control-flow graph
+//!
+//! Recovers basic blocks and the edges between them from the PCs a trace
+//! executed, so loops can be found without the program's ELF. Only executed
+//! instructions and taken edges appear; the graph describes one run, not
+//! every path the program could take.
+
+use crate::decode::{DecodedInsn, OpcodeClass};
+use crate::trace::ExecutionTrace;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Maximal run of executed instructions entered only at its first PC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// PC of the first instruction
+    pub start: u64,
+    /// PC of the last instruction (inclusive)
+    pub end: u64,
+    /// Times execution entered the block
+    pub executions: usize,
+}
+
+impl BasicBlock {
+    /// Whether `pc` lies in this block
+    pub fn contains(&self, pc: u64) -> bool {
+        (self.start..=self.end).contains(&pc)
+    }
+}
+
+/// Transfer of control between two blocks, as taken during execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    /// Start PC of the block control leaves
+    pub from: u64,
+    /// Start PC of the block control enters
+    pub to: u64,
+    /// Times the edge was taken
+    pub count: usize,
+    /// Whether the edge returns to a block still being visited when it is
+    /// reached from the entry, i.e. closes a loop
+    pub back_edge: bool,
+}
+
+/// Control-flow graph of an executed trace
+///
+/// Built with [`ExecutionTrace::build_cfg`]. Blocks and edges are sorted by
+/// PC.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+    /// Start PC of the block execution began in, if anything executed
+    pub entry: Option<u64>,
+    /// Basic blocks
+    pub blocks: Vec<BasicBlock>,
+    /// Edges between blocks
+    pub edges: Vec<Edge>,
+}
+
+impl Cfg {
+    /// Block containing `pc`, if it was executed
+    pub fn block_at(&self, pc: u64) -> Option<&BasicBlock> {
+        self.block_index(pc).map(|index| &self.blocks[index])
+    }
+
+    fn block_index(&self, pc: u64) -> Option<usize> {
+        let index = self.blocks.partition_point(|block| block.start <= pc).checked_sub(1)?;
+        Some(index).filter(|&index| self.blocks[index].contains(pc))
+    }
+
+    /// Edges that close a loop
+    pub fn back_edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(|edge| edge.back_edge)
+    }
+
+    /// Start PCs of the blocks in the loop a back edge closes, sorted
+    ///
+    /// The loop is the edge's target (the header) plus every block that
+    /// reaches the edge's source without passing through the header.
+    pub fn loop_body(&self, back_edge: &Edge) -> Vec<u64> {
+        let mut body = BTreeSet::from([back_edge.to]);
+        let mut pending = vec![back_edge.from];
+        while let Some(block) = pending.pop() {
+            if body.insert(block) {
+                pending.extend(self.edges.iter().filter(|e| e.to == block).map(|e| e.from));
+            }
+        }
+        body.into_iter().collect()
+    }
+
+    /// Mark the edges that reach a block still on the depth-first stack
+    fn mark_back_edges(&mut self) {
+        let Some(entry) = self.entry else { return };
+        let successors = |block: u64| -> Vec<u64> {
+            self.edges.iter().filter(|e| e.from == block).map(|e| e.to).collect()
+        };
+
+        let mut back_edges = HashSet::new();
+        let mut visited = HashSet::from([entry]);
+        let mut on_stack = HashSet::from([entry]);
+        let mut stack = vec![(entry, successors(entry), 0)];
+        while let Some((block, next, index)) = stack.last_mut() {
+            let Some(&to) = next.get(*index) else {
+                on_stack.remove(&*block);
+                stack.pop();
+                continue;
+            };
+            *index += 1;
+            if on_stack.contains(&to) {
+                back_edges.insert((*block, to));
+            } else if visited.insert(to) {
+                on_stack.insert(to);
+                stack.push((to, successors(to), 0));
+            }
+        }
+
+        for edge in &mut self.edges {
+            edge.back_edge = back_edges.contains(&(edge.from, edge.to));
+        }
+    }
+}
+
+impl ExecutionTrace {
+    /// Build the control-flow graph of the executed instructions
+    ///
+    /// A block ends at a jump, call or exit, and wherever execution does not
+    /// continue at the next PC; the PCs after such a point start new blocks.
+    /// Back edges are found by a depth-first walk from the entry block.
+    pub fn build_cfg(&self) -> Cfg {
+        let pcs: Vec<u64> = self.instructions.iter().map(|instr| instr.pc).collect();
+        let Some(&entry) = pcs.first() else {
+            return Cfg::default();
+        };
+        let executed: BTreeSet<u64> = pcs.iter().copied().collect();
+
+        let mut leaders = BTreeSet::from([entry]);
+        for (i, instr) in self.instructions.iter().enumerate() {
+            let next = pcs.get(i + 1).copied();
+            let class = DecodedInsn::decode(self.instruction_bytes(instr)).map(|insn| insn.class());
+            let transfers = matches!(
+                class,
+                Ok(OpcodeClass::Jump | OpcodeClass::Call | OpcodeClass::Exit)
+            );
+            if transfers || next.is_some_and(|next| next != instr.pc.wrapping_add(1)) {
+                leaders.insert(instr.pc.wrapping_add(1));
+                leaders.extend(next);
+            }
+        }
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        for &pc in &executed {
+            match blocks.last_mut() {
+                Some(block) if !leaders.contains(&pc) && block.end.wrapping_add(1) == pc => {
+                    block.end = pc;
+                }
+                _ => blocks.push(BasicBlock { start: pc, end: pc, executions: 0 }),
+            }
+        }
+
+        let mut cfg = Cfg { entry: Some(entry), blocks, edges: Vec::new() };
+        let mut edges: BTreeMap<(u64, u64), usize> = BTreeMap::new();
+        for (i, &pc) in pcs.iter().enumerate() {
+            let index = cfg.block_index(pc).expect("executed PC has a block");
+            let block = &mut cfg.blocks[index];
+            let (start, end) = (block.start, block.end);
+            if pc == start {
+                block.executions += 1;
+            }
+            if let Some(&next) = pcs.get(i + 1).filter(|_| pc == end) {
+                *edges.entry((start, next)).or_default() += 1;
+            }
+        }
+        cfg.edges = edges
+            .into_iter()
+            .map(|((from, to), count)| Edge { from, to, count, back_edge: false })
+            .collect();
+        cfg.mark_back_edges();
+        cfg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InsnBuilder;
+
+    #[test]
+    fn test_loop_back_edge_detected() {
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 3),
+            InsnBuilder::add64_imm(1, -1),
+            InsnBuilder::jne_imm(1, 0, -2),
+            InsnBuilder::exit(),
+        ]);
+        let trace = crate::trace_program(&bytecode).unwrap();
+        let cfg = trace.build_cfg();
+
+        let blocks: Vec<(u64, u64, usize)> =
+            cfg.blocks.iter().map(|b| (b.start, b.end, b.executions)).collect();
+        assert_eq!(blocks, vec![(0, 0, 1), (1, 2, 3), (3, 3, 1)]);
+        assert_eq!(cfg.block_at(2).map(|b| b.start), Some(1));
+
+        let back_edges: Vec<_> = cfg.back_edges().collect();
+        assert_eq!(back_edges.len(), 1);
+        assert_eq!((back_edges[0].from, back_edges[0].to, back_edges[0].count), (1, 1, 2));
+        assert_eq!(cfg.loop_body(back_edges[0]), vec![1]);
+    }
+
+    #[test]
+    fn test_forward_jump_is_not_a_loop() {
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 1),
+            InsnBuilder::ja(1),
+            InsnBuilder::mov64_imm(0, 2),
+            InsnBuilder::exit(),
+        ]);
+        let trace = crate::trace_program(&bytecode).unwrap();
+        let cfg = trace.build_cfg();
+
+        assert_eq!(cfg.blocks.len(), 2);
+        assert_eq!(cfg.block_at(2), None);
+        assert_eq!(cfg.back_edges().count(), 0);
+    }
+}
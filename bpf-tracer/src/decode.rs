@@ -0,0 +1,510 @@
+//! Instruction decoding
+//!
+//! Decodes the 8-byte sBPF instruction encoding into its fields, and
+//! encodes instructions back ([`InsnBuilder`] builds test programs).
+//!
+//! Encoding (little-endian):
+//! - byte 0: opcode
+//! - byte 1: dst register (low nibble), src register (high nibble)
+//! - bytes 2-3: signed offset
+//! - bytes 4-7: signed immediate
+
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use solana_sbpf::{ebpf, program::SBPFVersion};
+
+/// Size of one encoded instruction in bytes
+pub const INSN_SIZE: usize = ebpf::INSN_SIZE;
+
+/// A decoded sBPF instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedInsn {
+    /// Operation code
+    pub opcode: u8,
+    /// Destination register index
+    pub dst: u8,
+    /// Source register index
+    pub src: u8,
+    /// Signed offset (memory displacement or jump offset)
+    pub off: i16,
+    /// Immediate value
+    ///
+    /// 32 bits as encoded; 64-bit operations sign-extend it, unsigned PQR
+    /// operations zero-extend it.
+    pub imm: i32,
+}
+
+impl DecodedInsn {
+    /// Decode an instruction from its 8-byte encoding
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != ebpf::INSN_SIZE {
+            return Err(anyhow::anyhow!(
+                "Invalid instruction length: expected {} bytes, got {}",
+                ebpf::INSN_SIZE,
+                bytes.len()
+            ));
+        }
+
+        Ok(Self {
+            opcode: bytes[0],
+            dst: bytes[1] & 0x0f,
+            src: (bytes[1] & 0xf0) >> 4,
+            off: i16::from_le_bytes([bytes[2], bytes[3]]),
+            imm: i32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        })
+    }
+
+    /// Encode the instruction into its 8 bytes
+    ///
+    /// The inverse of [`Self::decode`]; register indices are truncated to
+    /// 4 bits. Decoding, changing a field and re-encoding is the way to
+    /// mutate an instruction.
+    pub fn encode(&self) -> [u8; INSN_SIZE] {
+        let mut bytes = [0u8; INSN_SIZE];
+        bytes[0] = self.opcode;
+        bytes[1] = ((self.src & 0x0f) << 4) | (self.dst & 0x0f);
+        bytes[2..4].copy_from_slice(&self.off.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.imm.to_le_bytes());
+        bytes
+    }
+
+    /// Assembly text of the instruction, e.g. `hor64 r0, 1`
+    ///
+    /// Covers the opcodes in [`opcodes`]; anything else is shown as
+    /// `unknown` with its opcode byte. Encodings of the same operation in
+    /// different sBPF versions disassemble alike.
+    pub fn disassemble(&self) -> String {
+        let (dst, src, off, imm) = (self.dst, self.src, self.off, self.imm);
+        let imm_op = |name: &str| format!("{} r{}, {}", name, dst, imm);
+        match self.opcode {
+            opcodes::NOP => "nop".to_string(),
+            opcodes::ADD64_IMM => imm_op("add64"),
+            opcodes::ADD64_REG => format!("add64 r{}, r{}", dst, src),
+            opcodes::MOV64_IMM => imm_op("mov64"),
+            opcodes::MOV32_IMM => imm_op("mov32"),
+            opcodes::HOR64_IMM => imm_op("hor64"),
+            opcodes::MUL64_IMM | opcodes::LMUL64_IMM => imm_op("mul64"),
+            opcodes::DIV64_IMM | opcodes::UDIV64_IMM => imm_op("div64"),
+            opcodes::UDIV64_REG => format!("udiv64 r{}, r{}", dst, src),
+            opcodes::UREM64_IMM => imm_op("urem64"),
+            opcodes::UREM64_REG => format!("urem64 r{}, r{}", dst, src),
+            opcodes::LSH64_IMM => imm_op("lsh64"),
+            opcodes::RSH64_IMM => imm_op("rsh64"),
+            opcodes::LD_DW_REG | opcodes::LD_8B_REG => {
+                format!("ldxdw r{}, [r{}{:+}]", dst, src, off)
+            }
+            opcodes::ST_DW_REG | opcodes::ST_8B_REG => {
+                format!("stxdw [r{}{:+}], r{}", dst, off, src)
+            }
+            opcodes::EXIT => "exit".to_string(),
+            opcode => format!("unknown 0x{:02x}", opcode),
+        }
+    }
+
+    /// Opcode family for the sBPF version raw bytecode is traced as
+    pub fn class(&self) -> OpcodeClass {
+        self.class_for(crate::vm::SBPF_VERSION)
+    }
+
+    /// Opcode family under a given sBPF version
+    ///
+    /// The version matters because SBPFv2 moves the memory instructions into
+    /// the ALU instruction classes, reusing opcodes of disabled ALU32
+    /// operations, and SBPFv3 turns `exit` into a static syscall.
+    pub fn class_for(&self, version: SBPFVersion) -> OpcodeClass {
+        match self.opcode {
+            opcodes::NOP => return OpcodeClass::Nop,
+            ebpf::LD_DW_IMM => return OpcodeClass::LoadImm64,
+            ebpf::LE | ebpf::BE => return OpcodeClass::ByteSwap,
+            ebpf::CALL_IMM | ebpf::CALL_REG => return OpcodeClass::Call,
+            ebpf::RETURN => return OpcodeClass::Exit,
+            ebpf::EXIT if version.static_syscalls() => return OpcodeClass::Call,
+            ebpf::EXIT => return OpcodeClass::Exit,
+            _ => {}
+        }
+
+        if version.move_memory_instruction_classes() {
+            match self.opcode {
+                ebpf::LD_1B_REG | ebpf::LD_2B_REG | ebpf::LD_4B_REG | ebpf::LD_8B_REG => {
+                    return OpcodeClass::Load
+                }
+                ebpf::ST_1B_IMM
+                | ebpf::ST_2B_IMM
+                | ebpf::ST_4B_IMM
+                | ebpf::ST_8B_IMM
+                | ebpf::ST_1B_REG
+                | ebpf::ST_2B_REG
+                | ebpf::ST_4B_REG
+                | ebpf::ST_8B_REG => return OpcodeClass::Store,
+                _ => {}
+            }
+        }
+
+        match self.opcode & 0x07 {
+            ebpf::BPF_LDX => OpcodeClass::Load,
+            ebpf::BPF_ST | ebpf::BPF_STX => OpcodeClass::Store,
+            ebpf::BPF_ALU32_LOAD => OpcodeClass::Alu32,
+            ebpf::BPF_ALU64_STORE => OpcodeClass::Alu64,
+            ebpf::BPF_PQR => OpcodeClass::Pqr,
+            ebpf::BPF_JMP => OpcodeClass::Jump,
+            _ => OpcodeClass::Unknown,
+        }
+    }
+}
+
+/// Opcode family of an sBPF instruction
+///
+/// Groups opcodes the way chip dispatch and trace reports treat them, so
+/// callers can switch on a handful of families instead of every opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum OpcodeClass {
+    /// Padding no-op (see [`opcodes::NOP`])
+    Nop,
+    /// 64-bit arithmetic and logic
+    Alu64,
+    /// 32-bit arithmetic and logic
+    Alu32,
+    /// Product/quotient/remainder class (SBPFv2 and later)
+    Pqr,
+    /// Conditional and unconditional jumps
+    Jump,
+    /// Loads from memory
+    Load,
+    /// Stores to memory
+    Store,
+    /// `lddw`, the two-slot 64-bit immediate load
+    LoadImm64,
+    /// Function calls and syscalls
+    Call,
+    /// Program exit or function return
+    Exit,
+    /// Endianness conversion (`le`/`be`)
+    ByteSwap,
+    /// Opcode outside every sBPF instruction class
+    Unknown,
+}
+
+/// Encoder for readable test programs
+///
+/// Each method returns one encoded instruction, using the opcodes of the
+/// sBPF version raw bytecode is traced as (e.g. [`opcodes::mul64_imm`]).
+/// Jump offsets count instructions relative to the next one, as in the
+/// encoding.
+///
+/// ```
+/// use bpf_tracer::InsnBuilder;
+///
+/// let bytecode = InsnBuilder::program([InsnBuilder::mov64_imm(0, 42), InsnBuilder::exit()]);
+/// assert_eq!(bytecode.len(), 16);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct InsnBuilder;
+
+impl InsnBuilder {
+    /// Concatenate encoded instructions into bytecode
+    pub fn program(insns: impl IntoIterator<Item = [u8; INSN_SIZE]>) -> Vec<u8> {
+        insns.into_iter().flatten().collect()
+    }
+
+    /// Encode an instruction from its fields
+    pub fn insn(opcode: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; INSN_SIZE] {
+        DecodedInsn { opcode, dst, src, off, imm }.encode()
+    }
+
+    /// `mov64 dst, imm`
+    pub fn mov64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::MOV64_IMM, dst, 0, 0, imm)
+    }
+
+    /// `mov64 dst, src`
+    pub fn mov64_reg(dst: u8, src: u8) -> [u8; INSN_SIZE] {
+        Self::insn(ebpf::MOV64_REG, dst, src, 0, 0)
+    }
+
+    /// `add64 dst, imm`
+    pub fn add64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::ADD64_IMM, dst, 0, 0, imm)
+    }
+
+    /// `add64 dst, src`
+    pub fn add64_reg(dst: u8, src: u8) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::ADD64_REG, dst, src, 0, 0)
+    }
+
+    /// `mov32 dst, imm`, zero-extending `imm` to 64 bits
+    pub fn mov32_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::MOV32_IMM, dst, 0, 0, imm)
+    }
+
+    /// `hor64 dst, imm`, setting the high 32 bits of `dst` from `imm`
+    pub fn hor64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::HOR64_IMM, dst, 0, 0, imm)
+    }
+
+    /// `mul64 dst, imm`
+    pub fn mul64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::mul64_imm(), dst, 0, 0, imm)
+    }
+
+    /// `div64 dst, imm`
+    pub fn div64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::div64_imm(), dst, 0, 0, imm)
+    }
+
+    /// `lsh64 dst, imm`
+    pub fn lsh64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::LSH64_IMM, dst, 0, 0, imm)
+    }
+
+    /// `rsh64 dst, imm`
+    pub fn rsh64_imm(dst: u8, imm: i32) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::RSH64_IMM, dst, 0, 0, imm)
+    }
+
+    /// `ldxdw dst, [src + off]`
+    pub fn ldxdw(dst: u8, src: u8, off: i16) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::ldxdw(), dst, src, off, 0)
+    }
+
+    /// `stxdw [dst + off], src`
+    pub fn stxdw(dst: u8, off: i16, src: u8) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::stxdw(), dst, src, off, 0)
+    }
+
+    /// `ja off`
+    pub fn ja(off: i16) -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::JA, 0, 0, off, 0)
+    }
+
+    /// `jne dst, imm, off`
+    pub fn jne_imm(dst: u8, imm: i32, off: i16) -> [u8; INSN_SIZE] {
+        Self::insn(ebpf::JNE_IMM, dst, 0, off, imm)
+    }
+
+    /// `call imm`, an internal call to the function at instruction `target`
+    pub fn call(target: i32) -> [u8; INSN_SIZE] {
+        Self::insn(ebpf::CALL_IMM, 0, 0, 0, target)
+    }
+
+    /// `exit`
+    pub fn exit() -> [u8; INSN_SIZE] {
+        Self::insn(opcodes::EXIT, 0, 0, 0, 0)
+    }
+}
+
+/// sBPF opcodes referenced by the tracer and the circuits
+///
+/// Re-exported here so downstream crates don't depend on solana-sbpf directly.
+pub mod opcodes {
+    use solana_sbpf::{ebpf, program::SBPFVersion};
+
+    /// Padding no-op used to fill fixed-size chunks (not a real sBPF opcode)
+    pub const NOP: u8 = 0x00;
+    /// dst += imm (64-bit)
+    pub const ADD64_IMM: u8 = ebpf::ADD64_IMM;
+    /// dst += src (64-bit)
+    pub const ADD64_REG: u8 = ebpf::ADD64_REG;
+    /// dst = imm (64-bit)
+    pub const MOV64_IMM: u8 = ebpf::MOV64_IMM;
+    /// dst = (u32) imm
+    pub const MOV32_IMM: u8 = ebpf::MOV32_IMM;
+    /// dst |= imm << 32 (SBPFv2 and later, replacing `lddw`)
+    pub const HOR64_IMM: u8 = ebpf::HOR64_IMM;
+    /// dst = *(u64 *)(src + off)
+    pub const LD_DW_REG: u8 = ebpf::LD_DW_REG;
+    /// *(u64 *)(dst + off) = src
+    pub const ST_DW_REG: u8 = ebpf::ST_DW_REG;
+    /// dst = *(u64 *)(src + off), SBPFv2 memory instruction class
+    pub const LD_8B_REG: u8 = ebpf::LD_8B_REG;
+    /// *(u64 *)(dst + off) = src, SBPFv2 memory instruction class
+    pub const ST_8B_REG: u8 = ebpf::ST_8B_REG;
+    /// dst *= imm (64-bit, SBPFv0 encoding)
+    pub const MUL64_IMM: u8 = ebpf::MUL64_IMM;
+    /// dst *= imm (64-bit, SBPFv2 product/quotient/remainder class)
+    pub const LMUL64_IMM: u8 = ebpf::LMUL64_IMM;
+    /// dst /= imm (64-bit, SBPFv0 encoding)
+    pub const DIV64_IMM: u8 = ebpf::DIV64_IMM;
+    /// dst /= (u32) imm (64-bit, SBPFv2 product/quotient/remainder class)
+    pub const UDIV64_IMM: u8 = ebpf::UDIV64_IMM;
+    /// dst /= src (64-bit, unsigned)
+    pub const UDIV64_REG: u8 = ebpf::UDIV64_REG;
+    /// dst %= (u32) imm (64-bit, unsigned)
+    pub const UREM64_IMM: u8 = ebpf::UREM64_IMM;
+    /// dst %= src (64-bit, unsigned)
+    pub const UREM64_REG: u8 = ebpf::UREM64_REG;
+    /// dst <<= imm (64-bit)
+    pub const LSH64_IMM: u8 = ebpf::LSH64_IMM;
+    /// dst >>= imm (64-bit, logical)
+    pub const RSH64_IMM: u8 = ebpf::RSH64_IMM;
+    /// Return from program
+    pub const EXIT: u8 = ebpf::EXIT;
+    /// Unconditional jump by `off`
+    pub const JA: u8 = ebpf::JA;
+
+    /// Opcode of `ldxdw` for the sBPF version raw bytecode is traced as
+    pub fn ldxdw() -> u8 {
+        ldxdw_for(crate::vm::SBPF_VERSION)
+    }
+
+    /// Opcode of `ldxdw` under a given sBPF version
+    pub fn ldxdw_for(version: SBPFVersion) -> u8 {
+        if version.move_memory_instruction_classes() {
+            LD_8B_REG
+        } else {
+            LD_DW_REG
+        }
+    }
+
+    /// Opcode of `stxdw` for the sBPF version raw bytecode is traced as
+    pub fn stxdw() -> u8 {
+        stxdw_for(crate::vm::SBPF_VERSION)
+    }
+
+    /// Opcode of `stxdw` under a given sBPF version
+    pub fn stxdw_for(version: SBPFVersion) -> u8 {
+        if version.move_memory_instruction_classes() {
+            ST_8B_REG
+        } else {
+            ST_DW_REG
+        }
+    }
+
+    /// Opcode of `mul64 dst, imm` for the sBPF version raw bytecode is traced as
+    pub fn mul64_imm() -> u8 {
+        mul64_imm_for(crate::vm::SBPF_VERSION)
+    }
+
+    /// Opcode of `mul64 dst, imm` under a given sBPF version
+    pub fn mul64_imm_for(version: SBPFVersion) -> u8 {
+        if version.enable_pqr() {
+            LMUL64_IMM
+        } else {
+            MUL64_IMM
+        }
+    }
+
+    /// Opcode of `div64 dst, imm` for the sBPF version raw bytecode is traced as
+    pub fn div64_imm() -> u8 {
+        div64_imm_for(crate::vm::SBPF_VERSION)
+    }
+
+    /// Opcode of `div64 dst, imm` under a given sBPF version
+    pub fn div64_imm_for(version: SBPFVersion) -> u8 {
+        if version.enable_pqr() {
+            UDIV64_IMM
+        } else {
+            DIV64_IMM
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_add_imm() {
+        let insn = DecodedInsn::decode(&[0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00]).unwrap();
+
+        assert_eq!(insn.opcode, opcodes::ADD64_IMM);
+        assert_eq!(insn.dst, 1);
+        assert_eq!(insn.src, 0);
+        assert_eq!(insn.off, 0);
+        assert_eq!(insn.imm, 42);
+    }
+
+    #[test]
+    fn test_decode_registers_and_negative_fields() {
+        // ldxdw r2, [r10-8]
+        let insn = DecodedInsn::decode(&[0x79, 0xa2, 0xf8, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap();
+
+        assert_eq!(insn.dst, 2);
+        assert_eq!(insn.src, 10);
+        assert_eq!(insn.off, -8);
+        assert_eq!(insn.imm, -1);
+    }
+
+    #[test]
+    fn test_opcode_classes() {
+        let class = |opcode: u8| DecodedInsn { opcode, dst: 0, src: 0, off: 0, imm: 0 }.class();
+
+        assert_eq!(class(opcodes::MOV64_IMM), OpcodeClass::Alu64);
+        assert_eq!(class(opcodes::ADD64_IMM), OpcodeClass::Alu64);
+        assert_eq!(class(opcodes::ADD64_REG), OpcodeClass::Alu64);
+        assert_eq!(class(ebpf::ADD32_IMM), OpcodeClass::Alu32);
+        assert_eq!(class(ebpf::JA), OpcodeClass::Jump);
+        assert_eq!(class(ebpf::JEQ_IMM), OpcodeClass::Jump);
+        assert_eq!(class(opcodes::EXIT), OpcodeClass::Exit);
+        assert_eq!(class(opcodes::ldxdw()), OpcodeClass::Load);
+        assert_eq!(class(opcodes::stxdw()), OpcodeClass::Store);
+        assert_eq!(class(opcodes::UREM64_REG), OpcodeClass::Pqr);
+        assert_eq!(class(opcodes::NOP), OpcodeClass::Nop);
+        assert_eq!(class(opcodes::MOV32_IMM), OpcodeClass::Alu32);
+        assert_eq!(class(opcodes::HOR64_IMM), OpcodeClass::Alu64);
+    }
+
+    #[test]
+    fn test_opcode_classes_depend_on_version() {
+        // 0x8c falls in the ALU32 class in SBPFv0 and is ldxw in SBPFv2
+        let insn = DecodedInsn { opcode: ebpf::LD_4B_REG, dst: 0, src: 1, off: 0, imm: 0 };
+        assert_eq!(insn.class_for(SBPFVersion::V0), OpcodeClass::Alu32);
+        assert_eq!(insn.class_for(SBPFVersion::V2), OpcodeClass::Load);
+
+        let ldxdw = DecodedInsn { opcode: opcodes::LD_DW_REG, ..insn };
+        assert_eq!(ldxdw.class_for(SBPFVersion::V0), OpcodeClass::Load);
+
+        let exit = DecodedInsn { opcode: opcodes::EXIT, ..insn };
+        assert_eq!(exit.class_for(SBPFVersion::V2), OpcodeClass::Exit);
+        assert_eq!(exit.class_for(SBPFVersion::V3), OpcodeClass::Call);
+    }
+
+    #[test]
+    fn test_builder_matches_hand_written_arithmetic_program() {
+        #[rustfmt::skip]
+        let fixture: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let program = InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 10),
+            InsnBuilder::mov64_imm(1, 20),
+            InsnBuilder::add64_reg(0, 1),
+            InsnBuilder::exit(),
+        ]);
+
+        assert_eq!(program, fixture);
+    }
+
+    #[test]
+    fn test_encode_roundtrips_decode() {
+        // jne r1, 3, -2
+        let bytes = InsnBuilder::jne_imm(1, 3, -2);
+        assert_eq!(bytes, [0x55, 0x01, 0xfe, 0xff, 0x03, 0x00, 0x00, 0x00]);
+
+        // ldxdw r2, [r10-8], then retarget it to r3
+        let mut insn = DecodedInsn::decode(&InsnBuilder::ldxdw(2, 10, -8)).unwrap();
+        assert_eq!((insn.dst, insn.src, insn.off), (2, 10, -8));
+        insn.dst = 3;
+        assert_eq!(DecodedInsn::decode(&insn.encode()).unwrap(), insn);
+        assert_eq!(InsnBuilder::ja(-1), [0x05, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let text = |bytes: [u8; INSN_SIZE]| DecodedInsn::decode(&bytes).unwrap().disassemble();
+
+        assert_eq!(text(InsnBuilder::mov32_imm(0, -1)), "mov32 r0, -1");
+        assert_eq!(text(InsnBuilder::hor64_imm(0, 0x1234)), "hor64 r0, 4660");
+        assert_eq!(text(InsnBuilder::ldxdw(2, 10, -8)), "ldxdw r2, [r10-8]");
+        assert_eq!(text(InsnBuilder::stxdw(10, -8, 1)), "stxdw [r10-8], r1");
+        assert_eq!(text(InsnBuilder::jne_imm(1, 3, -2)), "unknown 0x55");
+    }
+
+    #[test]
+    fn test_decode_wrong_length() {
+        assert!(DecodedInsn::decode(&[0x95, 0x00]).is_err());
+    }
+}
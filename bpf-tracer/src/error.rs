@@ -0,0 +1,267 @@
+//! Typed tracer errors
+//!
+//! Most tracer failures are reported as plain `anyhow` errors. Failures a
+//! caller may want to act on are raised as [`TraceError`] so they can be
+//! recovered with `anyhow::Error::downcast_ref`.
+
+use solana_pubkey::Pubkey;
+use solana_sbpf::{elf::ElfError, error::EbpfError, memory_region::AccessType};
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use crate::memory::MemoryRegionKind;
+
+/// Errors raised while loading or tracing a program
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TraceError {
+    /// An ELF relocation could not be applied
+    ///
+    /// The program was most likely not linked for sBPF (e.g. built with a
+    /// non-Solana toolchain or linker script).
+    #[error(
+        "ELF relocation failed: {kind}{}; the program was not linked for sBPF correctly",
+        .symbol.as_ref().map(|s| format!(" (symbol `{}`)", s)).unwrap_or_default()
+    )]
+    Relocation {
+        /// What went wrong
+        kind: RelocationFailure,
+        /// Symbol the relocation refers to, if known
+        symbol: Option<String>,
+    },
+    /// An instruction's output does not match the state that follows it
+    ///
+    /// Raised by `ExecutionTrace::check_consistency`. The next state is the
+    /// following instruction's `registers_before`, or the final registers
+    /// after the last instruction.
+    #[error(
+        "instruction {instruction} leaves register {register} = {after:#x}, but the next state has {next:#x}"
+    )]
+    BrokenChain {
+        /// Index of the instruction whose output disagrees
+        instruction: usize,
+        /// Register index (11 is the PC)
+        register: usize,
+        /// Value in the instruction's `registers_after`
+        after: u64,
+        /// Value in the next state
+        next: u64,
+    },
+    /// The program stored into its read-only program region
+    ///
+    /// Bytecode and read-only data are immutable; the VM faults on such a
+    /// store, and the circuit rejects stores below the stack region.
+    #[error("instruction at pc {pc} writes to read-only address {address:#x}")]
+    ReadOnlyWrite {
+        /// Address the store targeted
+        address: u64,
+        /// PC of the storing instruction
+        pc: u64,
+    },
+    /// The program has no instructions to execute
+    ///
+    /// Raised before loading, for raw bytecode shorter than one instruction
+    /// and for an ELF whose `.text` section is empty.
+    #[error("program has no instructions")]
+    EmptyProgram,
+    /// The ELF or raw bytecode could not be loaded
+    ///
+    /// Holds the loader's message. Relocation problems are reported as
+    /// [`TraceError::Relocation`] instead.
+    #[error("failed to load program: {0}")]
+    Load(String),
+    /// The program loaded but the sBPF verifier rejected it
+    ///
+    /// Holds the verifier's message, e.g. a jump out of bounds or an
+    /// invalid register.
+    #[error("program failed verification: {0}")]
+    Verify(String),
+    /// The VM panicked instead of returning an error
+    ///
+    /// Holds the panic message. Tracing untrusted bytecode must not take the
+    /// caller's process down, so panics are caught at the public entry
+    /// points. Builds with `panic = "abort"` cannot catch them.
+    #[error("VM panicked: {0}")]
+    InternalVmPanic(String),
+    /// Account JSON could not be parsed
+    ///
+    /// Raised by `AccountState::from_json`; `field` names the offending
+    /// field, or is `account` when the document itself is malformed.
+    #[error("invalid account JSON field `{field}`: {reason}")]
+    InvalidAccountJson {
+        /// Field that failed to parse
+        field: &'static str,
+        /// What was wrong with it
+        reason: String,
+    },
+    /// The same account has more than one state change in a trace
+    ///
+    /// Raised by `ExecutionTrace::check_consistency`. Account commitments
+    /// assume one change per pubkey; the tracer merges repeated accounts.
+    #[error("account {0} has more than one state change")]
+    DuplicateAccount(Pubkey),
+}
+
+/// Why an ELF relocation failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationFailure {
+    /// Relocation type sBPF does not support (raw ELF `r_type`)
+    UnsupportedType(u32),
+    /// Call to a symbol no syscall or function is registered for
+    UnresolvedSymbol,
+    /// Relocation references a missing symbol table entry
+    UnknownSymbol(usize),
+    /// Relocation target is outside every loadable section
+    InvalidAddress(u64),
+    /// Relocation table could not be read
+    Malformed,
+}
+
+impl fmt::Display for RelocationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedType(r_type) => write!(f, "unsupported relocation type {}", r_type),
+            Self::UnresolvedSymbol => write!(f, "unresolved symbol"),
+            Self::UnknownSymbol(index) => write!(f, "unknown symbol index {}", index),
+            Self::InvalidAddress(addr) => write!(f, "invalid relocation address {:#x}", addr),
+            Self::Malformed => write!(f, "unreadable relocation table"),
+        }
+    }
+}
+
+impl TraceError {
+    /// Classify an ELF loader error as a relocation failure
+    ///
+    /// Returns `None` for errors unrelated to relocation.
+    pub fn from_elf_error(err: &ElfError) -> Option<Self> {
+        let (kind, symbol) = match err {
+            ElfError::UnknownRelocation(r_type) => (RelocationFailure::UnsupportedType(*r_type), None),
+            ElfError::UnresolvedSymbol(name, _, _) => {
+                (RelocationFailure::UnresolvedSymbol, Some(name.clone()))
+            }
+            ElfError::UnknownSymbol(index) => (RelocationFailure::UnknownSymbol(*index), None),
+            ElfError::AddressOutsideLoadableSection(addr) | ElfError::InvalidVirtualAddress(addr) => {
+                (RelocationFailure::InvalidAddress(*addr), None)
+            }
+            ElfError::FailedToReadRelocationInfo => (RelocationFailure::Malformed, None),
+            _ => return None,
+        };
+        Some(Self::Relocation { kind, symbol })
+    }
+}
+
+/// Convert a program load failure into [`TraceError::Load`]
+///
+/// Relocation problems are surfaced as [`TraceError::Relocation`].
+pub(crate) fn load_error(err: EbpfError) -> anyhow::Error {
+    match &err {
+        EbpfError::ElfError(elf_err) => TraceError::from_elf_error(elf_err)
+            .unwrap_or_else(|| TraceError::Load(elf_err.to_string()))
+            .into(),
+        _ => TraceError::Load(err.to_string()).into(),
+    }
+}
+
+/// Convert a verifier rejection into [`TraceError::Verify`]
+pub(crate) fn verify_error(err: EbpfError) -> anyhow::Error {
+    match &err {
+        EbpfError::VerifierError(verifier_err) => TraceError::Verify(verifier_err.to_string()),
+        _ => TraceError::Verify(err.to_string()),
+    }
+    .into()
+}
+
+/// Convert a VM execution failure at `pc`, surfacing read-only writes as [`TraceError`]
+pub(crate) fn execution_error(err: EbpfError, pc: u64) -> anyhow::Error {
+    if let EbpfError::AccessViolation(AccessType::Store, address, _, _) = err {
+        if MemoryRegionKind::of(address) == MemoryRegionKind::Program {
+            return TraceError::ReadOnlyWrite { address, pc }.into();
+        }
+    }
+    anyhow::anyhow!("Program execution failed: {:?}", err)
+}
+
+thread_local! {
+    /// Whether this thread is inside [`catch_vm_panic`]
+    static IN_VM: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`, converting a panic into [`TraceError::InternalVmPanic`]
+///
+/// The first call installs a panic hook that sends panics raised inside
+/// `f` to `tracing` instead of stderr, and leaves every other panic to the
+/// previously installed hook.
+pub(crate) fn catch_vm_panic<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if IN_VM.with(Cell::get) {
+                tracing::error!("VM panicked: {}", info);
+            } else {
+                previous(info);
+            }
+        }));
+    });
+
+    let was_in_vm = IN_VM.with(|in_vm| in_vm.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    IN_VM.with(|in_vm| in_vm.set(was_in_vm));
+
+    result.unwrap_or_else(|payload| {
+        Err(TraceError::InternalVmPanic(panic_message(&*payload)).into())
+    })
+}
+
+/// The message a panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unresolved_symbol_is_relocation_error() {
+        let err = ElfError::UnresolvedSymbol("log".to_string(), 39, 312);
+        let trace_err = TraceError::from_elf_error(&err).unwrap();
+
+        assert_eq!(
+            trace_err,
+            TraceError::Relocation {
+                kind: RelocationFailure::UnresolvedSymbol,
+                symbol: Some("log".to_string()),
+            }
+        );
+        assert!(trace_err.to_string().contains("symbol `log`"));
+    }
+
+    #[test]
+    fn test_non_relocation_elf_error_passes_through() {
+        assert!(TraceError::from_elf_error(&ElfError::EntrypointOutOfBounds).is_none());
+    }
+
+    #[test]
+    fn test_vm_panic_becomes_error() {
+        let err = catch_vm_panic(|| -> anyhow::Result<()> { panic!("invalid syscall") })
+            .unwrap_err();
+
+        match err.downcast_ref::<TraceError>() {
+            Some(TraceError::InternalVmPanic(message)) => assert_eq!(message, "invalid syscall"),
+            other => panic!("Expected a VM panic, got {:?}", other),
+        }
+
+        // Errors and values pass through untouched
+        assert_eq!(catch_vm_panic(|| Ok(7)).unwrap(), 7);
+        let err = catch_vm_panic(|| -> anyhow::Result<()> { Err(TraceError::EmptyProgram.into()) });
+        assert_eq!(err.unwrap_err().downcast_ref(), Some(&TraceError::EmptyProgram));
+    }
+}
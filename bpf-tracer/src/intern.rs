@@ -0,0 +1,268 @@
+//! Register-state interning
+//!
+//! Loop-heavy programs revisit the same register states, and every
+//! instruction's "before" state is the previous instruction's "after"
+//! state. [`InternedTrace`] stores each distinct [`RegisterState`] once in a
+//! pool and refers to it by index.
+
+use crate::trace::{
+    AccountStateChange, ExecutionTrace, ExitKind, HashCall, InstructionTrace, MemorySnapshot,
+    RegisterState, TraceMeta,
+};
+use crate::memory::MemoryAccess;
+use crate::sysvar::SysvarValues;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Index of a register state in an [`InternedTrace`] pool
+pub type RegisterStateId = u32;
+
+/// Instruction trace with register states stored as pool indices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InternedInstruction {
+    /// Program counter (instruction address)
+    pub pc: u64,
+    /// Raw instruction bytes
+    pub instruction_bytes: Vec<u8>,
+    /// Pool index of the register state before execution
+    pub registers_before: RegisterStateId,
+    /// Pool index of the register state after execution
+    pub registers_after: RegisterStateId,
+    /// Resolved `call imm` target (see [`InstructionTrace::call_target`])
+    #[serde(default)]
+    pub call_target: Option<u64>,
+}
+
+/// Execution trace with deduplicated register states
+///
+/// Built with [`ExecutionTrace::intern`] and converted back with
+/// [`InternedTrace::deintern`]. Use the accessors rather than indexing the
+/// pool directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InternedTrace {
+    /// Distinct register states, in first-seen order
+    register_pool: Vec<RegisterState>,
+    /// Instructions in execution order
+    instructions: Vec<InternedInstruction>,
+    /// Pool index of the initial register state
+    initial_registers: RegisterStateId,
+    /// Pool index of the final register state
+    final_registers: RegisterStateId,
+    /// Account state changes during execution
+    pub account_states: Vec<AccountStateChange>,
+    /// Whether tracing stopped at a breakpoint
+    pub breakpoint_hit: bool,
+    /// Execution parameters the trace was captured with
+    pub meta: TraceMeta,
+    /// How execution terminated
+    pub exit_kind: ExitKind,
+    /// Whether the trace covers the run from the program's entry
+    #[serde(default)]
+    pub complete: bool,
+    /// Hash syscalls made by the program
+    pub hash_calls: Vec<HashCall>,
+    /// Messages logged by the program
+    pub logs: Vec<String>,
+    /// Values the sysvar syscalls returned
+    #[serde(default)]
+    pub sysvars: SysvarValues,
+    /// Loads and stores of the executed instructions
+    #[serde(default)]
+    pub memory_ops: Vec<MemoryAccess>,
+    /// Whether memory accesses went unrecorded past the cap
+    #[serde(default)]
+    pub memory_ops_truncated: bool,
+    /// Final contents of the writable memory regions, if captured
+    pub memory_snapshot: Vec<MemorySnapshot>,
+    /// Program text the instructions were read from
+    #[serde(default)]
+    pub program_text: Vec<u8>,
+}
+
+/// Builds a register-state pool, assigning each distinct state one index
+#[derive(Default)]
+struct PoolBuilder {
+    pool: Vec<RegisterState>,
+    index: HashMap<[u64; 12], RegisterStateId>,
+}
+
+impl PoolBuilder {
+    fn intern(&mut self, regs: &RegisterState) -> RegisterStateId {
+        let pool = &mut self.pool;
+        *self.index.entry(regs.regs).or_insert_with(|| {
+            pool.push(regs.clone());
+            (pool.len() - 1) as RegisterStateId
+        })
+    }
+}
+
+impl ExecutionTrace {
+    /// Convert to the interned representation
+    pub fn intern(&self) -> InternedTrace {
+        let mut pool = PoolBuilder::default();
+
+        let initial_registers = pool.intern(&self.initial_registers);
+        let instructions = self
+            .instructions
+            .iter()
+            .map(|instr| InternedInstruction {
+                pc: instr.pc,
+                instruction_bytes: self.instruction_bytes(instr).to_vec(),
+                registers_before: pool.intern(&instr.registers_before),
+                registers_after: pool.intern(&instr.registers_after),
+                call_target: instr.call_target,
+            })
+            .collect();
+        let final_registers = pool.intern(&self.final_registers);
+
+        InternedTrace {
+            register_pool: pool.pool,
+            instructions,
+            initial_registers,
+            final_registers,
+            account_states: self.account_states.clone(),
+            breakpoint_hit: self.breakpoint_hit,
+            meta: self.meta.clone(),
+            exit_kind: self.exit_kind,
+            complete: self.complete,
+            hash_calls: self.hash_calls.clone(),
+            logs: self.logs.clone(),
+            sysvars: self.sysvars,
+            memory_ops: self.memory_ops.clone(),
+            memory_ops_truncated: self.memory_ops_truncated,
+            memory_snapshot: self.memory_snapshot.clone(),
+            program_text: self.program_text.clone(),
+        }
+    }
+}
+
+impl InternedTrace {
+    /// Convert back to the flat representation
+    pub fn deintern(&self) -> ExecutionTrace {
+        ExecutionTrace {
+            instructions: (0..self.instruction_count())
+                .map(|i| InstructionTrace {
+                    pc: self.instructions[i].pc,
+                    instruction_bytes: self.instructions[i].instruction_bytes.clone(),
+                    registers_before: self.registers_before(i).clone(),
+                    registers_after: self.registers_after(i).clone(),
+                    call_target: self.instructions[i].call_target,
+                    text_offset: None,
+                })
+                .collect(),
+            account_states: self.account_states.clone(),
+            initial_registers: self.initial_registers().clone(),
+            final_registers: self.final_registers().clone(),
+            breakpoint_hit: self.breakpoint_hit,
+            meta: self.meta.clone(),
+            exit_kind: self.exit_kind,
+            complete: self.complete,
+            hash_calls: self.hash_calls.clone(),
+            logs: self.logs.clone(),
+            sysvars: self.sysvars,
+            memory_ops: self.memory_ops.clone(),
+            memory_ops_truncated: self.memory_ops_truncated,
+            memory_snapshot: self.memory_snapshot.clone(),
+            program_text: self.program_text.clone(),
+        }
+    }
+
+    /// Get number of instructions executed
+    pub fn instruction_count(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Number of distinct register states stored
+    pub fn register_pool_len(&self) -> usize {
+        self.register_pool.len()
+    }
+
+    /// Instructions in execution order
+    pub fn instructions(&self) -> &[InternedInstruction] {
+        &self.instructions
+    }
+
+    /// Look up a register state by pool index
+    pub fn register_state(&self, id: RegisterStateId) -> &RegisterState {
+        &self.register_pool[id as usize]
+    }
+
+    /// Initial register state at program start
+    pub fn initial_registers(&self) -> &RegisterState {
+        self.register_state(self.initial_registers)
+    }
+
+    /// Final register state at program exit
+    pub fn final_registers(&self) -> &RegisterState {
+        self.register_state(self.final_registers)
+    }
+
+    /// Register state before the `index`-th instruction
+    pub fn registers_before(&self, index: usize) -> &RegisterState {
+        self.register_state(self.instructions[index].registers_before)
+    }
+
+    /// Register state after the `index`-th instruction
+    pub fn registers_after(&self, index: usize) -> &RegisterState {
+        self.register_state(self.instructions[index].registers_after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3-instruction loop body run `iterations` times; r1 cycles 0, 1, 2
+    fn loop_trace(iterations: usize) -> ExecutionTrace {
+        let state = |r1: u64, pc: u64| {
+            RegisterState::from_regs([0, r1, 0, 0, 0, 0, 0, 0, 0, 0, 0, pc])
+        };
+
+        let mut trace = ExecutionTrace::new();
+        for _ in 0..iterations {
+            for step in 0..3u64 {
+                trace.instructions.push(InstructionTrace {
+                    pc: step * 8,
+                    instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+                    registers_before: state(step, step * 8),
+                    registers_after: state((step + 1) % 3, ((step + 1) % 3) * 8),
+                    call_target: None,
+                    text_offset: None,
+                });
+            }
+        }
+        trace.initial_registers = state(0, 0);
+        trace.final_registers = state(0, 0);
+        trace
+    }
+
+    #[test]
+    fn test_intern_loop_trace_reduces_states() {
+        let trace = loop_trace(100);
+        let interned = trace.intern();
+
+        // Flat form stores before + after per instruction, plus initial and final
+        let flat_states = 2 * trace.instruction_count() + 2;
+        assert_eq!(flat_states, 602);
+        assert_eq!(interned.register_pool_len(), 3);
+        assert!(interned.register_pool_len() < flat_states);
+    }
+
+    #[test]
+    fn test_intern_roundtrip() {
+        let trace = loop_trace(4);
+        let interned = trace.intern();
+
+        assert_eq!(interned.instruction_count(), trace.instruction_count());
+        for (i, instr) in trace.instructions.iter().enumerate() {
+            assert_eq!(interned.registers_before(i).regs, instr.registers_before.regs);
+            assert_eq!(interned.registers_after(i).regs, instr.registers_after.regs);
+        }
+
+        let roundtrip = interned.deintern();
+        assert_eq!(
+            serde_json::to_string(&roundtrip).unwrap(),
+            serde_json::to_string(&trace).unwrap()
+        );
+    }
+}
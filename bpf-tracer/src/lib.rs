@@ -34,16 +34,37 @@
 //!   instrumentation API. The `memory_ops` field in `ExecutionTrace` will be empty.
 //! * Programs must be valid BPF bytecode or ELF format supported by solana-sbpf.
 
+pub mod cfg;
+pub mod decode;
+pub mod error;
+pub mod intern;
+pub mod memory;
+pub mod options;
 pub mod syscalls;
+pub mod sysvar;
 pub mod trace;
 pub mod transaction;
 pub mod vm;
+mod vm_compat;
 
+pub use cfg::{BasicBlock, Cfg, Edge};
+pub use decode::{DecodedInsn, InsnBuilder, OpcodeClass};
+pub use error::TraceError;
+pub use intern::InternedTrace;
+pub use memory::{MemoryAccess, MemoryAccessKind, MemoryRegionKind};
 pub use trace::{
-    AccountState, AccountStateChange, ExecutionTrace, InstructionTrace, RegisterState,
+    AccountState, AccountStateChange, ExecutionTrace, ExitKind, HashCall, InstructionTrace, MemorySnapshot, RegisterState, TraceFormat, TraceMeta,
+    log_commitment, program_hash, sbpf_version_from_name, unroll,
+};
+pub use options::TraceOptions;
+pub use solana_sbpf::program::SBPFVersion;
+pub use sysvar::{Clock, Rent, SysvarValues};
+pub use transaction::{deterministic_pubkey, TransactionContext};
+pub use vm::{
+    reference_execute, trace_program, trace_program_with_accounts,
+    trace_program_with_accounts_and_options, trace_program_with_options, TracerContext,
+    SBPF_VERSION,
 };
-pub use transaction::TransactionContext;
-pub use vm::{trace_program, trace_program_with_accounts, TracerContext};
 
 /// Result type for BPF tracer operations
 pub type Result<T> = anyhow::Result<T>;
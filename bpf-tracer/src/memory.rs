@@ -0,0 +1,210 @@
+//! sBPF virtual memory layout
+//!
+//! The sBPF address space is split into 4 GiB regions; the upper 32 bits of
+//! an address select the region. These helpers compute and classify the
+//! addresses accessed by load/store instructions.
+
+use crate::decode::{DecodedInsn, OpcodeClass};
+use serde::{Deserialize, Serialize};
+use solana_sbpf::{ebpf, program::SBPFVersion, vm::Config};
+
+/// Number of low address bits addressing within a region
+pub const REGION_BITS: u32 = 32;
+/// Start of the stack region
+pub const MM_STACK_START: u64 = ebpf::MM_STACK_START;
+/// Start of the heap region
+pub const MM_HEAP_START: u64 = ebpf::MM_HEAP_START;
+/// Start of the input (serialized accounts) region
+pub const MM_INPUT_START: u64 = ebpf::MM_INPUT_START;
+
+/// Memory region an address belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryRegionKind {
+    /// Program bytecode and read-only data
+    Program,
+    /// Call stack (r10 points here)
+    Stack,
+    /// Heap
+    Heap,
+    /// Serialized accounts and instruction data
+    Input,
+    /// No region is mapped at this address
+    Unmapped,
+}
+
+impl MemoryRegionKind {
+    /// Classify a virtual address by its region
+    pub fn of(address: u64) -> Self {
+        match address >> REGION_BITS {
+            0 | 1 => Self::Program,
+            2 => Self::Stack,
+            3 => Self::Heap,
+            4 => Self::Input,
+            _ => Self::Unmapped,
+        }
+    }
+}
+
+/// Bytes of stack in one call frame, as the tracer's VM is configured
+pub fn stack_frame_size() -> u64 {
+    Config::default().stack_frame_size as u64
+}
+
+/// How far a `call` moves the frame pointer under `version`
+///
+/// With fixed frames (sBPF v0) the callee's frame starts one frame above
+/// the caller's, past a gap frame if the VM enables gaps, as the tracer's
+/// does. With dynamic frames the VM leaves the frame pointer where it is.
+pub fn call_frame_stride(version: SBPFVersion) -> u64 {
+    if version.dynamic_stack_frames() {
+        return 0;
+    }
+    let config = Config::default();
+    let frames = if config.enable_stack_frame_gaps { 2 } else { 1 };
+    (config.stack_frame_size * frames) as u64
+}
+
+/// Compute the address accessed by `[base + offset]`
+///
+/// The 16-bit offset is sign-extended and added with 64-bit wrapping, so
+/// frame-pointer spills like `[r10 - 8]` address just below `r10`.
+pub fn effective_address(base: u64, offset: i16) -> u64 {
+    base.wrapping_add(offset as i64 as u64)
+}
+
+/// Whether a memory access read or wrote memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryAccessKind {
+    /// Load into a register
+    Load,
+    /// Store from a register or immediate
+    Store,
+}
+
+/// A load or store performed by an executed instruction
+///
+/// Recorded in `ExecutionTrace::memory_ops`, up to
+/// `TraceOptions::max_memory_ops`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryAccess {
+    /// Index of the instruction in `ExecutionTrace::instructions`
+    pub instruction: usize,
+    /// PC of the instruction
+    pub pc: u64,
+    /// Load or store
+    pub kind: MemoryAccessKind,
+    /// Address of the first byte accessed
+    pub address: u64,
+    /// Access width in bytes (1, 2, 4 or 8)
+    pub size: u8,
+    /// Value loaded or stored, zero-extended to 64 bits
+    pub value: u64,
+}
+
+impl MemoryAccess {
+    /// The access `insn` performs, given the registers around it
+    ///
+    /// Returns `None` unless `insn` is a load or store under `version`.
+    /// Addresses and values are inferred from the registers, so a faulting
+    /// access is reported as if it had succeeded.
+    pub fn infer(
+        instruction: usize,
+        pc: u64,
+        insn: &DecodedInsn,
+        version: SBPFVersion,
+        before: &[u64],
+        after: &[u64],
+    ) -> Option<Self> {
+        let register = |regs: &[u64], index: u8| regs.get(index as usize).copied();
+        let moved = version.move_memory_instruction_classes();
+        let size = if moved {
+            match insn.opcode & 0xf0 {
+                ebpf::BPF_1B => 1,
+                ebpf::BPF_2B => 2,
+                ebpf::BPF_4B => 4,
+                _ => 8,
+            }
+        } else {
+            match insn.opcode & 0x18 {
+                ebpf::BPF_B => 1,
+                ebpf::BPF_H => 2,
+                ebpf::BPF_W => 4,
+                _ => 8,
+            }
+        };
+
+        let (kind, base, value) = match insn.class_for(version) {
+            OpcodeClass::Load => (MemoryAccessKind::Load, insn.src, register(after, insn.dst)?),
+            OpcodeClass::Store => {
+                let from_register = if moved {
+                    insn.opcode & ebpf::BPF_X != 0
+                } else {
+                    insn.opcode & 0x07 == ebpf::BPF_STX
+                };
+                let value = if from_register {
+                    register(before, insn.src)?
+                } else {
+                    insn.imm as i64 as u64
+                };
+                (MemoryAccessKind::Store, insn.dst, value)
+            }
+            _ => return None,
+        };
+        let mask = u64::MAX >> (64 - 8 * size as u32);
+
+        Some(Self {
+            instruction,
+            pc,
+            kind,
+            address: effective_address(register(before, base)?, insn.off),
+            size,
+            value: value & mask,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_address_negative_offset() {
+        let frame_pointer = MM_STACK_START + 0x1000;
+        assert_eq!(effective_address(frame_pointer, -8), MM_STACK_START + 0xff8);
+        assert_eq!(effective_address(frame_pointer, 16), MM_STACK_START + 0x1010);
+    }
+
+    #[test]
+    fn test_region_of_address() {
+        assert_eq!(MemoryRegionKind::of(MM_STACK_START), MemoryRegionKind::Stack);
+        assert_eq!(MemoryRegionKind::of(MM_HEAP_START - 1), MemoryRegionKind::Stack);
+        assert_eq!(MemoryRegionKind::of(MM_HEAP_START), MemoryRegionKind::Heap);
+        assert_eq!(MemoryRegionKind::of(MM_INPUT_START + 42), MemoryRegionKind::Input);
+        assert_eq!(MemoryRegionKind::of(u64::MAX), MemoryRegionKind::Unmapped);
+    }
+
+    #[test]
+    fn test_call_frame_stride_by_version() {
+        assert_eq!(call_frame_stride(SBPFVersion::V2), 0);
+        assert!(call_frame_stride(SBPFVersion::V0) >= stack_frame_size());
+    }
+
+    #[test]
+    fn test_infer_access_width_by_version() {
+        let regs = [0, MM_STACK_START, 0x1234, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        // st [r1 + 4], -1 with a 1-byte width, in each version's encoding
+        let store = |opcode| DecodedInsn { opcode, dst: 1, src: 0, off: 4, imm: -1 };
+
+        let v2 = MemoryAccess::infer(0, 0, &store(ebpf::ST_1B_IMM), SBPFVersion::V2, &regs, &regs);
+        let v2 = v2.unwrap();
+        assert_eq!((v2.kind, v2.size, v2.value), (MemoryAccessKind::Store, 1, 0xff));
+        assert_eq!(v2.address, MM_STACK_START + 4);
+
+        let v0 = MemoryAccess::infer(0, 0, &store(ebpf::ST_B_IMM), SBPFVersion::V0, &regs, &regs);
+        assert_eq!(v0, Some(v2));
+
+        // An ALU instruction does not access memory
+        let mov = DecodedInsn { opcode: ebpf::MOV64_IMM, ..store(0) };
+        assert_eq!(MemoryAccess::infer(0, 0, &mov, SBPFVersion::V2, &regs, &regs), None);
+    }
+}
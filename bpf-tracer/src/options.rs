@@ -0,0 +1,116 @@
+//! Tracing options
+//!
+//! Controls how the tracer executes a program and what it captures.
+
+use crate::sysvar::SysvarValues;
+use solana_sbpf::program::SBPFVersion;
+
+/// Options controlling program execution and trace capture
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+    /// Maximum number of instructions the VM may execute
+    pub instruction_limit: u64,
+    /// Stop capturing the first time execution reaches this PC
+    ///
+    /// The returned trace includes the instruction at this PC and its
+    /// resulting register state, like a debugger breakpoint.
+    pub break_at_pc: Option<u64>,
+    /// sBPF version raw bytecode is loaded as
+    ///
+    /// ELF programs declare their own version and ignore this setting.
+    pub sbpf_version: SBPFVersion,
+    /// Copy the stack, heap and input regions into the trace after execution
+    ///
+    /// Off by default: the stack alone is 256 KiB.
+    pub capture_memory_snapshot: bool,
+    /// Read executed instructions' bytes from the program text on the
+    /// trace instead of copying each one
+    ///
+    /// Instructions then carry a `text_offset`; read their bytes with
+    /// `ExecutionTrace::instruction_bytes`.
+    pub reference_program_text: bool,
+    /// Return the trace up to a fault instead of an error
+    ///
+    /// The trace ends at the faulting instruction and has exit kind
+    /// `ExitKind::Fault`. Off by default, so faults stay errors.
+    pub trace_on_fault: bool,
+    /// Values the sysvar syscalls return
+    ///
+    /// Recorded on the trace as `ExecutionTrace::sysvars`.
+    pub sysvars: SysvarValues,
+    /// Record at most this many memory accesses
+    ///
+    /// Further loads and stores still execute, but are not recorded and set
+    /// `ExecutionTrace::memory_ops_truncated`. Unbounded by default.
+    pub max_memory_ops: Option<usize>,
+}
+
+impl TraceOptions {
+    /// Create options with default settings
+    pub fn new() -> Self {
+        Self {
+            instruction_limit: 100_000,
+            break_at_pc: None,
+            sbpf_version: crate::vm::SBPF_VERSION,
+            capture_memory_snapshot: false,
+            reference_program_text: false,
+            trace_on_fault: false,
+            sysvars: SysvarValues::default(),
+            max_memory_ops: None,
+        }
+    }
+
+    /// Set the instruction limit
+    pub fn with_instruction_limit(mut self, instruction_limit: u64) -> Self {
+        self.instruction_limit = instruction_limit;
+        self
+    }
+
+    /// Stop capturing at the first occurrence of `pc`
+    pub fn with_break_at_pc(mut self, pc: u64) -> Self {
+        self.break_at_pc = Some(pc);
+        self
+    }
+
+    /// Load raw bytecode as a specific sBPF version
+    pub fn with_sbpf_version(mut self, sbpf_version: SBPFVersion) -> Self {
+        self.sbpf_version = sbpf_version;
+        self
+    }
+
+    /// Capture the final memory region contents (see [`Self::capture_memory_snapshot`])
+    pub fn with_memory_snapshot(mut self, capture: bool) -> Self {
+        self.capture_memory_snapshot = capture;
+        self
+    }
+
+    /// Reference instruction bytes into the program text (see [`Self::reference_program_text`])
+    pub fn with_program_text_reference(mut self, reference: bool) -> Self {
+        self.reference_program_text = reference;
+        self
+    }
+
+    /// Keep the trace of a faulting execution (see [`Self::trace_on_fault`])
+    pub fn with_trace_on_fault(mut self, trace_on_fault: bool) -> Self {
+        self.trace_on_fault = trace_on_fault;
+        self
+    }
+
+    /// Answer the sysvar syscalls with `sysvars`
+    pub fn with_sysvars(mut self, sysvars: SysvarValues) -> Self {
+        self.sysvars = sysvars;
+        self
+    }
+
+    /// Cap the recorded memory accesses (see [`Self::max_memory_ops`])
+    pub fn with_max_memory_ops(mut self, max_memory_ops: usize) -> Self {
+        self.max_memory_ops = Some(max_memory_ops);
+        self
+    }
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -4,7 +4,8 @@
 //! For zkVM purposes, these are mostly stubs that allow programs to execute
 //! without errors, while we focus on capturing execution traces and account states.
 
-use crate::TracerContext;
+use crate::{HashCall, TracerContext};
+use sha3::{Digest, Keccak256};
 use solana_sbpf::{
     declare_builtin_function,
     error::EbpfError,
@@ -15,11 +16,11 @@ use std::str::from_utf8;
 declare_builtin_function!(
     /// sol_log: Log a string message
     ///
-    /// Used by the msg! macro in Solana programs.
-    /// For zkVM, we just log to tracing and return success.
+    /// Used by the msg! macro in Solana programs. The message is recorded
+    /// in the trace's logs (invalid UTF-8 replaced) and echoed to tracing.
     SyscallLog,
     fn rust(
-        _context_object: &mut TracerContext,
+        context_object: &mut TracerContext,
         message_addr: u64,
         message_len: u64,
         _arg3: u64,
@@ -28,6 +29,7 @@ declare_builtin_function!(
         memory_mapping: &mut MemoryMapping,
     ) -> Result<u64, Box<dyn std::error::Error>> {
         if message_len == 0 {
+            context_object.logs.push(String::new());
             return Ok(0);
         }
 
@@ -50,6 +52,7 @@ declare_builtin_function!(
                 tracing::debug!("sol_log: <non-UTF8 message>");
             }
         }
+        context_object.logs.push(String::from_utf8_lossy(message_bytes).into_owned());
 
         Ok(0)
     }
@@ -58,10 +61,11 @@ declare_builtin_function!(
 declare_builtin_function!(
     /// sol_log_64: Log up to 5 u64 values
     ///
-    /// Used for logging numeric values in Solana programs.
+    /// Used for logging numeric values in Solana programs. Recorded in the
+    /// trace's logs formatted as the Solana runtime prints them.
     SyscallLog64,
     fn rust(
-        _context_object: &mut TracerContext,
+        context_object: &mut TracerContext,
         arg1: u64,
         arg2: u64,
         arg3: u64,
@@ -70,6 +74,10 @@ declare_builtin_function!(
         _memory_mapping: &mut MemoryMapping,
     ) -> Result<u64, Box<dyn std::error::Error>> {
         tracing::debug!("sol_log_64: {}, {}, {}, {}, {}", arg1, arg2, arg3, arg4, arg5);
+        context_object.logs.push(format!(
+            "{:#x}, {:#x}, {:#x}, {:#x}, {:#x}",
+            arg1, arg2, arg3, arg4, arg5
+        ));
         Ok(0)
     }
 );
@@ -234,6 +242,59 @@ declare_builtin_function!(
     }
 );
 
+/// Copy a sysvar's bytes to `var_addr` in program memory
+fn write_sysvar(
+    memory_mapping: &mut MemoryMapping,
+    var_addr: u64,
+    bytes: &[u8],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let host_addr: Result<u64, EbpfError> =
+        memory_mapping.map(AccessType::Store, var_addr, bytes.len() as u64).into();
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), host_addr? as *mut u8, bytes.len());
+    }
+    Ok(0)
+}
+
+declare_builtin_function!(
+    /// sol_get_clock_sysvar: Read the clock sysvar
+    ///
+    /// Writes the clock from the context's sysvar values to `var_addr`.
+    SyscallGetClockSysvar,
+    fn rust(
+        context_object: &mut TracerContext,
+        var_addr: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        tracing::debug!("sol_get_clock_sysvar: slot {}", context_object.sysvars.clock.slot);
+        write_sysvar(memory_mapping, var_addr, &context_object.sysvars.clock.to_bytes())
+    }
+);
+
+declare_builtin_function!(
+    /// sol_get_rent_sysvar: Read the rent sysvar
+    ///
+    /// Writes the rent parameters from the context's sysvar values to
+    /// `var_addr`.
+    SyscallGetRentSysvar,
+    fn rust(
+        context_object: &mut TracerContext,
+        var_addr: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        tracing::debug!("sol_get_rent_sysvar");
+        write_sysvar(memory_mapping, var_addr, &context_object.sysvars.rent.to_bytes())
+    }
+);
+
 declare_builtin_function!(
     /// abort: Program abort
     ///
@@ -254,6 +315,76 @@ declare_builtin_function!(
     }
 );
 
+declare_builtin_function!(
+    /// sol_keccak256: Keccak-256 over a list of byte slices
+    ///
+    /// `vals_addr` points to `vals_len` `(addr: u64, len: u64)` slice
+    /// descriptors; their contents are hashed in order and the 32-byte
+    /// digest is written to `result_addr`. The call is recorded on the
+    /// context so it appears in the trace.
+    SyscallKeccak256,
+    fn rust(
+        context_object: &mut TracerContext,
+        vals_addr: u64,
+        vals_len: u64,
+        result_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &mut MemoryMapping,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let mut preimage = Vec::new();
+
+        if vals_len > 0 {
+            // Each descriptor is two u64s: address and length
+            let descriptors_len = vals_len.saturating_mul(16);
+            let descriptors_host_addr: Result<u64, EbpfError> =
+                memory_mapping.map(AccessType::Load, vals_addr, descriptors_len).into();
+            let descriptors = unsafe {
+                std::slice::from_raw_parts(
+                    descriptors_host_addr? as *const u8,
+                    descriptors_len as usize,
+                )
+            };
+
+            for descriptor in descriptors.chunks_exact(16) {
+                let addr = u64::from_le_bytes(descriptor[0..8].try_into().unwrap());
+                let len = u64::from_le_bytes(descriptor[8..16].try_into().unwrap());
+                if len == 0 {
+                    continue;
+                }
+
+                let host_addr: Result<u64, EbpfError> =
+                    memory_mapping.map(AccessType::Load, addr, len).into();
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(host_addr? as *const u8, len as usize)
+                };
+                preimage.extend_from_slice(bytes);
+            }
+        }
+
+        let digest: [u8; 32] = Keccak256::digest(&preimage).into();
+
+        let result_host_addr: Result<u64, EbpfError> =
+            memory_mapping.map(AccessType::Store, result_addr, 32).into();
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                digest.as_ptr(),
+                result_host_addr? as *mut u8,
+                digest.len(),
+            );
+        }
+
+        tracing::debug!("sol_keccak256: hashed {} bytes", preimage.len());
+        context_object.hash_calls.push(HashCall {
+            syscall: "sol_keccak256".to_string(),
+            preimage,
+            digest,
+        });
+
+        Ok(0)
+    }
+);
+
 /// Register all Solana syscalls with the BPF program loader
 ///
 /// This function registers the minimal set of syscalls needed for
@@ -271,6 +402,13 @@ pub fn register_syscalls(
     loader.register_function("sol_memmove_", SyscallMemmove::vm)?;
     loader.register_function("sol_memcmp_", SyscallMemcmp::vm)?;
 
+    // Hashing syscalls
+    loader.register_function("sol_keccak256", SyscallKeccak256::vm)?;
+
+    // Sysvar syscalls
+    loader.register_function("sol_get_clock_sysvar", SyscallGetClockSysvar::vm)?;
+    loader.register_function("sol_get_rent_sysvar", SyscallGetRentSysvar::vm)?;
+
     // Runtime syscalls
     loader.register_function("abort", SyscallAbort::vm)?;
 
@@ -307,6 +445,7 @@ mod tests {
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 0);
+        assert_eq!(context.logs, ["0x1, 0x2, 0x3, 0x4, 0x5"]);
     }
 
     #[test]
@@ -343,4 +482,47 @@ mod tests {
         let copied = &heap.as_slice()[100..100 + data.len()];
         assert_eq!(copied, data);
     }
+
+    #[test]
+    fn test_syscall_keccak256_records_call() {
+        let config = Config::default();
+        let mut context = TracerContext::new(10000);
+
+        // Two input slices ("hello " and "world") described at offset 64
+        let mut heap = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(1024);
+        heap.as_slice_mut()[0..6].copy_from_slice(b"hello ");
+        heap.as_slice_mut()[8..13].copy_from_slice(b"world");
+        let descriptors = [
+            (ebpf::MM_HEAP_START, 6u64),
+            (ebpf::MM_HEAP_START + 8, 5u64),
+        ];
+        for (i, (addr, len)) in descriptors.iter().enumerate() {
+            let offset = 64 + i * 16;
+            heap.as_slice_mut()[offset..offset + 8].copy_from_slice(&addr.to_le_bytes());
+            heap.as_slice_mut()[offset + 8..offset + 16].copy_from_slice(&len.to_le_bytes());
+        }
+
+        let regions: Vec<MemoryRegion> = vec![
+            MemoryRegion::new_writable(heap.as_slice_mut(), ebpf::MM_HEAP_START),
+        ];
+        let mut memory_mapping =
+            MemoryMapping::new(regions, &config, solana_sbpf::program::SBPFVersion::V2).unwrap();
+
+        let result = SyscallKeccak256::rust(
+            &mut context,
+            ebpf::MM_HEAP_START + 64,
+            2,
+            ebpf::MM_HEAP_START + 256,
+            0,
+            0,
+            &mut memory_mapping,
+        );
+        assert_eq!(result.unwrap(), 0);
+
+        let expected: [u8; 32] = Keccak256::digest(b"hello world").into();
+        assert_eq!(context.hash_calls.len(), 1);
+        assert_eq!(context.hash_calls[0].preimage, b"hello world");
+        assert_eq!(context.hash_calls[0].digest, expected);
+        assert_eq!(&heap.as_slice()[256..288], &expected);
+    }
 }
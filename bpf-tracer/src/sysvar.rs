@@ -0,0 +1,117 @@
+//! Sysvar values
+//!
+//! Programs read the clock and rent sysvars through the
+//! `sol_get_clock_sysvar` and `sol_get_rent_sysvar` syscalls. The tracer
+//! answers them from the [`SysvarValues`] in `TraceOptions`, so a trace is
+//! reproducible and a proof can commit to the values the program saw.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Clock sysvar, laid out as the runtime copies it into program memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    /// Current slot
+    pub slot: u64,
+    /// Timestamp of the first slot in the epoch
+    pub epoch_start_timestamp: i64,
+    /// Current epoch
+    pub epoch: u64,
+    /// Epoch the leader schedule was generated for
+    pub leader_schedule_epoch: u64,
+    /// Estimated Unix timestamp of the current slot
+    pub unix_timestamp: i64,
+}
+
+impl Clock {
+    /// Size of the clock in program memory
+    pub const SIZE: usize = 40;
+
+    /// Clock with every field zero except `slot`
+    pub fn at_slot(slot: u64) -> Self {
+        Self { slot, ..Self::default() }
+    }
+
+    /// The `#[repr(C)]` bytes the syscall writes: five little-endian 64-bit
+    /// fields in declaration order
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        let fields = [
+            self.slot.to_le_bytes(),
+            self.epoch_start_timestamp.to_le_bytes(),
+            self.epoch.to_le_bytes(),
+            self.leader_schedule_epoch.to_le_bytes(),
+            self.unix_timestamp.to_le_bytes(),
+        ];
+        for (chunk, field) in bytes.chunks_exact_mut(8).zip(fields) {
+            chunk.copy_from_slice(&field);
+        }
+        bytes
+    }
+}
+
+/// Rent sysvar, laid out as the runtime copies it into program memory
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rent {
+    /// Rental rate in lamports per byte-year
+    pub lamports_per_byte_year: u64,
+    /// Years of rent an account must hold to be exempt
+    pub exemption_threshold: f64,
+    /// Percentage of collected rent that is burned
+    pub burn_percent: u8,
+}
+
+impl Default for Rent {
+    /// Mainnet rent parameters
+    fn default() -> Self {
+        Self { lamports_per_byte_year: 3480, exemption_threshold: 2.0, burn_percent: 50 }
+    }
+}
+
+impl Rent {
+    /// Size of the rent sysvar in program memory, including padding
+    pub const SIZE: usize = 24;
+
+    /// The `#[repr(C)]` bytes the syscall writes; the trailing padding is zero
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.lamports_per_byte_year.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.exemption_threshold.to_le_bytes());
+        bytes[16] = self.burn_percent;
+        bytes
+    }
+}
+
+/// Sysvar values a program is traced with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SysvarValues {
+    /// Returned by `sol_get_clock_sysvar`
+    pub clock: Clock,
+    /// Returned by `sol_get_rent_sysvar`
+    pub rent: Rent,
+}
+
+impl SysvarValues {
+    /// Replace the clock
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Replace the rent parameters
+    pub fn with_rent(mut self, rent: Rent) -> Self {
+        self.rent = rent;
+        self
+    }
+
+    /// Commitment to the sysvar values
+    ///
+    /// SHA-256 of the clock bytes followed by the rent bytes, exactly as the
+    /// syscalls write them into program memory.
+    pub fn commitment(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.clock.to_bytes());
+        hasher.update(self.rent.to_bytes());
+        hasher.finalize().into()
+    }
+}
@@ -1,7 +1,17 @@
 //! Data structures for execution traces
 
-use serde::{Deserialize, Serialize};
+use crate::decode::{DecodedInsn, OpcodeClass};
+use crate::memory::{MemoryAccess, MemoryRegionKind};
+use crate::sysvar::SysvarValues;
+use crate::TraceError;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use solana_pubkey::Pubkey;
+use solana_sbpf::{ebpf, program::SBPFVersion};
+use std::fmt;
+use std::str::FromStr;
 
 /// Complete execution trace of a BPF program
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +24,160 @@ pub struct ExecutionTrace {
     pub initial_registers: RegisterState,
     /// Final register state at program exit
     pub final_registers: RegisterState,
+    /// Whether tracing stopped at a breakpoint (see `TraceOptions::break_at_pc`)
+    #[serde(default)]
+    pub breakpoint_hit: bool,
+    /// Execution parameters the trace was captured with
+    #[serde(default)]
+    pub meta: TraceMeta,
+    /// How execution terminated
+    #[serde(default)]
+    pub exit_kind: ExitKind,
+    /// Whether the trace covers the run from the program's entry to where
+    /// it stopped (see `exit_kind`)
+    ///
+    /// Set by the tracer, unless it stopped at a breakpoint. A window of a
+    /// longer trace (see [`Self::slice`]) or a hand-built trace is not
+    /// complete.
+    #[serde(default)]
+    pub complete: bool,
+    /// Hash syscalls made by the program, in call order
+    #[serde(default)]
+    pub hash_calls: Vec<HashCall>,
+    /// Messages logged through `sol_log_` and `sol_log_64_`, in call order
+    ///
+    /// Commit to them with [`log_commitment`].
+    #[serde(default)]
+    pub logs: Vec<String>,
+    /// Values the sysvar syscalls returned (see `TraceOptions::sysvars`)
+    ///
+    /// Commit to them with [`SysvarValues::commitment`].
+    #[serde(default)]
+    pub sysvars: SysvarValues,
+    /// Loads and stores of the executed instructions, in execution order
+    #[serde(default)]
+    pub memory_ops: Vec<MemoryAccess>,
+    /// Whether accesses past `TraceOptions::max_memory_ops` went unrecorded
+    #[serde(default)]
+    pub memory_ops_truncated: bool,
+    /// Final contents of the writable memory regions
+    ///
+    /// Only captured when `TraceOptions::capture_memory_snapshot` is set.
+    #[serde(default)]
+    pub memory_snapshot: Vec<MemorySnapshot>,
+    /// Program text the executed instructions were read from
+    ///
+    /// Instructions with a `text_offset` refer into it. Empty for traces
+    /// built by hand.
+    #[serde(default)]
+    pub program_text: Vec<u8>,
+}
+
+/// Contents of one memory region after execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemorySnapshot {
+    /// Region the bytes were read from
+    pub region: MemoryRegionKind,
+    /// Virtual address the region is mapped at
+    pub base_addr: u64,
+    /// Region contents
+    ///
+    /// For a stack mapped with frame gaps these are the frames back to back;
+    /// the gaps have no backing memory.
+    pub data: Vec<u8>,
+}
+
+/// A hash computed by the program through a syscall
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashCall {
+    /// Syscall name (e.g. "sol_keccak256")
+    pub syscall: String,
+    /// Bytes hashed (all input slices concatenated)
+    pub preimage: Vec<u8>,
+    /// Digest written back to the program
+    pub digest: [u8; 32],
+}
+
+/// How program execution terminated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitKind {
+    /// The program executed `exit`
+    #[default]
+    Exit,
+    /// Execution ran past the end of the text section without `exit`
+    RanOffEnd,
+    /// The instruction limit was reached before `exit`
+    BudgetExhausted,
+    /// Execution faulted (e.g. an access violation or division by zero)
+    ///
+    /// Only recorded with `TraceOptions::trace_on_fault`; the last captured
+    /// instruction is the one that faulted, and its after-state is the
+    /// unchanged state it faulted in.
+    Fault,
+}
+
+/// Execution parameters used to capture a trace
+///
+/// Recorded so a verifier or re-runner can reconstruct identical conditions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TraceMeta {
+    /// sBPF version the program was loaded as (e.g. "V2")
+    pub sbpf_version: String,
+    /// VM stack size in bytes
+    pub stack_size: usize,
+    /// Maximum number of instructions the VM was allowed to execute
+    pub instruction_limit: u64,
+    /// Maximum call depth
+    pub max_call_depth: usize,
+    /// SHA-256 of the loaded program bytes (see [`program_hash`])
+    #[serde(default)]
+    pub program_hash: [u8; 32],
+}
+
+impl TraceMeta {
+    /// sBPF version the program was loaded as
+    ///
+    /// See [`sbpf_version_from_name`] for traces that record none.
+    pub fn version(&self) -> SBPFVersion {
+        sbpf_version_from_name(&self.sbpf_version)
+    }
+}
+
+/// Parse an sBPF version name as [`TraceMeta::sbpf_version`] records it
+///
+/// Names other than `V0` to `V3`, including the empty name of a trace
+/// built by hand, give [`SBPF_VERSION`](crate::SBPF_VERSION), the version
+/// raw bytecode is traced as.
+pub fn sbpf_version_from_name(name: &str) -> SBPFVersion {
+    match name {
+        "V0" => SBPFVersion::V0,
+        "V1" => SBPFVersion::V1,
+        "V2" => SBPFVersion::V2,
+        "V3" => SBPFVersion::V3,
+        _ => crate::vm::SBPF_VERSION,
+    }
+}
+
+/// Commitment to a program: SHA-256 of its bytecode (raw text bytes or ELF)
+pub fn program_hash(bytecode: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytecode).into()
+}
+
+/// Commitment to a program's log output
+///
+/// SHA-256 over the number of messages (u64, little-endian), then each
+/// message as its byte length (u64, little-endian) followed by its UTF-8
+/// bytes. Length prefixes keep message boundaries part of the commitment,
+/// so `["ab", "c"]` and `["a", "bc"]` differ.
+pub fn log_commitment<S: AsRef<str>>(logs: &[S]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((logs.len() as u64).to_le_bytes());
+    for message in logs {
+        let message = message.as_ref().as_bytes();
+        hasher.update((message.len() as u64).to_le_bytes());
+        hasher.update(message);
+    }
+    hasher.finalize().into()
 }
 
 /// Trace of a single instruction execution
@@ -22,11 +186,22 @@ pub struct InstructionTrace {
     /// Program counter (instruction address)
     pub pc: u64,
     /// Raw instruction bytes
+    ///
+    /// Empty when the bytes are referenced through `text_offset` instead;
+    /// prefer [`ExecutionTrace::instruction_bytes`], which handles both.
     pub instruction_bytes: Vec<u8>,
+    /// Byte offset of the instruction in [`ExecutionTrace::program_text`]
+    #[serde(default)]
+    pub text_offset: Option<usize>,
     /// Register state before instruction execution
     pub registers_before: RegisterState,
     /// Register state after instruction execution
     pub registers_after: RegisterState,
+    /// PC a `call imm` jumps to, as resolved by the VM
+    ///
+    /// `None` for other instructions and for `call imm` syscalls.
+    #[serde(default)]
+    pub call_target: Option<u64>,
 }
 
 /// Solana account state with all account fields
@@ -88,6 +263,79 @@ impl AccountState {
             rent_epoch: 0,
         }
     }
+
+    /// Parse an account from RPC-style JSON
+    ///
+    /// Pubkeys are base58 strings and `data` is base64, either as a plain
+    /// string or as RPC's `["<base64>", "base64"]` pair:
+    ///
+    /// ```json
+    /// {"pubkey": "...", "lamports": 1000, "data": "AQID", "owner": "...",
+    ///  "executable": false, "rentEpoch": 0}
+    /// ```
+    ///
+    /// `executable` and `rentEpoch` default to `false` and 0. Failures are
+    /// [`TraceError::InvalidAccountJson`] naming the bad field. This is not
+    /// the [`Serialize`] format, which stores pubkeys as byte arrays.
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|err| invalid_account_json("account", err.to_string()))?;
+        let Value::Object(fields) = value else {
+            return Err(invalid_account_json("account", "expected a JSON object"));
+        };
+        let field = |name: &'static str| {
+            fields.get(name).ok_or_else(|| invalid_account_json(name, "missing required field"))
+        };
+        let pubkey = |name: &'static str| -> crate::Result<Pubkey> {
+            let value = field(name)?;
+            let text = value
+                .as_str()
+                .ok_or_else(|| invalid_account_json(name, "expected a base58 string"))?;
+            Pubkey::from_str(text).map_err(|err| {
+                invalid_account_json(name, format!("not a base58 public key: {}", err))
+            })
+        };
+        let u64_field = |name: &'static str, value: &Value| {
+            value.as_u64().ok_or_else(|| invalid_account_json(name, "expected an unsigned integer"))
+        };
+
+        let data = match field("data")? {
+            Value::String(text) => Some(text),
+            Value::Array(pair) => match pair.as_slice() {
+                [Value::String(text), encoding] if *encoding == "base64" => Some(text),
+                _ => None,
+            },
+            _ => None,
+        }
+        .ok_or_else(|| invalid_account_json("data", "expected a base64 string"))?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|err| invalid_account_json("data", format!("not base64: {}", err)))?;
+
+        let executable = match fields.get("executable") {
+            None => false,
+            Some(value) => value
+                .as_bool()
+                .ok_or_else(|| invalid_account_json("executable", "expected a boolean"))?,
+        };
+        let rent_epoch = match fields.get("rentEpoch") {
+            None => 0,
+            Some(value) => u64_field("rentEpoch", value)?,
+        };
+
+        Ok(Self {
+            pubkey: pubkey("pubkey")?,
+            lamports: u64_field("lamports", field("lamports")?)?,
+            data,
+            owner: pubkey("owner")?,
+            executable,
+            rent_epoch,
+        })
+    }
+}
+
+fn invalid_account_json(field: &'static str, reason: impl Into<String>) -> anyhow::Error {
+    TraceError::InvalidAccountJson { field, reason: reason.into() }.into()
 }
 
 impl AccountStateChange {
@@ -112,7 +360,10 @@ impl AccountStateChange {
 }
 
 /// State of all BPF registers (r0-r10) and PC
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `Debug` prints each register by name with its calling-convention role,
+/// e.g. `r0=42 (ret) r1=0 (arg) ... r10=8589938688 (fp) pc=3`.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RegisterState {
     /// General purpose registers r0-r10 and PC (r11)
     /// r0: return value
@@ -120,6 +371,7 @@ pub struct RegisterState {
     /// r6-r9: callee saved
     /// r10: frame pointer (read-only)
     /// r11: program counter
+    #[serde(deserialize_with = "deserialize_regs")]
     pub regs: [u64; 12],
 }
 
@@ -133,6 +385,120 @@ impl RegisterState {
     pub fn from_regs(regs: [u64; 12]) -> Self {
         Self { regs }
     }
+
+    /// Create register state from a slice of exactly 12 values (r0-r10 + PC)
+    pub fn from_slice(regs: &[u64]) -> crate::Result<Self> {
+        let regs = regs_from_slice(regs).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(Self { regs })
+    }
+}
+
+impl From<[u64; 12]> for RegisterState {
+    /// r0-r10 followed by the PC, as in [`RegisterState::from_regs`]
+    fn from(regs: [u64; 12]) -> Self {
+        Self::from_regs(regs)
+    }
+}
+
+impl From<[u64; 11]> for RegisterState {
+    /// r0-r10, with the PC at 0
+    fn from(gprs: [u64; 11]) -> Self {
+        let mut regs = [0; 12];
+        regs[..11].copy_from_slice(&gprs);
+        Self { regs }
+    }
+}
+
+impl fmt::Debug for RegisterState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, value) in self.regs[..11].iter().enumerate() {
+            let role = match index {
+                0 => "ret",
+                1..=5 => "arg",
+                6..=9 => "saved",
+                _ => "fp",
+            };
+            write!(f, "r{}={} ({}) ", index, value, role)?;
+        }
+        write!(f, "pc={}", self.regs[11])
+    }
+}
+
+/// Convert a register slice to the fixed register array, rejecting wrong lengths
+fn regs_from_slice(regs: &[u64]) -> std::result::Result<[u64; 12], String> {
+    regs.try_into().map_err(|_| {
+        format!(
+            "Malformed register state: expected 12 registers (r0-r10 + PC), got {}",
+            regs.len()
+        )
+    })
+}
+
+/// A serialized register value: decimal number or `0x`-prefixed hex string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RegisterValue {
+    Decimal(u64),
+    Hex(String),
+}
+
+impl RegisterValue {
+    fn parse(self) -> std::result::Result<u64, String> {
+        match self {
+            Self::Decimal(value) => Ok(value),
+            Self::Hex(s) => {
+                let digits = s
+                    .strip_prefix("0x")
+                    .ok_or_else(|| format!("Invalid register value {:?}: expected 0x prefix", s))?;
+                u64::from_str_radix(digits, 16)
+                    .map_err(|e| format!("Invalid register value {:?}: {}", s, e))
+            }
+        }
+    }
+}
+
+/// Deserialize registers (decimal or hex) with a descriptive error on length mismatch
+fn deserialize_regs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<[u64; 12], D::Error> {
+    let regs = Vec::<RegisterValue>::deserialize(deserializer)?
+        .into_iter()
+        .map(RegisterValue::parse)
+        .collect::<std::result::Result<Vec<u64>, String>>()
+        .map_err(serde::de::Error::custom)?;
+    regs_from_slice(&regs).map_err(serde::de::Error::custom)
+}
+
+/// Serialization format for execution traces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// JSON with register values as decimal numbers
+    #[default]
+    Json,
+    /// JSON with register values as `0x`-prefixed hex strings
+    JsonHex,
+}
+
+/// Rewrite every register array in a JSON value as hex strings
+fn registers_to_hex(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                match field {
+                    serde_json::Value::Array(regs) if key == "regs" => {
+                        for reg in regs.iter_mut() {
+                            if let Some(n) = reg.as_u64() {
+                                *reg = serde_json::Value::String(format!("0x{:x}", n));
+                            }
+                        }
+                    }
+                    _ => registers_to_hex(field),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(registers_to_hex),
+        _ => {}
+    }
 }
 
 impl Default for RegisterState {
@@ -149,6 +515,17 @@ impl ExecutionTrace {
             account_states: Vec::new(),
             initial_registers: RegisterState::new(),
             final_registers: RegisterState::new(),
+            breakpoint_hit: false,
+            meta: TraceMeta::default(),
+            exit_kind: ExitKind::Exit,
+            complete: false,
+            hash_calls: Vec::new(),
+            logs: Vec::new(),
+            sysvars: SysvarValues::default(),
+            memory_ops: Vec::new(),
+            memory_ops_truncated: false,
+            memory_snapshot: Vec::new(),
+            program_text: Vec::new(),
         }
     }
 
@@ -161,6 +538,255 @@ impl ExecutionTrace {
     pub fn account_change_count(&self) -> usize {
         self.account_states.len()
     }
+
+    /// Sort account state changes by pubkey
+    ///
+    /// The canonical order the tracer emits, so account commitments do not
+    /// depend on the order accounts were passed to the program.
+    pub fn sort_account_states(&mut self) {
+        self.account_states.sort_by_key(|change| change.pubkey);
+    }
+
+    /// Check that no account has more than one state change
+    ///
+    /// Reports the first repeated pubkey in trace order as
+    /// [`TraceError::DuplicateAccount`].
+    ///
+    /// [`TraceError::DuplicateAccount`]: crate::TraceError::DuplicateAccount
+    pub fn check_unique_accounts(&self) -> crate::Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        match self.account_states.iter().find(|change| !seen.insert(change.pubkey)) {
+            Some(change) => Err(TraceError::DuplicateAccount(change.pubkey).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that consecutive register states chain together
+    ///
+    /// The first instruction must start from the initial registers, each
+    /// instruction's `registers_after` must equal the next instruction's
+    /// `registers_before`, and the last must equal the final registers.
+    /// An output mismatch is reported as [`TraceError::BrokenChain`] for the
+    /// first instruction it occurs at. Account pubkeys must also be unique,
+    /// see [`Self::check_unique_accounts`].
+    ///
+    /// [`TraceError::BrokenChain`]: crate::TraceError::BrokenChain
+    pub fn check_consistency(&self) -> crate::Result<()> {
+        self.check_unique_accounts()?;
+
+        if let Some(first) = self.instructions.first() {
+            if first.registers_before.regs != self.initial_registers.regs {
+                anyhow::bail!("instruction 0 does not start from the initial registers");
+            }
+        }
+
+        for (index, instr) in self.instructions.iter().enumerate() {
+            let next = self
+                .instructions
+                .get(index + 1)
+                .map_or(&self.final_registers, |next| &next.registers_before);
+            let mismatch = instr
+                .registers_after
+                .regs
+                .iter()
+                .zip(&next.regs)
+                .position(|(after, next)| after != next);
+            if let Some(register) = mismatch {
+                return Err(crate::TraceError::BrokenChain {
+                    instruction: index,
+                    register,
+                    after: instr.registers_after.regs[register],
+                    next: next.regs[register],
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Raw bytes of an instruction in this trace
+    ///
+    /// Slices the program text for instructions captured with a
+    /// `text_offset`, and returns the owned bytes otherwise.
+    ///
+    /// # Panics
+    /// If `text_offset` points past the end of the program text.
+    pub fn instruction_bytes<'a>(&'a self, instr: &'a InstructionTrace) -> &'a [u8] {
+        match instr.text_offset {
+            Some(offset) => &self.program_text[offset..offset + ebpf::INSN_SIZE],
+            None => &instr.instruction_bytes,
+        }
+    }
+
+    /// Linear timeline of executed instructions, each with its call depth
+    ///
+    /// Instructions come in execution order, annotated with the depth of the
+    /// program invocation they ran in (0 for the top-level program). The
+    /// tracer does not follow cross-program invocations yet, so every
+    /// instruction is currently at depth 0; nested traces will be merged in
+    /// here once CPI is traced.
+    pub fn flatten(&self) -> Vec<(u32, &InstructionTrace)> {
+        self.instructions.iter().map(|instr| (0, instr)).collect()
+    }
+
+    /// Absolute target PC of each executed instruction, in trace order
+    ///
+    /// Jumps resolve to `pc + 1 + off`, whether or not a conditional jump
+    /// was taken, and `call imm` to its [`InstructionTrace::call_target`].
+    /// Everything else is `None`, including syscalls, `callx` and
+    /// undecodable instructions.
+    pub fn resolve_jump_targets(&self) -> Vec<Option<u64>> {
+        self.instructions
+            .iter()
+            .map(|instr| {
+                let insn = DecodedInsn::decode(self.instruction_bytes(instr)).ok()?;
+                match insn.class() {
+                    OpcodeClass::Jump => {
+                        Some(instr.pc.wrapping_add(1).wrapping_add_signed(insn.off as i64))
+                    }
+                    OpcodeClass::Call => instr.call_target,
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The instruction that ended execution
+    ///
+    /// The `exit` of a clean run, the faulting instruction of a
+    /// [`ExitKind::Fault`] trace, or the last instruction executed before
+    /// running off the end or out of budget. `None` for an empty trace and
+    /// for one stopped at a breakpoint, where execution went on.
+    pub fn terminating_instruction(&self) -> Option<&InstructionTrace> {
+        if self.breakpoint_hit {
+            return None;
+        }
+        self.instructions.last()
+    }
+
+    /// Sub-trace of instructions `start..end`, for proving one window
+    ///
+    /// The slice starts from instruction `start`'s `registers_before` and
+    /// ends at instruction `end - 1`'s `registers_after`, so it passes
+    /// [`Self::check_consistency`] whenever this trace does. An empty window
+    /// starts and ends in the state before `start`.
+    ///
+    /// Account changes are only visible once execution ends, so they, the
+    /// breakpoint flag and the exit kind are kept only by a window that
+    /// reaches the end of the trace, and only the window of the whole trace
+    /// stays [`complete`](Self::complete). Hash calls and logs are not tied to
+    /// instructions and are left out. Memory accesses of the window's
+    /// instructions are kept, renumbered from the window's start, along with
+    /// the truncation flag. Execution parameters, sysvars and the program
+    /// text are kept.
+    ///
+    /// # Panics
+    /// If `start > end` or `end` is past the last instruction.
+    pub fn slice(&self, start: usize, end: usize) -> ExecutionTrace {
+        let instructions = self.instructions[start..end].to_vec();
+        let initial_registers = self
+            .instructions
+            .get(start)
+            .map_or(&self.final_registers, |instr| &instr.registers_before)
+            .clone();
+        let final_registers = instructions
+            .last()
+            .map_or(&initial_registers, |instr| &instr.registers_after)
+            .clone();
+        let reaches_end = end == self.instructions.len();
+        let memory_ops = self
+            .memory_ops
+            .iter()
+            .filter(|op| (start..end).contains(&op.instruction))
+            .map(|op| MemoryAccess { instruction: op.instruction - start, ..*op })
+            .collect();
+
+        ExecutionTrace {
+            instructions,
+            account_states: if reaches_end { self.account_states.clone() } else { Vec::new() },
+            initial_registers,
+            final_registers,
+            breakpoint_hit: reaches_end && self.breakpoint_hit,
+            meta: self.meta.clone(),
+            exit_kind: if reaches_end { self.exit_kind } else { ExitKind::default() },
+            complete: self.complete && start == 0 && reaches_end,
+            sysvars: self.sysvars,
+            memory_ops,
+            memory_ops_truncated: self.memory_ops_truncated,
+            program_text: self.program_text.clone(),
+            ..ExecutionTrace::new()
+        }
+    }
+
+    /// Copy the trace with one instruction's output register overwritten
+    ///
+    /// Sets `registers_after.regs[reg]` of instruction `instr_index` to
+    /// `value` without re-chaining the states that follow, so the copy fails
+    /// [`Self::check_consistency`] at that instruction. Meant for building
+    /// negative proving tests.
+    ///
+    /// # Panics
+    /// If `instr_index` or `reg` (0-11) is out of range.
+    pub fn corrupt(&self, instr_index: usize, reg: usize, value: u64) -> ExecutionTrace {
+        let mut corrupted = self.clone();
+        corrupted.instructions[instr_index].registers_after.regs[reg] = value;
+        corrupted
+    }
+
+    /// Check a property of the final register state
+    ///
+    /// Returns an error showing the final registers if `predicate` fails,
+    /// which reads better in test output than a bare `assert!`.
+    pub fn assert_final<P>(&self, predicate: P) -> crate::Result<()>
+    where
+        P: FnOnce(&RegisterState) -> bool,
+    {
+        if !predicate(&self.final_registers) {
+            anyhow::bail!(
+                "final register assertion failed after {} instructions: r0-r10 = {:?}",
+                self.instruction_count(),
+                &self.final_registers.regs[..11]
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that final register `index` (r0-r10) holds `value`
+    pub fn assert_register(&self, index: usize, value: u64) -> crate::Result<()> {
+        if index > 10 {
+            anyhow::bail!("invalid register index r{}: expected r0-r10", index);
+        }
+        let actual = self.final_registers.regs[index];
+        if actual != value {
+            anyhow::bail!(
+                "final r{} is {} ({:#x}), expected {} ({:#x})",
+                index,
+                actual,
+                actual,
+                value,
+                value
+            );
+        }
+        Ok(())
+    }
+
+    /// Serialize the trace to JSON in the given format
+    pub fn to_json(&self, format: TraceFormat) -> crate::Result<String> {
+        match format {
+            TraceFormat::Json => Ok(serde_json::to_string(self)?),
+            TraceFormat::JsonHex => {
+                let mut value = serde_json::to_value(self)?;
+                registers_to_hex(&mut value);
+                Ok(serde_json::to_string(&value)?)
+            }
+        }
+    }
+
+    /// Deserialize a trace from JSON; register values may be decimal or hex
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
 }
 
 impl Default for ExecutionTrace {
@@ -169,6 +795,49 @@ impl Default for ExecutionTrace {
     }
 }
 
+/// Expand one traced loop iteration into `iterations` chained iterations
+///
+/// `body` is a single iteration in execution order, ending with the
+/// back-edge jump to `body[0]`. Every iteration repeats the body's PCs and
+/// instruction bytes, and each instruction's register change is replayed
+/// as a per-register wrapping difference (`registers_after -
+/// registers_before`) on top of the previous state. That is exact for the
+/// increments, induction variables and jumps simple counting loops are
+/// made of. Instructions whose effect depends on the values they read
+/// (e.g. multiplies or loads) need a real trace instead.
+///
+/// The back-edge of the final iteration falls through to the instruction
+/// after it, ending the loop. The result chains from
+/// `body[0].registers_before`, as [`ExecutionTrace::check_consistency`]
+/// expects.
+pub fn unroll(body: &[InstructionTrace], iterations: usize) -> Vec<InstructionTrace> {
+    let mut unrolled = Vec::with_capacity(body.len() * iterations);
+    let Some(first) = body.first() else { return unrolled };
+
+    let mut regs = first.registers_before.regs;
+    for _ in 0..iterations {
+        for instr in body {
+            let registers_before = RegisterState::from_regs(regs);
+            for (i, reg) in regs.iter_mut().enumerate() {
+                let delta =
+                    instr.registers_after.regs[i].wrapping_sub(instr.registers_before.regs[i]);
+                *reg = reg.wrapping_add(delta);
+            }
+            unrolled.push(InstructionTrace {
+                registers_before,
+                registers_after: RegisterState::from_regs(regs),
+                ..instr.clone()
+            });
+        }
+    }
+
+    // Leave the loop instead of taking the final back-edge
+    if let Some(back_edge) = unrolled.last_mut() {
+        back_edge.registers_after.regs[11] = back_edge.pc + 1;
+    }
+    unrolled
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -293,6 +962,226 @@ mod tests {
         assert_eq!(trace.account_change_count(), 1);
     }
 
+    #[test]
+    fn test_sort_account_states_by_pubkey() {
+        let mut trace = ExecutionTrace::new();
+        for seed in ["vault", "counter", "authority"] {
+            let account = AccountState::empty(crate::deterministic_pubkey(seed));
+            trace.account_states.push(AccountStateChange::new(account.pubkey, account.clone(), account));
+        }
+
+        trace.sort_account_states();
+
+        let pubkeys: Vec<Pubkey> = trace.account_states.iter().map(|c| c.pubkey).collect();
+        let mut sorted = pubkeys.clone();
+        sorted.sort();
+        assert_eq!(pubkeys, sorted);
+    }
+
+    #[test]
+    fn test_duplicate_account_rejected() {
+        let mut trace = ExecutionTrace::new();
+        for seed in ["vault", "counter", "vault"] {
+            let account = AccountState::empty(crate::deterministic_pubkey(seed));
+            let change = AccountStateChange::new(account.pubkey, account.clone(), account);
+            trace.account_states.push(change);
+        }
+
+        let err = trace.check_consistency().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TraceError>(),
+            Some(&TraceError::DuplicateAccount(crate::deterministic_pubkey("vault")))
+        );
+
+        trace.account_states.pop();
+        trace.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn test_slice_middle_instructions() {
+        // r0 = 1; r0 += 2; r0 *= 5; exit
+        let bytecode = crate::InsnBuilder::program([
+            crate::InsnBuilder::mov64_imm(0, 1),
+            crate::InsnBuilder::add64_imm(0, 2),
+            crate::InsnBuilder::mul64_imm(0, 5),
+            crate::InsnBuilder::exit(),
+        ]);
+        let mut trace = crate::trace_program(&bytecode).unwrap();
+        let pubkey = crate::deterministic_pubkey("counter");
+        let account = AccountState::empty(pubkey);
+        trace.account_states.push(AccountStateChange::new(pubkey, account.clone(), account));
+
+        assert!(trace.complete);
+        assert!(trace.slice(0, 4).complete);
+
+        let window = trace.slice(1, 3);
+        window.check_consistency().unwrap();
+        assert!(!window.complete);
+        assert_eq!(window.instructions.iter().map(|i| i.pc).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(window.initial_registers.regs[0], 1);
+        assert_eq!(window.final_registers.regs[0], 15);
+        assert_eq!(window.final_registers.regs[11], 3);
+        assert!(window.account_states.is_empty());
+
+        let tail = trace.slice(3, 4);
+        tail.check_consistency().unwrap();
+        assert_eq!(tail.account_states.len(), 1);
+        assert!(!tail.complete);
+        assert!(!trace.slice(0, 3).complete);
+    }
+
+    fn sample_trace() -> ExecutionTrace {
+        let mut trace = ExecutionTrace::new();
+        trace.initial_registers = RegisterState::from_regs([0, 0x2_0000_1000, 0, 0, 0, 0, 0, 0, 0, 0, u64::MAX, 0]);
+        trace.instructions.push(InstructionTrace {
+            pc: 0,
+            instruction_bytes: vec![0x95, 0, 0, 0, 0, 0, 0, 0],
+            registers_before: trace.initial_registers.clone(),
+            registers_after: trace.initial_registers.clone(),
+            call_target: None,
+            text_offset: None,
+        });
+        trace.final_registers = trace.initial_registers.clone();
+        trace
+    }
+
+    #[test]
+    fn test_flatten_top_level_trace() {
+        let mut trace = ExecutionTrace::new();
+        for pc in 0..3 {
+            trace.instructions.push(InstructionTrace {
+                pc,
+                instruction_bytes: vec![0; 8],
+                registers_before: RegisterState::new(),
+                registers_after: RegisterState::new(),
+                call_target: None,
+                text_offset: None,
+            });
+        }
+
+        let timeline = trace.flatten();
+        let steps: Vec<_> = timeline.iter().map(|(depth, instr)| (*depth, instr.pc)).collect();
+        assert_eq!(steps, vec![(0, 0), (0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn test_unroll_counter_loop() {
+        // add64 r0, 1; jne r0, 3, -2
+        let body = vec![
+            InstructionTrace {
+                pc: 1,
+                instruction_bytes: vec![0x07, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+                registers_before: RegisterState::from_regs([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
+                registers_after: RegisterState::from_regs([1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]),
+                call_target: None,
+                text_offset: None,
+            },
+            InstructionTrace {
+                pc: 2,
+                instruction_bytes: vec![0x55, 0x00, 0xfe, 0xff, 0x03, 0x00, 0x00, 0x00],
+                registers_before: RegisterState::from_regs([1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]),
+                registers_after: RegisterState::from_regs([1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
+                call_target: None,
+                text_offset: None,
+            },
+        ];
+
+        let instructions = unroll(&body, 3);
+        let pcs: Vec<_> = instructions.iter().map(|instr| instr.pc).collect();
+        assert_eq!(pcs, vec![1, 2, 1, 2, 1, 2]);
+
+        let trace = ExecutionTrace {
+            initial_registers: body[0].registers_before.clone(),
+            final_registers: instructions.last().unwrap().registers_after.clone(),
+            instructions,
+            ..Default::default()
+        };
+        trace.check_consistency().unwrap();
+        trace.assert_register(0, 3).unwrap();
+        // The last jump falls through
+        assert_eq!(trace.final_registers.regs[11], 3);
+
+        assert!(unroll(&body, 0).is_empty());
+    }
+
+    #[test]
+    fn test_register_state_from_arrays() {
+        let full = RegisterState::from([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(full.regs, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+
+        let state: RegisterState = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11].into();
+        assert_eq!(state.regs, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0]);
+    }
+
+    #[test]
+    fn test_register_state_debug_labels_registers() {
+        let state = RegisterState::from_regs([42, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0x2_0000_1000, 3]);
+        let debug = format!("{:?}", state);
+
+        assert!(debug.starts_with("r0=42 (ret) r1=1 (arg)"), "Unexpected Debug output: {}", debug);
+        assert!(debug.contains("r6=0 (saved)"), "Unexpected Debug output: {}", debug);
+        assert!(debug.contains("r10=8589938688 (fp)"), "Unexpected Debug output: {}", debug);
+        assert!(debug.ends_with(" pc=3"), "Unexpected Debug output: {}", debug);
+    }
+
+    #[test]
+    fn test_json_hex_roundtrip() {
+        let trace = sample_trace();
+
+        let json = trace.to_json(TraceFormat::JsonHex).unwrap();
+        assert!(json.contains("\"0x200001000\""), "Registers should be hex: {}", json);
+        assert!(json.contains("\"0xffffffffffffffff\""));
+
+        let decoded = ExecutionTrace::from_json(&json).unwrap();
+        assert_eq!(decoded.initial_registers.regs, trace.initial_registers.regs);
+        assert_eq!(
+            decoded.instructions[0].registers_after.regs,
+            trace.instructions[0].registers_after.regs
+        );
+    }
+
+    #[test]
+    fn test_json_decimal_still_deserializes() {
+        let trace = sample_trace();
+
+        let json = trace.to_json(TraceFormat::Json).unwrap();
+        assert!(json.contains("8589938688"), "Registers should be decimal: {}", json);
+
+        let decoded = ExecutionTrace::from_json(&json).unwrap();
+        assert_eq!(decoded.final_registers.regs, trace.final_registers.regs);
+    }
+
+    #[test]
+    fn test_account_state_from_json() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let json = format!(
+            r#"{{"pubkey": "{}", "lamports": 1000, "data": ["AQID", "base64"], "owner": "{}"}}"#,
+            pubkey, owner
+        );
+        let account = AccountState::from_json(&json).unwrap();
+        assert_eq!(account, AccountState::new(pubkey, 1000, vec![1, 2, 3], owner, false, 0));
+
+        let invalid = |json: String| match AccountState::from_json(&json)
+            .unwrap_err()
+            .downcast::<TraceError>()
+        {
+            Ok(TraceError::InvalidAccountJson { field, reason }) => (field, reason),
+            other => panic!("Expected an account JSON error, got {:?}", other),
+        };
+
+        let (field, reason) = invalid(json.replace(&pubkey.to_string(), "not-base58-0OIl"));
+        assert_eq!(field, "pubkey");
+        assert!(reason.starts_with("not a base58 public key"), "Unexpected reason: {}", reason);
+
+        let (field, reason) = invalid(json.replace("AQID", "AQ*D"));
+        assert_eq!(field, "data");
+        assert!(reason.starts_with("not base64"), "Unexpected reason: {}", reason);
+
+        let (field, reason) = invalid(json.replace(r#""lamports": 1000, "#, ""));
+        assert_eq!((field, reason.as_str()), ("lamports", "missing required field"));
+    }
+
     #[test]
     fn test_serialization() {
         let pubkey = Pubkey::new_unique();
@@ -313,4 +1202,36 @@ mod tests {
 
         assert_eq!(account, deserialized);
     }
+
+    #[test]
+    fn test_register_state_from_slice() {
+        let regs: Vec<u64> = (0..12).collect();
+        let state = RegisterState::from_slice(&regs).unwrap();
+        assert_eq!(state.regs[11], 11);
+
+        let err = RegisterState::from_slice(&regs[..5]).unwrap_err();
+        assert!(err.to_string().contains("expected 12 registers"));
+    }
+
+    #[test]
+    fn test_resolve_jump_targets() {
+        use crate::InsnBuilder;
+
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 2),
+            InsnBuilder::ja(1),
+            InsnBuilder::exit(),
+            InsnBuilder::add64_imm(1, -1),
+            InsnBuilder::jne_imm(1, 0, -2),
+            InsnBuilder::ja(-4),
+        ]);
+        let trace = crate::trace_program(&bytecode).unwrap();
+
+        let pcs: Vec<u64> = trace.instructions.iter().map(|instr| instr.pc).collect();
+        assert_eq!(pcs, vec![0, 1, 3, 4, 3, 4, 5, 2]);
+        assert_eq!(
+            trace.resolve_jump_targets(),
+            vec![None, Some(3), None, Some(3), None, Some(3), Some(2), None]
+        );
+    }
 }
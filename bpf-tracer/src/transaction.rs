@@ -5,9 +5,19 @@
 
 use crate::trace::AccountState;
 use crate::Result;
+use sha2::{Digest, Sha256};
 use solana_pubkey::Pubkey;
 use std::mem;
 
+/// Derive a fixed pubkey from a seed string
+///
+/// The SHA-256 of the seed, so the same seed gives the same pubkey in every
+/// run. Use it instead of `Pubkey::new_unique` for accounts whose
+/// commitments must be reproducible. Not a program-derived address.
+pub fn deterministic_pubkey(seed: &str) -> Pubkey {
+    Pubkey::new_from_array(Sha256::digest(seed.as_bytes()).into())
+}
+
 /// Transaction context holding accounts for BPF program execution
 ///
 /// Manages account data and provides serialization in Solana's format
@@ -228,6 +238,12 @@ impl TransactionContext {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_deterministic_pubkey() {
+        assert_eq!(deterministic_pubkey("counter"), deterministic_pubkey("counter"));
+        assert_ne!(deterministic_pubkey("counter"), deterministic_pubkey("vault"));
+    }
+
     #[test]
     fn test_serialize_empty() {
         let program_id = Pubkey::new_unique();
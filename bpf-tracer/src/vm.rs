@@ -2,24 +2,40 @@
 //!
 //! This module wraps solana-sbpf to capture complete execution traces.
 
+use crate::error::{catch_vm_panic, execution_error, load_error, verify_error, TraceError};
+use crate::decode::DecodedInsn;
+use crate::memory::{MemoryAccess, MemoryRegionKind};
+use crate::options::TraceOptions;
+use crate::sysvar::SysvarValues;
 use crate::trace::*;
 use crate::transaction::TransactionContext;
+use crate::vm_compat;
 use crate::Result;
 use solana_sbpf::{
     aligned_memory::AlignedMemory,
     elf::Executable,
-    error::ProgramResult,
+    elf_parser::Elf64,
+    error::{EbpfError, ProgramResult},
     memory_region::{MemoryMapping, MemoryRegion},
     program::{BuiltinProgram, FunctionRegistry, SBPFVersion},
     vm::{Config, ContextObject, EbpfVm},
 };
 use std::sync::Arc;
 
-/// Simple context object for instruction counting
+/// sBPF version raw bytecode is loaded as unless [`TraceOptions`] overrides it
+pub const SBPF_VERSION: SBPFVersion = SBPFVersion::V2;
+
+/// Context object for instruction counting and syscall recording
 #[derive(Debug, Clone)]
 pub struct TracerContext {
     /// Remaining instructions allowed
     pub remaining: u64,
+    /// Hash syscalls made so far
+    pub hash_calls: Vec<HashCall>,
+    /// Messages logged so far
+    pub logs: Vec<String>,
+    /// Values the sysvar syscalls return
+    pub sysvars: SysvarValues,
 }
 
 impl ContextObject for TracerContext {
@@ -34,7 +50,18 @@ impl ContextObject for TracerContext {
 
 impl TracerContext {
     pub fn new(remaining: u64) -> Self {
-        Self { remaining }
+        Self {
+            remaining,
+            hash_calls: Vec::new(),
+            logs: Vec::new(),
+            sysvars: SysvarValues::default(),
+        }
+    }
+
+    /// Answer the sysvar syscalls with `sysvars`
+    pub fn with_sysvars(mut self, sysvars: SysvarValues) -> Self {
+        self.sysvars = sysvars;
+        self
     }
 }
 
@@ -50,7 +77,33 @@ impl TracerContext {
 /// * `Ok(ExecutionTrace)` - Complete trace of program execution
 /// * `Err(_)` - If program loading or execution fails
 pub fn trace_program(bytecode: &[u8]) -> Result<ExecutionTrace> {
+    trace_program_with_options(bytecode, &TraceOptions::default())
+}
+
+/// Trace the execution of a BPF program with custom options
+///
+/// Like [`trace_program`], but lets the caller control the instruction
+/// limit and set a breakpoint PC (see [`TraceOptions`]).
+///
+/// # Arguments
+/// * `bytecode` - Raw BPF program bytecode
+/// * `options` - Execution and capture options
+///
+/// # Returns
+/// * `Ok(ExecutionTrace)` - Trace of program execution (up to the breakpoint, if hit)
+/// * `Err(_)` - If program loading or execution fails, including
+///   [`TraceError::InternalVmPanic`] if the VM panics
+pub fn trace_program_with_options(
+    bytecode: &[u8],
+    options: &TraceOptions,
+) -> Result<ExecutionTrace> {
+    catch_vm_panic(|| trace_with_options(bytecode, options))
+}
+
+/// [`trace_program_with_options`] without the panic guard
+fn trace_with_options(bytecode: &[u8], options: &TraceOptions) -> Result<ExecutionTrace> {
     tracing::info!("Starting BPF program trace, bytecode size: {} bytes", bytecode.len());
+    ensure_has_instructions(bytecode)?;
 
     // Create VM configuration
     let mut config = Config::default();
@@ -67,15 +120,15 @@ pub fn trace_program(bytecode: &[u8]) -> Result<ExecutionTrace> {
     let executable = Executable::from_text_bytes(
         bytecode,
         loader.clone(),
-        SBPFVersion::V2,
-        FunctionRegistry::default(),
+        options.sbpf_version,
+        call_target_registry(bytecode, &loader, options.sbpf_version)?,
     )
-    .map_err(|e| anyhow::anyhow!("Failed to load BPF program: {:?}", e))?;
+    .map_err(load_error)?;
 
     // Verify the executable
     executable
         .verify::<solana_sbpf::verifier::RequisiteVerifier>()
-        .map_err(|e| anyhow::anyhow!("Failed to verify executable: {:?}", e))?;
+        .map_err(verify_error)?;
 
     // Set up memory regions
     let mut stack = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(config.stack_size());
@@ -101,7 +154,7 @@ pub fn trace_program(bytecode: &[u8]) -> Result<ExecutionTrace> {
         .map_err(|e| anyhow::anyhow!("Failed to create memory mapping: {:?}", e))?;
 
     // Create context object with instruction limit
-    let mut context = TracerContext::new(100_000);
+    let mut context = TracerContext::new(options.instruction_limit).with_sysvars(options.sysvars);
 
     // Create VM
     let mut vm = EbpfVm::new(
@@ -113,14 +166,14 @@ pub fn trace_program(bytecode: &[u8]) -> Result<ExecutionTrace> {
     );
 
     // Capture initial register state
-    let initial_registers = RegisterState::from_regs(vm.registers);
+    let initial_registers = RegisterState::from_regs(vm_compat::read_registers(&vm));
 
     // Execute program in interpreter mode for tracing
     let (instruction_count, result) = vm.execute_program(&executable, true);
 
     // Capture final register state after execution
     // The registers in vm are updated during execution
-    let mut final_registers = RegisterState::from_regs(vm.registers);
+    let mut final_registers = RegisterState::from_regs(vm_compat::read_registers(&vm));
 
     // The return value (r0) is stored in the result
     if let ProgramResult::Ok(return_value) = result {
@@ -137,65 +190,168 @@ pub fn trace_program(bytecode: &[u8]) -> Result<ExecutionTrace> {
     let mut trace = ExecutionTrace::new();
     trace.initial_registers = initial_registers.clone();
     trace.final_registers = final_registers.clone();
+    trace.meta = trace_meta(&config, executable.get_sbpf_version(), options, bytecode);
+    trace.complete = true;
+    trace.hash_calls = std::mem::take(&mut vm_compat::context_mut(&mut vm).hash_calls);
+    trace.logs = std::mem::take(&mut vm_compat::context_mut(&mut vm).logs);
+    trace.sysvars = options.sysvars;
 
     // Capture instruction-level traces from VM register trace
     if config.enable_register_tracing {
-        tracing::debug!("Captured {} instruction traces", vm.register_trace.len());
-
-        // Get the program bytes to extract instruction data
-        let (_program_vm_addr, program_bytes) = executable.get_text_bytes();
-
-        for (idx, registers) in vm.register_trace.iter().enumerate() {
-            let pc = registers[11];
-
-            // Calculate instruction offset in the program
-            let insn_offset = (pc as usize).saturating_mul(ebpf::INSN_SIZE);
-
-            // Extract instruction bytes (8 bytes per BPF instruction)
-            let instruction_bytes = if insn_offset + ebpf::INSN_SIZE <= program_bytes.len() {
-                program_bytes[insn_offset..insn_offset + ebpf::INSN_SIZE].to_vec()
-            } else {
-                vec![0; ebpf::INSN_SIZE]
-            };
-
-            // The register_trace entries are the state BEFORE executing the instruction at that PC
-            let registers_before = RegisterState::from_regs(*registers);
+        tracing::debug!(
+            "Captured {} instruction traces",
+            vm_compat::read_register_trace(&vm).len()
+        );
 
-            // Get register state after this instruction
-            // Look at the next trace entry or use final registers
-            let registers_after = if idx + 1 < vm.register_trace.len() {
-                RegisterState::from_regs(vm.register_trace[idx + 1])
-            } else {
-                // Last instruction - use final registers
-                final_registers.clone()
-            };
-
-            trace.instructions.push(InstructionTrace {
-                pc,
-                instruction_bytes,
-                registers_before,
-                registers_after,
-            });
-        }
+        capture_instructions(
+            vm_compat::read_register_trace(&vm),
+            &executable,
+            &final_registers,
+            options,
+            &mut trace,
+        );
     }
 
-    // Memory operation tracking:
-    // solana-sbpf doesn't provide built-in memory operation tracing like it does for registers.
-    // To implement full memory tracking, we would need to either:
-    // 1. Fork solana-sbpf and add instrumentation to MemoryMapping load/store methods
-    // 2. Use a custom memory region that logs all accesses
-    // 3. Parse instructions and infer memory operations from load/store opcodes
-    //
-    // For now, we leave trace.memory_ops empty. This can be extended in the future.
-    tracing::debug!("Memory operation tracking not yet implemented");
+    if options.capture_memory_snapshot {
+        trace.memory_snapshot = vec![snapshot_region(
+            MemoryRegionKind::Stack,
+            ebpf::MM_STACK_START,
+            stack.as_slice(),
+        )];
+    }
 
     match result {
         ProgramResult::Ok(_) => Ok(trace),
+        ProgramResult::Err(err) if trace.breakpoint_hit => {
+            // The captured prefix ends at the breakpoint, before the failure
+            tracing::debug!("Execution failed after breakpoint: {:?}", err);
+            Ok(trace)
+        }
+        ProgramResult::Err(EbpfError::ExecutionOverrun) => {
+            Ok(unterminated(trace, ExitKind::RanOffEnd))
+        }
+        ProgramResult::Err(EbpfError::ExceededMaxInstructions) => {
+            Ok(unterminated(trace, ExitKind::BudgetExhausted))
+        }
+        ProgramResult::Err(err) if options.trace_on_fault => {
+            tracing::warn!("Program faulted at pc {}: {:?}", failing_pc(&vm), err);
+            Ok(unterminated(trace, ExitKind::Fault))
+        }
         ProgramResult::Err(err) => {
             tracing::error!("Program execution failed with error: {:?}", err);
             tracing::error!("Instruction count before failure: {}", instruction_count);
-            Err(anyhow::anyhow!("Program execution failed: {:?}", err))
+            Err(execution_error(err, failing_pc(&vm)))
+        }
+    }
+}
+
+/// Execute a BPF program and return only its final register state
+///
+/// A lighter oracle than [`trace_program_with_options`] for cross-checking
+/// a trace's `final_registers`: register tracing is off, so no per-instruction
+/// state is recorded. r0-r9 start from `initial_registers`; r10 (frame
+/// pointer) and the PC are set up by the VM as usual.
+///
+/// Unlike tracing, any execution error (including running out of
+/// instructions) is returned as an error, since there is no final state.
+pub fn reference_execute(
+    bytecode: &[u8],
+    initial_registers: &RegisterState,
+    options: &TraceOptions,
+) -> Result<RegisterState> {
+    catch_vm_panic(|| execute_reference(bytecode, initial_registers, options))
+}
+
+/// [`reference_execute`] without the panic guard
+fn execute_reference(
+    bytecode: &[u8],
+    initial_registers: &RegisterState,
+    options: &TraceOptions,
+) -> Result<RegisterState> {
+    ensure_has_instructions(bytecode)?;
+
+    let mut config = Config::default();
+    config.enable_instruction_meter = true;
+    config.enable_register_tracing = false;
+
+    let mut loader = BuiltinProgram::new_loader(config.clone());
+    crate::syscalls::register_syscalls(&mut loader)
+        .map_err(|e| anyhow::anyhow!("Failed to register syscalls: {:?}", e))?;
+    let loader = Arc::new(loader);
+
+    let executable = Executable::from_text_bytes(
+        bytecode,
+        loader.clone(),
+        options.sbpf_version,
+        call_target_registry(bytecode, &loader, options.sbpf_version)?,
+    )
+    .map_err(load_error)?;
+    executable
+        .verify::<solana_sbpf::verifier::RequisiteVerifier>()
+        .map_err(verify_error)?;
+
+    let mut stack = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(config.stack_size());
+    let vm_gap_size = if config.enable_stack_frame_gaps {
+        config.stack_frame_size as u64
+    } else {
+        0
+    };
+    let regions: Vec<MemoryRegion> = vec![
+        executable.get_ro_region(),
+        MemoryRegion::new_writable_gapped(
+            stack.as_slice_mut(),
+            ebpf::MM_STACK_START,
+            vm_gap_size,
+        ),
+    ];
+    let memory_mapping = MemoryMapping::new(regions, &config, executable.get_sbpf_version())
+        .map_err(|e| anyhow::anyhow!("Failed to create memory mapping: {:?}", e))?;
+
+    let mut context = TracerContext::new(options.instruction_limit).with_sysvars(options.sysvars);
+    let mut vm = EbpfVm::new(
+        loader,
+        executable.get_sbpf_version(),
+        &mut context,
+        memory_mapping,
+        config.stack_size(),
+    );
+    for (reg, &value) in initial_registers.regs[..10].iter().enumerate() {
+        vm_compat::write_register(&mut vm, reg, value);
+    }
+
+    let (_instruction_count, result) = vm.execute_program(&executable, true);
+    match result {
+        ProgramResult::Ok(return_value) => {
+            let mut final_registers = RegisterState::from_regs(vm_compat::read_registers(&vm));
+            final_registers.regs[0] = return_value;
+            Ok(final_registers)
         }
+        ProgramResult::Err(err) => Err(anyhow::anyhow!("Program execution failed: {:?}", err)),
+    }
+}
+
+/// Copy a memory region's final contents into a snapshot
+fn snapshot_region(region: MemoryRegionKind, base_addr: u64, data: &[u8]) -> MemorySnapshot {
+    MemorySnapshot {
+        region,
+        base_addr,
+        data: data.to_vec(),
+    }
+}
+
+/// Record the execution parameters a trace is captured with
+fn trace_meta(
+    config: &Config,
+    sbpf_version: SBPFVersion,
+    options: &TraceOptions,
+    bytecode: &[u8],
+) -> TraceMeta {
+    TraceMeta {
+        sbpf_version: format!("{:?}", sbpf_version),
+        stack_size: config.stack_size(),
+        instruction_limit: options.instruction_limit,
+        max_call_depth: config.max_call_depth,
+        program_hash: program_hash(bytecode),
     }
 }
 
@@ -214,12 +370,41 @@ pub fn trace_program(bytecode: &[u8]) -> Result<ExecutionTrace> {
 pub fn trace_program_with_accounts(
     bytecode: &[u8],
     context: &mut TransactionContext,
+) -> Result<ExecutionTrace> {
+    let options = TraceOptions::default().with_instruction_limit(1_000_000);
+    trace_program_with_accounts_and_options(bytecode, context, &options)
+}
+
+/// Trace the execution of a BPF program with Solana account context and custom options
+///
+/// Like [`trace_program_with_accounts`], but lets the caller control the
+/// instruction limit and set a breakpoint PC (see [`TraceOptions`]).
+///
+/// When a breakpoint is hit, account state changes are not captured since
+/// they reflect the completed execution rather than the breakpoint state.
+///
+/// A panic inside the VM is reported as [`TraceError::InternalVmPanic`];
+/// `context` may then hold partially updated accounts.
+pub fn trace_program_with_accounts_and_options(
+    bytecode: &[u8],
+    context: &mut TransactionContext,
+    options: &TraceOptions,
+) -> Result<ExecutionTrace> {
+    catch_vm_panic(|| trace_with_accounts(bytecode, context, options))
+}
+
+/// [`trace_program_with_accounts_and_options`] without the panic guard
+fn trace_with_accounts(
+    bytecode: &[u8],
+    context: &mut TransactionContext,
+    options: &TraceOptions,
 ) -> Result<ExecutionTrace> {
     tracing::info!(
         "Starting BPF program trace with {} accounts, bytecode size: {} bytes",
         context.accounts.len(),
         bytecode.len()
     );
+    ensure_has_instructions(bytecode)?;
 
     // Snapshot account states before execution
     let accounts_before = context.snapshot_accounts();
@@ -241,23 +426,22 @@ pub fn trace_program_with_accounts(
     // Load the BPF program (try ELF first, fall back to text bytes)
     let executable = if bytecode.starts_with(b"\x7fELF") {
         // ELF file
-        Executable::from_elf(bytecode, loader.clone())
-            .map_err(|e| anyhow::anyhow!("Failed to load ELF program: {:?}", e))?
+        Executable::from_elf(bytecode, loader.clone()).map_err(load_error)?
     } else {
         // Raw bytecode
         Executable::from_text_bytes(
             bytecode,
             loader.clone(),
-            SBPFVersion::V2,
-            FunctionRegistry::default(),
+            options.sbpf_version,
+            call_target_registry(bytecode, &loader, options.sbpf_version)?,
         )
-        .map_err(|e| anyhow::anyhow!("Failed to load BPF program: {:?}", e))?
+        .map_err(load_error)?
     };
 
     // Verify the executable
     executable
         .verify::<solana_sbpf::verifier::RequisiteVerifier>()
-        .map_err(|e| anyhow::anyhow!("Failed to verify executable: {:?}", e))?;
+        .map_err(verify_error)?;
 
     // Set up memory regions with account data
     let mut stack = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(config.stack_size());
@@ -290,7 +474,8 @@ pub fn trace_program_with_accounts(
         .map_err(|e| anyhow::anyhow!("Failed to create memory mapping: {:?}", e))?;
 
     // Create context object with instruction limit
-    let mut tracer_context = TracerContext::new(1_000_000);
+    let mut tracer_context =
+        TracerContext::new(options.instruction_limit).with_sysvars(options.sysvars);
 
     // Create VM
     let mut vm = EbpfVm::new(
@@ -302,16 +487,16 @@ pub fn trace_program_with_accounts(
     );
 
     // Set r1 to point to input data (MM_INPUT_START)
-    vm.registers[1] = ebpf::MM_INPUT_START;
+    vm_compat::write_register(&mut vm, 1, ebpf::MM_INPUT_START);
 
     // Capture initial register state
-    let initial_registers = RegisterState::from_regs(vm.registers);
+    let initial_registers = RegisterState::from_regs(vm_compat::read_registers(&vm));
 
     // Execute program in interpreter mode for tracing
     let (instruction_count, result) = vm.execute_program(&executable, true);
 
     // Capture final register state after execution
-    let mut final_registers = RegisterState::from_regs(vm.registers);
+    let mut final_registers = RegisterState::from_regs(vm_compat::read_registers(&vm));
 
     // The return value (r0) is stored in the result
     if let ProgramResult::Ok(return_value) = result {
@@ -334,59 +519,55 @@ pub fn trace_program_with_accounts(
     let mut trace = ExecutionTrace::new();
     trace.initial_registers = initial_registers.clone();
     trace.final_registers = final_registers.clone();
+    trace.meta = trace_meta(&config, executable.get_sbpf_version(), options, bytecode);
+    trace.complete = true;
+    trace.hash_calls = std::mem::take(&mut vm_compat::context_mut(&mut vm).hash_calls);
+    trace.logs = std::mem::take(&mut vm_compat::context_mut(&mut vm).logs);
+    trace.sysvars = options.sysvars;
 
     // Capture instruction-level traces from VM register trace
     if config.enable_register_tracing {
-        tracing::debug!("Captured {} instruction traces", vm.register_trace.len());
-
-        // Get the program bytes to extract instruction data
-        let (_program_vm_addr, program_bytes) = executable.get_text_bytes();
-
-        for (idx, registers) in vm.register_trace.iter().enumerate() {
-            let pc = registers[11];
-
-            // Calculate instruction offset in the program
-            let insn_offset = (pc as usize).saturating_mul(ebpf::INSN_SIZE);
-
-            // Extract instruction bytes (8 bytes per BPF instruction)
-            let instruction_bytes = if insn_offset + ebpf::INSN_SIZE <= program_bytes.len() {
-                program_bytes[insn_offset..insn_offset + ebpf::INSN_SIZE].to_vec()
-            } else {
-                vec![0; ebpf::INSN_SIZE]
-            };
+        tracing::debug!(
+            "Captured {} instruction traces",
+            vm_compat::read_register_trace(&vm).len()
+        );
 
-            // The register_trace entries are the state BEFORE executing the instruction at that PC
-            let registers_before = RegisterState::from_regs(*registers);
+        capture_instructions(
+            vm_compat::read_register_trace(&vm),
+            &executable,
+            &final_registers,
+            options,
+            &mut trace,
+        );
+    }
 
-            // Get register state after this instruction
-            // Look at the next trace entry or use final registers
-            let registers_after = if idx + 1 < vm.register_trace.len() {
-                RegisterState::from_regs(vm.register_trace[idx + 1])
-            } else {
-                // Last instruction - use final registers
-                final_registers.clone()
-            };
-
-            trace.instructions.push(InstructionTrace {
-                pc,
-                instruction_bytes,
-                registers_before,
-                registers_after,
-            });
-        }
+    if options.capture_memory_snapshot {
+        trace.memory_snapshot = vec![
+            snapshot_region(MemoryRegionKind::Stack, ebpf::MM_STACK_START, stack.as_slice()),
+            snapshot_region(MemoryRegionKind::Heap, ebpf::MM_HEAP_START, heap.as_slice()),
+            snapshot_region(MemoryRegionKind::Input, ebpf::MM_INPUT_START, input_buffer.as_slice()),
+        ];
     }
 
-    // Capture account state changes
+    // Capture account state changes (skipped when stopped at a breakpoint).
+    // An account passed more than once is one account: keep its first
+    // before-state and the latest change to it.
     for (before, after) in accounts_before.iter().zip(accounts_after.iter()) {
-        if before != after {
-            trace.account_states.push(AccountStateChange::new(
+        if before == after || trace.breakpoint_hit {
+            continue;
+        }
+        match trace.account_states.iter_mut().find(|c| c.pubkey == before.pubkey) {
+            Some(change) => change.after = after.clone(),
+            None => trace.account_states.push(AccountStateChange::new(
                 before.pubkey,
                 before.clone(),
                 after.clone(),
-            ));
+            )),
         }
     }
 
+    trace.sort_account_states();
+
     tracing::info!(
         "Captured {} account state changes",
         trace.account_states.len()
@@ -394,31 +575,256 @@ pub fn trace_program_with_accounts(
 
     match result {
         ProgramResult::Ok(_) => Ok(trace),
+        ProgramResult::Err(err) if trace.breakpoint_hit => {
+            // The captured prefix ends at the breakpoint, before the failure
+            tracing::debug!("Execution failed after breakpoint: {:?}", err);
+            Ok(trace)
+        }
+        // A failed transaction leaves its accounts unchanged
+        ProgramResult::Err(EbpfError::ExecutionOverrun) => {
+            trace.account_states.clear();
+            Ok(unterminated(trace, ExitKind::RanOffEnd))
+        }
+        ProgramResult::Err(EbpfError::ExceededMaxInstructions) => {
+            trace.account_states.clear();
+            Ok(unterminated(trace, ExitKind::BudgetExhausted))
+        }
+        ProgramResult::Err(err) if options.trace_on_fault => {
+            tracing::warn!("Program faulted at pc {}: {:?}", failing_pc(&vm), err);
+            trace.account_states.clear();
+            Ok(unterminated(trace, ExitKind::Fault))
+        }
         ProgramResult::Err(err) => {
             tracing::error!("Program execution failed with error: {:?}", err);
             tracing::error!("Instruction count before failure: {}", instruction_count);
-            tracing::error!("PC at failure: {}", vm.registers[11]);
-            tracing::error!("Registers at failure: {:?}", vm.registers);
+            tracing::error!("PC at failure: {}", vm_compat::read_registers(&vm)[11]);
+            tracing::error!("Registers at failure: {:?}", vm_compat::read_registers(&vm));
 
             // Log last few instructions executed
-            if !vm.register_trace.is_empty() {
-                let num_to_show = std::cmp::min(5, vm.register_trace.len());
+            let register_trace = vm_compat::read_register_trace(&vm);
+            if !register_trace.is_empty() {
+                let num_to_show = std::cmp::min(5, register_trace.len());
                 tracing::error!("Last {} instructions executed:", num_to_show);
-                for (i, regs) in vm.register_trace.iter().rev().take(num_to_show).enumerate() {
+                for (i, regs) in register_trace.iter().rev().take(num_to_show).enumerate() {
                     tracing::error!("  -{}: PC={}", i, regs[11]);
                 }
             }
 
-            Err(anyhow::anyhow!("Program execution failed: {:?}", err))
+            Err(execution_error(err, failing_pc(&vm)))
+        }
+    }
+}
+
+/// PC of the instruction a failed execution stopped at
+///
+/// The last register trace entry is the state before that instruction; the
+/// registers' PC is the fallback when tracing is off.
+fn failing_pc(vm: &EbpfVm<TracerContext>) -> u64 {
+    vm_compat::read_register_trace(vm)
+        .last()
+        .map_or(vm_compat::read_registers(vm)[11], |regs| regs[11])
+}
+
+/// Record that a program stopped without executing `exit`
+fn unterminated(mut trace: ExecutionTrace, exit_kind: ExitKind) -> ExecutionTrace {
+    tracing::warn!(
+        "Program terminated without exit ({:?}) after {} instructions",
+        exit_kind,
+        trace.instruction_count()
+    );
+    trace.exit_kind = exit_kind;
+    trace
+}
+
+/// Build per-instruction traces from the VM's register trace
+///
+/// Each register trace entry is the state BEFORE executing the instruction
+/// at its PC; the state after is the next entry (or the final registers for
+/// the last instruction). Stops after the first instruction at
+/// `options.break_at_pc`, marking the trace and snapshotting its state.
+///
+/// Loads and stores are recorded in `trace.memory_ops`, inferred from the
+/// instruction and its registers, until `options.max_memory_ops` is reached.
+fn capture_instructions(
+    register_trace: &[[u64; 12]],
+    executable: &Executable<TracerContext>,
+    final_registers: &RegisterState,
+    options: &TraceOptions,
+    trace: &mut ExecutionTrace,
+) {
+    // Get the program bytes to extract instruction data
+    let (_program_vm_addr, program_bytes) = executable.get_text_bytes();
+    trace.program_text = program_bytes.to_vec();
+
+    for (idx, registers) in register_trace.iter().enumerate() {
+        let pc = registers[11];
+
+        // Calculate instruction offset in the program
+        let insn_offset = (pc as usize).saturating_mul(ebpf::INSN_SIZE);
+
+        // Extract instruction bytes (8 bytes per BPF instruction)
+        // A PC past the end of the program was never executed (the VM
+        // stopped with an overrun), so the previous entry is the last one
+        if insn_offset + ebpf::INSN_SIZE > program_bytes.len() {
+            break;
+        }
+        let (instruction_bytes, text_offset) = if options.reference_program_text {
+            (Vec::new(), Some(insn_offset))
+        } else {
+            (program_bytes[insn_offset..insn_offset + ebpf::INSN_SIZE].to_vec(), None)
+        };
+        let insn = ebpf::get_insn(program_bytes, pc as usize);
+        let call_target = match insn.opc {
+            ebpf::CALL_IMM => resolve_call_target(executable, pc, insn.imm),
+            _ => None,
+        };
+
+        // The register_trace entries are the state BEFORE executing the instruction at that PC
+        let registers_before = RegisterState::from_regs(*registers);
+
+        // Get register state after this instruction
+        // Look at the next trace entry or use final registers
+        let registers_after = if idx + 1 < register_trace.len() {
+            RegisterState::from_regs(register_trace[idx + 1])
+        } else {
+            // Last instruction - use final registers
+            final_registers.clone()
+        };
+
+        let access = DecodedInsn::decode(&program_bytes[insn_offset..insn_offset + ebpf::INSN_SIZE])
+            .ok()
+            .and_then(|decoded| {
+                MemoryAccess::infer(
+                    idx,
+                    pc,
+                    &decoded,
+                    executable.get_sbpf_version(),
+                    &registers_before.regs,
+                    &registers_after.regs,
+                )
+            });
+        if let Some(access) = access {
+            if options.max_memory_ops.is_some_and(|max| trace.memory_ops.len() >= max) {
+                trace.memory_ops_truncated = true;
+            } else {
+                trace.memory_ops.push(access);
+            }
+        }
+
+        trace.instructions.push(InstructionTrace {
+            pc,
+            instruction_bytes,
+            registers_before,
+            registers_after: registers_after.clone(),
+            call_target,
+            text_offset,
+        });
+
+        if options.break_at_pc == Some(pc) {
+            tracing::info!("Breakpoint hit at PC {} after {} instructions", pc, idx + 1);
+            trace.breakpoint_hit = true;
+            trace.complete = false;
+            trace.final_registers = registers_after;
+            break;
         }
     }
 }
 
+/// Fail with [`TraceError::EmptyProgram`] if `bytecode` has no instructions
+///
+/// Raw bytecode is all text. For an ELF the `.text` section is checked
+/// before loading, since the loader reports an empty one as an unrelated
+/// entrypoint error. ELFs the parser cannot read, or without a `.text`
+/// section, are left to the loader.
+fn ensure_has_instructions(bytecode: &[u8]) -> Result<()> {
+    let text_len = if bytecode.starts_with(b"\x7fELF") {
+        // The parser needs the headers aligned
+        let aligned = AlignedMemory::<{ ebpf::HOST_ALIGN }>::from_slice(bytecode);
+        let Ok(elf) = Elf64::parse(aligned.as_slice()) else { return Ok(()) };
+        let text = elf
+            .section_header_table()
+            .iter()
+            .find(|header| elf.section_name(header.sh_name).is_ok_and(|name| name == b".text"));
+        match text {
+            Some(header) => header.sh_size as usize,
+            None => return Ok(()),
+        }
+    } else {
+        bytecode.len()
+    };
+
+    if text_len < ebpf::INSN_SIZE {
+        return Err(TraceError::EmptyProgram.into());
+    }
+    Ok(())
+}
+
+/// Build the function registry for raw bytecode
+///
+/// Raw bytecode has no symbol table, so every in-program `call imm` target
+/// is registered the way solana-sbpf's assembler does, keyed as
+/// `SBPFVersion::calculate_call_imm_target_pc` computes. ELF files carry
+/// their own registry.
+fn call_target_registry<C: ContextObject>(
+    bytecode: &[u8],
+    loader: &BuiltinProgram<C>,
+    version: SBPFVersion,
+) -> Result<FunctionRegistry<usize>> {
+    let mut registry = FunctionRegistry::default();
+    let insn_count = bytecode.len() / ebpf::INSN_SIZE;
+    for pc in 0..insn_count {
+        let insn = ebpf::get_insn(bytecode, pc);
+        if insn.opc != ebpf::CALL_IMM || is_syscall(loader, version, insn.imm) {
+            continue;
+        }
+        let target = version.calculate_call_imm_target_pc(pc, insn.imm) as usize;
+        if target < insn_count {
+            registry
+                .register_function(target as u32, format!("function_{}", target), target)
+                .map_err(|e| {
+                    anyhow::anyhow!("Failed to register call target {}: {:?}", target, e)
+                })?;
+        }
+    }
+    Ok(registry)
+}
+
+/// Resolve the PC a `call imm` at `pc` jumps to, as the interpreter does
+///
+/// Returns `None` for syscalls and unregistered targets.
+fn resolve_call_target<C: ContextObject>(
+    executable: &Executable<C>,
+    pc: u64,
+    imm: i64,
+) -> Option<u64> {
+    let version = executable.get_sbpf_version();
+    if is_syscall(executable.get_loader(), version, imm) {
+        return None;
+    }
+    let key = version.calculate_call_imm_target_pc(pc as usize, imm);
+    executable
+        .get_function_registry()
+        .lookup_by_key(key)
+        .map(|(_name, target_pc)| target_pc as u64)
+}
+
+/// Whether `call imm` is a syscall (static-syscall versions use SYSCALL instead)
+fn is_syscall<C: ContextObject>(
+    loader: &BuiltinProgram<C>,
+    version: SBPFVersion,
+    imm: i64,
+) -> bool {
+    !version.static_syscalls()
+        && loader.get_function_registry().lookup_by_key(imm as u32).is_some()
+}
+
 use solana_sbpf::ebpf;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::memory::MemoryAccessKind;
+    use crate::InsnBuilder;
 
     #[test]
     fn test_trace_simple_program() {
@@ -439,6 +845,7 @@ mod tests {
 
         // Verify final register state
         assert_eq!(trace.final_registers.regs[0], 42, "Register r0 should be 42");
+        assert_eq!(trace.exit_kind, ExitKind::Exit, "Program should exit cleanly");
 
         // Verify instruction traces were captured
         assert!(
@@ -493,11 +900,616 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trace_u64_max_register() {
+        // r0 = -1 sign-extends to u64::MAX; r1 = u64::MAX + 1 wraps to 0
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, -1),
+            InsnBuilder::mov64_imm(1, -1),
+            InsnBuilder::add64_imm(1, 1),
+            InsnBuilder::exit(),
+        ]);
+        let trace = trace_program(&bytecode).unwrap();
+
+        assert_eq!(trace.instructions[0].registers_after.regs[0], u64::MAX);
+        assert_eq!(trace.instructions[2].registers_before.regs[1], u64::MAX);
+        assert_eq!(trace.instructions[2].registers_after.regs[1], 0);
+        assert_eq!(trace.final_registers.regs[0], u64::MAX);
+
+        // Both formats keep the top of the range intact
+        for format in [TraceFormat::Json, TraceFormat::JsonHex] {
+            let decoded = ExecutionTrace::from_json(&trace.to_json(format).unwrap()).unwrap();
+            assert_eq!(decoded.final_registers.regs, trace.final_registers.regs, "{:?}", format);
+            assert_eq!(
+                decoded.instructions[2].registers_before.regs,
+                trace.instructions[2].registers_before.regs,
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_program_text_reference_matches_owned_bytes() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let owned = trace_program(bytecode).unwrap();
+        let options = TraceOptions::new().with_program_text_reference(true);
+        let referenced = trace_program_with_options(bytecode, &options).unwrap();
+
+        assert_eq!(owned.program_text, bytecode);
+        assert_eq!(referenced.program_text, bytecode);
+        assert_eq!(referenced.instruction_count(), owned.instruction_count());
+        for (instr, owned_instr) in referenced.instructions.iter().zip(&owned.instructions) {
+            assert!(instr.instruction_bytes.is_empty());
+            assert_eq!(instr.text_offset, Some(instr.pc as usize * ebpf::INSN_SIZE));
+            assert_eq!(referenced.instruction_bytes(instr), owned.instruction_bytes(owned_instr));
+            assert_eq!(owned.instruction_bytes(owned_instr), &owned_instr.instruction_bytes[..]);
+        }
+    }
+
+    #[test]
+    fn test_trace_assertion_helpers() {
+        // BPF program: r0 = 10; r1 = 20; r0 = r0 + r1; exit
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let trace = trace_program(bytecode).unwrap();
+
+        trace.assert_register(0, 30).unwrap();
+        trace.assert_final(|regs| regs.regs[0] == 10 + regs.regs[1]).unwrap();
+
+        let err = trace.assert_register(0, 31).unwrap_err().to_string();
+        assert!(err.contains("r0 is 30"), "unexpected error: {}", err);
+        assert!(trace.assert_final(|regs| regs.regs[0] == 0).is_err());
+        assert!(trace.assert_register(11, 0).is_err());
+    }
+
+    #[test]
+    fn test_memory_snapshot_contains_stack_writes() {
+        // BPF program: r1 = 0x12345678; *(u64 *)(r10 - 8) = r1; exit
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x01, 0x00, 0x00, 0x78, 0x56, 0x34, 0x12,                      // mov64 r1, 0x12345678
+            crate::decode::opcodes::stxdw(), 0x1a, 0xf8, 0xff, 0x00, 0x00, 0x00, 0x00, // stxdw [r10-8], r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,                      // exit
+        ];
+
+        let trace = trace_program(bytecode).unwrap();
+        assert!(trace.memory_snapshot.is_empty(), "Snapshots are opt-in");
+
+        let options = TraceOptions::default().with_memory_snapshot(true);
+        let trace = trace_program_with_options(bytecode, &options).unwrap();
+
+        let stack = trace
+            .memory_snapshot
+            .iter()
+            .find(|snapshot| snapshot.region == MemoryRegionKind::Stack)
+            .expect("Stack should be captured");
+        assert_eq!(stack.base_addr, ebpf::MM_STACK_START);
+
+        let pattern = 0x12345678u64.to_le_bytes();
+        assert!(
+            stack.data.windows(pattern.len()).any(|window| window == pattern),
+            "Stack snapshot should contain the stored value"
+        );
+    }
+
+    #[test]
+    fn test_trace_records_logs() {
+        let sol_log = ebpf::hash_symbol_name(b"sol_log_") as i32;
+        let bytecode = crate::InsnBuilder::program([
+            crate::InsnBuilder::mov64_imm(1, i32::from_le_bytes(*b"zkvm")),
+            crate::InsnBuilder::stxdw(10, -8, 1),
+            crate::InsnBuilder::mov64_reg(1, 10),
+            crate::InsnBuilder::add64_imm(1, -8),
+            crate::InsnBuilder::mov64_imm(2, 4),
+            crate::InsnBuilder::insn(ebpf::CALL_IMM, 0, 0, 0, sol_log),
+            crate::InsnBuilder::exit(),
+        ]);
+
+        let trace = trace_program(&bytecode).unwrap();
+        assert_eq!(trace.logs, ["zkvm"]);
+        assert_eq!(crate::log_commitment(&trace.logs), crate::log_commitment(&["zkvm"]));
+        assert_ne!(crate::log_commitment(&trace.logs), crate::log_commitment(&["zk", "vm"]));
+    }
+
+    #[test]
+    fn test_trace_reads_stubbed_clock() {
+        let get_clock = ebpf::hash_symbol_name(b"sol_get_clock_sysvar") as i32;
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_reg(1, 10),
+            InsnBuilder::add64_imm(1, -40),
+            InsnBuilder::insn(ebpf::CALL_IMM, 0, 0, 0, get_clock),
+            InsnBuilder::ldxdw(0, 10, -40),
+            InsnBuilder::exit(),
+        ]);
+        let sysvars = SysvarValues::default().with_clock(crate::Clock::at_slot(123_456));
+        let options = TraceOptions::default().with_sysvars(sysvars);
+
+        let trace = trace_program_with_options(&bytecode, &options).unwrap();
+        assert_eq!(trace.final_registers.regs[0], 123_456);
+        assert_eq!(trace.sysvars, sysvars);
+        assert_ne!(trace.sysvars.commitment(), SysvarValues::default().commitment());
+    }
+
+    #[test]
+    fn test_memory_ops_capped() {
+        // Store r1 to the stack for r1 = 10..1
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 10),
+            InsnBuilder::stxdw(10, -8, 1),
+            InsnBuilder::add64_imm(1, -1),
+            InsnBuilder::jne_imm(1, 0, -3),
+            InsnBuilder::exit(),
+        ]);
+
+        let trace = trace_program(&bytecode).unwrap();
+        assert_eq!(trace.memory_ops.len(), 10);
+        assert!(!trace.memory_ops_truncated);
+        let first = &trace.memory_ops[0];
+        assert_eq!((first.instruction, first.pc), (1, 1));
+        assert_eq!((first.kind, first.size, first.value), (MemoryAccessKind::Store, 8, 10));
+        assert_eq!(first.address, trace.initial_registers.regs[10] - 8);
+
+        let options = TraceOptions::default().with_max_memory_ops(4);
+        let capped = trace_program_with_options(&bytecode, &options).unwrap();
+        assert!(capped.memory_ops_truncated);
+        assert_eq!(capped.memory_ops, trace.memory_ops[..4]);
+        assert_eq!(capped.instructions.len(), trace.instructions.len());
+    }
+
+    #[test]
+    fn test_reference_execute_matches_trace() {
+        // BPF program: r0 = 10; r1 = 20; r0 = r0 + r1; exit
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let trace = trace_program(bytecode).unwrap();
+        let options = TraceOptions::default();
+        let final_registers = reference_execute(bytecode, &RegisterState::new(), &options).unwrap();
+
+        assert_eq!(final_registers.regs[0], 30);
+        assert_eq!(final_registers.regs[0], trace.final_registers.regs[0]);
+    }
+
+    #[test]
+    fn test_corrupted_trace_fails_consistency() {
+        // BPF program: r0 = 10; r1 = 20; r0 = r0 + r1; exit
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let trace = trace_program(bytecode).unwrap();
+        trace.check_consistency().unwrap();
+
+        let corrupted = trace.corrupt(1, 1, 99);
+        assert_eq!(corrupted.instructions[1].registers_after.regs[1], 99);
+        assert_eq!(corrupted.instructions[2].registers_before.regs[1], 20);
+
+        let err = corrupted.check_consistency().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::TraceError>(),
+            Some(&crate::TraceError::BrokenChain {
+                instruction: 1,
+                register: 1,
+                after: 99,
+                next: 20,
+            })
+        );
+    }
+
+    #[test]
+    fn test_trace_break_at_pc() {
+        // BPF program: r0 = 10; r1 = 20; r0 = r0 + r1; exit
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        // Break on the add instruction (PC 2)
+        let options = TraceOptions::default().with_break_at_pc(2);
+        let trace = trace_program_with_options(bytecode, &options).unwrap();
+
+        assert!(trace.breakpoint_hit, "Breakpoint should be hit");
+        assert_eq!(trace.instruction_count(), 3, "Trace should stop at the add instruction");
+
+        let last = trace.instructions.last().unwrap();
+        assert_eq!(last.pc, 2);
+        assert_eq!(last.instruction_bytes[0], 0x0f, "Last instruction should be ADD64");
+        assert_eq!(trace.final_registers.regs[0], 30, "Final state should be snapshotted after the add");
+    }
+
+    #[test]
+    fn test_trace_records_call_target() {
+        // Call a function at PC 3 that adds 41 to r0
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,  // mov64 r0, 1
+            0x85, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,  // call 3
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+            0x07, 0x00, 0x00, 0x00, 0x29, 0x00, 0x00, 0x00,  // add64 r0, 41
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit (return)
+        ];
+
+        let trace = trace_program(bytecode).unwrap();
+
+        assert_eq!(trace.final_registers.regs[0], 42);
+        let pcs: Vec<u64> = trace.instructions.iter().map(|instr| instr.pc).collect();
+        assert_eq!(pcs, vec![0, 1, 3, 4, 2]);
+
+        let call = &trace.instructions[1];
+        assert_eq!(call.call_target, Some(3));
+        assert_eq!(call.registers_after.regs[11], 3, "The call should land on its target");
+        assert!(trace
+            .instructions
+            .iter()
+            .filter(|instr| instr.pc != 1)
+            .all(|instr| instr.call_target.is_none()));
+    }
+
+    #[test]
+    fn test_trace_break_at_unreached_pc() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r0, 42
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let options = TraceOptions::default().with_break_at_pc(100);
+        let trace = trace_program_with_options(bytecode, &options).unwrap();
+
+        assert!(!trace.breakpoint_hit);
+        assert_eq!(trace.final_registers.regs[0], 42);
+    }
+
+    #[test]
+    fn test_trace_meta_reflects_options() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r0, 42
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let options = TraceOptions::default().with_instruction_limit(500);
+        let trace = trace_program_with_options(bytecode, &options).unwrap();
+
+        let config = Config::default();
+        assert_eq!(trace.meta.instruction_limit, 500);
+        assert_eq!(trace.meta.sbpf_version, "V2");
+        assert_eq!(trace.meta.stack_size, config.stack_size());
+        assert_eq!(trace.meta.max_call_depth, config.max_call_depth);
+        assert_eq!(trace.meta.program_hash, program_hash(bytecode));
+    }
+
+    #[test]
+    fn test_trace_program_without_exit() {
+        // r0 = 1; r0 += 2; (falls off the end)
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,  // mov64 r0, 1
+            0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,  // add64 r0, 2
+        ];
+
+        let trace = trace_program(bytecode).unwrap();
+
+        assert_eq!(trace.exit_kind, ExitKind::RanOffEnd);
+        assert_eq!(trace.instruction_count(), 2);
+        let last = trace.instructions.last().unwrap();
+        assert_eq!(last.instruction_bytes[0], 0x07, "Last instruction should be ADD64");
+        assert_eq!(last.registers_after.regs[0], 3);
+    }
+
+    #[test]
+    fn test_trace_budget_exhausted() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0x05, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,  // ja -1 (loop forever)
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let options = TraceOptions::default().with_instruction_limit(10);
+        let trace = trace_program_with_options(bytecode, &options).unwrap();
+
+        assert_eq!(trace.exit_kind, ExitKind::BudgetExhausted);
+        assert_eq!(trace.instruction_count(), 10);
+    }
+
+    #[test]
+    fn test_trace_with_accounts_unterminated() {
+        #[rustfmt::skip]
+        let looping: &[u8] = &[
+            0x05, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,  // ja -1 (loop forever)
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        let program_id = crate::deterministic_pubkey("loop");
+        let mut context = TransactionContext::new(program_id, vec![], vec![]);
+        let options = TraceOptions::default().with_instruction_limit(10);
+        let trace =
+            trace_program_with_accounts_and_options(looping, &mut context, &options).unwrap();
+        assert_eq!(trace.exit_kind, ExitKind::BudgetExhausted);
+        assert_eq!(trace.instruction_count(), 10);
+        assert!(trace.account_states.is_empty());
+
+        #[rustfmt::skip]
+        let no_exit: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,  // mov64 r0, 1
+        ];
+        let program_id = crate::deterministic_pubkey("end");
+        let mut context = TransactionContext::new(program_id, vec![], vec![]);
+        let trace = trace_program_with_accounts(no_exit, &mut context).unwrap();
+        assert_eq!(trace.exit_kind, ExitKind::RanOffEnd);
+        assert_eq!(trace.instruction_count(), 1);
+    }
+
+    #[test]
+    fn test_trace_rodata_store_is_read_only_write() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,  // mov64 r1, 1
+            0x67, 0x01, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00,  // lsh64 r1, 32 (MM_RODATA_START)
+            0xb7, 0x02, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r2, 42
+            crate::decode::opcodes::stxdw(), 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // stxdw [r1], r2
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let err = trace_program(bytecode).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::TraceError>(),
+            Some(&crate::TraceError::ReadOnlyWrite { address: ebpf::MM_RODATA_START, pc: 3 })
+        );
+    }
+
+    #[test]
+    fn test_terminating_instruction() {
+        let bytecode = InsnBuilder::program([InsnBuilder::mov64_imm(0, 1), InsnBuilder::exit()]);
+        let trace = trace_program(&bytecode).unwrap();
+        let last = trace.terminating_instruction().unwrap();
+        assert_eq!((last.pc, last.instruction_bytes[0]), (1, ebpf::EXIT));
+
+        // The store into read-only data at pc 3 faults
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 1),
+            InsnBuilder::lsh64_imm(1, 32),
+            InsnBuilder::mov64_imm(2, 42),
+            InsnBuilder::stxdw(1, 0, 2),
+            InsnBuilder::exit(),
+        ]);
+        assert!(trace_program(&bytecode).is_err());
+
+        let options = TraceOptions::default().with_trace_on_fault(true);
+        let trace = trace_program_with_options(&bytecode, &options).unwrap();
+        assert_eq!(trace.exit_kind, ExitKind::Fault);
+        assert_eq!(trace.instruction_count(), 4);
+        let faulting = trace.terminating_instruction().unwrap();
+        assert_eq!(faulting.pc, 3);
+        assert_eq!(faulting.registers_after.regs[..11], faulting.registers_before.regs[..11]);
+    }
+
     #[test]
     fn test_trace_empty_program() {
         // Empty program should fail to load
         let bytecode = &[];
         let result = trace_program(bytecode);
         assert!(result.is_err(), "Empty program should fail");
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<TraceError>(),
+            Some(&TraceError::EmptyProgram)
+        );
+
+        let result = reference_execute(bytecode, &RegisterState::new(), &TraceOptions::new());
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<TraceError>(),
+            Some(&TraceError::EmptyProgram)
+        );
+    }
+
+    /// A minimal ELF whose `.text` section is empty
+    ///
+    /// Layout: file header, `.shstrtab` contents, then the section header
+    /// table (null, `.text`, `.shstrtab`).
+    fn elf_with_empty_text() -> Vec<u8> {
+        let names = b"\0.text\0.shstrtab\0";
+        let names_offset = 64u64;
+        let shoff = 88u64;
+
+        let mut elf = vec![0u8; 64];
+        elf[..8].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        elf[16..18].copy_from_slice(&3u16.to_le_bytes()); // ET_DYN
+        elf[18..20].copy_from_slice(&247u16.to_le_bytes()); // EM_BPF
+        elf[20..24].copy_from_slice(&1u32.to_le_bytes()); // EV_CURRENT
+        elf[40..48].copy_from_slice(&shoff.to_le_bytes());
+        elf[52..54].copy_from_slice(&64u16.to_le_bytes()); // e_ehsize
+        elf[54..56].copy_from_slice(&56u16.to_le_bytes()); // e_phentsize
+        elf[58..60].copy_from_slice(&64u16.to_le_bytes()); // e_shentsize
+        elf[60..62].copy_from_slice(&3u16.to_le_bytes()); // e_shnum
+        elf[62..64].copy_from_slice(&2u16.to_le_bytes()); // e_shstrndx
+
+        elf.extend_from_slice(names);
+        elf.resize(shoff as usize, 0);
+
+        let section = |name: u32, sh_type: u32, flags: u64, offset: u64, size: u64| {
+            let mut header = [0u8; 64];
+            header[0..4].copy_from_slice(&name.to_le_bytes());
+            header[4..8].copy_from_slice(&sh_type.to_le_bytes());
+            header[8..16].copy_from_slice(&flags.to_le_bytes());
+            header[24..32].copy_from_slice(&offset.to_le_bytes());
+            header[32..40].copy_from_slice(&size.to_le_bytes());
+            header
+        };
+        elf.extend_from_slice(&[0u8; 64]);
+        // SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, no bytes
+        elf.extend_from_slice(&section(1, 1, 6, names_offset, 0));
+        // SHT_STRTAB
+        elf.extend_from_slice(&section(7, 3, 0, names_offset, names.len() as u64));
+        elf
+    }
+
+    #[test]
+    fn test_trace_elf_without_instructions() {
+        let elf = elf_with_empty_text();
+        let mut context = TransactionContext::new(crate::deterministic_pubkey("empty"), vec![], vec![]);
+
+        let err = trace_program_with_accounts(&elf, &mut context).unwrap_err();
+        assert_eq!(err.downcast_ref::<TraceError>(), Some(&TraceError::EmptyProgram));
+    }
+
+    #[test]
+    fn test_trace_malformed_elf_is_load_error() {
+        // ELF magic followed by a header of zeros (no class, machine or sections)
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        let mut context = TransactionContext::new(crate::deterministic_pubkey("bad"), vec![], vec![]);
+
+        let err = trace_program_with_accounts(&elf, &mut context).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<TraceError>(), Some(TraceError::Load(_))),
+            "Unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_trace_out_of_bounds_jump_is_verify_error() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0x05, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00,  // ja +5 (past the end)
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let err = trace_program(bytecode).unwrap_err();
+        match err.downcast_ref::<TraceError>() {
+            Some(TraceError::Verify(message)) => {
+                assert!(message.contains("jump out of code"), "Unexpected message: {}", message)
+            }
+            other => panic!("Expected a verification error, got {:?}", other),
+        }
+    }
+
+    /// Register file after `limit` instructions of a bare solana-sbpf run,
+    /// and whether the program returned within them
+    ///
+    /// An oracle for [`capture_instructions`]: the VM is set up
+    /// independently of the tracer, without syscalls or register tracing,
+    /// and the instruction meter stops it before the instruction past
+    /// `limit`, leaving the registers (and PC) as that instruction saw them.
+    fn native_registers_after(bytecode: &[u8], limit: u64) -> ([u64; 12], bool) {
+        let mut config = Config::default();
+        config.enable_instruction_meter = true;
+
+        let loader = Arc::new(BuiltinProgram::new_loader(config.clone()));
+        let executable = Executable::<TracerContext>::from_text_bytes(
+            bytecode,
+            loader.clone(),
+            SBPF_VERSION,
+            FunctionRegistry::default(),
+        )
+        .unwrap();
+        executable.verify::<solana_sbpf::verifier::RequisiteVerifier>().unwrap();
+
+        let mut stack = AlignedMemory::<{ ebpf::HOST_ALIGN }>::zero_filled(config.stack_size());
+        let regions = vec![
+            executable.get_ro_region(),
+            MemoryRegion::new_writable(stack.as_slice_mut(), ebpf::MM_STACK_START),
+        ];
+        let memory_mapping =
+            MemoryMapping::new(regions, &config, executable.get_sbpf_version()).unwrap();
+
+        let mut context = TracerContext::new(limit);
+        let mut vm = EbpfVm::new(
+            loader,
+            executable.get_sbpf_version(),
+            &mut context,
+            memory_mapping,
+            config.stack_size(),
+        );
+        let (_, result) = vm.execute_program(&executable, true);
+
+        let mut registers = vm_compat::read_registers(&vm);
+        match result {
+            ProgramResult::Ok(return_value) => {
+                registers[0] = return_value;
+                (registers, true)
+            }
+            ProgramResult::Err(EbpfError::ExceededMaxInstructions) => (registers, false),
+            ProgramResult::Err(err) => panic!("Native run failed: {:?}", err),
+        }
+    }
+
+    /// Register file before each step and after the last
+    ///
+    /// Entry `k` comes from a native run stopped after `k` instructions, so
+    /// the reference never reads the VM's register trace or pairs entries.
+    fn native_register_states(bytecode: &[u8]) -> Vec<[u64; 12]> {
+        let mut states = Vec::new();
+        for limit in 0..1000 {
+            let (registers, returned) = native_registers_after(bytecode, limit);
+            states.push(registers);
+            if returned {
+                return states;
+            }
+        }
+        panic!("Native run did not return within 1000 instructions");
+    }
+
+    /// Assert our trace matches the native register states step for step
+    fn assert_matches_native_trace(bytecode: &[u8]) {
+        let trace = trace_program(bytecode).unwrap();
+        let native = native_register_states(bytecode);
+
+        assert_eq!(trace.instructions.len() + 1, native.len(), "step count differs");
+        for (step, instr) in trace.instructions.iter().enumerate() {
+            assert_eq!(instr.registers_before.regs, native[step], "before step {}", step);
+            assert_eq!(instr.registers_after.regs, native[step + 1], "after step {}", step);
+            assert_eq!(instr.pc, native[step][11], "pc of step {}", step);
+        }
+        assert_eq!(Some(&trace.final_registers.regs), native.last());
+    }
+
+    #[test]
+    fn test_arithmetic_trace_matches_native() {
+        assert_matches_native_trace(&InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 10),
+            InsnBuilder::mov64_imm(1, 20),
+            InsnBuilder::add64_reg(0, 1),
+            InsnBuilder::mul64_imm(0, 3),
+            InsnBuilder::exit(),
+        ]));
+    }
+
+    #[test]
+    fn test_loop_trace_matches_native() {
+        // r0 = 5 + 4 + 3 + 2 + 1, revisiting the loop body's PCs
+        assert_matches_native_trace(&InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 0),
+            InsnBuilder::mov64_imm(1, 5),
+            InsnBuilder::add64_reg(0, 1),
+            InsnBuilder::add64_imm(1, -1),
+            InsnBuilder::jne_imm(1, 0, -3),
+            InsnBuilder::exit(),
+        ]));
     }
 }
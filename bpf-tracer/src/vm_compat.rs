@@ -0,0 +1,55 @@
+//! solana-sbpf compatibility shim
+//!
+//! Every access to `EbpfVm` internals goes through this module, so a
+//! solana-sbpf bump that renames or reshapes those fields only has to be
+//! handled here. `register_trace` comes from the pinned fork in `deps/sbpf`
+//! and is the field most likely to move.
+
+use solana_sbpf::vm::{ContextObject, EbpfVm};
+
+/// Number of values in a VM register file: r0-r10 plus the PC
+pub(crate) const REGISTER_FILE_LEN: usize = 12;
+
+/// Read the VM's registers (r0-r10, then the PC)
+pub(crate) fn read_registers<C: ContextObject>(vm: &EbpfVm<C>) -> [u64; REGISTER_FILE_LEN] {
+    vm.registers
+}
+
+/// Set a VM register before execution
+pub(crate) fn write_register<C: ContextObject>(vm: &mut EbpfVm<C>, reg: usize, value: u64) {
+    vm.registers[reg] = value;
+}
+
+/// Read the per-instruction register trace
+///
+/// Each entry is the register file before the instruction at its PC ran.
+/// Empty unless the VM was created with register tracing enabled.
+pub(crate) fn read_register_trace<'v, C: ContextObject>(
+    vm: &'v EbpfVm<C>,
+) -> &'v [[u64; REGISTER_FILE_LEN]] {
+    &vm.register_trace
+}
+
+/// Borrow the context object the VM runs with
+pub(crate) fn context_mut<'v, C: ContextObject>(vm: &'v mut EbpfVm<'_, C>) -> &'v mut C {
+    &mut *vm.context_object_pointer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::TracerContext;
+
+    /// Pin the shim's shapes against the current solana-sbpf; a bump that
+    /// changes them fails to compile here rather than deep inside vm.rs
+    #[test]
+    fn test_shim_shapes() {
+        let _: fn(&EbpfVm<TracerContext>) -> [u64; 12] = read_registers::<TracerContext>;
+        let _: fn(&mut EbpfVm<TracerContext>, usize, u64) = write_register::<TracerContext>;
+        let _: for<'v, 'a> fn(&'v EbpfVm<'a, TracerContext>) -> &'v [[u64; 12]] =
+            read_register_trace::<TracerContext>;
+        let _: for<'v, 'a> fn(&'v mut EbpfVm<'a, TracerContext>) -> &'v mut TracerContext =
+            context_mut::<TracerContext>;
+        assert_eq!(REGISTER_FILE_LEN, 12);
+    }
+}
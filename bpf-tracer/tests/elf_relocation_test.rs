@@ -0,0 +1,32 @@
+//! Test that ELF relocation failures are reported as typed errors
+
+use bpf_tracer::{
+    error::{RelocationFailure, TraceError},
+    trace_program_with_accounts, TransactionContext,
+};
+use solana_pubkey::Pubkey;
+use std::path::PathBuf;
+
+#[test]
+fn test_unsupported_relocation_type() {
+    // solana-sbpf's reloc_64_64 fixture with its R_X86_64_64 relocation
+    // rewritten to R_X86_64_32S (type 11), which sBPF does not support
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/reloc_unsupported_type_sbpfv0.so");
+    let elf = std::fs::read(path).expect("Fixture should exist");
+
+    let mut context = TransactionContext::new(Pubkey::new_unique(), vec![], vec![]);
+    let err = trace_program_with_accounts(&elf, &mut context)
+        .expect_err("Loading should fail on the unsupported relocation");
+
+    let trace_err = err
+        .downcast_ref::<TraceError>()
+        .expect("Relocation failures should be a TraceError");
+    assert_eq!(
+        *trace_err,
+        TraceError::Relocation {
+            kind: RelocationFailure::UnsupportedType(11),
+            symbol: None,
+        }
+    );
+}
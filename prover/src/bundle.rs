@@ -0,0 +1,111 @@
+//! Proof Bundle
+//!
+//! Packages a proof with the public inputs it was generated for.
+
+use serde::{Deserialize, Serialize};
+use crate::{PublicInputs, Proof, Result};
+
+/// A proof together with its public inputs
+///
+/// This is what a prover hands to a verifier: everything needed to check
+/// the proof except the verifying key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofBundle {
+    /// Serialized Halo2 proof
+    pub proof: Proof,
+    /// Public inputs the proof was generated for
+    pub public_inputs: PublicInputs,
+    /// Number of instructions in the proven trace
+    pub instruction_count: usize,
+}
+
+impl ProofBundle {
+    /// Create a new proof bundle
+    pub fn new(proof: Proof, public_inputs: PublicInputs, instruction_count: usize) -> Self {
+        Self {
+            proof,
+            public_inputs,
+            instruction_count,
+        }
+    }
+
+    /// Serialize the bundle to bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Deserialize a bundle from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    /// Break the serialized size down by what the bytes carry
+    pub fn size_breakdown(&self) -> Result<SizeBreakdown> {
+        let total = self.to_bytes()?.len();
+        let proof_bytes = serde_json::to_vec(&self.proof)?.len();
+        let public_input_bytes = serde_json::to_vec(&self.public_inputs)?.len();
+        Ok(SizeBreakdown {
+            proof_bytes,
+            public_input_bytes,
+            metadata_bytes: total - proof_bytes - public_input_bytes,
+            public_field_elements: self.public_inputs.num_field_elements(),
+        })
+    }
+}
+
+/// Where the bytes of a serialized [`ProofBundle`] go
+///
+/// The byte counts are of the encoding [`ProofBundle::to_bytes`] produces
+/// and add up to its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBreakdown {
+    /// Bytes encoding the Halo2 proof
+    pub proof_bytes: usize,
+    /// Bytes encoding the public inputs
+    pub public_input_bytes: usize,
+    /// Everything else: field names, framing and the instruction count
+    pub metadata_bytes: usize,
+    /// Public field elements the proof is verified against
+    pub public_field_elements: usize,
+}
+
+impl SizeBreakdown {
+    /// Total serialized size of the bundle
+    pub fn total(&self) -> usize {
+        self.proof_bytes + self.public_input_bytes + self.metadata_bytes
+    }
+}
+
+#[cfg(all(test, feature = "trace"))]
+mod tests {
+    use super::*;
+    use bpf_tracer::ExecutionTrace;
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let public_inputs = PublicInputs::from_trace(&ExecutionTrace::new()).unwrap();
+        let bundle = ProofBundle::new(vec![1, 2, 3], public_inputs, 0);
+
+        let bytes = bundle.to_bytes().unwrap();
+        let decoded = ProofBundle::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.proof, bundle.proof);
+        assert_eq!(decoded.instruction_count, 0);
+        assert_eq!(
+            decoded.public_inputs.final_value_hash,
+            bundle.public_inputs.final_value_hash
+        );
+    }
+
+    #[test]
+    fn test_size_breakdown_sums_to_serialized_length() {
+        let public_inputs = PublicInputs::from_trace(&ExecutionTrace::new()).unwrap();
+        let bundle = ProofBundle::new(vec![7; 256], public_inputs, 0);
+
+        let breakdown = bundle.size_breakdown().unwrap();
+        assert_eq!(breakdown.total(), bundle.to_bytes().unwrap().len());
+        assert!(breakdown.proof_bytes > 256);
+        assert!(breakdown.metadata_bytes > 0);
+        assert_eq!(breakdown.public_field_elements, PublicInputs::NUM_FIELD_ELEMENTS);
+    }
+}
@@ -0,0 +1,129 @@
+//! Proof Cache
+//!
+//! Content-addressed on-disk cache of proof bundles, so identical work is
+//! proven once.
+
+use anyhow::Context;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::{ProofBundle, PublicInputs, Result};
+
+/// Identifies a proof by what it proves
+///
+/// The key covers the program and everything the proof is bound to, so a
+/// cached proof can never be stale: different work has a different key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProofCacheKey {
+    /// Hash of the proven program (see `bpf_tracer::program_hash`)
+    pub program_commitment: [u8; 32],
+    /// Hash of the public inputs and the circuit size they were proven at
+    pub public_inputs_digest: [u8; 32],
+}
+
+impl ProofCacheKey {
+    /// Key the proof of `program_commitment` with `public_inputs` at size `k`
+    pub fn new(
+        program_commitment: [u8; 32],
+        public_inputs: &PublicInputs,
+        k: u32,
+    ) -> Result<Self> {
+        #[derive(Serialize)]
+        struct Digested<'a> {
+            public_inputs: &'a PublicInputs,
+            k: u32,
+        }
+
+        let bytes = serde_json::to_vec(&Digested { public_inputs, k })?;
+        Ok(Self {
+            program_commitment,
+            public_inputs_digest: Sha256::digest(&bytes).into(),
+        })
+    }
+
+    fn file_name(&self) -> String {
+        format!(
+            "{}_{}.json",
+            hex::encode(self.program_commitment),
+            hex::encode(self.public_inputs_digest)
+        )
+    }
+}
+
+/// On-disk cache of [`ProofBundle`]s keyed by [`ProofCacheKey`]
+///
+/// Entries are never invalidated, only looked up by key. Unreadable
+/// entries are treated as misses and overwritten on the next insert.
+#[derive(Debug)]
+pub struct ProofCache {
+    dir: PathBuf,
+    hits: AtomicUsize,
+}
+
+impl ProofCache {
+    /// Create a cache storing bundles under `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), hits: AtomicUsize::new(0) }
+    }
+
+    /// Look up the bundle for `key`
+    pub fn get(&self, key: &ProofCacheKey) -> Option<ProofBundle> {
+        let path = self.dir.join(key.file_name());
+        let bytes = fs::read(&path).ok()?;
+        match ProofBundle::from_bytes(&bytes) {
+            Ok(bundle) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                tracing::info!("Loaded cached proof from {:?}", path);
+                Some(bundle)
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable cached proof {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Store the bundle for `key`
+    pub fn insert(&self, key: &ProofCacheKey, bundle: &ProofBundle) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create proof cache directory {:?}", self.dir))?;
+        let path = self.dir.join(key.file_name());
+        fs::write(&path, bundle.to_bytes()?)
+            .with_context(|| format!("Failed to write cached proof to {:?}", path))
+    }
+
+    /// Number of lookups answered from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bpf_tracer::ExecutionTrace;
+    use std::env;
+
+    #[test]
+    fn test_proof_cache_roundtrip() {
+        let dir = env::temp_dir().join(format!("sbpf_zkvm_proof_cache_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos()));
+        let cache = ProofCache::new(&dir);
+
+        let public_inputs = PublicInputs::from_trace(&ExecutionTrace::new()).unwrap();
+        let key = ProofCacheKey::new([1; 32], &public_inputs, 10).unwrap();
+        assert!(cache.get(&key).is_none());
+
+        cache.insert(&key, &ProofBundle::new(vec![1, 2, 3], public_inputs.clone(), 0)).unwrap();
+        assert_eq!(cache.get(&key).unwrap().proof, vec![1, 2, 3]);
+        assert_eq!(cache.hits(), 1);
+
+        // Another program or circuit size is different work
+        let other_program = ProofCacheKey::new([2; 32], &public_inputs, 10).unwrap();
+        let other_k = ProofCacheKey::new([1; 32], &public_inputs, 11).unwrap();
+        assert!(cache.get(&other_program).is_none());
+        assert!(cache.get(&other_k).is_none());
+        assert_eq!(cache.hits(), 1);
+    }
+}
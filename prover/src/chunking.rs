@@ -59,6 +59,8 @@ pub fn split_trace_into_chunks(trace: ExecutionTrace, chunk_size: usize) -> Resu
             account_states: vec![], // TODO: Handle account states in chunks
             initial_registers,
             final_registers,
+            program_text: trace.program_text.clone(),
+            ..Default::default()
         };
 
         chunks.push(chunk);
@@ -85,6 +87,8 @@ pub struct ChunkProof {
     pub initial_registers: bpf_tracer::RegisterState,
     /// Final register state for this chunk
     pub final_registers: bpf_tracer::RegisterState,
+    /// Identifier of the circuit this chunk was proven with
+    pub circuit_id: String,
 }
 
 #[cfg(test)]
@@ -101,6 +105,8 @@ mod tests {
             instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00], // ADD_IMM r1, 1
             registers_before: regs_before,
             registers_after: regs_after,
+            call_target: None,
+            text_offset: None,
         }
     }
 
@@ -127,6 +133,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         let chunks = split_trace_into_chunks(trace, 1000).unwrap();
@@ -151,6 +158,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs.clone(),
             final_registers: final_regs.clone(),
+            ..Default::default()
         };
 
         // Split into chunks of 100
@@ -188,6 +196,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         let chunks = split_trace_into_chunks(trace, 100).unwrap();
@@ -3,7 +3,7 @@
 //! Handles generation, caching, and loading of Halo2 proving and verifying keys.
 
 use anyhow::{Context, Result};
-use bpf_tracer::ExecutionTrace;
+use bpf_tracer::{ExecutionTrace, ExitKind};
 use halo2_base::{
     gates::{
         circuit::{
@@ -20,15 +20,26 @@ use halo2_base::{
         SerdeFormat,
     },
     halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine},
+    utils::ScalarField,
 };
 use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use zk_circuits::CounterCircuit;
+use std::sync::Arc;
+use zeroize::Zeroize;
+use zk_circuits::{CircuitMode, CounterCircuit};
+use crate::{ProgressCallback, ProofCache, ProvingStage, PublicInputs};
 
 /// Configuration for key generation
-#[derive(Debug, Clone)]
+///
+/// Serializable so settings can live in a config file (see
+/// [`Self::from_file`]); fields left out take their [`Default`] values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct KeygenConfig {
     /// Circuit size parameter (circuit has 2^k rows)
     pub k: u32,
@@ -38,9 +49,94 @@ pub struct KeygenConfig {
     pub lookup_bits: usize,
     /// Maximum instructions per chunk (for recursive proving)
     pub chunk_size: usize,
+    /// Identifier of the circuit the cached keys belong to (see [`circuit_id`])
+    ///
+    /// `None` selects keys for the empty (all-padding) trace.
+    pub circuit_id: Option<String>,
+    /// Cache `prove_program` checks before proving (see [`ProofCache`])
+    ///
+    /// Not read from config files; attach one with [`Self::with_proof_cache`].
+    #[serde(skip)]
+    pub proof_cache: Option<Arc<ProofCache>>,
+    /// Prove instructions without a chip unconstrained (see
+    /// [`CounterCircuit::with_allow_unconstrained`])
+    ///
+    /// Unsound; only for bringing up new programs.
+    pub allow_unconstrained: bool,
+    /// File to read the KZG parameters (SRS) from
+    ///
+    /// `None` runs a fresh local setup. An SRS for a larger `k` is downsized.
+    pub srs_path: Option<PathBuf>,
+    /// What [`KeyPair::load_or_generate`] does with the key cache
+    pub cache_policy: CachePolicy,
+}
+
+/// How [`KeyPair::load_or_generate`] treats cached keys
+///
+/// Cached keys are only used if they match the hashes in the cache manifest
+/// written alongside them, so a partially written cache counts as corrupt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CachePolicy {
+    /// Use cached keys, regenerating them if missing or corrupt
+    #[default]
+    UseOrRegenerate,
+    /// Use cached keys, failing if they are missing or corrupt
+    ///
+    /// For CI, where regenerating would silently take minutes.
+    UseOrError,
+    /// Ignore cached keys and regenerate, overwriting the cache
+    AlwaysRegenerate,
 }
 
 impl KeygenConfig {
+    /// Start a validated configuration (see [`KeygenConfigBuilder`])
+    pub fn builder() -> KeygenConfigBuilder {
+        KeygenConfigBuilder::default()
+    }
+
+    /// Load a configuration file
+    ///
+    /// `.toml` files are read as TOML, anything else as JSON. Relative
+    /// `cache_dir` and `srs_path` are taken relative to the file's
+    /// directory. The result is validated like [`KeygenConfigBuilder::build`],
+    /// which also creates the cache directory.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read keygen config {:?}", path))?;
+        let mut config: Self = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&text)
+                .with_context(|| format!("Failed to parse keygen config {:?}", path))?
+        } else {
+            serde_json::from_str(&text)
+                .with_context(|| format!("Failed to parse keygen config {:?}", path))?
+        };
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.cache_dir = base.join(&config.cache_dir);
+        config.srs_path = config.srs_path.map(|srs_path| base.join(srs_path));
+
+        config
+            .validate()
+            .with_context(|| format!("Invalid keygen config {:?}", path))?;
+        Ok(config)
+    }
+
+    /// Check the invariants [`KeygenConfigBuilder::build`] documents
+    fn validate(&self) -> Result<()> {
+        anyhow::ensure!(
+            (self.lookup_bits as u64) < self.k as u64,
+            "lookup_bits ({}) must be less than k ({})",
+            self.lookup_bits,
+            self.k
+        );
+        ensure_writable(&self.cache_dir)?;
+        if let Some(srs_path) = &self.srs_path {
+            anyhow::ensure!(srs_path.is_file(), "SRS file {:?} does not exist", srs_path);
+        }
+        Ok(())
+    }
+
     /// Create a new keygen configuration
     pub fn new(k: u32, cache_dir: impl Into<PathBuf>, lookup_bits: usize) -> Self {
         Self {
@@ -48,6 +144,11 @@ impl KeygenConfig {
             cache_dir: cache_dir.into(),
             lookup_bits,
             chunk_size: 1000, // Default: 1000 instructions per chunk
+            circuit_id: None,
+            proof_cache: None,
+            allow_unconstrained: false,
+            srs_path: None,
+            cache_policy: CachePolicy::default(),
         }
     }
 
@@ -57,6 +158,120 @@ impl KeygenConfig {
         self
     }
 
+    /// Select cached keys for a specific circuit
+    pub fn with_circuit_id(mut self, circuit_id: impl Into<String>) -> Self {
+        self.circuit_id = Some(circuit_id.into());
+        self
+    }
+
+    /// Select cached keys for the circuit `trace` synthesizes to
+    ///
+    /// How a verifier names the circuit it expects from a trace it trusts,
+    /// rather than taking the id a proof claims (see [`circuit_id`]).
+    pub fn with_circuit_of(self, trace: &ExecutionTrace) -> Self {
+        let id = circuit_id(trace, self.chunk_size);
+        self.with_circuit_id(id)
+    }
+
+    /// Reuse proofs of identical work from `cache`
+    pub fn with_proof_cache(mut self, cache: Arc<ProofCache>) -> Self {
+        self.proof_cache = Some(cache);
+        self
+    }
+
+    /// Let instructions without a chip through unconstrained
+    ///
+    /// The resulting proofs do not cover those instructions.
+    pub fn with_allow_unconstrained(mut self, allow_unconstrained: bool) -> Self {
+        if allow_unconstrained {
+            tracing::warn!("Unconstrained instructions allowed: proofs may be incomplete");
+        }
+        self.allow_unconstrained = allow_unconstrained;
+        self
+    }
+
+    /// Choose how cached keys are used (see [`CachePolicy`])
+    pub fn with_cache_policy(mut self, cache_policy: CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        self
+    }
+
+    /// Pick the smallest `k` at or above `self.k` whose proofs of `trace`'s
+    /// circuit fit in `max_proof_bytes`
+    ///
+    /// A larger `k` packs the circuit into fewer advice columns, and every
+    /// column adds a commitment and evaluations to the proof, so proofs
+    /// shrink as `k` grows until the circuit fits in one column. Each
+    /// candidate is sized with [`estimate_proof_size`], at the cost of a
+    /// parameter setup and verifying key per step. Fails if no `k` up to
+    /// 22 fits, or once growing `k` stops shrinking the proof.
+    ///
+    /// Prove and verify with the returned configuration: keys are cached
+    /// per `k`.
+    pub fn fit_proof_size(self, trace: &ExecutionTrace, max_proof_bytes: usize) -> Result<Self> {
+        let mut smallest: Option<(u32, usize)> = None;
+        for k in self.k..=MAX_FIT_K {
+            let candidate = Self { k, ..self.clone() };
+            let size = estimate_proof_size(&verifying_key_for(&candidate, trace)?);
+            tracing::info!("k={} gives {}-byte proofs", k, size);
+            if size <= max_proof_bytes {
+                return Ok(candidate);
+            }
+            if smallest.is_some_and(|(_, smallest_size)| size >= smallest_size) {
+                break;
+            }
+            smallest = Some((k, size));
+        }
+
+        let (k, size) = smallest.context("No circuit size to try: k is above 22")?;
+        anyhow::bail!(
+            "Proofs cannot be kept under {} bytes: the smallest is {} bytes, at k={}",
+            max_proof_bytes,
+            size,
+            k
+        )
+    }
+
+    /// Pick the smallest `k` that lays `trace`'s circuit out in a single
+    /// advice column
+    ///
+    /// Synthesizes the circuit once (see [`circuit_stats`]) and sizes it
+    /// with the same rule [`estimate_k`] applies to its offline bound.
+    pub fn for_trace(self, trace: &ExecutionTrace) -> Result<Self> {
+        let stats = circuit_stats(&self, trace)?;
+        let k = k_for_advice_cells(stats.advice_cells, self.lookup_bits);
+        Ok(Self { k, ..self })
+    }
+
+    /// File name for a cached circuit-specific artifact
+    fn key_file(&self, stem: &str, ext: &str) -> PathBuf {
+        let name = match &self.circuit_id {
+            Some(id) => format!("counter_{}_k{}_{}.{}", stem, self.k, id, ext),
+            None => format!("counter_{}_k{}.{}", stem, self.k, ext),
+        };
+        self.cache_dir.join(name)
+    }
+
+    /// KZG parameters for `k`: read from `srs_path` if set, otherwise a
+    /// fresh local setup
+    fn setup_params(&self) -> Result<ParamsKZG<Bn256>> {
+        let Some(srs_path) = &self.srs_path else {
+            return Ok(ParamsKZG::<Bn256>::setup(self.k, OsRng));
+        };
+        let mut params = load_params(srs_path).context("Failed to load SRS")?;
+        anyhow::ensure!(
+            params.k() >= self.k,
+            "SRS {:?} has k={}, below the circuit's k={}",
+            srs_path,
+            params.k(),
+            self.k
+        );
+        if params.k() > self.k {
+            params.downsize(self.k);
+        }
+        Ok(params)
+    }
+
     /// Get path to cached parameters file
     fn params_path(&self) -> PathBuf {
         self.cache_dir.join(format!("params_k{}.bin", self.k))
@@ -64,22 +279,38 @@ impl KeygenConfig {
 
     /// Get path to cached verifying key file
     fn vk_path(&self) -> PathBuf {
-        self.cache_dir.join(format!("counter_vk_k{}.bin", self.k))
+        self.key_file("vk", "bin")
     }
 
     /// Get path to cached proving key file
     fn pk_path(&self) -> PathBuf {
-        self.cache_dir.join(format!("counter_pk_k{}.bin", self.k))
+        self.key_file("pk", "bin")
     }
 
     /// Get path to cached break points file
     fn break_points_path(&self) -> PathBuf {
-        self.cache_dir.join(format!("counter_bp_k{}.json", self.k))
+        self.key_file("bp", "json")
     }
 
     /// Get path to cached circuit params file
     fn circuit_params_path(&self) -> PathBuf {
-        self.cache_dir.join(format!("counter_params_k{}.json", self.k))
+        self.key_file("params", "json")
+    }
+
+    /// Get path to the manifest of cached file hashes
+    fn manifest_path(&self) -> PathBuf {
+        self.key_file("manifest", "json")
+    }
+
+    /// Every cached file the manifest covers
+    fn cached_files(&self) -> [PathBuf; 5] {
+        [
+            self.params_path(),
+            self.vk_path(),
+            self.pk_path(),
+            self.break_points_path(),
+            self.circuit_params_path(),
+        ]
     }
 }
 
@@ -90,11 +321,190 @@ impl Default for KeygenConfig {
             cache_dir: PathBuf::from(".cache/keys"),
             lookup_bits: 8,
             chunk_size: 1000, // Default: 1000 instructions per chunk
+            circuit_id: None,
+            proof_cache: None,
+            allow_unconstrained: false,
+            srs_path: None,
+            cache_policy: CachePolicy::default(),
         }
     }
 }
 
+/// Builder for a [`KeygenConfig`] whose invariants are checked up front
+///
+/// Starts from [`KeygenConfig::default`]; options not covered here are set
+/// on the built configuration with its `with_*` methods.
+#[derive(Debug, Clone, Default)]
+pub struct KeygenConfigBuilder {
+    config: KeygenConfig,
+}
+
+impl KeygenConfigBuilder {
+    /// Circuit size parameter (circuit has 2^k rows)
+    pub fn k(mut self, k: u32) -> Self {
+        self.config.k = k;
+        self
+    }
+
+    /// Lookup bits for range checks
+    pub fn lookup_bits(mut self, lookup_bits: usize) -> Self {
+        self.config.lookup_bits = lookup_bits;
+        self
+    }
+
+    /// Directory to cache keys in
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.config.cache_dir = cache_dir.into();
+        self
+    }
+
+    /// Read the KZG parameters from an SRS file instead of a local setup
+    pub fn srs_path(mut self, srs_path: impl Into<PathBuf>) -> Self {
+        self.config.srs_path = Some(srs_path.into());
+        self
+    }
+
+    /// Check the configuration and build it
+    ///
+    /// Fails if `lookup_bits` is not below `k` (the range-check table needs
+    /// 2^lookup_bits rows plus blinding rows), if the cache directory cannot
+    /// be created or written to, or if the SRS file does not exist. Creates
+    /// the cache directory.
+    pub fn build(self) -> Result<KeygenConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Create `dir` if needed and check files can be written in it
+fn ensure_writable(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create cache directory {:?}", dir))?;
+    let probe = dir.join(".write_check");
+    File::create(&probe)
+        .with_context(|| format!("Cache directory {:?} is not writable", dir))?;
+    fs::remove_file(&probe)
+        .with_context(|| format!("Failed to clean up {:?}", probe))?;
+    Ok(())
+}
+
+/// Identify the circuit a trace synthesizes to
+///
+/// Chips are selected per instruction, so the constraint system (and hence
+/// the keys) depends on the instruction bytes of the padded trace, on the
+/// program the steps are looked up in (see [`CounterCircuit::program_words`])
+/// and on the order the memory table checks its loads and stores in (see
+/// [`CounterCircuit::memory_order`]). The keys also fix the public program
+/// commitment, so the program hash is part of the id, and the sBPF version
+/// decides which chip an opcode selects, so it is too, as is the
+/// [`circuit_mode`]. Traces of the same program with the same instructions
+/// and memory order share an id and can share keys.
+pub fn circuit_id(trace: &ExecutionTrace, chunk_size: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((chunk_size as u64).to_le_bytes());
+    hasher.update(trace.meta.program_hash);
+    hasher.update(format!("{:?}", trace.meta.version()));
+    hasher.update(format!("{:?}", circuit_mode(trace)));
+    for instr in trace.instructions.iter().take(chunk_size) {
+        hasher.update(trace.instruction_bytes(instr));
+    }
+    // Padding instructions are all-zero NOPs
+    for _ in trace.instructions.len()..chunk_size {
+        hasher.update([0u8; 8]);
+    }
+    // A trace whose program or memory operations cannot be derived fails
+    // synthesis anyway, so it needs no distinct id
+    let circuit = CounterCircuit::from_trace_chunked(trace.clone(), chunk_size);
+    for word in circuit.program_words().unwrap_or_default() {
+        hasher.update(word.to_le_bytes());
+    }
+    for index in circuit.memory_order().unwrap_or_default() {
+        hasher.update((index as u64).to_le_bytes());
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// What the circuit proving `trace` requires of its end
+///
+/// A [complete](ExecutionTrace::complete) trace that ends in `exit` is a
+/// whole execution and is proven as [`CircuitMode::FullProgram`]: it must
+/// end in `exit` and start from the VM's initial memory. Windows, chunks
+/// and runs that stopped early are proven as [`CircuitMode::Partial`].
+/// The mode changes the circuit, so keygen, proving and [`circuit_id`] all
+/// take it from here.
+pub fn circuit_mode(trace: &ExecutionTrace) -> CircuitMode {
+    if trace.complete && trace.exit_kind == ExitKind::Exit {
+        CircuitMode::FullProgram
+    } else {
+        CircuitMode::Partial
+    }
+}
+
+/// Largest `k` [`KeygenConfig::fit_proof_size`] tries
+const MAX_FIT_K: u32 = 22;
+
+/// Rows at the bottom of each column halo2 reserves for blinding
+const MINIMUM_ROWS: usize = 9;
+
+/// Upper bound on the advice cells one circuit step assigns
+///
+/// Covers the widest chips (64-bit decompositions on both operands) with
+/// headroom; padding steps assign far fewer.
+const MAX_ADVICE_CELLS_PER_STEP: usize = 512;
+
+/// Advice cells assigned once per circuit: registers and public inputs
+const FIXED_ADVICE_CELLS: usize = 1024;
+
+/// Estimate the `k` [`KeygenConfig::for_trace`] picks, without synthesizing
+///
+/// Bounds the advice cells of an `instruction_count`-step circuit and returns
+/// the smallest `k` that fits them in one column, and at least
+/// `lookup_bits + 1` so the range-check table fits. Since the bound is
+/// conservative the estimate may exceed the measured `k`, never undercut
+/// it. Keygen pads traces to `chunk_size`, so pass the chunk size when
+/// planning keys for a [`KeygenConfig`].
+pub fn estimate_k(instruction_count: usize, lookup_bits: usize) -> u32 {
+    let cells = instruction_count
+        .saturating_mul(MAX_ADVICE_CELLS_PER_STEP)
+        .saturating_add(FIXED_ADVICE_CELLS);
+    k_for_advice_cells(cells, lookup_bits)
+}
+
+/// Smallest `k` with room for `cells` advice cells in one column and for
+/// the `2^lookup_bits`-row lookup table
+fn k_for_advice_cells(cells: usize, lookup_bits: usize) -> u32 {
+    let mut k = lookup_bits as u32 + 1;
+    while (1usize << k) - MINIMUM_ROWS < cells && k < usize::BITS - 1 {
+        k += 1;
+    }
+    k
+}
+
+/// Magic bytes opening a key archive (see [`KeyPair::save_archive`])
+const ARCHIVE_MAGIC: &[u8; 8] = b"SBZKKEYS";
+
+/// Key archive format version
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Everything in a key archive besides the params and keys
+#[derive(Serialize, Deserialize)]
+struct ArchiveMetadata {
+    circuit_params: BaseCircuitParams,
+    break_points: Vec<Vec<usize>>,
+}
+
+/// Hashes of the cached key files, written after them
+#[derive(Serialize, Deserialize)]
+struct CacheManifest {
+    /// Hex SHA-256 of each cached file, by file name
+    files: BTreeMap<String, String>,
+}
+
 /// Key pair for proving and verification
+///
+/// KZG needs a pairing-friendly curve, so keys are over BN256, the curve
+/// Solana's `alt_bn128` syscalls verify on. Only circuit synthesis is
+/// generic over [`ScalarField`].
 #[derive(Debug)]
 pub struct KeyPair {
     /// KZG parameters
@@ -113,25 +523,68 @@ impl KeyPair {
     /// Load or generate keys based on configuration
     ///
     /// If cached keys exist and are valid, loads them from disk.
-    /// Otherwise, generates new keys and caches them.
+    /// Otherwise, generates new keys and caches them. `config.cache_policy`
+    /// can instead make missing or corrupt keys an error, or skip the cache.
+    ///
+    /// Only keys for the empty (all-padding) trace can be generated here:
+    /// if `config.circuit_id` names another circuit, missing keys are an
+    /// error (see [`Self::load_or_generate_for_trace`]).
     pub fn load_or_generate(config: &KeygenConfig) -> Result<Self> {
-        // Check if cached keys exist
-        if Self::cache_exists(config) {
-            tracing::info!("Found cached keys, attempting to load...");
-            match Self::load_from_cache(config) {
+        Self::load_or_generate_with(config, &ExecutionTrace::new())
+    }
+
+    /// Load or generate keys for the circuit a specific trace synthesizes to
+    ///
+    /// Keys are cached under the trace's [`circuit_id`].
+    pub fn load_or_generate_for_trace(config: &KeygenConfig, trace: &ExecutionTrace) -> Result<Self> {
+        let config = config
+            .clone()
+            .with_circuit_id(circuit_id(trace, config.chunk_size));
+        Self::load_or_generate_with(&config, trace)
+    }
+
+    fn load_or_generate_with(config: &KeygenConfig, trace: &ExecutionTrace) -> Result<Self> {
+        if config.cache_policy != CachePolicy::AlwaysRegenerate {
+            let cached = if Self::cache_exists(config) {
+                tracing::info!("Found cached keys, attempting to load...");
+                Self::load_from_cache(config)
+            } else {
+                Err(anyhow::anyhow!("No cached keys in {:?}", config.cache_dir))
+            };
+            match cached {
                 Ok(keypair) => {
-                    tracing::info!("Successfully loaded keys from cache");
+                    tracing::info!(
+                        "Successfully loaded keys from cache (vk fingerprint {})",
+                        keypair.vk_fingerprint()
+                    );
                     return Ok(keypair);
                 }
+                Err(e) if config.cache_policy == CachePolicy::UseOrError => {
+                    return Err(e.context("Cached keys are unusable and the policy is UseOrError"));
+                }
                 Err(e) => {
-                    tracing::warn!("Failed to load cached keys: {}. Regenerating...", e);
+                    tracing::warn!("Failed to load cached keys: {:#}. Regenerating...", e);
                 }
             }
         }
 
+        // Never cache keys for one circuit under another's id
+        if let Some(id) = &config.circuit_id {
+            let trace_id = circuit_id(trace, config.chunk_size);
+            anyhow::ensure!(
+                *id == trace_id,
+                "No usable cached keys for circuit {}, and they cannot be generated from a trace \
+                 of circuit {}",
+                id,
+                trace_id
+            );
+        }
+
         // Generate new keys
         tracing::info!("Generating new keys...");
-        let keypair = Self::generate(config)?;
+        let keypair = Self::generate_for_trace(config, trace)?;
+
+        tracing::info!("Generated keys (vk fingerprint {})", keypair.vk_fingerprint());
 
         // Cache the generated keys
         keypair.save_to_cache(config)
@@ -140,8 +593,24 @@ impl KeyPair {
         Ok(keypair)
     }
 
-    /// Generate new keys (bypasses cache)
+    /// Generate new keys for the empty (all-padding) trace (bypasses cache)
     pub fn generate(config: &KeygenConfig) -> Result<Self> {
+        Self::generate_for_trace(config, &ExecutionTrace::new())
+    }
+
+    /// Generate new keys for the circuit a trace synthesizes to (bypasses cache)
+    pub fn generate_for_trace(config: &KeygenConfig, trace: &ExecutionTrace) -> Result<Self> {
+        Self::generate_for_trace_with_progress(config, trace, &|_, _| {})
+    }
+
+    /// Generate keys like [`Self::generate_for_trace`], reporting progress
+    ///
+    /// See [`ProgressCallback`] for what is reported.
+    pub fn generate_for_trace_with_progress(
+        config: &KeygenConfig,
+        trace: &ExecutionTrace,
+        progress: ProgressCallback<'_>,
+    ) -> Result<Self> {
         tracing::info!(
             "Generating proving and verifying keys for k={}, lookup_bits={}",
             config.k,
@@ -150,42 +619,24 @@ impl KeyPair {
 
         // Set up KZG parameters
         tracing::info!("Setting up KZG parameters...");
-        let params = ParamsKZG::<Bn256>::setup(config.k, OsRng);
-
-        // Set environment variable for lookup bits
-        std::env::set_var("LOOKUP_BITS", config.lookup_bits.to_string());
-
-        // Create a dummy circuit for keygen with fixed chunk size
-        // This circuit will be padded to chunk_size, establishing the fixed circuit shape
-        tracing::info!(
-            "Creating dummy circuit for keygen with chunk_size={}...",
-            config.chunk_size
-        );
-        let dummy_trace = ExecutionTrace::new();
-        let circuit_logic = CounterCircuit::from_trace_chunked(dummy_trace, config.chunk_size);
-
-        // Build the circuit using BaseCircuitBuilder
-        let mut builder = BaseCircuitBuilder::<Fr>::from_stage(CircuitBuilderStage::Keygen)
-            .use_k(config.k as usize)
-            .use_lookup_bits(config.lookup_bits);
+        progress(ProvingStage::Setup, 0.0);
+        let params = config.setup_params()?;
 
-        // Create a gate chip
-        let gate = GateChip::<Fr>::default();
-
-        // Synthesize the circuit
-        circuit_logic.synthesize(builder.main(0), &gate)
-            .context("Failed to synthesize circuit")?;
+        progress(ProvingStage::WitnessAssignment, 0.2);
+        let mut builder = keygen_builder::<Fr>(config, trace)?;
 
         // Configure the builder and get the circuit params
-        let circuit_params = builder.calculate_params(Some(9));
+        let circuit_params = builder.calculate_params(Some(MINIMUM_ROWS));
 
         // Generate verifying key
         tracing::info!("Generating verifying key...");
+        progress(ProvingStage::VerifyingKey, 0.3);
         let vk = keygen_vk(&params, &builder)
             .context("Failed to generate verifying key")?;
 
         // Generate proving key
         tracing::info!("Generating proving key...");
+        progress(ProvingStage::ProvingKey, 0.6);
         let pk = keygen_pk(&params, vk, &builder)
             .context("Failed to generate proving key")?;
 
@@ -197,13 +648,19 @@ impl KeyPair {
         tracing::debug!("Break points from keygen: {:?}", break_points);
 
         tracing::info!("Key generation complete");
+        progress(ProvingStage::ProvingKey, 1.0);
         Ok(Self { params, pk, vk, break_points, circuit_params })
     }
 
     /// Load keys from cache
+    ///
+    /// Fails without deserializing anything if a cached file does not match
+    /// the cache manifest.
     pub fn load_from_cache(config: &KeygenConfig) -> Result<Self> {
         tracing::info!("Loading keys from cache: {:?}", config.cache_dir);
 
+        check_cache_manifest(config).context("Cached keys failed the integrity check")?;
+
         let params = load_params(&config.params_path())
             .context("Failed to load KZG parameters")?;
 
@@ -224,11 +681,19 @@ impl KeyPair {
     }
 
     /// Save keys to cache
+    ///
+    /// The cache manifest is removed first and written last, so an
+    /// interrupted save never leaves a cache that passes its integrity check.
     pub fn save_to_cache(&self, config: &KeygenConfig) -> Result<()> {
         // Create cache directory if it doesn't exist
         fs::create_dir_all(&config.cache_dir)
             .context("Failed to create cache directory")?;
 
+        let manifest_path = config.manifest_path();
+        if manifest_path.exists() {
+            fs::remove_file(&manifest_path).context("Failed to remove stale cache manifest")?;
+        }
+
         tracing::info!("Saving keys to cache: {:?}", config.cache_dir);
 
         save_params(&self.params, &config.params_path())
@@ -246,10 +711,95 @@ impl KeyPair {
         save_circuit_params(&self.circuit_params, &config.circuit_params_path())
             .context("Failed to save circuit params")?;
 
+        save_cache_manifest(config).context("Failed to save cache manifest")?;
+
         tracing::info!("Successfully saved keys to cache");
         Ok(())
     }
 
+    /// Save params, keys and their metadata to a single archive file
+    ///
+    /// The archive is the magic bytes `SBZKKEYS`, a little-endian `u32` format
+    /// version, then four sections each prefixed by a little-endian `u64`
+    /// length: metadata JSON (circuit params and break points), params,
+    /// verifying key and proving key, the latter three in halo2's raw
+    /// encoding. Unlike the cache, one file carries everything needed to
+    /// prove and verify, regardless of `k` or circuit id.
+    pub fn save_archive(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        tracing::info!("Saving key archive to {:?}", path);
+
+        let metadata = serde_json::to_vec(&ArchiveMetadata {
+            circuit_params: self.circuit_params.clone(),
+            break_points: self.break_points.clone(),
+        })
+        .context("Failed to serialize archive metadata")?;
+        let mut params = Vec::new();
+        self.params.write(&mut params).context("Failed to serialize params")?;
+        let mut vk = Vec::new();
+        self.vk
+            .write(&mut vk, SerdeFormat::RawBytesUnchecked)
+            .context("Failed to serialize VK")?;
+        let mut pk = Vec::new();
+        self.pk
+            .write(&mut pk, SerdeFormat::RawBytesUnchecked)
+            .context("Failed to serialize PK")?;
+
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create key archive: {:?}", path))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        for section in [&metadata, &params, &vk, &pk] {
+            writer.write_all(&(section.len() as u64).to_le_bytes())?;
+            writer.write_all(section)?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write key archive: {:?}", path))
+    }
+
+    /// Load a key pair from an archive written by [`Self::save_archive`]
+    pub fn load_archive(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        tracing::info!("Loading key archive from {:?}", path);
+
+        let bytes = fs::read(path)
+            .with_context(|| format!("Failed to read key archive: {:?}", path))?;
+        let mut rest = bytes
+            .strip_prefix(ARCHIVE_MAGIC.as_slice())
+            .with_context(|| format!("Not a key archive: {:?}", path))?;
+        let version = u32::from_le_bytes(*take_bytes::<4>(&mut rest)?);
+        anyhow::ensure!(
+            version == ARCHIVE_VERSION,
+            "Unsupported key archive version {} (expected {})",
+            version,
+            ARCHIVE_VERSION
+        );
+
+        let ArchiveMetadata { circuit_params, break_points } =
+            serde_json::from_slice(take_section(&mut rest)?)
+                .context("Failed to deserialize archive metadata")?;
+        let params = ParamsKZG::<Bn256>::read(&mut take_section(&mut rest)?)
+            .context("Failed to deserialize params")?;
+        let vk = VerifyingKey::<G1Affine>::read::<_, BaseCircuitBuilder<Fr>>(
+            &mut take_section(&mut rest)?,
+            SerdeFormat::RawBytesUnchecked,
+            circuit_params.clone(),
+        )
+        .context("Failed to deserialize VK")?;
+        let pk = ProvingKey::<G1Affine>::read::<_, BaseCircuitBuilder<Fr>>(
+            &mut take_section(&mut rest)?,
+            SerdeFormat::RawBytesUnchecked,
+            circuit_params.clone(),
+        )
+        .context("Failed to deserialize PK")?;
+        anyhow::ensure!(rest.is_empty(), "Trailing bytes after key archive sections");
+
+        tracing::info!("Successfully loaded key archive");
+        Ok(Self { params, pk, vk, break_points, circuit_params })
+    }
+
     /// Check if cached keys exist for given configuration
     pub fn cache_exists(config: &KeygenConfig) -> bool {
         config.params_path().exists()
@@ -260,6 +810,211 @@ impl KeyPair {
     }
 }
 
+impl KeyPair {
+    /// Scrub the key material this crate owns
+    ///
+    /// Overwrites the break points with zeros and empties them; the key pair
+    /// cannot prove afterwards. Also runs on drop.
+    ///
+    /// halo2's `ProvingKey`, `VerifyingKey` and `ParamsKZG` expose no mutable
+    /// access to their buffers, so they are left to the allocator. None of
+    /// them hold the KZG setup secret: `ParamsKZG::setup` keeps only its
+    /// public powers, and the secret itself is a temporary inside halo2
+    /// that this crate cannot reach. Proofs and verification are unaffected.
+    pub fn zeroize(&mut self) {
+        self.break_points.zeroize();
+    }
+}
+
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Size in bytes of every proof made with `vk`
+///
+/// Halo2 proofs have a fixed layout for a given constraint system, so the
+/// size follows from it: KZG commitments (32-byte compressed points) to
+/// the advice columns, lookup arguments, permutation products and
+/// quotient pieces, the SHPLONK opening points, and a 32-byte evaluation
+/// per column query. Instance columns are not committed to with KZG.
+pub fn estimate_proof_size(vk: &VerifyingKey<G1Affine>) -> usize {
+    const POINT_BYTES: usize = 32;
+    const SCALAR_BYTES: usize = 32;
+
+    let cs = vk.cs();
+    let degree = cs.degree();
+    let lookups = cs.lookups().len();
+    let permuted_columns = cs.permutation().get_columns().len();
+    let permutation_chunks = permuted_columns.div_ceil(degree - 2);
+
+    let commitments = cs.num_advice_columns()
+        // permuted input, permuted table and product per lookup
+        + 3 * lookups
+        + permutation_chunks
+        // random polynomial, then the quotient in `degree - 1` pieces
+        + 1
+        + (degree - 1)
+        // SHPLONK opening
+        + 2;
+    let evaluations = cs.advice_queries().len()
+        + cs.fixed_queries().len()
+        + 5 * lookups
+        // products at x and x*w, and all but the last at the last row
+        + (3 * permutation_chunks).saturating_sub(1)
+        // permutation polynomials
+        + permuted_columns
+        // random polynomial
+        + 1;
+
+    commitments * POINT_BYTES + evaluations * SCALAR_BYTES
+}
+
+/// Generate only the verifying key for `trace`'s circuit under `config`
+fn verifying_key_for(config: &KeygenConfig, trace: &ExecutionTrace) -> Result<VerifyingKey<G1Affine>> {
+    let params = config.setup_params()?;
+    let mut builder = keygen_builder::<Fr>(config, trace)?;
+    builder.calculate_params(Some(MINIMUM_ROWS));
+    keygen_vk(&params, &builder).context("Failed to generate verifying key")
+}
+
+/// Cell and column counts of a synthesized circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// Advice cells assigned (all phases)
+    pub advice_cells: usize,
+    /// Advice cells copied into lookup columns for range checks
+    pub lookup_advice_cells: usize,
+    /// Fixed cells holding constants
+    pub fixed_cells: usize,
+    /// Advice columns the cells need at the configured `k`
+    pub advice_columns: usize,
+    /// Lookup advice columns the cells need at the configured `k`
+    pub lookup_advice_columns: usize,
+}
+
+/// Measure the circuit keygen would build for a trace
+///
+/// Synthesizes exactly as [`KeyPair::generate_for_trace`] does, without
+/// generating keys, so tests can guard the circuit size cheaply.
+pub fn circuit_stats(config: &KeygenConfig, trace: &ExecutionTrace) -> Result<CircuitStats> {
+    let mut builder = keygen_builder::<Fr>(config, trace)?;
+    let circuit_params = builder.calculate_params(Some(MINIMUM_ROWS));
+    let statistics = builder.statistics();
+
+    Ok(CircuitStats {
+        advice_cells: statistics.gate.total_advice_per_phase.iter().sum(),
+        lookup_advice_cells: statistics.total_lookup_advice_per_phase.iter().sum(),
+        fixed_cells: statistics.gate.total_fixed,
+        advice_columns: circuit_params.num_advice_per_phase.iter().sum(),
+        lookup_advice_columns: circuit_params.num_lookup_advice_per_phase.iter().sum(),
+    })
+}
+
+/// Synthesize the keygen circuit for a trace
+///
+/// The trace is padded to `chunk_size`; its instructions select the chips,
+/// which establishes the circuit shape.
+fn keygen_builder<F: ScalarField>(
+    config: &KeygenConfig,
+    trace: &ExecutionTrace,
+) -> Result<BaseCircuitBuilder<F>> {
+    tracing::info!(
+        "Creating circuit for keygen with chunk_size={}...",
+        config.chunk_size
+    );
+    let circuit_logic = CounterCircuit::from_trace_chunked(trace.clone(), config.chunk_size)
+        .with_mode(circuit_mode(trace))
+        .with_allow_unconstrained(config.allow_unconstrained);
+    let public_inputs = PublicInputs::from_trace(trace)?;
+
+    // Build the circuit using BaseCircuitBuilder; lookup bits go to the
+    // builder, not the process environment
+    let mut builder = BaseCircuitBuilder::<F>::from_stage(CircuitBuilderStage::Keygen)
+        .use_k(config.k as usize)
+        .use_lookup_bits(config.lookup_bits)
+        .use_instance_columns(PublicInputs::NUM_INSTANCE_COLUMNS);
+
+    // Create a gate chip
+    let gate = GateChip::<F>::default();
+
+    // Synthesize the circuit
+    let output = circuit_logic.synthesize(builder.main(0), &gate)
+        .context("Failed to synthesize circuit")?;
+    public_inputs.assign_instances(&mut builder, &output);
+
+    Ok(builder)
+}
+
+/// Split `N` bytes off the front of an archive
+fn take_bytes<'a, const N: usize>(bytes: &mut &'a [u8]) -> Result<&'a [u8; N]> {
+    let (head, tail) = bytes
+        .split_first_chunk::<N>()
+        .context("Key archive is truncated")?;
+    *bytes = tail;
+    Ok(head)
+}
+
+/// Split a length-prefixed section off the front of an archive
+fn take_section<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = u64::from_le_bytes(*take_bytes::<8>(bytes)?) as usize;
+    anyhow::ensure!(bytes.len() >= len, "Key archive is truncated");
+    let (section, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Ok(section)
+}
+
+/// Hex SHA-256 of a file's contents
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// File name a cached file is listed under in the manifest
+fn manifest_key(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Record the hashes of the cached files
+fn save_cache_manifest(config: &KeygenConfig) -> Result<()> {
+    let files = config
+        .cached_files()
+        .iter()
+        .map(|path| Ok((manifest_key(path), file_sha256(path)?)))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    let path = config.manifest_path();
+    let file = File::create(&path)
+        .with_context(|| format!("Failed to create cache manifest: {:?}", path))?;
+
+    serde_json::to_writer_pretty(BufWriter::new(file), &CacheManifest { files })
+        .with_context(|| format!("Failed to serialize cache manifest to {:?}", path))
+}
+
+/// Check every cached file against the hash the manifest records for it
+fn check_cache_manifest(config: &KeygenConfig) -> Result<()> {
+    let path = config.manifest_path();
+    let file = File::open(&path)
+        .with_context(|| format!("Failed to open cache manifest: {:?}", path))?;
+    let manifest: CacheManifest = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Failed to deserialize cache manifest from {:?}", path))?;
+
+    for path in config.cached_files() {
+        let expected = manifest
+            .files
+            .get(&manifest_key(&path))
+            .with_context(|| format!("{:?} is not in the cache manifest", path))?;
+        anyhow::ensure!(
+            file_sha256(&path)? == *expected,
+            "{:?} does not match the cache manifest",
+            path
+        );
+    }
+    Ok(())
+}
+
 /// Load KZG parameters from file
 fn load_params(path: &Path) -> Result<ParamsKZG<Bn256>> {
     let file = File::open(path)
@@ -398,6 +1153,83 @@ mod tests {
         assert_eq!(config.lookup_bits, 8);
     }
 
+    #[test]
+    fn test_keygen_config_builder() {
+        let cache_dir = env::temp_dir().join(format!("sbpf_zkvm_builder_{}", std::process::id()));
+        let config = KeygenConfig::builder()
+            .k(10)
+            .lookup_bits(8)
+            .cache_dir(&cache_dir)
+            .build()
+            .unwrap();
+
+        assert_eq!((config.k, config.lookup_bits), (10, 8));
+        assert_eq!(config.cache_dir, cache_dir);
+        assert_eq!(config.srs_path, None);
+        assert!(cache_dir.is_dir());
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn test_keygen_config_builder_rejects_lookup_bits_at_k() {
+        let cache_dir = env::temp_dir().join("sbpf_zkvm_builder_rejected");
+        for lookup_bits in [10, 11] {
+            let err = KeygenConfig::builder()
+                .k(10)
+                .lookup_bits(lookup_bits)
+                .cache_dir(&cache_dir)
+                .build()
+                .unwrap_err();
+            assert!(err.to_string().contains("less than k"), "Unexpected error: {}", err);
+        }
+
+        let missing_srs = KeygenConfig::builder()
+            .k(10)
+            .cache_dir(&cache_dir)
+            .srs_path(cache_dir.join("missing.srs"))
+            .build();
+        assert!(missing_srs.is_err());
+    }
+
+    #[test]
+    fn test_keygen_config_from_file() {
+        let dir = env::temp_dir().join(format!("sbpf_zkvm_config_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Serde round trip; the proof cache is not serialized
+        let config = KeygenConfig::new(12, "keys", 8)
+            .with_chunk_size(64)
+            .with_allow_unconstrained(true)
+            .with_proof_cache(Arc::new(ProofCache::new(dir.join("proofs"))));
+        let json = serde_json::to_string(&config).unwrap();
+        let decoded: KeygenConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!((decoded.k, decoded.chunk_size, decoded.allow_unconstrained), (12, 64, true));
+        assert!(decoded.proof_cache.is_none());
+
+        // Relative paths resolve against the file; omitted fields default
+        let json_path = dir.join("keygen.json");
+        fs::write(&json_path, &json).unwrap();
+        let loaded = KeygenConfig::from_file(&json_path).unwrap();
+        assert_eq!(loaded.cache_dir, dir.join("keys"));
+        assert!(loaded.cache_dir.is_dir());
+
+        let toml_path = dir.join("keygen.toml");
+        fs::write(&toml_path, "k = 11\ncache_dir = \"toml_keys\"\n").unwrap();
+        let loaded = KeygenConfig::from_file(&toml_path).unwrap();
+        assert_eq!((loaded.k, loaded.lookup_bits), (11, KeygenConfig::default().lookup_bits));
+        assert_eq!(loaded.cache_dir, dir.join("toml_keys"));
+
+        // Loading validates
+        fs::write(&toml_path, "k = 8\nlookup_bits = 8\n").unwrap();
+        let err = KeygenConfig::from_file(&toml_path).unwrap_err();
+        assert!(format!("{:#}", err).contains("less than k"), "Unexpected error: {:#}", err);
+
+        fs::write(&toml_path, "k = 11\nlookup_bit = 8\n").unwrap();
+        assert!(KeygenConfig::from_file(&toml_path).is_err(), "Unknown fields are rejected");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_keygen_config_paths() {
         let config = KeygenConfig::new(10, "/tmp/test_keys", 8);
@@ -415,5 +1247,242 @@ mod tests {
         assert!(!KeyPair::cache_exists(&config));
     }
 
+    #[test]
+    fn test_keygen_config_paths_with_circuit_id() {
+        let config = KeygenConfig::new(10, "/tmp/test_keys", 8).with_circuit_id("abcd");
+
+        assert_eq!(config.params_path(), PathBuf::from("/tmp/test_keys/params_k10.bin"));
+        assert_eq!(config.vk_path(), PathBuf::from("/tmp/test_keys/counter_vk_k10_abcd.bin"));
+        assert_eq!(config.pk_path(), PathBuf::from("/tmp/test_keys/counter_pk_k10_abcd.bin"));
+    }
+
+    #[test]
+    fn test_circuit_id_depends_on_instructions() {
+        let mut trace = ExecutionTrace::new();
+        let empty_id = circuit_id(&trace, 10);
+        assert_eq!(empty_id, circuit_id(&ExecutionTrace::new(), 10));
+        assert_ne!(empty_id, circuit_id(&trace, 20));
+
+        trace.instructions.push(bpf_tracer::InstructionTrace {
+            pc: 0,
+            instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00], // ADD_IMM r1, 1
+            registers_before: bpf_tracer::RegisterState::new(),
+            registers_after: bpf_tracer::RegisterState::new(),
+            call_target: None,
+            text_offset: None,
+        });
+        assert_ne!(empty_id, circuit_id(&trace, 10));
+    }
+
+    #[test]
+    fn test_zeroize_keeps_prior_proofs_valid() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_zeroize_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let mut keypair = KeyPair::generate(&config).expect("Key generation should succeed");
+
+        let trace = ExecutionTrace::new();
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace, config.chunk_size);
+        let proof = crate::prove_circuit(
+            &circuit,
+            &public_inputs,
+            &keypair.pk,
+            &keypair.params,
+            config.k,
+            config.lookup_bits,
+            &keypair.break_points,
+        )
+        .expect("Proof generation should succeed");
+
+        keypair.zeroize();
+        assert!(keypair.break_points.is_empty());
+
+        let is_valid = crate::verify_proof(&proof, &keypair.vk, &keypair.params, &public_inputs)
+            .expect("Proof verification should not error");
+        assert!(is_valid, "Proof should stay valid after zeroize");
+    }
+
+    #[test]
+    fn test_keygen_progress_is_monotonic() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_progress_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+
+        let updates = std::cell::RefCell::new(Vec::new());
+        let record = |stage: ProvingStage, fraction: f32| updates.borrow_mut().push((stage, fraction));
+        KeyPair::generate_for_trace_with_progress(&config, &ExecutionTrace::new(), &record)
+            .expect("Key generation should succeed");
+
+        let updates = updates.into_inner();
+        assert_eq!(updates.first(), Some(&(ProvingStage::Setup, 0.0)));
+        assert_eq!(updates.last().map(|(_, fraction)| *fraction), Some(1.0));
+        assert!(
+            updates.windows(2).all(|pair| pair[0].1 <= pair[1].1),
+            "progress went backwards: {:?}",
+            updates
+        );
+    }
+
+    #[test]
+    fn test_key_archive_roundtrip() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_archive_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        fs::create_dir_all(&test_cache).unwrap();
+        let config = KeygenConfig::new(10, &test_cache, 8).with_chunk_size(4);
+        let archive = test_cache.join("keys.bin");
+        KeyPair::generate(&config)
+            .expect("Key generation should succeed")
+            .save_archive(&archive)
+            .expect("Saving the archive should succeed");
+
+        let keypair = KeyPair::load_archive(&archive).expect("Loading the archive should succeed");
+        let trace = ExecutionTrace::new();
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace, config.chunk_size);
+        let proof = crate::prove_circuit(
+            &circuit,
+            &public_inputs,
+            &keypair.pk,
+            &keypair.params,
+            config.k,
+            config.lookup_bits,
+            &keypair.break_points,
+        )
+        .expect("Proof generation should succeed");
+
+        let is_valid = crate::verify_proof(&proof, &keypair.vk, &keypair.params, &public_inputs)
+            .expect("Proof verification should not error");
+        assert!(is_valid, "Proof with archived keys should be valid");
+
+        // Anything else is rejected up front
+        fs::write(&archive, b"not a key archive").unwrap();
+        let err = KeyPair::load_archive(&archive).unwrap_err();
+        assert!(err.to_string().contains("Not a key archive"), "Unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_fit_proof_size_trades_k_for_columns() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_fit_size_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(9, test_cache, 8).with_chunk_size(64);
+        let trace = ExecutionTrace::new();
+
+        // At k=9 the padded circuit needs several advice columns
+        let loose = config.clone().fit_proof_size(&trace, usize::MAX).unwrap();
+        assert_eq!(loose.k, 9);
+        let loose_size = estimate_proof_size(&verifying_key_for(&loose, &trace).unwrap());
+
+        let tight = config.clone().fit_proof_size(&trace, loose_size - 1).unwrap();
+        assert!(tight.k > loose.k, "tight limit kept k={}", tight.k);
+        let columns = |config: &KeygenConfig| circuit_stats(config, &trace).unwrap().advice_columns;
+        assert!(columns(&tight) < columns(&loose));
+
+        let keypair = KeyPair::generate_for_trace(&tight, &trace).expect("Key generation should succeed");
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace.clone(), tight.chunk_size);
+        let proof = crate::prove_circuit(
+            &circuit,
+            &public_inputs,
+            &keypair.pk,
+            &keypair.params,
+            tight.k,
+            tight.lookup_bits,
+            &keypair.break_points,
+        )
+        .expect("Proof generation should succeed");
+        assert!(proof.len() < loose_size, "{}-byte proof is over the limit", proof.len());
+
+        let is_valid = crate::verify_proof(&proof, &keypair.vk, &keypair.params, &public_inputs)
+            .expect("Proof verification should not error");
+        assert!(is_valid, "Proof at the fitted k should be valid");
+
+        assert!(config.fit_proof_size(&trace, 64).is_err());
+    }
+
+    #[test]
+    fn test_estimate_k_monotonic_and_matches_for_trace() {
+        let mut previous = 0;
+        for count in [0, 1, 10, 100, 1_000, 10_000, 100_000] {
+            let k = estimate_k(count, 8);
+            assert!(k >= previous, "estimate_k({}) = {} dropped below {}", count, k, previous);
+            assert!(k > 8, "k={} leaves no room for the lookup table", k);
+            previous = k;
+        }
+
+        // A small padded circuit fits in the lookup table's rows either way
+        let config = KeygenConfig::new(13, env::temp_dir(), 12).with_chunk_size(4);
+        let chosen = config.clone().for_trace(&ExecutionTrace::new()).unwrap();
+        assert_eq!(chosen.k, estimate_k(config.chunk_size, config.lookup_bits));
+        assert_eq!(chosen.k, 13);
+    }
+
+    #[test]
+    fn test_cache_policy_on_corrupt_cache() {
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_cache_policy_{}", std::process::id()));
+        let config = KeygenConfig::new(10, &test_cache, 8).with_chunk_size(4);
+        let use_or_error = config.clone().with_cache_policy(CachePolicy::UseOrError);
+
+        let err = KeyPair::load_or_generate(&use_or_error).unwrap_err();
+        assert!(format!("{:#}", err).contains("No cached keys"), "Unexpected error: {:#}", err);
+
+        let fingerprint = KeyPair::load_or_generate(&config).unwrap().vk_fingerprint();
+        let loaded = KeyPair::load_or_generate(&use_or_error).unwrap();
+        assert_eq!(loaded.vk_fingerprint(), fingerprint);
+
+        // Flip one byte of the proving key, as a torn write might
+        let corrupt = || {
+            let mut pk = fs::read(config.pk_path()).unwrap();
+            *pk.last_mut().unwrap() ^= 1;
+            fs::write(config.pk_path(), pk).unwrap();
+        };
+
+        corrupt();
+        let err = KeyPair::load_or_generate(&use_or_error).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("does not match the cache manifest"),
+            "Unexpected error: {:#}",
+            err
+        );
+
+        // Regeneration repairs the cache
+        KeyPair::load_or_generate(&config).unwrap();
+        KeyPair::load_from_cache(&config).unwrap();
+
+        corrupt();
+        let always = config.clone().with_cache_policy(CachePolicy::AlwaysRegenerate);
+        let regenerated = KeyPair::load_or_generate(&always).unwrap();
+        let loaded = KeyPair::load_or_generate(&use_or_error).unwrap();
+        assert_eq!(loaded.vk_fingerprint(), regenerated.vk_fingerprint());
+
+        fs::remove_dir_all(&test_cache).unwrap();
+    }
+
+    #[test]
+    fn test_load_or_generate_refuses_other_circuit_ids() {
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_foreign_id_{}", std::process::id()));
+        let config = KeygenConfig::new(10, &test_cache, 8).with_chunk_size(4);
+        let bytecode = bpf_tracer::InsnBuilder::program([
+            bpf_tracer::InsnBuilder::mov64_imm(0, 7),
+            bpf_tracer::InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+
+        // A cold cache must not be filled with empty-trace keys under this id
+        let claimed = config.clone().with_circuit_of(&trace);
+        let err = KeyPair::load_or_generate(&claimed).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("cannot be generated"),
+            "Unexpected error: {:#}",
+            err
+        );
+        assert!(!KeyPair::cache_exists(&claimed));
+
+        // The trace itself can generate them, after which they load by id
+        let generated = KeyPair::load_or_generate_for_trace(&config, &trace).unwrap();
+        let loaded = KeyPair::load_or_generate(&claimed).unwrap();
+        assert_eq!(loaded.vk_fingerprint(), generated.vk_fingerprint());
+
+        fs::remove_dir_all(&test_cache).unwrap();
+    }
+
     // Note: test_load_or_generate removed - now tests actual key generation in integration tests
 }
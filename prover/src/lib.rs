@@ -2,17 +2,63 @@
 //!
 //! This crate connects execution tracing, circuit generation, and proof
 //! creation into a high-level API for proving BPF program execution.
+//!
+//! Key generation and proving need file access and OS randomness and sit
+//! behind the default `prove` feature. Anything built from an execution
+//! trace sits behind the `trace` feature, which `prove` enables. Without
+//! either only verification is built (see [`verifier`]), with no
+//! dependency on the tracer or the circuits, and it compiles for
+//! `wasm32-unknown-unknown`; the `wasm` feature adds a wasm-bindgen entry
+//! point.
 
 pub mod public_inputs;
+#[cfg(feature = "trace")]
 pub mod witness;
+#[cfg(feature = "prove")]
 pub mod keygen;
+#[cfg(feature = "trace")]
 pub mod chunking;
+pub mod bundle;
+#[cfg(feature = "prove")]
+pub mod cache;
+pub mod metrics;
+pub mod progress;
+pub mod proof_header;
+#[cfg(feature = "trace")]
+pub mod report;
+pub mod verifier;
+pub mod vk_export;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use public_inputs::PublicInputs;
+#[cfg(feature = "trace")]
 pub use witness::Witness;
-pub use keygen::{KeygenConfig, KeyPair};
+#[cfg(feature = "prove")]
+pub use keygen::{
+    circuit_id, circuit_mode, circuit_stats, estimate_k, estimate_proof_size, CachePolicy,
+    CircuitStats, KeygenConfig, KeygenConfigBuilder, KeyPair,
+};
+#[cfg(feature = "trace")]
 pub use chunking::{split_trace_into_chunks, ChunkProof};
+pub use bundle::{ProofBundle, SizeBreakdown};
+#[cfg(feature = "prove")]
+pub use cache::{ProofCache, ProofCacheKey};
+pub use metrics::ProveMetrics;
+pub use progress::{ProgressCallback, ProvingStage};
+pub use proof_header::ProofHeader;
+#[cfg(feature = "trace")]
+pub use report::{AccountChangeReport, ExecutionReport, InstructionReport, RegisterDelta};
+pub use verifier::{
+    verify_from_bytes, verify_proof, verify_proof_detailed, BatchVerifier, VerifierKey,
+    VerifyOutcome,
+};
+pub use vk_export::{vk_fingerprint, G1Point, G2Point, VkConstants};
+#[cfg(feature = "trace")]
 use bpf_tracer::ExecutionTrace;
+#[cfg(feature = "prove")]
+use bpf_tracer::TraceOptions;
+#[cfg(feature = "prove")]
 use halo2_base::{
     gates::{
         circuit::{builder::BaseCircuitBuilder, CircuitBuilderStage},
@@ -20,20 +66,22 @@ use halo2_base::{
     },
     halo2_proofs::{
         halo2curves::bn256::{Bn256, Fr, G1Affine},
-        plonk::{create_proof as halo2_create_proof, verify_proof as halo2_verify_proof, ProvingKey, VerifyingKey},
+        plonk::{create_proof as halo2_create_proof, ProvingKey},
         poly::kzg::{
             commitment::KZGCommitmentScheme, commitment::ParamsKZG, multiopen::ProverSHPLONK,
-            multiopen::VerifierSHPLONK, strategy::SingleStrategy,
         },
-        transcript::{
-            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
-        },
-        poly::commitment::ParamsProver,
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
     },
 };
-use rand::rngs::StdRng;
-use rand::SeedableRng;
+#[cfg(feature = "prove")]
+use rand::rngs::OsRng;
+#[cfg(feature = "prove")]
 use rayon::prelude::*;
+#[cfg(feature = "prove")]
+use std::collections::HashMap;
+#[cfg(feature = "prove")]
+use std::time::Instant;
+#[cfg(feature = "prove")]
 use zk_circuits::CounterCircuit;
 
 /// Result type for prover operations
@@ -46,6 +94,7 @@ pub type Proof = Vec<u8>;
 ///
 /// Converts an execution trace into the witness data needed
 /// for circuit constraint satisfaction.
+#[cfg(feature = "trace")]
 pub fn generate_witness(trace: &ExecutionTrace) -> Result<Vec<u8>> {
     tracing::info!("Generating witness from trace with {} instructions",
                    trace.instruction_count());
@@ -68,37 +117,111 @@ pub fn generate_witness(trace: &ExecutionTrace) -> Result<Vec<u8>> {
 ///
 /// Generates a Halo2 proof that the execution trace satisfies
 /// all circuit constraints.
+#[cfg(feature = "prove")]
 pub fn create_proof(
     trace: ExecutionTrace,
     pk: &ProvingKey<G1Affine>,
     params: &ParamsKZG<Bn256>,
     config: &KeygenConfig,
     break_points: &[Vec<usize>],
+) -> Result<Proof> {
+    create_proof_with_progress(trace, pk, params, config, break_points, &|_, _| {})
+}
+
+/// Create a ZK proof, reporting progress through `progress`
+///
+/// Like [`create_proof`]; see [`ProgressCallback`] for what is reported.
+#[cfg(feature = "prove")]
+pub fn create_proof_with_progress(
+    trace: ExecutionTrace,
+    pk: &ProvingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    config: &KeygenConfig,
+    break_points: &[Vec<usize>],
+    progress: ProgressCallback<'_>,
 ) -> Result<Proof> {
     tracing::info!(
         "Creating proof for trace with {} instructions",
         trace.instruction_count()
     );
 
-    // Set environment variable for lookup bits
-    std::env::set_var("LOOKUP_BITS", config.lookup_bits.to_string());
-
     // Create circuit from trace with chunking
     // This ensures the circuit shape matches keygen (padded to chunk_size)
-    let circuit_logic = CounterCircuit::from_trace_chunked(trace, config.chunk_size);
+    let public_inputs = PublicInputs::from_trace(&trace)?;
+    let mode = circuit_mode(&trace);
+    let circuit_logic = CounterCircuit::from_trace_chunked(trace, config.chunk_size)
+        .with_mode(mode)
+        .with_allow_unconstrained(config.allow_unconstrained);
+
+    prove_circuit_with_progress(
+        &circuit_logic,
+        &public_inputs,
+        pk,
+        params,
+        config.k,
+        config.lookup_bits,
+        break_points,
+        progress,
+    )
+}
+
+/// Create a ZK proof for an already-built counter circuit
+///
+/// The instance values of `public_inputs` (see
+/// [`PublicInputs::to_field_elements`]) are exposed in the instance column,
+/// binding the proof to them.
+#[cfg(feature = "prove")]
+fn prove_circuit(
+    circuit_logic: &CounterCircuit,
+    public_inputs: &PublicInputs,
+    pk: &ProvingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    k: u32,
+    lookup_bits: usize,
+    break_points: &[Vec<usize>],
+) -> Result<Proof> {
+    prove_circuit_with_progress(
+        circuit_logic,
+        public_inputs,
+        pk,
+        params,
+        k,
+        lookup_bits,
+        break_points,
+        &|_, _| {},
+    )
+}
+
+/// [`prove_circuit`], reporting progress through `progress`
+#[cfg(feature = "prove")]
+#[allow(clippy::too_many_arguments)]
+fn prove_circuit_with_progress(
+    circuit_logic: &CounterCircuit,
+    public_inputs: &PublicInputs,
+    pk: &ProvingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    k: u32,
+    lookup_bits: usize,
+    break_points: &[Vec<usize>],
+    progress: ProgressCallback<'_>,
+) -> Result<Proof> {
+    progress(ProvingStage::WitnessAssignment, 0.0);
 
     // Build the prover circuit with break points from keygen
     let mut builder = BaseCircuitBuilder::<Fr>::from_stage(CircuitBuilderStage::Prover)
-        .use_k(config.k as usize)
-        .use_lookup_bits(config.lookup_bits)
+        .use_k(k as usize)
+        .use_lookup_bits(lookup_bits)
+        .use_instance_columns(PublicInputs::NUM_INSTANCE_COLUMNS)
         .use_break_points(break_points.to_vec());
 
     // Create a gate chip
     let gate = GateChip::<Fr>::default();
 
     // Synthesize the circuit with real witness
-    circuit_logic.synthesize(builder.main(0), &gate)
+    let output = circuit_logic.synthesize(builder.main(0), &gate)
         .map_err(|e| anyhow::anyhow!("Failed to synthesize circuit: {}", e))?;
+    public_inputs.assign_instances(&mut builder, &output);
+    let instances = public_inputs.to_field_elements::<Fr>();
 
     // Configure the builder - sets config params
     builder.calculate_params(Some(9));
@@ -107,8 +230,11 @@ pub fn create_proof(
     let circuit = builder;
 
     // Generate proof using SHPLONK and Blake2b
+    progress(ProvingStage::Proof, 0.2);
     tracing::info!("Generating Halo2 proof...");
-    let rng = StdRng::seed_from_u64(0);
+    // halo2 draws its blinding factors from this; a fixed seed would make
+    // them public and the proof no longer zero-knowledge
+    let rng = OsRng;
     let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
 
     halo2_create_proof::<
@@ -118,53 +244,16 @@ pub fn create_proof(
         _,
         Blake2bWrite<Vec<u8>, G1Affine, _>,
         _,
-    >(params, pk, &[circuit], &[&[]], rng, &mut transcript)
+    >(params, pk, &[circuit], &[&[&instances]], rng, &mut transcript)
         .map_err(|e| anyhow::anyhow!("Proof generation failed: {:?}", e))?;
 
     let proof = transcript.finalize();
     tracing::info!("Proof generated ({} bytes)", proof.len());
+    progress(ProvingStage::Proof, 1.0);
 
     Ok(proof)
 }
 
-/// Verify a ZK proof with public inputs
-///
-/// Checks that a proof is valid for the given public inputs
-/// (initial and final state commitments).
-pub fn verify_proof(
-    proof: &Proof,
-    vk: &VerifyingKey<G1Affine>,
-    params: &ParamsKZG<Bn256>,
-    _public_inputs: &PublicInputs,
-) -> Result<bool> {
-    tracing::info!("Verifying proof ({} bytes)", proof.len());
-
-    // Get verifier params
-    let verifier_params = params.verifier_params();
-    let strategy = SingleStrategy::new(params);
-    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
-
-    // Verify using SHPLONK and Blake2b
-    let result = halo2_verify_proof::<
-        KZGCommitmentScheme<Bn256>,
-        VerifierSHPLONK<'_, Bn256>,
-        Challenge255<G1Affine>,
-        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
-        SingleStrategy<'_, Bn256>,
-    >(verifier_params, vk, strategy, &[&[]], &mut transcript);
-
-    match result {
-        Ok(_) => {
-            tracing::info!("Proof verification succeeded");
-            Ok(true)
-        }
-        Err(e) => {
-            tracing::warn!("Proof verification failed: {:?}", e);
-            Ok(false)
-        }
-    }
-}
-
 /// High-level API: Prove execution of a BPF program
 ///
 /// Takes a program execution trace and returns a proof with public inputs.
@@ -173,29 +262,223 @@ pub fn verify_proof(
 /// **Note**: This function currently handles traces up to `chunk_size` instructions.
 /// For traces > chunk_size, the trace will be truncated (padding handles the rest).
 /// For true multi-chunk proving, use `prove_execution_chunked` once aggregation is implemented.
+#[cfg(feature = "prove")]
 pub fn prove_execution(
     trace: ExecutionTrace,
     config: &KeygenConfig,
 ) -> Result<(Proof, PublicInputs)> {
-    // Generate public inputs from trace
-    let public_inputs = PublicInputs::from_trace(&trace)?;
+    let (proof, public_inputs, _metrics) = prove_execution_with_metrics(trace, config)?;
+    Ok((proof, public_inputs))
+}
 
-    // Load or generate keys
-    tracing::info!("Loading proving keys...");
-    let keypair = KeyPair::load_or_generate(config)?;
+/// [`prove_execution`], also returning sizes and stage timings
+#[cfg(feature = "prove")]
+pub fn prove_execution_with_metrics(
+    trace: ExecutionTrace,
+    config: &KeygenConfig,
+) -> Result<(Proof, PublicInputs, ProveMetrics)> {
+    let started = Instant::now();
+    let witness = Witness::from_trace(&trace)?;
+    let witness_bytes = witness.to_bytes()?.len();
+    let witness_ms = elapsed_ms(started);
+
+    let (proof, public_inputs, mut metrics) = prove_witness(&trace, witness, config)?;
+    metrics.witness_bytes = witness_bytes;
+    metrics.witness_ms = witness_ms;
+    Ok((proof, public_inputs, metrics))
+}
+
+/// Prove `trace` with the circuit built from its already generated witness
+///
+/// The trace selects the keys and public inputs; synthesis reads only the
+/// witness. Witness sizes and timings are left for the caller to fill in.
+#[cfg(feature = "prove")]
+fn prove_witness(
+    trace: &ExecutionTrace,
+    witness: Witness,
+    config: &KeygenConfig,
+) -> Result<(Proof, PublicInputs, ProveMetrics)> {
+    let _span = tracing::info_span!(
+        "prove_execution",
+        instruction_count = trace.instruction_count(),
+        k = config.k,
+        chunk_size = config.chunk_size
+    )
+    .entered();
+
+    // Generate public inputs from trace
+    let mut public_inputs = PublicInputs::from_trace(trace)?;
+    let id = circuit_id(trace, config.chunk_size);
+    public_inputs.circuit_id = Some(id.clone());
+
+    let mut metrics = ProveMetrics {
+        trace_instructions: trace.instruction_count(),
+        ..Default::default()
+    };
+
+    // Load or generate keys for this trace's circuit
+    let started = Instant::now();
+    let keypair = tracing::info_span!("keygen", k = config.k, circuit_id = %id).in_scope(|| {
+        tracing::info!("Loading proving keys...");
+        KeyPair::load_or_generate_for_trace(config, trace)
+    })?;
+    metrics.keygen_ms = elapsed_ms(started);
 
     // Create circuit and log constraints
-    let circuit = CounterCircuit::from_trace_chunked(trace.clone(), config.chunk_size);
+    let circuit = CounterCircuit::from_witness_chunked(witness, config.chunk_size)
+        .with_mode(circuit_mode(trace))
+        .with_allow_unconstrained(config.allow_unconstrained);
+    metrics.num_constraints = circuit.num_constraints();
     tracing::info!(
         "Circuit has ~{} constraints (chunk_size={})",
-        circuit.num_constraints(),
+        metrics.num_constraints,
         config.chunk_size
     );
 
     // Generate proof
-    let proof = create_proof(trace, &keypair.pk, &keypair.params, config, &keypair.break_points)?;
+    let started = Instant::now();
+    let proof = tracing::info_span!(
+        "prove",
+        instruction_count = trace.instruction_count(),
+        k = config.k
+    )
+    .in_scope(|| {
+        prove_circuit(
+            &circuit,
+            &public_inputs,
+            &keypair.pk,
+            &keypair.params,
+            config.k,
+            config.lookup_bits,
+            &keypair.break_points,
+        )
+    })?;
+    metrics.proof_ms = elapsed_ms(started);
+    metrics.proof_bytes = proof.len();
 
-    Ok((proof, public_inputs))
+    Ok((proof, public_inputs, metrics))
+}
+
+/// [`prove_execution`], also returning a readable report of what was proven
+///
+/// The report summarizes the same trace the proof is generated from and
+/// carries the bundle's public inputs, so an audit trail can pair each
+/// proof with an explanation of the execution behind it.
+#[cfg(feature = "prove")]
+pub fn prove_execution_with_report(
+    trace: ExecutionTrace,
+    config: &KeygenConfig,
+) -> Result<(ProofBundle, ExecutionReport)> {
+    let instruction_count = trace.instruction_count();
+    let (proof, public_inputs) = prove_execution(trace.clone(), config)?;
+    let report = ExecutionReport::new(&trace, &public_inputs);
+    Ok((ProofBundle::new(proof, public_inputs, instruction_count), report))
+}
+
+/// Milliseconds since `started`
+#[cfg(feature = "prove")]
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+/// High-level API: Trace and prove a BPF program in one call
+///
+/// Runs the program with `trace_opts`, builds the witness, loads or
+/// generates keys for the resulting circuit, and proves the circuit built
+/// from that witness. With a
+/// `config.proof_cache`, a proof of the same program and public inputs is
+/// returned from the cache instead of proving again.
+#[cfg(feature = "prove")]
+pub fn prove_program(
+    bytecode: &[u8],
+    trace_opts: &TraceOptions,
+    config: &KeygenConfig,
+) -> Result<ProofBundle> {
+    let _span = tracing::info_span!("prove_program", bytecode_len = bytecode.len()).entered();
+
+    let trace = {
+        let span = tracing::info_span!("trace", instruction_count = tracing::field::Empty);
+        let _entered = span.enter();
+        tracing::info!("Tracing program ({} bytes)", bytecode.len());
+        let trace = bpf_tracer::trace_program_with_options(bytecode, trace_opts)?;
+        span.record("instruction_count", trace.instruction_count());
+        trace
+    };
+    let instruction_count = trace.instruction_count();
+
+    // Fail early on traces the witness can't represent; proving reuses it
+    let witness =
+        tracing::info_span!("witness", instruction_count).in_scope(|| Witness::from_trace(&trace))?;
+
+    let cache_key = match &config.proof_cache {
+        Some(cache) => {
+            let mut public_inputs = PublicInputs::from_trace(&trace)?;
+            public_inputs.circuit_id = Some(circuit_id(&trace, config.chunk_size));
+            let key = ProofCacheKey::new(trace.meta.program_hash, &public_inputs, config.k)?;
+            if let Some(bundle) = cache.get(&key) {
+                return Ok(bundle);
+            }
+            Some((cache, key))
+        }
+        None => None,
+    };
+
+    let (proof, public_inputs, _metrics) = prove_witness(&trace, witness, config)?;
+    let bundle = ProofBundle::new(proof, public_inputs, instruction_count);
+
+    if let Some((cache, key)) = cache_key {
+        cache.insert(&key, &bundle)?;
+    }
+
+    Ok(bundle)
+}
+
+/// Prove a batch of circuits in parallel with one key pair
+///
+/// Every circuit must synthesize to the circuit `keypair` was generated
+/// for (same [`circuit_id`]); the key pair and parameters are shared
+/// read-only across the rayon thread pool. Results are returned in input
+/// order, one per circuit.
+///
+/// Public inputs are derived from each circuit's trace, so circuits built
+/// with `CounterCircuit::from_witness` are rejected.
+#[cfg(feature = "prove")]
+pub fn prove_batch(keypair: &KeyPair, circuits: Vec<CounterCircuit>) -> Vec<Result<ProofBundle>> {
+    let k = keypair.circuit_params.k as u32;
+    let lookup_bits = keypair
+        .circuit_params
+        .lookup_bits
+        .unwrap_or(KeygenConfig::default().lookup_bits);
+
+    tracing::info!(
+        "Proving batch of {} circuits with {} threads",
+        circuits.len(),
+        rayon::current_num_threads()
+    );
+
+    circuits
+        .par_iter()
+        .map(|circuit| {
+            let trace = circuit.trace().ok_or_else(|| {
+                anyhow::anyhow!("Batch proving needs circuits built from a trace")
+            })?;
+            let mut public_inputs = PublicInputs::from_trace(trace)?;
+            let chunk_size = circuit.chunk_size().unwrap_or(trace.instruction_count());
+            public_inputs.circuit_id = Some(circuit_id(trace, chunk_size));
+
+            let proof = prove_circuit(
+                circuit,
+                &public_inputs,
+                &keypair.pk,
+                &keypair.params,
+                k,
+                lookup_bits,
+                &keypair.break_points,
+            )?;
+
+            Ok(ProofBundle::new(proof, public_inputs, circuit.executed_instructions()))
+        })
+        .collect()
 }
 
 /// Prove execution with automatic chunking (sequential)
@@ -207,6 +490,7 @@ pub fn prove_execution(
 ///
 /// **Phase 1**: Returns individual chunk proofs (no aggregation yet)
 /// **Phase 2**: Will add recursive aggregation to produce single final proof
+#[cfg(feature = "prove")]
 pub fn prove_execution_chunked(
     trace: ExecutionTrace,
     config: &KeygenConfig,
@@ -222,12 +506,15 @@ pub fn prove_execution_chunked(
     let chunks = split_trace_into_chunks(trace, config.chunk_size)?;
     tracing::info!("Split into {} chunks", chunks.len());
 
-    // Load or generate keys
-    let keypair = KeyPair::load_or_generate(config)?;
+    // Load or generate keys for each distinct chunk circuit
+    let keypairs = load_chunk_keypairs(&chunks, config)?;
 
     // Prove each chunk sequentially
     let mut chunk_proofs = Vec::new();
     for (i, chunk) in chunks.iter().enumerate() {
+        let id = circuit_id(chunk, config.chunk_size);
+        let keypair = &keypairs[&id];
+
         tracing::info!(
             "Proving chunk {}/{} ({} instructions)",
             i + 1,
@@ -248,6 +535,7 @@ pub fn prove_execution_chunked(
             index: i,
             initial_registers: chunk.initial_registers.clone(),
             final_registers: chunk.final_registers.clone(),
+            circuit_id: id,
         });
     }
 
@@ -266,6 +554,7 @@ pub fn prove_execution_chunked(
 ///
 /// **Phase 1**: Returns individual chunk proofs (no aggregation yet)
 /// **Phase 2**: Will add recursive aggregation to produce single final proof
+#[cfg(feature = "prove")]
 pub fn prove_execution_chunked_parallel(
     trace: ExecutionTrace,
     config: &KeygenConfig,
@@ -281,14 +570,9 @@ pub fn prove_execution_chunked_parallel(
     let chunks = split_trace_into_chunks(trace, config.chunk_size)?;
     tracing::info!("Split into {} chunks for parallel proving", chunks.len());
 
-    // Load or generate keys
-    let keypair = KeyPair::load_or_generate(config)?;
-
-    // Clone the necessary data for parallel access
+    // Load or generate keys for each distinct chunk circuit
     // Note: ProvingKey and params are large, but Rayon will share them efficiently
-    let pk = &keypair.pk;
-    let params = &keypair.params;
-    let break_points = &keypair.break_points;
+    let keypairs = load_chunk_keypairs(&chunks, config)?;
 
     // Prove chunks in parallel using Rayon
     tracing::info!("Starting parallel proof generation with {} threads", rayon::current_num_threads());
@@ -303,12 +587,14 @@ pub fn prove_execution_chunked_parallel(
                 chunk.instruction_count()
             );
 
+            let id = circuit_id(chunk, config.chunk_size);
+            let keypair = &keypairs[&id];
             let proof = create_proof(
                 chunk.clone(),
-                pk,
-                params,
+                &keypair.pk,
+                &keypair.params,
                 config,
-                break_points,
+                &keypair.break_points,
             )?;
 
             Ok(ChunkProof {
@@ -316,6 +602,7 @@ pub fn prove_execution_chunked_parallel(
                 index: i,
                 initial_registers: chunk.initial_registers.clone(),
                 final_registers: chunk.final_registers.clone(),
+                circuit_id: id,
             })
         })
         .collect();
@@ -326,27 +613,779 @@ pub fn prove_execution_chunked_parallel(
     Ok(chunk_proofs)
 }
 
+/// Load or generate keys for every distinct circuit among the chunks
+///
+/// Chunks with identical instructions share a circuit and thus a key pair.
+#[cfg(feature = "prove")]
+fn load_chunk_keypairs(
+    chunks: &[ExecutionTrace],
+    config: &KeygenConfig,
+) -> Result<HashMap<String, KeyPair>> {
+    let mut keypairs = HashMap::new();
+    for chunk in chunks {
+        let id = circuit_id(chunk, config.chunk_size);
+        if !keypairs.contains_key(&id) {
+            let keypair = KeyPair::load_or_generate_for_trace(config, chunk)?;
+            keypairs.insert(id, keypair);
+        }
+    }
+    Ok(keypairs)
+}
+
 /// High-level API: Verify execution proof
 ///
 /// Verifies that a proof correctly proves the claimed state transition.
-/// Loads keys from cache or generates them if needed.
+/// The circuit and its public-input layout come from the verifier's own
+/// `config`, not from the proof: name the expected circuit with
+/// [`KeygenConfig::with_circuit_of`]. Public inputs claiming another
+/// circuit are an error, and so are keys missing from the cache, which the
+/// verifier never generates.
+///
+/// If `expected_program_commitment` is given, the proof is only accepted if
+/// it was made for that program (see `bpf_tracer::program_hash`).
+///
+/// See [`verify_execution_detailed`] for why a proof was rejected.
+#[cfg(feature = "prove")]
 pub fn verify_execution(
     proof: &Proof,
     public_inputs: &PublicInputs,
     config: &KeygenConfig,
+    expected_program_commitment: Option<[u8; 32]>,
 ) -> Result<bool> {
-    // Load or generate keys
+    let outcome =
+        verify_execution_detailed(proof, public_inputs, config, expected_program_commitment)?;
+    Ok(outcome.is_valid())
+}
+
+/// Verify an execution proof, reporting why it was rejected
+///
+/// Performs the checks of [`verify_execution`] and errors in the same
+/// cases. A program commitment other than `expected_program_commitment`
+/// is reported as a [`VerifyOutcome::PublicInputMismatch`] at its first
+/// differing field element, without loading keys.
+#[cfg(feature = "prove")]
+pub fn verify_execution_detailed(
+    proof: &Proof,
+    public_inputs: &PublicInputs,
+    config: &KeygenConfig,
+    expected_program_commitment: Option<[u8; 32]>,
+) -> Result<VerifyOutcome> {
+    if let Some(expected) = expected_program_commitment {
+        let mut expected_inputs = public_inputs.clone();
+        expected_inputs.program_commitment = expected;
+        let claimed = public_inputs.to_field_elements::<Fr>();
+        let expected_elements = expected_inputs.to_field_elements::<Fr>();
+        if let Some(index) = claimed.iter().zip(&expected_elements).position(|(a, b)| a != b) {
+            tracing::warn!(
+                "Program commitment mismatch: proof is for {}, expected {}",
+                public_inputs.program_commitment_hex(),
+                hex::encode(expected)
+            );
+            return Ok(VerifyOutcome::PublicInputMismatch { index });
+        }
+    }
+
+    check_claimed_circuit(public_inputs, config)?;
     tracing::info!("Loading verifying key...");
-    let keypair = KeyPair::load_or_generate(config)?;
+    let keypair = load_verifier_keys(config)?;
 
-    verify_proof(proof, &keypair.vk, &keypair.params, public_inputs)
+    verifier::verify_proof_detailed(proof, &keypair.vk, &keypair.params, public_inputs)
+}
+
+/// Fail if `public_inputs` claim a circuit other than the one `config`
+/// expects
+#[cfg(feature = "prove")]
+fn check_claimed_circuit(public_inputs: &PublicInputs, config: &KeygenConfig) -> Result<()> {
+    if let Some(claimed) = &public_inputs.circuit_id {
+        anyhow::ensure!(
+            config.circuit_id.as_ref() == Some(claimed),
+            "Proof is for circuit {}, but the verifier expects {}",
+            claimed,
+            config.circuit_id.as_deref().unwrap_or("the empty-trace circuit")
+        );
+    }
+    Ok(())
+}
+
+/// Keys for the circuit a verifier's `config` names
+///
+/// Keys are only read from the cache, and missing keys are an error:
+/// generating them takes a trace of the circuit, which a verifier must get
+/// from a source it trusts. Fill the cache with
+/// [`KeyPair::load_or_generate_for_trace`] from such a trace, or import
+/// the prover's keys.
+#[cfg(feature = "prove")]
+fn load_verifier_keys(config: &KeygenConfig) -> Result<KeyPair> {
+    let id = config.circuit_id.as_deref().ok_or_else(|| {
+        anyhow::anyhow!("The verifier config names no circuit (see KeygenConfig::with_circuit_of)")
+    })?;
+    anyhow::ensure!(
+        KeyPair::cache_exists(config),
+        "No cached keys for circuit {} in {:?}",
+        id,
+        config.cache_dir
+    );
+    KeyPair::load_from_cache(config)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prove"))]
 mod tests {
     use super::*;
     use bpf_tracer::{InstructionTrace, RegisterState};
     use std::env;
+    use std::sync::Arc;
+
+    /// `config` as a verifier of `bytecode`'s proofs sees it
+    fn verifier_config(config: &KeygenConfig, bytecode: &[u8]) -> KeygenConfig {
+        let trace = bpf_tracer::trace_program(bytecode).unwrap();
+        config.clone().with_circuit_of(&trace)
+    }
+
+    /// Test the one-call API on raw bytecode
+    #[test]
+    fn test_prove_program_bytecode() {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        // mov64 r0, 40; add64 r0, 2; exit
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00,
+            0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_program_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(10);
+        let verifier_config = verifier_config(&config, bytecode);
+
+        let bundle = prove_program(bytecode, &TraceOptions::default(), &config)
+            .expect("Proving the program should succeed");
+
+        assert_eq!(bundle.instruction_count, 3);
+        assert!(!bundle.proof.is_empty(), "Proof should not be empty");
+
+        let is_valid =
+            verify_execution(&bundle.proof, &bundle.public_inputs, &verifier_config, None)
+                .expect("Proof verification should not error");
+        assert!(is_valid, "Proof should be valid");
+    }
+
+    /// Test that proving the same program twice reads the second proof from cache
+    #[test]
+    fn test_prove_program_uses_proof_cache() {
+        // mov64 r0, 5; exit
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_cached_program_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let cache = Arc::new(ProofCache::new(test_cache.join("proofs")));
+        let config = KeygenConfig::new(10, test_cache, 8)
+            .with_chunk_size(4)
+            .with_proof_cache(cache.clone());
+        let verifier_config = verifier_config(&config, bytecode);
+
+        let first = prove_program(bytecode, &TraceOptions::default(), &config).unwrap();
+        assert_eq!(cache.hits(), 0);
+
+        let second = prove_program(bytecode, &TraceOptions::default(), &config).unwrap();
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(second.proof, first.proof);
+        let public_inputs = &second.public_inputs;
+        assert!(verify_execution(&second.proof, public_inputs, &verifier_config, None).unwrap());
+    }
+
+    /// Test that `mov r0, 42; exit` proves 42 as the public return value
+    #[test]
+    fn test_return_constant_is_public() {
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_return_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let verifier_config = verifier_config(&config, bytecode);
+
+        let bundle = prove_program(bytecode, &TraceOptions::default(), &config)
+            .expect("Proving the program should succeed");
+        assert_eq!(bundle.public_inputs.return_value, 42);
+        assert_eq!(bundle.public_inputs.instruction_count, 2);
+        let public_inputs = &bundle.public_inputs;
+        assert!(verify_execution(&bundle.proof, public_inputs, &verifier_config, None).unwrap());
+
+        let mut claimed = bundle.public_inputs.clone();
+        claimed.return_value = 43;
+        assert!(!verify_execution(&bundle.proof, &claimed, &verifier_config, None).unwrap());
+
+        // The padded chunk's two NOP steps are not counted
+        let mut claimed = bundle.public_inputs.clone();
+        claimed.instruction_count = 4;
+        assert!(!verify_execution(&bundle.proof, &claimed, &verifier_config, None).unwrap());
+    }
+
+    /// Test that the report accompanying a proof agrees with its public inputs
+    #[test]
+    fn test_prove_execution_with_report() {
+        let bytecode = bpf_tracer::InsnBuilder::program([
+            bpf_tracer::InsnBuilder::mov64_imm(0, 40),
+            bpf_tracer::InsnBuilder::add64_imm(0, 2),
+            bpf_tracer::InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_report_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let verifier_config = config.clone().with_circuit_of(&trace);
+
+        let (bundle, report) = prove_execution_with_report(trace, &config).unwrap();
+        assert_eq!(report.return_value(), bundle.public_inputs.return_value);
+        assert_eq!(report.return_value(), 42);
+        assert_eq!(report.compute_units, bundle.public_inputs.instruction_count);
+        assert_eq!(report.public_inputs.final_value_hash, bundle.public_inputs.final_value_hash);
+        assert!(report.to_json().unwrap().contains("add64 r0, 2"));
+        let public_inputs = &bundle.public_inputs;
+        assert!(verify_execution(&bundle.proof, public_inputs, &verifier_config, None).unwrap());
+    }
+
+    /// prove_batch shares the key pair across threads
+    #[test]
+    fn test_keypair_is_sync() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<KeyPair>();
+    }
+
+    /// One ADD_IMM r1, 1 trace per starting value of r1
+    fn add_imm_traces(starts: &[u64]) -> Vec<ExecutionTrace> {
+        starts
+            .iter()
+            .map(|&start| {
+                let before = RegisterState::from_regs([0, start, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+                let after = RegisterState::from_regs([0, start + 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8]);
+                ExecutionTrace {
+                    instructions: vec![InstructionTrace {
+                        pc: 0,
+                        instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+                        registers_before: before.clone(),
+                        registers_after: after.clone(),
+                        call_target: None,
+                        text_offset: None,
+                    }],
+                    account_states: vec![],
+                    initial_registers: before,
+                    final_registers: after,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// Test proving several circuits concurrently with one key pair
+    #[test]
+    fn test_prove_batch() {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        // Same instruction from different starting values
+        let traces = add_imm_traces(&[10, 20, 30]);
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_batch_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate_for_trace(&config, &traces[0])
+            .expect("Key generation should succeed");
+
+        let circuits = traces
+            .into_iter()
+            .map(|trace| CounterCircuit::from_trace_chunked(trace, config.chunk_size))
+            .collect();
+
+        let bundles = prove_batch(&keypair, circuits);
+        assert_eq!(bundles.len(), 3);
+
+        for bundle in bundles {
+            let bundle = bundle.expect("Batch proof should succeed");
+            assert_eq!(bundle.instruction_count, 1);
+            let is_valid = verify_proof(&bundle.proof, &keypair.vk, &keypair.params, &bundle.public_inputs)
+                .expect("Proof verification should not error");
+            assert!(is_valid, "Batch proof should be valid");
+        }
+    }
+
+    /// Test verifying several proofs with one batch verifier
+    #[test]
+    fn test_batch_verifier() {
+        let traces = add_imm_traces(&[10, 20, 30]);
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_batch_verify_{}", std::process::id()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate_for_trace(&config, &traces[0])
+            .expect("Key generation should succeed");
+
+        let circuits = traces
+            .into_iter()
+            .map(|trace| CounterCircuit::from_trace_chunked(trace, config.chunk_size))
+            .collect();
+        let mut bundles: Vec<ProofBundle> = prove_batch(&keypair, circuits)
+            .into_iter()
+            .collect::<Result<_>>()
+            .expect("Batch proofs should succeed");
+
+        let verifier = BatchVerifier::new(&keypair);
+        drop(keypair);
+        for bundle in &bundles {
+            assert!(verifier.verify(bundle).unwrap());
+        }
+        assert_eq!(verifier.verify_all(&bundles).unwrap(), vec![true, true, true]);
+
+        // A proof claiming a different instruction count is singled out
+        bundles[1].public_inputs.instruction_count += 1;
+        assert_eq!(verifier.verify_all(&bundles).unwrap(), vec![true, false, true]);
+    }
+
+    /// Span name and recorded fields, captured by [`SpanRecorder`]
+    type RecordedSpan = (String, Vec<(String, String)>);
+
+    /// Subscriber layer recording every span created
+    #[derive(Clone, Default)]
+    struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<RecordedSpan>>>);
+
+    struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Vec::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            self.0.lock().unwrap().push((attrs.metadata().name().to_string(), fields));
+        }
+    }
+
+    /// Test that the proving pipeline emits spans for each stage
+    #[test]
+    fn test_prove_program_spans() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+        // mov64 r0, 40; add64 r0, 2; exit
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00,
+            0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_spans_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(10);
+
+        tracing::subscriber::with_default(subscriber, || {
+            prove_program(bytecode, &TraceOptions::default(), &config)
+        })
+        .expect("Proving the program should succeed");
+
+        let spans = recorder.0.lock().unwrap();
+        let field = |name: &str, key: &str| {
+            spans
+                .iter()
+                .find(|(span, _)| span == name)
+                .unwrap_or_else(|| panic!("Missing span {}", name))
+                .1
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+
+        for name in ["prove_program", "trace", "witness", "prove_execution", "keygen", "prove"] {
+            assert!(spans.iter().any(|(span, _)| span == name), "Missing span {}", name);
+        }
+        assert_eq!(field("witness", "instruction_count").as_deref(), Some("3"));
+        assert_eq!(field("prove_execution", "k").as_deref(), Some("10"));
+        assert_eq!(field("keygen", "k").as_deref(), Some("10"));
+        assert_eq!(field("prove", "instruction_count").as_deref(), Some("3"));
+    }
+
+    /// Test that a circuit built from a witness proves like its trace circuit
+    #[test]
+    fn test_witness_circuit_proves_like_trace_circuit() {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        let before = RegisterState::from_regs([0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let after = RegisterState::from_regs([0, 52, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8]);
+        let trace = ExecutionTrace {
+            instructions: vec![InstructionTrace {
+                pc: 0,
+                instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00],
+                registers_before: before.clone(),
+                registers_after: after.clone(),
+                call_target: None,
+                text_offset: None,
+            }],
+            account_states: vec![],
+            initial_registers: before,
+            final_registers: after,
+            ..Default::default()
+        };
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_witness_circuit_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate_for_trace(&config, &trace)
+            .expect("Key generation should succeed");
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+
+        let witness = Witness::from_trace(&trace).unwrap();
+        let circuits = [
+            CounterCircuit::from_trace_chunked(trace, config.chunk_size),
+            CounterCircuit::from_witness_chunked(witness, config.chunk_size),
+        ];
+
+        for circuit in &circuits {
+            let proof = prove_circuit(
+                circuit,
+                &public_inputs,
+                &keypair.pk,
+                &keypair.params,
+                config.k,
+                config.lookup_bits,
+                &keypair.break_points,
+            )
+            .expect("Proof generation should succeed");
+
+            let is_valid = verify_proof(&proof, &keypair.vk, &keypair.params, &public_inputs)
+                .expect("Proof verification should not error");
+            assert!(is_valid, "Proof should be valid");
+        }
+
+        let bundles = prove_batch(&keypair, vec![circuits.into_iter().last().unwrap()]);
+        assert!(bundles[0].is_err(), "Batch proving needs a trace");
+    }
+
+    /// Test that an undersized public-input set fails before verification
+    #[test]
+    fn test_verify_public_input_count_mismatch() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_instances_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate(&config).expect("Key generation should succeed");
+
+        let public_inputs = PublicInputs::from_trace(&ExecutionTrace::new()).unwrap();
+        let instances = public_inputs.to_field_elements::<Fr>();
+
+        let err = verifier::verify_instances(
+            &Vec::new(),
+            &keypair.vk,
+            &keypair.params,
+            &instances[..1],
+            PublicInputs::NUM_FIELD_ELEMENTS,
+        )
+        .expect_err("Undersized public inputs should be rejected");
+        assert_eq!(err.to_string(), "public input count mismatch: expected 4, got 1");
+    }
+
+    /// Test the verify-only entry point on serialized key, proof and inputs
+    #[test]
+    fn test_verify_from_bytes() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_verify_bytes_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate(&config).expect("Key generation should succeed");
+
+        let trace = ExecutionTrace::new();
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace, config.chunk_size);
+        let proof = prove_circuit(
+            &circuit,
+            &public_inputs,
+            &keypair.pk,
+            &keypair.params,
+            config.k,
+            config.lookup_bits,
+            &keypair.break_points,
+        )
+        .expect("Proof generation should succeed");
+
+        let vk = keypair.export_verifier_key().unwrap();
+        let inputs = serde_json::to_vec(&public_inputs).unwrap();
+        let proof = keypair.serialize_proof(&proof);
+        assert!(verify_from_bytes(&vk, &proof, &inputs).unwrap());
+
+        let mut claimed = public_inputs;
+        claimed.return_value = 1;
+        let claimed = serde_json::to_vec(&claimed).unwrap();
+        assert!(!verify_from_bytes(&vk, &proof, &claimed).unwrap());
+        assert!(verify_from_bytes(&vk[..vk.len() / 2], &proof, &inputs).is_err());
+    }
+
+    /// Test that a proof header for another circuit is rejected before the pairing check
+    #[test]
+    fn test_verify_from_bytes_rejects_mismatched_circuit_hash() {
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_proof_header_{}", std::process::id()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate(&config).expect("Key generation should succeed");
+        let vk = keypair.export_verifier_key().unwrap();
+        let inputs = serde_json::to_vec(&PublicInputs::from_trace(&ExecutionTrace::new()).unwrap())
+            .unwrap();
+
+        // The proof bytes are never read, so garbage shows the check comes first
+        let header = ProofHeader { circuit_hash: [0; 32], ..ProofHeader::for_vk(&keypair.vk) };
+        let err = verify_from_bytes(&vk, &header.prepend(b"not a proof"), &inputs).unwrap_err();
+        assert!(
+            err.to_string().contains("but the verifying key is for circuit"),
+            "Unexpected error: {}",
+            err
+        );
+
+        let err = verify_from_bytes(&vk, b"not a proof", &inputs).unwrap_err();
+        assert!(err.to_string().contains("no header"), "Unexpected error: {}", err);
+    }
+
+    /// Test verification against an expected program commitment
+    #[test]
+    fn test_verify_expected_program_commitment() {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        // mov64 r0, 7; exit
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_commitment_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(10);
+        let verifier_config = verifier_config(&config, bytecode);
+
+        let bundle = prove_program(bytecode, &TraceOptions::default(), &config)
+            .expect("Proving the program should succeed");
+
+        let right = bpf_tracer::program_hash(bytecode);
+        let public_inputs = &bundle.public_inputs;
+        assert!(verify_execution(&bundle.proof, public_inputs, &verifier_config, Some(right))
+            .expect("Proof verification should not error"));
+
+        let wrong = bpf_tracer::program_hash(b"some other program");
+        assert!(!verify_execution(&bundle.proof, public_inputs, &verifier_config, Some(wrong))
+            .expect("Proof verification should not error"));
+    }
+
+    /// Test verification against public inputs rebuilt from the transaction
+    #[test]
+    fn test_verify_inputs_from_transaction() {
+        let _ = tracing_subscriber::fmt::try_init();
+
+        // mov64 r0, 7; exit
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+        let dir = format!("sbpf_zkvm_from_transaction_{}", now.as_secs());
+        let test_cache = env::temp_dir().join(dir);
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(10);
+        let verifier_config = verifier_config(&config, bytecode);
+
+        let bundle = prove_program(bytecode, &TraceOptions::default(), &config)
+            .expect("Proving the program should succeed");
+        let count = bundle.public_inputs.instruction_count;
+
+        let derived = PublicInputs::from_transaction(bytecode, &[], &[], count, 7)
+            .expect("Deriving public inputs should succeed");
+        assert_eq!(derived.to_field_elements(), bundle.public_inputs.to_field_elements());
+        assert!(verify_execution(&bundle.proof, &derived, &verifier_config, None)
+            .expect("Proof verification should not error"));
+
+        // A different program or outcome does not verify
+        let other = &[
+            0xb7, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let wrong_program = PublicInputs::from_transaction(other, &[], &[], count, 7)
+            .expect("Deriving public inputs should succeed");
+        assert!(!verify_execution(&bundle.proof, &wrong_program, &verifier_config, None)
+            .expect("Proof verification should not error"));
+        let wrong_return = PublicInputs::from_transaction(bytecode, &[], &[], count, 0)
+            .expect("Deriving public inputs should succeed");
+        assert!(!verify_execution(&bundle.proof, &wrong_return, &verifier_config, None)
+            .expect("Proof verification should not error"));
+    }
+
+    /// Test that detailed verification names the check a proof failed
+    #[test]
+    fn test_verify_execution_detailed_outcomes() {
+        let bytecode = bpf_tracer::InsnBuilder::program([
+            bpf_tracer::InsnBuilder::mov64_imm(0, 7),
+            bpf_tracer::InsnBuilder::exit(),
+        ]);
+
+        let test_cache = env::temp_dir().join(format!(
+            "sbpf_zkvm_detailed_{}",
+            std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+        ));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let verifier = &verifier_config(&config, &bytecode);
+        let bundle = prove_program(&bytecode, &TraceOptions::default(), &config).unwrap();
+        let (proof, public_inputs) = (&bundle.proof, &bundle.public_inputs);
+
+        let right = Some(bpf_tracer::program_hash(&bytecode));
+        let outcome = verify_execution_detailed(proof, public_inputs, verifier, right).unwrap();
+        assert_eq!(outcome, VerifyOutcome::Valid);
+
+        // The program commitment leads the instance
+        let wrong = Some(bpf_tracer::program_hash(b"some other program"));
+        let outcome = verify_execution_detailed(proof, public_inputs, verifier, wrong).unwrap();
+        assert_eq!(outcome, VerifyOutcome::PublicInputMismatch { index: 0 });
+
+        let truncated = proof[..proof.len() / 2].to_vec();
+        let outcome = verify_execution_detailed(&truncated, public_inputs, verifier, None).unwrap();
+        assert_eq!(outcome, VerifyOutcome::MalformedProof);
+
+        let mut claimed = public_inputs.clone();
+        claimed.return_value = 8;
+        let outcome = verify_execution_detailed(proof, &claimed, verifier, None).unwrap();
+        assert_eq!(outcome, VerifyOutcome::InvalidPairing);
+    }
+
+    /// Test that the verifier checks proofs against the circuit it expects
+    #[test]
+    fn test_verifier_picks_the_circuit() {
+        let program = |value| {
+            bpf_tracer::InsnBuilder::program([
+                bpf_tracer::InsnBuilder::mov64_imm(0, value),
+                bpf_tracer::InsnBuilder::exit(),
+            ])
+        };
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_verifier_circuit_{}", std::process::id()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+
+        let bundle = prove_program(&program(7), &TraceOptions::default(), &config).unwrap();
+        let expected = verifier_config(&config, &program(7));
+        assert!(verify_execution(&bundle.proof, &bundle.public_inputs, &expected, None).unwrap());
+
+        // A proof claiming another circuit is rejected before any key is read
+        let other = verifier_config(&config, &program(8));
+        let err = verify_execution(&bundle.proof, &bundle.public_inputs, &other, None).unwrap_err();
+        assert!(err.to_string().contains("verifier expects"), "Unexpected error: {}", err);
+
+        // Without cached keys for the expected circuit the verifier fails
+        // rather than generating keys from a trace it does not have
+        let mut claimed = bundle.public_inputs.clone();
+        claimed.circuit_id = other.circuit_id.clone();
+        let err = verify_execution(&bundle.proof, &claimed, &other, None).unwrap_err();
+        assert!(err.to_string().contains("No cached keys"), "Unexpected error: {}", err);
+        assert!(!KeyPair::cache_exists(&other));
+
+        let unnamed = KeygenConfig { circuit_id: None, ..expected };
+        assert!(verify_execution(&bundle.proof, &bundle.public_inputs, &unnamed, None).is_err());
+    }
+
+    /// Test that a program that logs proves, without a claim about its logs
+    #[test]
+    fn test_logging_program_proof() {
+        use bpf_tracer::InsnBuilder;
+
+        // Store "zkvm" on the stack and pass it to sol_log_
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, i32::from_le_bytes(*b"zkvm")),
+            InsnBuilder::stxdw(10, -8, 1),
+            InsnBuilder::mov64_reg(1, 10),
+            InsnBuilder::add64_imm(1, -8),
+            InsnBuilder::mov64_imm(2, 4),
+            // call sol_log_ (murmur3 hash of the symbol name)
+            InsnBuilder::insn(0x85, 0, 0, 0, 0x207559bd),
+            InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+        assert_eq!(trace.logs, ["zkvm"]);
+
+        // The syscall has no chip, so it is let through unconstrained
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_logs_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8)
+            .with_chunk_size(8)
+            .with_allow_unconstrained(true);
+        let verifier_config = config.clone().with_circuit_of(&trace);
+
+        let (proof, public_inputs) = prove_execution(trace, &config)
+            .expect("Proof generation should succeed");
+        assert!(verify_execution(&proof, &public_inputs, &verifier_config, None).unwrap());
+    }
+
+    /// Test that a program reading a sysvar proves
+    #[test]
+    fn test_sysvar_program_proof() {
+        use bpf_tracer::{Clock, InsnBuilder, SysvarValues, TraceOptions};
+
+        // Read the clock into the stack and return the syscall's status;
+        // the syscall's stores are not modeled, so the program cannot load
+        // them back in a whole-program proof
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_reg(1, 10),
+            InsnBuilder::add64_imm(1, -40),
+            // call sol_get_clock_sysvar (murmur3 hash of the symbol name)
+            InsnBuilder::insn(0x85, 0, 0, 0, 0xd56b5fe9_u32 as i32),
+            InsnBuilder::exit(),
+        ]);
+        let sysvars = SysvarValues::default().with_clock(Clock::at_slot(250_000_000));
+        let options = TraceOptions::default().with_sysvars(sysvars);
+        let trace = bpf_tracer::trace_program_with_options(&bytecode, &options).unwrap();
+
+        // The syscall has no chip, so it is let through unconstrained
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_sysvars_{}", std::process::id()));
+        let config = KeygenConfig::new(10, test_cache, 8)
+            .with_chunk_size(8)
+            .with_allow_unconstrained(true);
+        let verifier_config = config.clone().with_circuit_of(&trace);
+
+        let (proof, public_inputs) = prove_execution(trace, &config)
+            .expect("Proof generation should succeed");
+        assert_eq!(public_inputs.return_value, 0);
+        assert!(verify_execution(&proof, &public_inputs, &verifier_config, None).unwrap());
+    }
+
+    /// Test that a whole execution cut short of its `exit` does not prove
+    #[test]
+    fn test_truncated_trace_does_not_prove() {
+        use bpf_tracer::InsnBuilder;
+        use zk_circuits::CircuitMode;
+
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 1),
+            InsnBuilder::add64_imm(0, 2),
+            InsnBuilder::exit(),
+        ]);
+        let whole = bpf_tracer::trace_program(&bytecode).unwrap();
+        assert_eq!(circuit_mode(&whole), CircuitMode::FullProgram);
+
+        // Drop the `exit` while still claiming the program exited
+        let mut truncated = whole.clone();
+        truncated.instructions.truncate(2);
+        truncated.final_registers = truncated.instructions[1].registers_after.clone();
+        assert_eq!(circuit_mode(&truncated), CircuitMode::FullProgram);
+
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_truncated_{}", std::process::id()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let verifier_config = config.clone().with_circuit_of(&truncated);
+        let (proof, public_inputs) = prove_execution(truncated, &config)
+            .expect("Proof generation should succeed");
+        assert!(!verify_execution(&proof, &public_inputs, &verifier_config, None).unwrap());
+
+        // The same prefix proves as a window of the execution
+        let window = whole.slice(0, 2);
+        assert_eq!(circuit_mode(&window), CircuitMode::Partial);
+        let verifier_config = config.clone().with_circuit_of(&window);
+        let (proof, public_inputs) = prove_execution(window, &config)
+            .expect("Proof generation should succeed");
+        assert!(verify_execution(&proof, &public_inputs, &verifier_config, None).unwrap());
+    }
 
     /// Test end-to-end proof generation and verification with a simple trace
     #[test]
@@ -364,6 +1403,8 @@ mod tests {
             instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
             registers_before: initial_regs.clone(),
             registers_after: after_regs,
+            call_target: None,
+            text_offset: None,
         };
 
         let trace = ExecutionTrace {
@@ -371,12 +1412,15 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         // Use a test-specific cache directory
         let test_cache = env::temp_dir().join("sbpf_zkvm_test_cache");
         let config = KeygenConfig::new(10, test_cache, 8); // Small k for faster testing
 
+        let verifier_config = config.clone().with_circuit_of(&trace);
+
         // Generate proof
         let (proof, public_inputs) = prove_execution(trace, &config)
             .expect("Proof generation should succeed");
@@ -385,7 +1429,7 @@ mod tests {
         tracing::info!("Generated proof of {} bytes", proof.len());
 
         // Verify proof
-        let is_valid = verify_execution(&proof, &public_inputs, &config)
+        let is_valid = verify_execution(&proof, &public_inputs, &verifier_config, None)
             .expect("Proof verification should not error");
 
         assert!(is_valid, "Proof should be valid");
@@ -402,6 +1446,7 @@ mod tests {
         // Use a test-specific cache directory with timestamp to avoid conflicts
         let test_cache = env::temp_dir().join(format!("sbpf_zkvm_empty_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
         let config = KeygenConfig::new(10, test_cache, 8);
+        let verifier_config = config.clone().with_circuit_of(&trace);
 
         // Generate proof for empty trace
         let (proof, public_inputs) = prove_execution(trace, &config)
@@ -410,12 +1455,38 @@ mod tests {
         assert!(!proof.is_empty());
 
         // Verify proof
-        let is_valid = verify_execution(&proof, &public_inputs, &config)
+        let is_valid = verify_execution(&proof, &public_inputs, &verifier_config, None)
             .expect("Verification should not error");
 
         assert!(is_valid, "Empty trace proof should be valid");
     }
 
+    #[test]
+    fn test_prove_execution_metrics() {
+        // mov64 r0, 40; add64 r0, 2; exit
+        let bytecode = &[
+            0xb7, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00,
+            0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let trace = bpf_tracer::trace_program(bytecode).unwrap();
+        let instruction_count = trace.instruction_count();
+        let witness_bytes = generate_witness(&trace).unwrap().len();
+
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_metrics_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let verifier_config = config.clone().with_circuit_of(&trace);
+
+        let (proof, public_inputs, metrics) = prove_execution_with_metrics(trace, &config)
+            .expect("Proof generation should succeed");
+
+        assert_eq!(metrics.trace_instructions, instruction_count);
+        assert_eq!(metrics.proof_bytes, proof.len());
+        assert_eq!(metrics.witness_bytes, witness_bytes);
+        assert!(metrics.num_constraints > 0);
+        assert!(verify_execution(&proof, &public_inputs, &verifier_config, None).unwrap());
+    }
+
     #[test]
     fn test_prove_and_verify_simple_trace_unique_cache() {
         // Initialize tracing for test output
@@ -431,6 +1502,8 @@ mod tests {
             instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
             registers_before: initial_regs.clone(),
             registers_after: after_regs,
+            call_target: None,
+            text_offset: None,
         };
 
         let trace = ExecutionTrace {
@@ -438,12 +1511,15 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         // Use a test-specific cache directory with timestamp
         let test_cache = env::temp_dir().join(format!("sbpf_zkvm_simple_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
         let config = KeygenConfig::new(10, test_cache, 8); // Small k for faster testing
 
+        let verifier_config = config.clone().with_circuit_of(&trace);
+
         // Generate proof
         let (proof, public_inputs) = prove_execution(trace, &config)
             .expect("Proof generation should succeed");
@@ -452,7 +1528,7 @@ mod tests {
         tracing::info!("Generated proof of {} bytes", proof.len());
 
         // Verify proof
-        let is_valid = verify_execution(&proof, &public_inputs, &config)
+        let is_valid = verify_execution(&proof, &public_inputs, &verifier_config, None)
             .expect("Proof verification should not error");
 
         assert!(is_valid, "Proof should be valid");
@@ -485,10 +1561,12 @@ mod tests {
             ]);
 
             instrs.push(InstructionTrace {
-                pc: i * 8,
+                pc: i,
                 instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00], // ADD_IMM r1, 1
                 registers_before: current_regs.clone(),
                 registers_after: next_regs.clone(),
+                call_target: None,
+                text_offset: None,
             });
 
             current_regs = next_regs;
@@ -501,6 +1579,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         let test_cache = env::temp_dir().join(format!(
@@ -567,10 +1646,12 @@ mod tests {
             ]);
 
             instrs.push(InstructionTrace {
-                pc: i * 8,
+                pc: i,
                 instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00], // ADD_IMM r1, 1
                 registers_before: current_regs.clone(),
                 registers_after: next_regs.clone(),
+                call_target: None,
+                text_offset: None,
             });
 
             current_regs = next_regs;
@@ -583,6 +1664,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         let test_cache = env::temp_dir().join(format!(
@@ -652,10 +1734,12 @@ mod tests {
             ]);
 
             instrs.push(InstructionTrace {
-                pc: i * 8,
+                pc: i,
                 instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00], // ADD_IMM r1, 1
                 registers_before: current_regs.clone(),
                 registers_after: next_regs.clone(),
+                call_target: None,
+                text_offset: None,
             });
 
             current_regs = next_regs;
@@ -668,6 +1752,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs.clone(),
+            ..Default::default()
         };
 
         let config = KeygenConfig::new(10, env::temp_dir().join("sbpf_benchmark"), 8).with_chunk_size(10);
@@ -0,0 +1,27 @@
+//! Proving Metrics
+//!
+//! Sizes and stage timings of one proving run, for services that want to
+//! observe proving without wrapping each stage in their own timers.
+
+use serde::{Deserialize, Serialize};
+
+/// Sizes and timings recorded by [`prove_execution_with_metrics`]
+///
+/// [`prove_execution_with_metrics`]: crate::prove_execution_with_metrics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProveMetrics {
+    /// Instructions in the proven trace, before padding
+    pub trace_instructions: usize,
+    /// Size of the serialized witness
+    pub witness_bytes: usize,
+    /// Estimated constraint count (see `CounterCircuit::num_constraints`)
+    pub num_constraints: usize,
+    /// Time to load or generate the key pair
+    pub keygen_ms: u64,
+    /// Time to build and serialize the witness
+    pub witness_ms: u64,
+    /// Time to synthesize the circuit and create the proof
+    pub proof_ms: u64,
+    /// Size of the proof
+    pub proof_bytes: usize,
+}
@@ -0,0 +1,25 @@
+//! Progress Reporting
+//!
+//! Coarse stage updates for key generation and proving, which can run for
+//! minutes at large `k`.
+
+/// Stage of key generation or proving
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvingStage {
+    /// KZG parameter setup
+    Setup,
+    /// Circuit synthesis and witness assignment
+    WitnessAssignment,
+    /// Verifying key generation
+    VerifyingKey,
+    /// Proving key generation
+    ProvingKey,
+    /// Proof generation
+    Proof,
+}
+
+/// Callback receiving the stage being entered and the overall progress
+///
+/// Progress is in `[0, 1]` and never decreases within one keygen or proving
+/// run; the last call of a run reports `1.0`.
+pub type ProgressCallback<'a> = &'a dyn Fn(ProvingStage, f32);
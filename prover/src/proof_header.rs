@@ -0,0 +1,113 @@
+//! Proof Header
+//!
+//! Serialized proofs start with a header naming the prover version and the
+//! circuit they were generated for, so a verifier running other code or
+//! holding another key rejects them before the pairing check.
+
+use anyhow::Context;
+use halo2_base::halo2_proofs::{halo2curves::bn256::G1Affine, plonk::VerifyingKey};
+use crate::{vk_export::vk_digest, Result};
+
+/// Magic bytes opening a serialized proof
+const PROOF_MAGIC: &[u8; 4] = b"SBZP";
+
+/// Metadata prepended to a serialized proof
+///
+/// Encoded as the magic bytes `SBZP`, a `u8` length and the UTF-8 crate
+/// version, the 32-byte circuit hash, then `k` as a little-endian `u32`.
+/// The proof bytes follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofHeader {
+    /// Version of the prover crate that generated the proof
+    pub version: String,
+    /// SHA-256 of the verifying key (the bytes behind `vk_fingerprint`)
+    pub circuit_hash: [u8; 32],
+    /// Circuit size parameter
+    pub k: u32,
+}
+
+impl ProofHeader {
+    /// Header for proofs verified by `vk`, from this crate version
+    pub fn for_vk(vk: &VerifyingKey<G1Affine>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            circuit_hash: vk_digest(vk),
+            k: vk.get_domain().k(),
+        }
+    }
+
+    /// Encode the header followed by `proof`
+    pub fn prepend(&self, proof: &[u8]) -> Vec<u8> {
+        let version = self.version.as_bytes();
+        let mut bytes = Vec::with_capacity(PROOF_MAGIC.len() + 37 + version.len() + proof.len());
+        bytes.extend_from_slice(PROOF_MAGIC);
+        bytes.push(version.len() as u8);
+        bytes.extend_from_slice(version);
+        bytes.extend_from_slice(&self.circuit_hash);
+        bytes.extend_from_slice(&self.k.to_le_bytes());
+        bytes.extend_from_slice(proof);
+        bytes
+    }
+
+    /// Decode the header of a serialized proof, returning it and the proof
+    pub fn split(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let rest = bytes
+            .strip_prefix(PROOF_MAGIC.as_slice())
+            .context("Proof has no header; serialize it with KeyPair::serialize_proof")?;
+        let (&len, rest) = rest.split_first().context("Proof header is truncated")?;
+        anyhow::ensure!(rest.len() >= len as usize, "Proof header is truncated");
+        let (version, rest) = rest.split_at(len as usize);
+        let version = std::str::from_utf8(version)
+            .context("Proof header version is not UTF-8")?
+            .to_string();
+        let (circuit_hash, rest) =
+            rest.split_first_chunk::<32>().context("Proof header is truncated")?;
+        let (k, proof) = rest.split_first_chunk::<4>().context("Proof header is truncated")?;
+
+        let header = Self { version, circuit_hash: *circuit_hash, k: u32::from_le_bytes(*k) };
+        Ok((header, proof))
+    }
+
+    /// Fail unless the proof was generated by this crate version for `vk`
+    pub fn check(&self, vk: &VerifyingKey<G1Affine>) -> Result<()> {
+        let expected = Self::for_vk(vk);
+        anyhow::ensure!(
+            self.version == expected.version,
+            "Proof was generated by prover {}, but this verifier is {}",
+            self.version,
+            expected.version
+        );
+        anyhow::ensure!(
+            self.k == expected.k,
+            "Proof is for k={}, but the verifying key is for k={}",
+            self.k,
+            expected.k
+        );
+        anyhow::ensure!(
+            self.circuit_hash == expected.circuit_hash,
+            "Proof is for circuit {}, but the verifying key is for circuit {}",
+            hex::encode(self.circuit_hash),
+            hex::encode(expected.circuit_hash)
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_header_roundtrip() {
+        let header = ProofHeader { version: "0.1.0".to_string(), circuit_hash: [7; 32], k: 10 };
+        let bytes = header.prepend(b"proof");
+
+        let (decoded, proof) = ProofHeader::split(&bytes).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(proof, b"proof");
+
+        let err = ProofHeader::split(b"proof").unwrap_err();
+        assert!(err.to_string().contains("no header"), "Unexpected error: {}", err);
+        assert!(ProofHeader::split(&bytes[..20]).is_err());
+    }
+}
@@ -2,28 +2,78 @@
 //!
 //! Defines the public inputs to the ZK circuit (state commitments).
 
-use bpf_tracer::ExecutionTrace;
+#[cfg(feature = "trace")]
+use bpf_tracer::{AccountState, AccountStateChange, ExecutionTrace};
+use halo2_base::utils::ScalarField;
+#[cfg(feature = "prove")]
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "trace")]
 use sha2::{Digest, Sha256};
 use crate::Result;
+#[cfg(feature = "trace")]
+use crate::{witness::bytes_to_field_elements, Witness};
+#[cfg(feature = "prove")]
+use zk_circuits::SynthesisOutput;
 
 /// Public inputs to the counter circuit
 ///
-/// These values are public (visible to the verifier) and represent
-/// commitments to the initial and final program state.
+/// Only the program commitment, instruction count and return value are
+/// part of the instance (see [`Self::to_field_elements`]), and so bound by
+/// a proof. The other commitments describe the traced execution for the
+/// verifier's information: the circuit does not derive them, so a proof
+/// does not establish them.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublicInputs {
     /// Hash of initial counter value
+    ///
+    /// Not part of the instance, so not proven.
     pub initial_value_hash: [u8; 32],
     /// Hash of final counter value
+    ///
+    /// Not part of the instance, so not proven.
     pub final_value_hash: [u8; 32],
+    /// Commitment to all account state changes (see `Witness::account_commitment`)
+    ///
+    /// Not part of the instance, so not proven.
+    #[serde(default)]
+    pub account_commitment: [u8; 32],
+    /// Commitment to the executed program (see `bpf_tracer::program_hash`)
+    ///
+    /// Bound by the verifying key: the circuit exposes it as a constant
+    /// fixed at keygen, and the program hash is part of the circuit id.
+    #[serde(default)]
+    pub program_commitment: [u8; 32],
+    /// Number of instructions executed, excluding padding
+    ///
+    /// Bound to the count the circuit sums over its non-padding steps.
+    #[serde(default)]
+    pub instruction_count: u64,
+    /// Program return value (final r0)
+    ///
+    /// Bound to the circuit's final r0 cell.
+    #[serde(default)]
+    pub return_value: u64,
+    /// Identifier of the circuit the proof was generated for
+    ///
+    /// The prover's claim (see `keygen::circuit_id`): verifiers check it
+    /// against the circuit they expect instead of picking keys by it.
+    #[serde(default)]
+    pub circuit_id: Option<String>,
 }
 
 impl PublicInputs {
+    /// Number of field elements in [`Self::to_field_elements`]
+    pub const NUM_FIELD_ELEMENTS: usize = 4;
+
+    /// Instance columns the circuit exposes public inputs in
+    pub const NUM_INSTANCE_COLUMNS: usize = 1;
+
     /// Create public inputs from an execution trace
     ///
     /// Computes commitments to the initial and final state
     /// from the trace's register states.
+    #[cfg(feature = "trace")]
     pub fn from_trace(trace: &ExecutionTrace) -> Result<Self> {
         // Hash initial register state
         let initial_bytes = serde_json::to_vec(&trace.initial_registers)?;
@@ -33,12 +83,204 @@ impl PublicInputs {
         let final_bytes = serde_json::to_vec(&trace.final_registers)?;
         let final_hash = Sha256::digest(&final_bytes);
 
+        // Commit to account changes exactly as the witness does
+        let account_commitment = Witness::from_trace(trace)?.account_commitment();
+
         Ok(Self {
             initial_value_hash: initial_hash.into(),
             final_value_hash: final_hash.into(),
+            account_commitment,
+            program_commitment: trace.meta.program_hash,
+            instruction_count: trace.instruction_count() as u64,
+            return_value: trace.final_registers.regs[0],
+            circuit_id: None,
+        })
+    }
+
+    /// Derive public inputs from the raw transaction a verifier holds
+    ///
+    /// The program commitment, which the instance binds, is recomputed from
+    /// `program`'s bytecode (see `bpf_tracer::program_hash`), and the
+    /// account commitment from the accounts (see
+    /// [`Self::account_commitment_from_transaction`]). The instruction count
+    /// and return value cannot be derived without re-executing, so they are
+    /// the outcome the verifier checks the proof for: the count the prover
+    /// claims, and say zero for a successful return. The register hashes are
+    /// left zero; like the account commitment, they are not in the instance.
+    #[cfg(feature = "trace")]
+    pub fn from_transaction(
+        program: &[u8],
+        accounts_before: &[AccountState],
+        accounts_after: &[AccountState],
+        instruction_count: u64,
+        return_value: u64,
+    ) -> Result<Self> {
+        Ok(Self {
+            initial_value_hash: [0; 32],
+            final_value_hash: [0; 32],
+            account_commitment: Self::account_commitment_from_transaction(
+                accounts_before,
+                accounts_after,
+            )?,
+            program_commitment: bpf_tracer::program_hash(program),
+            instruction_count,
+            return_value,
+            circuit_id: None,
         })
     }
 
+    /// Derive the account commitment of a transaction's account changes
+    ///
+    /// Lets a verifier holding the original accounts recompute
+    /// [`Self::account_commitment`] instead of trusting the prover's.
+    /// Accounts are paired by position, and only those that changed are
+    /// committed, in pubkey order, exactly as the tracer records them.
+    ///
+    /// The account commitment is not part of the instance, so a match shows
+    /// the prover reported these changes, not that the proof establishes them.
+    #[cfg(feature = "trace")]
+    pub fn account_commitment_from_transaction(
+        accounts_before: &[AccountState],
+        accounts_after: &[AccountState],
+    ) -> Result<[u8; 32]> {
+        anyhow::ensure!(
+            accounts_before.len() == accounts_after.len(),
+            "Transaction has {} accounts before and {} after",
+            accounts_before.len(),
+            accounts_after.len()
+        );
+
+        let mut trace = ExecutionTrace::new();
+        for (index, (before, after)) in accounts_before.iter().zip(accounts_after).enumerate() {
+            anyhow::ensure!(
+                before.pubkey == after.pubkey,
+                "Account {} is {} before and {} after",
+                index,
+                before.pubkey,
+                after.pubkey
+            );
+            if before != after {
+                trace.account_states.push(AccountStateChange::new(
+                    before.pubkey,
+                    before.clone(),
+                    after.clone(),
+                ));
+            }
+        }
+        trace.sort_account_states();
+
+        Ok(Witness::from_trace(&trace)?.account_commitment())
+    }
+
+    /// Number of field elements in [`Self::to_field_elements`]
+    pub fn num_field_elements(&self) -> usize {
+        Self::NUM_FIELD_ELEMENTS
+    }
+
+    /// Public inputs as circuit instance values
+    ///
+    /// The program commitment becomes two field elements (see
+    /// `witness::bytes_to_field_elements`), followed by the instruction
+    /// count and the return value as one element each. The other
+    /// commitments and `circuit_id` are not part of the instance.
+    pub fn to_field_elements<F: ScalarField>(&self) -> Vec<F> {
+        let mut elements = bytes_to_field_elements::<F>(&self.program_commitment).to_vec();
+        elements.push(F::from(self.instruction_count));
+        elements.push(F::from(self.return_value));
+        elements
+    }
+
+    /// Encode the instance values in a fixed binary layout
+    ///
+    /// Each element of [`Self::to_field_elements`] is written as a 32-byte
+    /// big-endian integer, in the same order, with no header or length
+    /// prefix: `32 * num_field_elements()` bytes in all. This is the form
+    /// on-chain verifiers take public inputs in. Fields outside the
+    /// instance are not encoded.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let halves = self
+            .program_commitment
+            .chunks_exact(16)
+            .map(|half| u128::from_le_bytes(half.try_into().unwrap()));
+        let values = halves.chain([self.instruction_count, self.return_value].map(u128::from));
+
+        let mut bytes = Vec::with_capacity(32 * self.num_field_elements());
+        for value in values {
+            bytes.extend_from_slice(&[0; 16]);
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Decode public inputs written by [`Self::to_compact_bytes`]
+    ///
+    /// Fails if the length does not match the layout. Fields outside the
+    /// instance decode as zeros.
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self> {
+        let expected = 32 * Self::NUM_FIELD_ELEMENTS;
+        anyhow::ensure!(
+            bytes.len() == expected,
+            "Compact public inputs are {} bytes, expected {}",
+            bytes.len(),
+            expected
+        );
+
+        let mut values = Vec::with_capacity(bytes.len() / 32);
+        for (index, element) in bytes.chunks_exact(32).enumerate() {
+            anyhow::ensure!(
+                element[..16].iter().all(|&b| b == 0),
+                "Compact public input element {} does not fit in 128 bits",
+                index
+            );
+            values.push(u128::from_be_bytes(element[16..].try_into().unwrap()));
+        }
+
+        let (commitments, scalars) = values.split_at(values.len() - 2);
+        let mut commitments = commitments.chunks_exact(2).map(|halves| {
+            let mut bytes = [0u8; 32];
+            bytes[..16].copy_from_slice(&halves[0].to_le_bytes());
+            bytes[16..].copy_from_slice(&halves[1].to_le_bytes());
+            bytes
+        });
+        let mut next = || commitments.next().unwrap();
+        let scalar = |index: usize, name: &str| {
+            u64::try_from(scalars[index])
+                .map_err(|_| anyhow::anyhow!("Compact public input {} does not fit in u64", name))
+        };
+
+        Ok(Self {
+            initial_value_hash: [0; 32],
+            final_value_hash: [0; 32],
+            account_commitment: [0; 32],
+            program_commitment: next(),
+            instruction_count: scalar(0, "instruction_count")?,
+            return_value: scalar(1, "return_value")?,
+            circuit_id: None,
+        })
+    }
+
+    /// Expose these public inputs in the builder's instance column
+    ///
+    /// `output` is what `CounterCircuit::synthesize` assigned. Its
+    /// instruction count and final r0 cells are exposed directly, binding
+    /// the public instruction count and return value to the proven
+    /// execution. The program commitment is loaded as a constant, so the
+    /// verifying key fixes it to the keygen trace's program.
+    #[cfg(feature = "prove")]
+    pub(crate) fn assign_instances<F: ScalarField>(
+        &self,
+        builder: &mut BaseCircuitBuilder<F>,
+        output: &SynthesisOutput<F>,
+    ) {
+        let mut cells: Vec<_> = bytes_to_field_elements::<F>(&self.program_commitment)
+            .into_iter()
+            .map(|value| builder.main(0).load_constant(value))
+            .collect();
+        cells.push(output.instruction_count);
+        cells.push(output.final_registers[0]);
+        builder.assigned_instances[0].extend(cells);
+    }
+
     /// Get initial value hash as hex string
     pub fn initial_hash_hex(&self) -> String {
         hex::encode(self.initial_value_hash)
@@ -48,4 +290,201 @@ impl PublicInputs {
     pub fn final_hash_hex(&self) -> String {
         hex::encode(self.final_value_hash)
     }
+
+    /// Get account commitment as hex string
+    pub fn account_commitment_hex(&self) -> String {
+        hex::encode(self.account_commitment)
+    }
+
+    /// Get program commitment as hex string
+    pub fn program_commitment_hex(&self) -> String {
+        hex::encode(self.program_commitment)
+    }
+}
+
+#[cfg(feature = "trace")]
+impl TryFrom<&ExecutionTrace> for PublicInputs {
+    type Error = anyhow::Error;
+
+    /// Same as [`PublicInputs::from_trace`]
+    fn try_from(trace: &ExecutionTrace) -> Result<Self> {
+        Self::from_trace(trace)
+    }
+}
+
+/// Same packing as `zk_circuits::witness::bytes_to_field_elements`, for
+/// verify-only builds without the circuits crate
+#[cfg(not(feature = "trace"))]
+fn bytes_to_field_elements<F: ScalarField>(bytes: &[u8; 32]) -> [F; 2] {
+    let two_pow_64 = F::from(1u64 << 32) * F::from(1u64 << 32);
+    let half = |half: &[u8]| {
+        let value = u128::from_le_bytes(half.try_into().unwrap());
+        F::from((value >> 64) as u64) * two_pow_64 + F::from(value as u64)
+    };
+    [half(&bytes[..16]), half(&bytes[16..])]
+}
+
+#[cfg(all(test, feature = "trace"))]
+mod tests {
+    use super::*;
+    use bpf_tracer::deterministic_pubkey;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use solana_pubkey::Pubkey;
+
+    #[test]
+    fn test_public_inputs_use_witness_account_commitment() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let before = AccountState::new(pubkey, 1000, vec![0; 8], owner, false, 0);
+        let after = AccountState::new(pubkey, 1000, vec![1; 8], owner, false, 0);
+
+        let mut trace = ExecutionTrace::new();
+        trace.account_states.push(AccountStateChange::new(pubkey, before, after));
+
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let witness = Witness::from_trace(&trace).unwrap();
+
+        assert_eq!(public_inputs.account_commitment, witness.account_commitment());
+    }
+
+    #[test]
+    fn test_transaction_commitment_matches_from_trace_for_transfer() {
+        let program_id = deterministic_pubkey("transfer_program");
+        let owner = program_id;
+        let account = |seed: &str, lamports: u64| {
+            AccountState::new(deterministic_pubkey(seed), lamports, vec![0; 8], owner, false, 0)
+        };
+
+        // Move 300 lamports from `from` to `to`; `fee_payer` is untouched
+        let accounts_before =
+            vec![account("to", 500), account("from", 1000), account("fee_payer", 50)];
+        let accounts_after =
+            vec![account("to", 800), account("from", 700), account("fee_payer", 50)];
+
+        // The trace the tracer would record: changed accounts, in pubkey order
+        let mut trace = ExecutionTrace::new();
+        for (before, after) in accounts_before.iter().zip(&accounts_after) {
+            if before != after {
+                trace.account_states.push(AccountStateChange::new(
+                    before.pubkey,
+                    before.clone(),
+                    after.clone(),
+                ));
+            }
+        }
+        trace.sort_account_states();
+
+        let proven = PublicInputs::from_trace(&trace).unwrap();
+        let derived =
+            PublicInputs::account_commitment_from_transaction(&accounts_before, &accounts_after)
+                .unwrap();
+
+        assert_eq!(derived, proven.account_commitment);
+
+        // A verifier with different accounts derives a different commitment
+        let mut tampered = accounts_after.clone();
+        tampered[0].lamports += 1;
+        let tampered =
+            PublicInputs::account_commitment_from_transaction(&accounts_before, &tampered).unwrap();
+        assert_ne!(tampered, proven.account_commitment);
+    }
+
+    #[test]
+    fn test_transaction_commitment_rejects_mismatched_accounts() {
+        let owner = Pubkey::new_unique();
+        let before = vec![AccountState::new(Pubkey::new_unique(), 1, vec![], owner, false, 0)];
+        let after = vec![AccountState::new(Pubkey::new_unique(), 1, vec![], owner, false, 0)];
+
+        assert!(PublicInputs::account_commitment_from_transaction(&before, &after).is_err());
+        assert!(PublicInputs::account_commitment_from_transaction(&before, &[]).is_err());
+    }
+
+    #[test]
+    fn test_try_from_matches_from_trace() {
+        let mut trace = ExecutionTrace::new();
+        trace.final_registers.regs[0] = 30;
+
+        let named = PublicInputs::from_trace(&trace).unwrap();
+        let converted: PublicInputs = (&trace).try_into().unwrap();
+
+        assert_eq!(converted.to_field_elements::<Fr>(), named.to_field_elements::<Fr>());
+        assert_eq!(converted.circuit_id, named.circuit_id);
+    }
+
+    #[test]
+    fn test_return_value_is_last_field_element() {
+        let mut trace = ExecutionTrace::new();
+        trace.final_registers.regs[0] = 42;
+
+        let elements = PublicInputs::from_trace(&trace).unwrap().to_field_elements::<Fr>();
+
+        assert_eq!(elements.last(), Some(&Fr::from(42u64)));
+    }
+
+    #[test]
+    fn test_instruction_count_precedes_return_value() {
+        #[rustfmt::skip]
+        let bytecode = [
+            0xb7, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r0, 42
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let elements = public_inputs.to_field_elements::<Fr>();
+
+        assert_eq!(public_inputs.instruction_count, 2);
+        assert_eq!(elements[elements.len() - 2..], [Fr::from(2u64), Fr::from(42u64)]);
+    }
+
+    #[test]
+    fn test_to_field_elements_count() {
+        let mut trace = ExecutionTrace::new();
+        trace.meta.program_hash = [7; 32];
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let elements = public_inputs.to_field_elements::<Fr>();
+
+        assert_eq!(elements.len(), PublicInputs::NUM_FIELD_ELEMENTS);
+        assert_eq!(elements[0..2], bytes_to_field_elements::<Fr>(&[7; 32]));
+    }
+
+    #[test]
+    fn test_unproven_commitments_stay_out_of_instance() {
+        let mut trace = ExecutionTrace::new();
+        trace.final_registers.regs[0] = 42;
+
+        let plain = PublicInputs::from_trace(&trace).unwrap();
+        let mut committed = plain.clone();
+        committed.account_commitment = [1; 32];
+        committed.initial_value_hash = [2; 32];
+
+        assert_eq!(committed.num_field_elements(), PublicInputs::NUM_FIELD_ELEMENTS);
+        assert_eq!(committed.to_field_elements::<Fr>(), plain.to_field_elements::<Fr>());
+    }
+
+    #[test]
+    fn test_compact_bytes_roundtrip() {
+        let mut trace = ExecutionTrace::new();
+        trace.meta.program_hash = [7; 32];
+        trace.final_registers.regs[0] = 42;
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+
+        let bytes = public_inputs.to_compact_bytes();
+        assert_eq!(bytes.len(), 32 * PublicInputs::NUM_FIELD_ELEMENTS);
+
+        // Each chunk is the big-endian encoding of the instance element
+        let elements = public_inputs.to_field_elements::<Fr>();
+        for (chunk, element) in bytes.chunks_exact(32).zip(&elements) {
+            let mut be = element.to_bytes_le();
+            be.reverse();
+            assert_eq!(chunk, be.as_slice());
+        }
+
+        let decoded = PublicInputs::from_compact_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_field_elements::<Fr>(), elements);
+        assert_eq!(decoded.program_commitment, public_inputs.program_commitment);
+        assert_eq!(decoded.return_value, 42);
+
+        assert!(PublicInputs::from_compact_bytes(&bytes[1..]).is_err());
+    }
 }
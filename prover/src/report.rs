@@ -0,0 +1,177 @@
+//! Execution Report
+//!
+//! A proof says that some execution satisfied the circuit; an auditor also
+//! wants to read what that execution did. An [`ExecutionReport`] summarizes
+//! a trace as JSON (instructions, register changes, account changes and
+//! the public inputs) to be kept alongside the proof it explains.
+
+use bpf_tracer::{AccountStateChange, DecodedInsn, ExecutionTrace, ExitKind, InstructionTrace};
+use serde::{Deserialize, Serialize};
+use crate::{PublicInputs, Result};
+
+/// Human-readable summary of a proven execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    /// sBPF version the program was loaded as
+    pub sbpf_version: String,
+    /// SHA-256 of the program bytes, hex-encoded
+    pub program_hash: String,
+    /// How execution terminated
+    pub exit_kind: ExitKind,
+    /// Executed instructions, in execution order
+    pub instructions: Vec<InstructionReport>,
+    /// Registers r0-r10 at program exit
+    pub final_registers: Vec<u64>,
+    /// Accounts the program changed, in pubkey order
+    pub account_changes: Vec<AccountChangeReport>,
+    /// Compute units consumed
+    ///
+    /// The VM charges one unit per executed instruction, and the tracer's
+    /// syscalls charge none, so this is the instruction count.
+    pub compute_units: u64,
+    /// Public inputs the proof was generated for
+    pub public_inputs: PublicInputs,
+}
+
+/// One executed instruction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionReport {
+    /// Position in the trace
+    pub index: usize,
+    /// Program counter (instruction index in the text section)
+    pub pc: u64,
+    /// Assembly text, e.g. `add64 r0, 2`
+    pub assembly: String,
+    /// Registers r0-r10 the instruction changed
+    pub register_deltas: Vec<RegisterDelta>,
+}
+
+/// A register an instruction changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterDelta {
+    /// Register index (0-10)
+    pub register: usize,
+    /// Value before the instruction
+    pub before: u64,
+    /// Value after the instruction
+    pub after: u64,
+}
+
+/// An account the program changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountChangeReport {
+    /// Account address, base58-encoded
+    pub pubkey: String,
+    /// Balance before execution
+    pub lamports_before: u64,
+    /// Balance after execution
+    pub lamports_after: u64,
+    /// Data length before execution
+    pub data_len_before: usize,
+    /// Data length after execution
+    pub data_len_after: usize,
+    /// Bytes that differ, counting any growth or shrinkage as changed
+    pub data_bytes_changed: usize,
+}
+
+impl ExecutionReport {
+    /// Summarize `trace`, proven with `public_inputs`
+    pub fn new(trace: &ExecutionTrace, public_inputs: &PublicInputs) -> Self {
+        Self {
+            sbpf_version: trace.meta.sbpf_version.clone(),
+            program_hash: hex::encode(trace.meta.program_hash),
+            exit_kind: trace.exit_kind,
+            instructions: trace
+                .instructions
+                .iter()
+                .enumerate()
+                .map(|(index, instr)| InstructionReport::new(trace, index, instr))
+                .collect(),
+            final_registers: trace.final_registers.regs[..11].to_vec(),
+            account_changes: trace.account_states.iter().map(AccountChangeReport::new).collect(),
+            compute_units: trace.instruction_count() as u64,
+            public_inputs: public_inputs.clone(),
+        }
+    }
+
+    /// Program return value (final r0)
+    pub fn return_value(&self) -> u64 {
+        self.final_registers[0]
+    }
+
+    /// Serialize the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserialize a report from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl InstructionReport {
+    fn new(trace: &ExecutionTrace, index: usize, instr: &InstructionTrace) -> Self {
+        let bytes = trace.instruction_bytes(instr);
+        let assembly = match DecodedInsn::decode(bytes) {
+            Ok(insn) => insn.disassemble(),
+            Err(_) => format!("invalid {}", hex::encode(bytes)),
+        };
+        let before = &instr.registers_before.regs[..11];
+        let after = &instr.registers_after.regs[..11];
+        let register_deltas = before
+            .iter()
+            .zip(after)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(register, (&before, &after))| RegisterDelta { register, before, after })
+            .collect();
+        Self { index, pc: instr.pc, assembly, register_deltas }
+    }
+}
+
+impl AccountChangeReport {
+    fn new(change: &AccountStateChange) -> Self {
+        let (before, after) = (&change.before.data, &change.after.data);
+        let differing = before.iter().zip(after).filter(|(a, b)| a != b).count();
+        Self {
+            pubkey: change.pubkey.to_string(),
+            lamports_before: change.before.lamports,
+            lamports_after: change.after.lamports,
+            data_len_before: before.len(),
+            data_len_after: after.len(),
+            data_bytes_changed: differing + before.len().abs_diff(after.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bpf_tracer::InsnBuilder;
+
+    #[test]
+    fn test_report_describes_trace() {
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 40),
+            InsnBuilder::add64_imm(0, 2),
+            InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let report = ExecutionReport::new(&trace, &public_inputs);
+
+        assert_eq!(report.compute_units, 3);
+        assert_eq!(report.return_value(), 42);
+        assert_eq!(report.instructions[1].assembly, "add64 r0, 2");
+        assert_eq!(
+            report.instructions[1].register_deltas,
+            vec![RegisterDelta { register: 0, before: 40, after: 42 }]
+        );
+        assert!(report.instructions[2].register_deltas.is_empty());
+
+        let decoded = ExecutionReport::from_json(&report.to_json().unwrap()).unwrap();
+        assert_eq!(decoded.instructions, report.instructions);
+        assert_eq!(decoded.public_inputs.return_value, 42);
+    }
+}
@@ -0,0 +1,314 @@
+//! Proof Verification
+//!
+//! The verify-only surface of the prover. Nothing here touches the file
+//! system or OS randomness, so it builds for `wasm32-unknown-unknown` with
+//! default features off (see the `wasm` feature for the browser entry point).
+//! [`BatchVerifier`] verifies many proofs against one key.
+
+use anyhow::Context;
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{verify_proof as halo2_verify_proof, Error as PlonkError, VerifyingKey},
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::VerifierSHPLONK,
+            strategy::{AccumulatorStrategy, SingleStrategy},
+        },
+        VerificationStrategy,
+    },
+    transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer},
+};
+#[cfg(feature = "prove")]
+use crate::KeyPair;
+use crate::{Proof, ProofBundle, ProofHeader, PublicInputs, Result, VkConstants};
+
+/// Why a proof was accepted or rejected
+///
+/// Returned by [`verify_proof_detailed`] and `verify_execution_detailed`,
+/// where a bare `false` would not say which check failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    /// The proof is valid for the public inputs
+    Valid,
+    /// The proof was read, but the pairing check failed: it does not prove
+    /// these public inputs under this key
+    InvalidPairing,
+    /// Public input `index`, in the order of
+    /// [`PublicInputs::to_field_elements`], is not the value the verifier
+    /// expected; checked before the proof is read
+    PublicInputMismatch {
+        /// Index of the first mismatching field element
+        index: usize,
+    },
+    /// The proof bytes could not be read as a proof (e.g. truncated, or a
+    /// commitment that is not a curve point)
+    MalformedProof,
+}
+
+impl VerifyOutcome {
+    /// Whether the proof was accepted
+    pub fn is_valid(&self) -> bool {
+        *self == Self::Valid
+    }
+}
+
+/// Verify a ZK proof with public inputs
+///
+/// Checks that a proof is valid for the given public inputs (see
+/// [`PublicInputs::to_field_elements`]).
+///
+/// Returns an error, rather than `Ok(false)`, if the number of public
+/// inputs does not match the instance layout.
+pub fn verify_proof(
+    proof: &Proof,
+    vk: &VerifyingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    public_inputs: &PublicInputs,
+) -> Result<bool> {
+    Ok(verify_proof_detailed(proof, vk, params, public_inputs)?.is_valid())
+}
+
+/// Verify a ZK proof with public inputs, reporting why it was rejected
+///
+/// Like [`verify_proof`], but tells a malformed proof apart from one that
+/// fails the pairing check. Errors in the same cases.
+pub fn verify_proof_detailed(
+    proof: &Proof,
+    vk: &VerifyingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    public_inputs: &PublicInputs,
+) -> Result<VerifyOutcome> {
+    let instances = public_inputs.to_field_elements::<Fr>();
+    verify_instances(proof, vk, params, &instances, PublicInputs::NUM_FIELD_ELEMENTS)
+}
+
+/// Verify a proof from its serialized parts
+///
+/// `vk` is a [`VerifierKey`] encoding (see `KeyPair::export_verifier_key`),
+/// `proof` starts with a [`ProofHeader`] (see `KeyPair::serialize_proof`)
+/// and `public_inputs` is JSON, as in a [`ProofBundle`](crate::ProofBundle).
+/// A header from another crate version, `k` or circuit is an error, raised
+/// before the pairing check.
+pub fn verify_from_bytes(vk: &[u8], proof: &[u8], public_inputs: &[u8]) -> Result<bool> {
+    let key = VerifierKey::from_bytes(vk)?;
+    let (header, proof) = ProofHeader::split(proof)?;
+    header.check(&key.vk)?;
+    let public_inputs: PublicInputs =
+        serde_json::from_slice(public_inputs).context("Failed to deserialize public inputs")?;
+    verify_proof(&proof.to_vec(), &key.vk, &key.params, &public_inputs)
+}
+
+/// Number of public field elements a verifying key expects
+///
+/// The key records how many instance columns the circuit has, not their
+/// length; counter circuits expose all public inputs in one column, holding
+/// `per_column` elements (see [`PublicInputs::NUM_FIELD_ELEMENTS`]).
+fn expected_public_input_count(vk: &VerifyingKey<G1Affine>, per_column: usize) -> usize {
+    vk.cs().num_instance_columns() * per_column
+}
+
+/// Fail if `instances` is not as long as `vk` expects
+fn check_public_input_count(
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[Fr],
+    per_column: usize,
+) -> Result<()> {
+    let expected = expected_public_input_count(vk, per_column);
+    if instances.len() != expected {
+        anyhow::bail!(
+            "public input count mismatch: expected {}, got {}",
+            expected,
+            instances.len()
+        );
+    }
+    Ok(())
+}
+
+/// Verify a proof against raw instance values
+pub(crate) fn verify_instances(
+    proof: &Proof,
+    vk: &VerifyingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    instances: &[Fr],
+    per_column: usize,
+) -> Result<VerifyOutcome> {
+    check_public_input_count(vk, instances, per_column)?;
+
+    tracing::info!("Verifying proof ({} bytes)", proof.len());
+
+    // Get verifier params
+    let verifier_params = params.verifier_params();
+    let strategy = SingleStrategy::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+
+    // Verify using SHPLONK and Blake2b
+    let result = halo2_verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(verifier_params, vk, strategy, &[&[instances]], &mut transcript);
+
+    match result {
+        Ok(_) => {
+            tracing::info!("Proof verification succeeded");
+            Ok(VerifyOutcome::Valid)
+        }
+        // Reading a commitment or evaluation off the transcript failed
+        Err(PlonkError::Transcript(e)) => {
+            tracing::warn!("Proof is malformed: {}", e);
+            Ok(VerifyOutcome::MalformedProof)
+        }
+        Err(e) => {
+            tracing::warn!("Proof verification failed: {:?}", e);
+            Ok(VerifyOutcome::InvalidPairing)
+        }
+    }
+}
+
+/// Verifier for many proofs against one verifying key
+///
+/// Holds the key and its parameters, so a service verifying a stream of
+/// proofs loads them once. [`Self::verify_all`] checks a whole batch with
+/// a single multi-pairing. Verifies ordinary proofs, as [`verify_proof`]
+/// does.
+#[derive(Debug)]
+pub struct BatchVerifier {
+    vk: VerifyingKey<G1Affine>,
+    params: ParamsKZG<Bn256>,
+}
+
+impl BatchVerifier {
+    /// Verifier for a key pair's verifying key
+    ///
+    /// Copies the key and parameters, so the key pair can be dropped.
+    #[cfg(feature = "prove")]
+    pub fn new(keypair: &KeyPair) -> Self {
+        Self { vk: keypair.vk.clone(), params: keypair.params.clone() }
+    }
+
+    /// Verifier for a decoded [`VerifierKey`]
+    pub fn from_verifier_key(key: VerifierKey) -> Self {
+        Self { vk: key.vk, params: key.params }
+    }
+
+    /// Verify one proof bundle (see [`verify_proof`])
+    pub fn verify(&self, bundle: &ProofBundle) -> Result<bool> {
+        verify_proof(&bundle.proof, &self.vk, &self.params, &bundle.public_inputs)
+    }
+
+    /// Verify several proof bundles, returning whether each is valid
+    ///
+    /// The proofs are accumulated into one KZG multi-pairing, so a batch of
+    /// valid proofs costs a single pairing check. Only if that check fails
+    /// are the proofs verified one by one to find the invalid ones.
+    pub fn verify_all(&self, bundles: &[ProofBundle]) -> Result<Vec<bool>> {
+        if self.verify_accumulated(bundles)? {
+            return Ok(vec![true; bundles.len()]);
+        }
+        tracing::warn!("Batch of {} proofs failed, verifying individually", bundles.len());
+        bundles.iter().map(|bundle| self.verify(bundle)).collect()
+    }
+
+    /// Whether every proof passes one accumulated pairing check
+    fn verify_accumulated(&self, bundles: &[ProofBundle]) -> Result<bool> {
+        tracing::info!("Verifying batch of {} proofs", bundles.len());
+
+        let mut strategy = AccumulatorStrategy::new(&self.params);
+        for bundle in bundles {
+            let instances = bundle.public_inputs.to_field_elements::<Fr>();
+            check_public_input_count(&self.vk, &instances, PublicInputs::NUM_FIELD_ELEMENTS)?;
+
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&bundle.proof[..]);
+            let result = halo2_verify_proof::<
+                KZGCommitmentScheme<Bn256>,
+                VerifierSHPLONK<'_, Bn256>,
+                Challenge255<G1Affine>,
+                Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+                AccumulatorStrategy<'_, Bn256>,
+            >(
+                self.params.verifier_params(),
+                &self.vk,
+                strategy,
+                &[&[&instances[..]]],
+                &mut transcript,
+            );
+            strategy = match result {
+                Ok(strategy) => strategy,
+                Err(e) => {
+                    tracing::warn!("Batch proof verification failed: {:?}", e);
+                    return Ok(false);
+                }
+            };
+        }
+
+        Ok(VerificationStrategy::<
+            '_,
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+        >::finalize(strategy))
+    }
+}
+
+/// A verifying key with the KZG parameters it verifies against
+///
+/// Encoded as a little-endian `u32` length, the [`VkConstants`] JSON, then
+/// the parameters in halo2's raw encoding. Self-contained, so a verifier
+/// needs no key cache.
+#[derive(Debug)]
+pub struct VerifierKey {
+    /// The verifying key
+    pub vk: VerifyingKey<G1Affine>,
+    /// KZG parameters the key was generated with
+    pub params: ParamsKZG<Bn256>,
+}
+
+impl VerifierKey {
+    /// Encode verifying key constants and parameters
+    pub fn encode(constants: &VkConstants, params: &ParamsKZG<Bn256>) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(constants)
+            .context("Failed to serialize verifying key constants")?;
+        let mut bytes = (json.len() as u32).to_le_bytes().to_vec();
+        bytes.extend(json);
+        params
+            .write(&mut bytes)
+            .context("Failed to serialize verifier parameters")?;
+        Ok(bytes)
+    }
+
+    /// Decode a key written by [`Self::encode`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (len, rest) = bytes
+            .split_first_chunk::<4>()
+            .context("Verifier key is truncated")?;
+        let len = u32::from_le_bytes(*len) as usize;
+        anyhow::ensure!(rest.len() >= len, "Verifier key is truncated");
+        let (json, mut params) = rest.split_at(len);
+
+        let vk = VkConstants::from_reader(json)?.to_vk()?;
+        let params = ParamsKZG::<Bn256>::read(&mut params)
+            .context("Failed to deserialize verifier parameters")?;
+        anyhow::ensure!(
+            params.k() == vk.get_domain().k(),
+            "Verifier parameters are for k={}, key is for k={}",
+            params.k(),
+            vk.get_domain().k()
+        );
+
+        Ok(Self { vk, params })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verifier_key_rejects_truncated_bytes() {
+        assert!(VerifierKey::from_bytes(&[]).is_err());
+        assert!(VerifierKey::from_bytes(&[0xff, 0, 0, 0, b'{']).is_err());
+    }
+}
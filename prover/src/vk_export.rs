@@ -0,0 +1,267 @@
+//! Verifying Key Export
+//!
+//! Emits a verifying key as constants an on-chain verifier can embed.
+
+use anyhow::Context;
+use halo2_base::{
+    gates::circuit::{builder::BaseCircuitBuilder, BaseCircuitParams},
+    halo2_proofs::{
+        halo2curves::{
+            bn256::{Fq, Fr, G1Affine},
+            ff::PrimeField,
+        },
+        plonk::VerifyingKey,
+        SerdeFormat,
+    },
+};
+#[cfg(feature = "prove")]
+use halo2_base::halo2_proofs::halo2curves::bn256::{Fq2, G2Affine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+#[cfg(feature = "prove")]
+use std::io::Write;
+use crate::Result;
+#[cfg(feature = "prove")]
+use crate::{KeyPair, ProofHeader, VerifierKey};
+
+/// A G1 point as big-endian hex coordinates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct G1Point {
+    /// x coordinate (0x-prefixed, 32 bytes big-endian)
+    pub x: String,
+    /// y coordinate (0x-prefixed, 32 bytes big-endian)
+    pub y: String,
+}
+
+/// A G2 point as big-endian hex coordinates over Fq2
+///
+/// Each coordinate is `[c0, c1]` for the element `c0 + c1 * u`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct G2Point {
+    /// x coordinate
+    pub x: [String; 2],
+    /// y coordinate
+    pub y: [String; 2],
+}
+
+/// Verifying key constants for embedding in a verifier program
+///
+/// The curve points are what a verifier checks commitments against.
+/// `vk_bytes` carries the full key in halo2's raw encoding; together with
+/// `circuit_params` it rebuilds the [`VerifyingKey`] (see [`Self::to_vk`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VkConstants {
+    /// Circuit size parameter (circuit has 2^k rows)
+    pub k: u32,
+    /// Circuit configuration the key was generated for
+    pub circuit_params: BaseCircuitParams,
+    /// Hash of the verifying key absorbed into the transcript
+    pub transcript_repr: String,
+    /// Commitments to the fixed columns
+    pub fixed_commitments: Vec<G1Point>,
+    /// Commitments to the permutation columns
+    pub permutation_commitments: Vec<G1Point>,
+    /// KZG G2 generator
+    pub g2: G2Point,
+    /// KZG G2 generator times the toxic-waste secret
+    pub s_g2: G2Point,
+    /// The verifying key in halo2's raw byte encoding (0x-prefixed hex)
+    pub vk_bytes: String,
+    /// [`vk_fingerprint`] of the key, for auditing which key is deployed
+    ///
+    /// Empty in constants exported before fingerprints were recorded.
+    #[serde(default)]
+    pub fingerprint: String,
+}
+
+impl VkConstants {
+    /// Read constants previously written by `KeyPair::export_vk_constants`
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        serde_json::from_reader(reader).context("Failed to deserialize verifying key constants")
+    }
+
+    /// Rebuild the verifying key
+    ///
+    /// Fails if the embedded key does not match the exported points, so the
+    /// constants cannot silently drift from the key they describe.
+    pub fn to_vk(&self) -> Result<VerifyingKey<G1Affine>> {
+        let bytes = decode_hex(&self.vk_bytes)?;
+        let vk = VerifyingKey::<G1Affine>::from_bytes::<BaseCircuitBuilder<Fr>>(
+            &bytes,
+            SerdeFormat::RawBytes,
+            self.circuit_params.clone(),
+        )
+        .context("Failed to deserialize verifying key")?;
+
+        if vk.get_domain().k() != self.k
+            || (!self.fingerprint.is_empty() && vk_fingerprint(&vk) != self.fingerprint)
+            || fq_hex(&vk.transcript_repr()) != self.transcript_repr
+            || g1_points(vk.fixed_commitments()) != self.fixed_commitments
+            || g1_points(vk.permutation().commitments()) != self.permutation_commitments
+        {
+            anyhow::bail!("Verifying key bytes do not match the exported constants");
+        }
+
+        Ok(vk)
+    }
+}
+
+#[cfg(feature = "prove")]
+impl KeyPair {
+    /// Export the verifying key as JSON constants
+    ///
+    /// Writes a [`VkConstants`] with every curve point as big-endian hex,
+    /// the layout EVM and Solana pairing precompiles expect.
+    pub fn export_vk_constants(&self, writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(writer, &self.vk_constants())
+            .context("Failed to serialize verifying key constants")
+    }
+
+    /// Export a self-contained key for `verify_from_bytes`
+    ///
+    /// See [`VerifierKey`] for the encoding.
+    pub fn export_verifier_key(&self) -> Result<Vec<u8>> {
+        VerifierKey::encode(&self.vk_constants(), &self.params)
+    }
+
+    /// Serialize a proof for `verify_from_bytes`, prefixed with its
+    /// [`ProofHeader`]
+    pub fn serialize_proof(&self, proof: &[u8]) -> Vec<u8> {
+        ProofHeader::for_vk(&self.vk).prepend(proof)
+    }
+
+    /// Fingerprint of the verifying key (see [`vk_fingerprint`])
+    pub fn vk_fingerprint(&self) -> String {
+        vk_fingerprint(&self.vk)
+    }
+
+    fn vk_constants(&self) -> VkConstants {
+        VkConstants {
+            k: self.vk.get_domain().k(),
+            circuit_params: self.circuit_params.clone(),
+            transcript_repr: fq_hex(&self.vk.transcript_repr()),
+            fixed_commitments: g1_points(self.vk.fixed_commitments()),
+            permutation_commitments: g1_points(self.vk.permutation().commitments()),
+            g2: g2_point(&self.params.g2()),
+            s_g2: g2_point(&self.params.s_g2()),
+            vk_bytes: format!("0x{}", hex::encode(self.vk.to_bytes(SerdeFormat::RawBytes))),
+            fingerprint: self.vk_fingerprint(),
+        }
+    }
+}
+
+/// Stable fingerprint of a verifying key
+///
+/// Hex SHA-256 of the key's raw byte encoding. Prover and verifier
+/// deployments agree on the key exactly when their fingerprints match.
+pub fn vk_fingerprint(vk: &VerifyingKey<G1Affine>) -> String {
+    hex::encode(vk_digest(vk))
+}
+
+/// SHA-256 of the key's raw byte encoding, the bytes behind [`vk_fingerprint`]
+pub(crate) fn vk_digest(vk: &VerifyingKey<G1Affine>) -> [u8; 32] {
+    Sha256::digest(vk.to_bytes(SerdeFormat::RawBytes)).into()
+}
+
+/// Encode a field element as 0x-prefixed big-endian hex
+fn fq_hex<F: PrimeField<Repr = [u8; 32]>>(value: &F) -> String {
+    // Field representations are little-endian
+    let mut bytes = value.to_repr();
+    bytes.reverse();
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn g1_points(points: &[G1Affine]) -> Vec<G1Point> {
+    points
+        .iter()
+        .map(|point| G1Point { x: fq_hex::<Fq>(&point.x), y: fq_hex::<Fq>(&point.y) })
+        .collect()
+}
+
+#[cfg(feature = "prove")]
+fn g2_point(point: &G2Affine) -> G2Point {
+    let fq2 = |value: &Fq2| [fq_hex(&value.c0), fq_hex(&value.c1)];
+    G2Point { x: fq2(&point.x), y: fq2(&point.y) }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(digits).with_context(|| format!("Invalid hex string: {}", value))
+}
+
+#[cfg(all(test, feature = "prove"))]
+mod tests {
+    use super::*;
+    use crate::{prove_circuit, verify_proof, KeygenConfig, PublicInputs};
+    use bpf_tracer::ExecutionTrace;
+    use std::env;
+    use zk_circuits::CounterCircuit;
+
+    #[test]
+    fn test_fq_hex_is_big_endian() {
+        assert_eq!(
+            fq_hex(&Fq::from(0x0102u64)),
+            format!("0x{}0102", "00".repeat(30))
+        );
+    }
+
+    /// Test that exported constants rebuild a key that verifies a proof
+    #[test]
+    fn test_vk_constants_roundtrip() {
+        let test_cache = env::temp_dir().join(format!("sbpf_zkvm_vk_export_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+        let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate(&config).expect("Key generation should succeed");
+
+        let mut exported = Vec::new();
+        keypair.export_vk_constants(&mut exported).unwrap();
+        let constants = VkConstants::from_reader(&exported[..]).unwrap();
+        assert_eq!(constants.k, config.k);
+        assert_eq!(constants.fixed_commitments.len(), keypair.vk.fixed_commitments().len());
+
+        let trace = ExecutionTrace::new();
+        let public_inputs = PublicInputs::from_trace(&trace).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace, config.chunk_size);
+        let proof = prove_circuit(
+            &circuit,
+            &public_inputs,
+            &keypair.pk,
+            &keypair.params,
+            config.k,
+            config.lookup_bits,
+            &keypair.break_points,
+        )
+        .expect("Proof generation should succeed");
+
+        let vk = constants.to_vk().expect("Constants should rebuild the key");
+        assert!(verify_proof(&proof, &vk, &keypair.params, &public_inputs).unwrap());
+
+        let mut tampered = constants;
+        tampered.transcript_repr = fq_hex(&Fr::from(0u64));
+        assert!(tampered.to_vk().is_err());
+    }
+
+    #[test]
+    fn test_vk_fingerprint_is_stable() {
+        let test_cache =
+            env::temp_dir().join(format!("sbpf_zkvm_vk_fingerprint_{}", std::process::id()));
+        let config = KeygenConfig::new(10, &test_cache, 8).with_chunk_size(4);
+        let keypair = KeyPair::generate(&config).expect("Key generation should succeed");
+        let fingerprint = keypair.vk_fingerprint();
+        assert_eq!(fingerprint.len(), 64);
+
+        keypair.save_to_cache(&config).unwrap();
+        let loaded = KeyPair::load_from_cache(&config).unwrap();
+        assert_eq!(loaded.vk_fingerprint(), fingerprint);
+
+        let mut exported = Vec::new();
+        loaded.export_vk_constants(&mut exported).unwrap();
+        assert_eq!(VkConstants::from_reader(&exported[..]).unwrap().fingerprint, fingerprint);
+
+        let other_config = config.clone().with_chunk_size(8);
+        let other = KeyPair::generate(&other_config).expect("Key generation should succeed");
+        assert_ne!(other.vk_fingerprint(), fingerprint);
+
+        std::fs::remove_dir_all(&test_cache).unwrap();
+    }
+}
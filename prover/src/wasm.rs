@@ -0,0 +1,23 @@
+//! WebAssembly Bindings
+//!
+//! Browser entry point for proof verification. Build the library as a
+//! `cdylib` for wasm-bindgen with:
+//!
+//! ```text
+//! cargo rustc -p prover --no-default-features --features wasm \
+//!     --target wasm32-unknown-unknown --crate-type cdylib --release
+//! ```
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Verify a proof from its serialized parts
+///
+/// Arguments are as for [`verify_from_bytes`](crate::verify_from_bytes).
+/// Malformed input counts as a failed verification.
+#[wasm_bindgen]
+pub fn wasm_verify(vk: &[u8], proof: &[u8], inputs: &[u8]) -> bool {
+    crate::verify_from_bytes(vk, proof, inputs).unwrap_or_else(|e| {
+        tracing::warn!("Proof verification failed: {}", e);
+        false
+    })
+}
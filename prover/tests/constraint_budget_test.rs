@@ -0,0 +1,54 @@
+//! Constraint-count regression guard
+//!
+//! Chip changes can silently inflate the circuit. These tests synthesize
+//! the keygen circuit for a fixed reference program and fail if its size
+//! leaves the budget below. If a change legitimately needs more cells,
+//! raise the budget in the same change and say why.
+
+use bpf_tracer::trace_program;
+use prover::{circuit_stats, KeygenConfig};
+use std::env;
+
+/// mov64 r0, 10; mov64 r1, 20; add64 r0, r1; exit
+#[rustfmt::skip]
+const ARITHMETIC_PROGRAM: &[u8] = &[
+    0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+    0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+    0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+    0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+];
+
+/// Advice-cell budget for the arithmetic program
+///
+/// Each instruction costs roughly 85 cells: 11 register loads, the 8-bit
+/// opcode decomposition that pins the dispatched chip, the PC sequence
+/// check, and a few cells for the chip itself. The initial/final register loads and the 8 public
+/// commitment elements add about 30 more. The budget leaves headroom above that.
+const ARITHMETIC_ADVICE_BUDGET: usize = 512;
+
+/// None of the arithmetic program's chips need range-check lookups
+const ARITHMETIC_LOOKUP_BUDGET: usize = 0;
+
+#[test]
+fn test_arithmetic_program_within_budget() {
+    let trace = trace_program(ARITHMETIC_PROGRAM).expect("Tracing should succeed");
+    assert_eq!(trace.instruction_count(), 4);
+
+    let config = KeygenConfig::new(10, env::temp_dir().join("sbpf_zkvm_budget"), 8)
+        .with_chunk_size(4);
+    let stats = circuit_stats(&config, &trace).expect("Synthesis should succeed");
+
+    assert!(
+        stats.advice_cells <= ARITHMETIC_ADVICE_BUDGET,
+        "arithmetic program uses {} advice cells, budget is {}",
+        stats.advice_cells,
+        ARITHMETIC_ADVICE_BUDGET
+    );
+    assert!(
+        stats.lookup_advice_cells <= ARITHMETIC_LOOKUP_BUDGET,
+        "arithmetic program uses {} lookup cells, budget is {}",
+        stats.lookup_advice_cells,
+        ARITHMETIC_LOOKUP_BUDGET
+    );
+    assert_eq!(stats.advice_columns, 1, "should fit in one advice column at k=10");
+}
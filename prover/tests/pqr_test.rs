@@ -0,0 +1,56 @@
+//! Integration tests for PQR-class division instructions
+//!
+//! The PQR instruction class (SIMD-0174) replaces the legacy mul/div/mod
+//! opcodes from SBPFv2 on. These tests trace and prove a remainder
+//! instruction and check that legacy SBPFv0 bytecode cannot use it.
+
+use bpf_tracer::{
+    decode::opcodes, trace_program, trace_program_with_options, SBPFVersion, TraceOptions,
+};
+use prover::{prove_execution, verify_execution, KeygenConfig};
+use std::env;
+
+/// mov64 r0, 100; mov64 r1, 7; urem64 r0, r1; exit
+fn remainder_program() -> Vec<u8> {
+    #[rustfmt::skip]
+    let bytecode = vec![
+        0xb7, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00,               // mov64 r0, 100
+        0xb7, 0x01, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00,               // mov64 r1, 7
+        opcodes::UREM64_REG, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // urem64 r0, r1
+        0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,               // exit
+    ];
+    bytecode
+}
+
+#[test]
+fn test_prove_pqr_remainder() {
+    let _ = tracing_subscriber::fmt::try_init();
+
+    let trace = trace_program(&remainder_program()).expect("Tracing should succeed");
+    trace.assert_register(0, 100 % 7).unwrap();
+    trace.assert_register(1, 7).unwrap();
+
+    let test_cache = env::temp_dir().join(format!(
+        "sbpf_zkvm_pqr_{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    ));
+    let config = KeygenConfig::new(10, test_cache, 8).with_chunk_size(10);
+    let verifier_config = config.clone().with_circuit_of(&trace);
+
+    let (proof, public_inputs) = prove_execution(trace, &config)
+        .expect("Proof generation should succeed");
+
+    let is_valid = verify_execution(&proof, &public_inputs, &verifier_config, None)
+        .expect("Proof verification should not error");
+    assert!(is_valid, "Proof should be valid");
+}
+
+#[test]
+fn test_pqr_rejected_before_v2() {
+    let options = TraceOptions::default().with_sbpf_version(SBPFVersion::V0);
+    let result = trace_program_with_options(&remainder_program(), &options);
+    assert!(result.is_err(), "SBPFv0 should reject PQR opcodes");
+}
@@ -0,0 +1,129 @@
+//! Per-opcode chip synthesis cost
+//!
+//! Prints the advice cells each chip assigns for one instruction, relative
+//! to `add64 dst, imm`, then times synthesis of each chip with criterion.
+//! Opcodes without a chip yet are listed as such.
+//!
+//! Run with `cargo bench -p zk-circuits --bench chip_synthesis`.
+
+use bpf_tracer::{decode::opcodes, DecodedInsn, InsnBuilder};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use halo2_base::{
+    gates::{circuit::builder::BaseCircuitBuilder, flex_gate::GateChip},
+    halo2_proofs::halo2curves::bn256::Fr,
+    AssignedValue, Context,
+};
+use zk_circuits::{word_opcode, InstructionChip};
+
+/// ALU64 register opcodes without a chip or an entry in `opcodes`
+const SUB64_REG: u8 = 0x1f;
+const OR64_REG: u8 = 0x4f;
+const AND64_REG: u8 = 0x5f;
+const XOR64_REG: u8 = 0xaf;
+
+/// One instruction as executed, with the chip the dispatcher picks for it
+struct Case {
+    name: &'static str,
+    word: u64,
+    chip: Option<InstructionChip>,
+    before: Vec<u64>,
+    after: Vec<u64>,
+}
+
+/// Trace `insn` after `mov64 r1, 1000; mov64 r2, 7` and keep its step
+fn case(name: &'static str, insn: [u8; 8]) -> Case {
+    let bytecode = InsnBuilder::program([
+        InsnBuilder::mov64_imm(1, 1000),
+        InsnBuilder::mov64_imm(2, 7),
+        insn,
+        InsnBuilder::exit(),
+    ]);
+    let trace = bpf_tracer::trace_program(&bytecode).expect("benchmark program traces");
+    let step = &trace.instructions[2];
+    let word = u64::from_le_bytes(insn);
+    let insn = DecodedInsn::decode(&insn).unwrap();
+    Case {
+        name,
+        chip: InstructionChip::select(&insn),
+        word,
+        before: step.registers_before.regs[..11].to_vec(),
+        after: step.registers_after.regs[..11].to_vec(),
+    }
+}
+
+fn cases() -> Vec<Case> {
+    vec![
+        case("add64 r1, 5", InsnBuilder::add64_imm(1, 5)),
+        case("add64 r1, r2", InsnBuilder::add64_reg(1, 2)),
+        case("sub64 r1, r2", InsnBuilder::insn(SUB64_REG, 1, 2, 0, 0)),
+        case("mov64 r3, 9", InsnBuilder::mov64_imm(3, 9)),
+        case("mul64 r1, 7", InsnBuilder::mul64_imm(1, 7)),
+        case("mul64 r1, 8", InsnBuilder::mul64_imm(1, 8)),
+        case("div64 r1, 7", InsnBuilder::div64_imm(1, 7)),
+        case("udiv64 r1, r2", InsnBuilder::insn(opcodes::UDIV64_REG, 1, 2, 0, 0)),
+        case("urem64 r1, r2", InsnBuilder::insn(opcodes::UREM64_REG, 1, 2, 0, 0)),
+        case("and64 r1, r2", InsnBuilder::insn(AND64_REG, 1, 2, 0, 0)),
+        case("or64 r1, r2", InsnBuilder::insn(OR64_REG, 1, 2, 0, 0)),
+        case("xor64 r1, r2", InsnBuilder::insn(XOR64_REG, 1, 2, 0, 0)),
+        case("lsh64 r1, 3", InsnBuilder::lsh64_imm(1, 3)),
+        case("rsh64 r1, 3", InsnBuilder::rsh64_imm(1, 3)),
+        case("jne r1, 0, +0", InsnBuilder::jne_imm(1, 0, 0)),
+        case("ldxdw r3, [r10-8]", InsnBuilder::ldxdw(3, 10, -8)),
+        case("stxdw [r10-8], r1", InsnBuilder::stxdw(10, -8, 1)),
+        case("exit", InsnBuilder::exit()),
+    ]
+}
+
+fn load_registers(ctx: &mut Context<Fr>, regs: &[u64]) -> [AssignedValue<Fr>; 11] {
+    std::array::from_fn(|i| ctx.load_witness(Fr::from(regs[i])))
+}
+
+/// Synthesize one chip in a fresh circuit, returning the advice cells it
+/// assigned beyond the register states
+fn synthesize(chip: &InstructionChip, case: &Case) -> usize {
+    let mut builder = BaseCircuitBuilder::<Fr>::new(false);
+    let gate = GateChip::<Fr>::default();
+    let ctx = builder.main(0);
+    let before = load_registers(ctx, &case.before);
+    let after = load_registers(ctx, &case.after);
+    let word = ctx.load_witness(Fr::from(case.word));
+    let opcode = word_opcode(ctx, &gate, word);
+
+    let start = ctx.advice.len();
+    chip.synthesize(ctx, &gate, opcode, &before, &after)
+        .expect("benchmark chip synthesizes");
+    ctx.advice.len() - start
+}
+
+fn print_cell_table(cases: &[Case]) {
+    let baseline = cases[0].chip.as_ref().map_or(1, |chip| synthesize(chip, &cases[0]));
+
+    println!("{:<20} {:>8} {:>10}", "instruction", "cells", "vs add imm");
+    for case in cases {
+        match &case.chip {
+            Some(chip) => {
+                let cells = synthesize(chip, case);
+                let ratio = cells as f64 / baseline as f64;
+                println!("{:<20} {:>8} {:>9.1}x", case.name, cells, ratio);
+            }
+            None => println!("{:<20} {:>8}", case.name, "no chip"),
+        }
+    }
+    println!();
+}
+
+fn chip_synthesis(c: &mut Criterion) {
+    let cases = cases();
+    print_cell_table(&cases);
+
+    let mut group = c.benchmark_group("chip_synthesis");
+    for case in &cases {
+        if let Some(chip) = &case.chip {
+            group.bench_function(case.name, |b| b.iter(|| synthesize(chip, black_box(case))));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, chip_synthesis);
+criterion_main!(benches);
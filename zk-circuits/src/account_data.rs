@@ -0,0 +1,511 @@
+//! Chunked account-data commitments
+//!
+//! Hashing a whole account into one digest means a one-byte write re-hashes
+//! megabytes. Instead, account data is split into fixed-size chunks, each
+//! chunk is hashed, and the chunk hashes are combined into a Merkle root.
+//! A write then only changes the hashes of the chunks it touched, and
+//! [`AccountDataChip`] only re-hashes those chunks and their Merkle paths
+//! in-circuit.
+//!
+//! [`AccountDataEqualityCircuit`] constrains whether two data blobs are
+//! identical without revealing either. Like the chip, it is a gadget: the
+//! prover does not expose its output, so a caller must bind it.
+
+use halo2_base::{
+    gates::GateInstructions,
+    poseidon::hasher::{spec::OptimizedPoseidonSpec, PoseidonHasher},
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use sha3::{Digest, Keccak256};
+use crate::{keccak, Result};
+
+/// Default account-data chunk size in bytes
+pub const DEFAULT_ACCOUNT_DATA_CHUNK_SIZE: usize = 1024;
+
+/// Domain tag of chunk (leaf) hashes
+const LEAF_TAG: u8 = 0;
+
+/// Domain tag of internal node hashes
+const NODE_TAG: u8 = 1;
+
+/// Domain tag of the root, which binds the data length to the tree
+const ROOT_TAG: u8 = 2;
+
+/// Leaf padding the tree out to a power of two
+///
+/// Not the hash of any chunk, so padding cannot stand in for data.
+const EMPTY_LEAF: [u8; 32] = [0; 32];
+
+/// Merkle commitment over fixed-size chunks of account data
+///
+/// Hashes are keccak256 with a one-byte domain tag: chunks are hashed as
+/// `keccak(0x00 || chunk)` and internal nodes as `keccak(0x01 || left ||
+/// right)`, over the chunk hashes padded to a power of two with all-zero
+/// leaves. The root is `keccak(0x02 || len || tree)` with `len` the
+/// data length as a little-endian `u64`, so neither a subtree nor a
+/// different length can pass for the same data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataCommitment {
+    /// Chunk size in bytes
+    pub chunk_size: usize,
+    /// Data length in bytes
+    pub len: usize,
+    /// Leaf hash of each chunk (the last chunk may be short)
+    pub chunk_hashes: Vec<[u8; 32]>,
+    /// Root over the data length and `chunk_hashes`
+    pub root: [u8; 32],
+}
+
+impl DataCommitment {
+    /// Commit to `data` in chunks of `chunk_size` bytes
+    pub fn new(data: &[u8], chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Chunk size must be non-zero");
+        let chunk_hashes: Vec<[u8; 32]> = data.chunks(chunk_size).map(leaf_hash).collect();
+        let root = data_root(data.len(), &tree_root(&chunk_hashes));
+        Self { chunk_size, len: data.len(), chunk_hashes, root }
+    }
+
+    /// Number of chunks
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_hashes.len()
+    }
+
+    /// Indices of the chunks whose hashes differ from `other`
+    ///
+    /// Chunks present in only one commitment (the data was resized) count
+    /// as changed.
+    pub fn changed_chunks(&self, other: &Self) -> Vec<usize> {
+        let count = self.chunk_count().max(other.chunk_count());
+        (0..count)
+            .filter(|&i| self.chunk_hashes.get(i) != other.chunk_hashes.get(i))
+            .collect()
+    }
+}
+
+/// `keccak256(tag || parts...)`
+fn tagged_hash(tag: u8, parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update([tag]);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Leaf hash of one chunk
+fn leaf_hash(chunk: &[u8]) -> [u8; 32] {
+    tagged_hash(LEAF_TAG, &[chunk])
+}
+
+/// Hash of an internal node
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    tagged_hash(NODE_TAG, &[left, right])
+}
+
+/// Root binding the data length to the tree root
+fn data_root(len: usize, tree_root: &[u8; 32]) -> [u8; 32] {
+    tagged_hash(ROOT_TAG, &[&(len as u64).to_le_bytes(), tree_root])
+}
+
+/// Leaves padded with [`EMPTY_LEAF`] to a power of two (at least one)
+fn padded_leaves(leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let mut padded = leaves.to_vec();
+    padded.resize(leaves.len().next_power_of_two(), EMPTY_LEAF);
+    padded
+}
+
+/// Merkle root over leaf hashes
+fn tree_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = padded_leaves(leaves);
+    while level.len() > 1 {
+        level = level.chunks(2).map(|pair| node_hash(&pair[0], &pair[1])).collect();
+    }
+    level[0]
+}
+
+/// Sibling hashes on the path from leaf `index` to the root, bottom-up
+fn merkle_path(leaves: &[[u8; 32]], mut index: usize) -> Vec<[u8; 32]> {
+    let mut level = padded_leaves(leaves);
+    let mut path = Vec::new();
+    while level.len() > 1 {
+        path.push(level[index ^ 1]);
+        level = level.chunks(2).map(|pair| node_hash(&pair[0], &pair[1])).collect();
+        index /= 2;
+    }
+    path
+}
+
+/// A 32-byte hash as assigned bytes
+type AssignedHash<F> = [AssignedValue<F>; 32];
+
+/// Account-data write chip
+///
+/// Recomputes both roots of an account's data write in-circuit. The before
+/// tree is witnessed by its root; for each written chunk in turn, the old
+/// leaf is checked against the current tree root along its Merkle path, and
+/// the leaf hashed from the new chunk bytes is carried up the same path
+/// (the same sibling cells) to the next tree root. The last one is the
+/// after tree. Chunks outside `written_chunks` thus keep their hashes, and
+/// written chunks hash to the new data. Both roots also hash in the data
+/// length, which the write must keep.
+///
+/// This is a gadget, not a proof: the counter circuit does not use it and
+/// the prover exposes neither root as an instance, so the roots bind
+/// nothing until a circuit constrains them to its public commitments.
+#[derive(Debug, Clone)]
+pub struct AccountDataChip {
+    /// Indices of the chunks the program wrote
+    pub written_chunks: Vec<usize>,
+}
+
+impl AccountDataChip {
+    /// Create a chip allowing changes to `written_chunks` only
+    pub fn new(written_chunks: Vec<usize>) -> Self {
+        Self { written_chunks }
+    }
+
+    /// Synthesize the constraints for one account's data write
+    ///
+    /// `after_data` is the data after the write; only its written chunks
+    /// are witnessed. Returns the assigned before and after roots, which
+    /// the caller must constrain to its public commitments. Fails if the write
+    /// resizes the data or names a chunk it does not have.
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        before: &DataCommitment,
+        after_data: &[u8],
+    ) -> Result<[AssignedHash<F>; 2]> {
+        anyhow::ensure!(
+            before.len == after_data.len(),
+            "Account data resized from {} to {} bytes",
+            before.len,
+            after_data.len()
+        );
+        let mut written = self.written_chunks.clone();
+        written.sort_unstable();
+        written.dedup();
+        if let Some(&index) = written.iter().find(|&&index| index >= before.chunk_count()) {
+            anyhow::bail!("Written chunk {} of {}", index, before.chunk_count());
+        }
+
+        let len = (before.len as u64).to_le_bytes().map(|byte| load_byte(ctx, byte));
+        let before_tree = tree_root(&before.chunk_hashes).map(|byte| load_byte(ctx, byte));
+        let before_root = hash_cells(ctx, gate, ROOT_TAG, &[&len, &before_tree]);
+
+        let mut leaves = before.chunk_hashes.clone();
+        let mut tree = before_tree;
+        for index in written {
+            let path: Vec<AssignedHash<F>> = merkle_path(&leaves, index)
+                .into_iter()
+                .map(|sibling| sibling.map(|byte| load_byte(ctx, byte)))
+                .collect();
+
+            // The old leaf is in the current tree
+            let old_leaf = leaves[index].map(|byte| load_byte(ctx, byte));
+            let old_tree = climb(ctx, gate, old_leaf, index, &path);
+            for (computed, current) in old_tree.iter().zip(&tree) {
+                ctx.constrain_equal(computed, current);
+            }
+
+            // The new leaf, hashed from the written bytes, replaces it
+            let start = index * before.chunk_size;
+            let bytes = &after_data[start..after_data.len().min(start + before.chunk_size)];
+            let chunk: Vec<_> = bytes.iter().map(|&byte| load_byte(ctx, byte)).collect();
+            let new_leaf = hash_cells(ctx, gate, LEAF_TAG, &[&chunk]);
+            tree = climb(ctx, gate, new_leaf, index, &path);
+            leaves[index] = leaf_hash(bytes);
+        }
+
+        let after_root = hash_cells(ctx, gate, ROOT_TAG, &[&len, &tree]);
+        Ok([before_root, after_root])
+    }
+}
+
+/// Witness a byte; the keccak gadget range-checks it
+fn load_byte<F: ScalarField>(ctx: &mut Context<F>, byte: u8) -> AssignedValue<F> {
+    ctx.load_witness(F::from(byte as u64))
+}
+
+/// `keccak256(tag || parts...)` over assigned bytes
+fn hash_cells<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    tag: u8,
+    parts: &[&[AssignedValue<F>]],
+) -> AssignedHash<F> {
+    let mut bytes = vec![ctx.load_constant(F::from(tag as u64))];
+    for part in parts {
+        bytes.extend_from_slice(part);
+    }
+    keccak::keccak256(ctx, gate, &bytes)
+}
+
+/// Hash `leaf` at `index` up its Merkle `path` to the tree root
+fn climb<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    leaf: AssignedHash<F>,
+    mut index: usize,
+    path: &[AssignedHash<F>],
+) -> AssignedHash<F> {
+    let mut node = leaf;
+    for sibling in path {
+        node = if index % 2 == 0 {
+            hash_cells(ctx, gate, NODE_TAG, &[&node, sibling])
+        } else {
+            hash_cells(ctx, gate, NODE_TAG, &[sibling, &node])
+        };
+        index /= 2;
+    }
+    node
+}
+
+/// Bytes packed into each field element before hashing
+const EQUALITY_BYTES_PER_ELEMENT: usize = 16;
+
+/// Poseidon width, rate and round counts for the equality hash
+const POSEIDON_T: usize = 3;
+const POSEIDON_RATE: usize = 2;
+const POSEIDON_R_F: usize = 8;
+const POSEIDON_R_P: usize = 57;
+
+/// Private account-data equality circuit
+///
+/// Output:
+/// - Whether the two blobs are equal (boolean), returned by
+///   [`Self::synthesize`] for the caller to expose as an instance
+///
+/// Private Witness:
+/// - Both data blobs
+///
+/// Unlike [`DataCommitment`], hashing happens inside the circuit: each blob
+/// and its length are hashed with Poseidon, and the claimed boolean is
+/// constrained to equal whether the two hashes match. Blobs are zero-padded
+/// to the longer length so both hashes take the same number of inputs; the
+/// length input keeps trailing zeros from colliding with a shorter blob.
+#[derive(Debug, Clone)]
+pub struct AccountDataEqualityCircuit {
+    /// First data blob (private witness)
+    left: Vec<u8>,
+    /// Second data blob (private witness)
+    right: Vec<u8>,
+    /// Claimed equality
+    claimed_equal: bool,
+}
+
+impl AccountDataEqualityCircuit {
+    /// Create a circuit claiming whether `left` and `right` are equal
+    pub fn new(left: Vec<u8>, right: Vec<u8>) -> Self {
+        let claimed_equal = left == right;
+        Self { left, right, claimed_equal }
+    }
+
+    /// Override the claimed equality
+    ///
+    /// A wrong claim leaves the circuit unsatisfied; this exists to check
+    /// exactly that.
+    pub fn with_claimed_equal(mut self, claimed_equal: bool) -> Self {
+        self.claimed_equal = claimed_equal;
+        self
+    }
+
+    /// The equality claim
+    pub fn claimed_equal(&self) -> bool {
+        self.claimed_equal
+    }
+
+    /// Synthesize the circuit constraints
+    ///
+    /// Returns the assigned equality boolean. No prover path exposes it as
+    /// an instance yet; until the caller does, a proof binds no claim.
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+    ) -> Result<AssignedValue<F>> {
+        let padded_len = self.left.len().max(self.right.len());
+
+        let mut hasher = PoseidonHasher::<F, POSEIDON_T, POSEIDON_RATE>::new(
+            OptimizedPoseidonSpec::new::<POSEIDON_R_F, POSEIDON_R_P, 0>(),
+        );
+        hasher.initialize_consts(ctx, gate);
+
+        let left_inputs = hash_inputs(ctx, gate, &self.left, padded_len);
+        let right_inputs = hash_inputs(ctx, gate, &self.right, padded_len);
+        let left_hash = hasher.hash_fix_len_array(ctx, gate, &left_inputs);
+        let right_hash = hasher.hash_fix_len_array(ctx, gate, &right_inputs);
+
+        // Constrain: claimed = (left_hash == right_hash)
+        let is_equal = gate.is_equal(ctx, left_hash, right_hash);
+        let claimed = ctx.load_witness(F::from(self.claimed_equal as u64));
+        gate.assert_bit(ctx, claimed);
+        ctx.constrain_equal(&is_equal, &claimed);
+
+        Ok(claimed)
+    }
+}
+
+/// Poseidon inputs for one blob: its length, then its bytes
+///
+/// Bytes are range-checked, zero-padded to `padded_len` and packed
+/// little-endian, [`EQUALITY_BYTES_PER_ELEMENT`] to a field element.
+fn hash_inputs<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    data: &[u8],
+    padded_len: usize,
+) -> Vec<AssignedValue<F>> {
+    let len = ctx.load_witness(F::from(data.len() as u64));
+    let _len_bits = gate.num_to_bits(ctx, len, 64);
+
+    let mut padded = data.to_vec();
+    padded.resize(padded_len, 0);
+
+    let mut inputs = vec![len];
+    for chunk in padded.chunks(EQUALITY_BYTES_PER_ELEMENT) {
+        let mut packed = ctx.load_constant(F::ZERO);
+        // Most significant byte first
+        for &byte in chunk.iter().rev() {
+            let cell = ctx.load_witness(F::from(byte as u64));
+            let _bits = gate.num_to_bits(ctx, cell, 8);
+            packed = gate.mul_add(ctx, packed, QuantumCell::Constant(F::from(256u64)), cell);
+        }
+        inputs.push(packed);
+    }
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    /// 1 MiB account with one byte written in the middle
+    fn large_account_write() -> (Vec<u8>, Vec<u8>) {
+        let before: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+        let mut after = before.clone();
+        after[500_000] ^= 0xff;
+        (before, after)
+    }
+
+    #[test]
+    fn test_one_chunk_changes() {
+        let (before, after) = large_account_write();
+        let chunk_size = 4096;
+
+        let before = DataCommitment::new(&before, chunk_size);
+        let after = DataCommitment::new(&after, chunk_size);
+
+        assert_eq!(before.chunk_count(), 256);
+        assert_eq!(before.changed_chunks(&after), vec![500_000 / chunk_size]);
+        assert_ne!(before.root, after.root);
+    }
+
+    #[test]
+    fn test_chunk_size_is_configurable() {
+        let (before, after) = large_account_write();
+
+        let coarse = DataCommitment::new(&before, DEFAULT_ACCOUNT_DATA_CHUNK_SIZE);
+        let fine = DataCommitment::new(&before, 64);
+        assert_eq!(coarse.chunk_count(), (1 << 20) / DEFAULT_ACCOUNT_DATA_CHUNK_SIZE);
+        assert_eq!(fine.chunk_count(), (1 << 20) / 64);
+
+        let fine_after = DataCommitment::new(&after, 64);
+        assert_eq!(fine.changed_chunks(&fine_after), vec![500_000 / 64]);
+    }
+
+    #[test]
+    fn test_root_separates_leaves_nodes_and_lengths() {
+        let leaves = vec![leaf_hash(b"a"), leaf_hash(b"b"), leaf_hash(b"c")];
+
+        // An internal node cannot pass for a leaf, and an odd node is not
+        // carried up unchanged
+        let folded = [node_hash(&leaves[0], &leaves[1]), leaves[2]];
+        assert_ne!(tree_root(&leaves), tree_root(&folded));
+
+        // The same tree under another length is another commitment
+        let tree = tree_root(&leaves);
+        assert_ne!(data_root(3, &tree), data_root(4, &tree));
+        assert_ne!(DataCommitment::new(&[], 4).root, DataCommitment::new(&[0], 4).root);
+    }
+
+    /// Run the chip over a write to chunk 1 of 2, pinning both roots
+    fn run_account_data_chip(written_chunks: Vec<usize>, expect_satisfied: bool) {
+        let before_data = vec![0u8; 64];
+        let mut after_data = before_data.clone();
+        after_data[40] = 1;
+
+        let before = DataCommitment::new(&before_data, 32);
+        let after = DataCommitment::new(&after_data, 32);
+
+        let chip = AccountDataChip::new(written_chunks);
+        base_test().k(20).expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let roots = chip.synthesize::<Fr>(ctx, gate, &before, &after_data).unwrap();
+            for (cells, root) in roots.iter().zip([before.root, after.root]) {
+                for (cell, byte) in cells.iter().zip(root) {
+                    gate.assert_is_const(ctx, cell, &Fr::from(byte as u64));
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_account_data_chip_written_chunk() {
+        run_account_data_chip(vec![1], true);
+    }
+
+    #[test]
+    fn test_account_data_chip_undeclared_write_fails() {
+        run_account_data_chip(vec![0], false);
+    }
+
+    #[test]
+    fn test_account_data_chip_rejects_resize() {
+        let before = DataCommitment::new(&[0; 64], 32);
+        base_test().run_gate(|ctx, gate| {
+            let chip = AccountDataChip::new(vec![0]);
+            assert!(chip.synthesize::<Fr>(ctx, gate, &before, &[0; 65]).is_err());
+            let chip = AccountDataChip::new(vec![2]);
+            assert!(chip.synthesize::<Fr>(ctx, gate, &before, &[0; 64]).is_err());
+        });
+    }
+
+    fn run_equality(circuit: &AccountDataEqualityCircuit, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            circuit.synthesize::<Fr>(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_equality_of_identical_blobs() {
+        let data: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let circuit = AccountDataEqualityCircuit::new(data.clone(), data);
+        assert!(circuit.claimed_equal());
+        run_equality(&circuit, true);
+    }
+
+    #[test]
+    fn test_differing_blobs_must_claim_inequality() {
+        let left: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let mut right = left.clone();
+        right[42] ^= 1;
+
+        let circuit = AccountDataEqualityCircuit::new(left, right);
+        assert!(!circuit.claimed_equal());
+        run_equality(&circuit, true);
+        run_equality(&circuit.with_claimed_equal(true), false);
+    }
+
+    #[test]
+    fn test_zero_padding_is_not_equality() {
+        let circuit = AccountDataEqualityCircuit::new(vec![1, 2, 3], vec![1, 2, 3, 0]);
+        assert!(!circuit.claimed_equal());
+        run_equality(&circuit.with_claimed_equal(true), false);
+    }
+}
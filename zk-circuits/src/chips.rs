@@ -9,6 +9,36 @@ use halo2_base::{
 };
 use crate::Result;
 
+/// 2^64 as a field element
+pub(crate) fn two_pow_64<F: ScalarField>() -> F {
+    F::from(1u64 << 32) * F::from(1u64 << 32)
+}
+
+/// Sign-extend a 32-bit immediate to the 64-bit value ALU64 operations use
+pub(crate) fn sign_extend_imm(imm: i32) -> u64 {
+    imm as i64 as u64
+}
+
+/// Witness `value` and constrain it to fit in `bits` bits
+///
+/// Values up to 128 bits are accepted; the bit decomposition both
+/// range-checks the cell and ties it to the witnessed value.
+pub(crate) fn load_bounded<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    value: u128,
+    bits: usize,
+) -> AssignedValue<F> {
+    let value_f = F::from((value >> 64) as u64) * two_pow_64::<F>() + F::from(value as u64);
+    let cell = ctx.load_witness(value_f);
+    if bits == 0 {
+        gate.assert_is_const(ctx, &cell, &F::ZERO);
+    } else {
+        let _bits = gate.num_to_bits(ctx, cell, bits);
+    }
+    cell
+}
+
 /// Trait for BPF instruction chips
 ///
 /// Each instruction type implements this trait to define its
@@ -38,10 +68,30 @@ pub trait BpfInstructionChip<F: ScalarField> {
 
 pub mod alu64_add_imm;
 pub mod alu64_add_reg;
+pub mod alu64_div_imm;
+pub mod alu64_mov_imm;
+pub mod alu64_mul_imm;
+pub mod alu64_shift_imm;
+pub mod compare;
+#[cfg(test)]
+mod coverage;
 pub mod exit;
+pub mod imm64;
+pub mod memcpy;
+pub mod memset;
 pub mod memory;
+pub mod pqr;
 
 pub use alu64_add_imm::Alu64AddImmChip;
 pub use alu64_add_reg::Alu64AddRegChip;
-pub use exit::ExitChip;
-pub use memory::{LdwChip, StwChip};
+pub use alu64_div_imm::Alu64DivImmChip;
+pub use alu64_mov_imm::Alu64MovImmChip;
+pub use alu64_mul_imm::Alu64MulImmChip;
+pub use alu64_shift_imm::{Alu64LshImmChip, Alu64RshImmChip};
+pub use compare::compare_u64;
+pub use exit::{ExitChip, ReturnChip, ReturnRangeChip};
+pub use imm64::{Hor64Chip, Mov32ImmChip};
+pub use memcpy::MemcpyChip;
+pub use memset::MemsetChip;
+pub use memory::{AssignedMemoryOp, LdwChip, StwChip};
+pub use pqr::{PqrChip, PqrDivisor, PqrOutput};
@@ -8,29 +8,29 @@ use halo2_base::{
     utils::ScalarField,
     AssignedValue, Context, QuantumCell,
 };
-use crate::{chips::BpfInstructionChip, Result};
+use crate::{
+    chips::{load_bounded, sign_extend_imm, two_pow_64, BpfInstructionChip},
+    Result,
+};
 
 /// ALU64_ADD_IMM instruction chip
 ///
 /// Constraints:
-/// 1. dst_after = dst_before + imm (mod field size)
-/// 2. All other registers remain unchanged
-///
-/// Note: We work in the field F, which is larger than 2^64.
-/// For proper 64-bit wrapping behavior, we would need range checks
-/// and modular arithmetic. For this MVP, we assume values stay within
-/// the field's valid range.
+/// 1. dst_before + imm = carry * 2^64 + dst_after, with imm sign-extended
+///    from 32 to 64 bits
+/// 2. carry is a bit and dst_after < 2^64
+/// 3. All other registers remain unchanged
 #[derive(Debug, Clone)]
 pub struct Alu64AddImmChip {
     /// Destination register index (0-10)
     pub dst_reg: usize,
-    /// Immediate value to add
-    pub imm: i64,
+    /// 32-bit immediate, sign-extended to 64 bits before the add
+    pub imm: i32,
 }
 
 impl Alu64AddImmChip {
     /// Create a new ALU64_ADD_IMM chip
-    pub fn new(dst_reg: usize, imm: i64) -> Self {
+    pub fn new(dst_reg: usize, imm: i32) -> Self {
         assert!(dst_reg < 11, "Invalid register index");
         Self { dst_reg, imm }
     }
@@ -44,17 +44,20 @@ impl<F: ScalarField> BpfInstructionChip<F> for Alu64AddImmChip {
         regs_before: &[AssignedValue<F>; 11],
         regs_after: &[AssignedValue<F>; 11],
     ) -> Result<()> {
-        // Convert immediate to field element
-        // Handle signed immediate by converting to unsigned
-        let imm_u64 = self.imm as u64;
-        let imm_f = F::from(imm_u64);
-
-        // Constrain: dst_after = dst_before + imm
+        let imm_u64 = sign_extend_imm(self.imm);
         let dst_before = regs_before[self.dst_reg];
-        let dst_after_expected = gate.add(ctx, dst_before, QuantumCell::Constant(imm_f));
 
-        // Constrain that the computed value equals the provided witness
-        ctx.constrain_equal(&dst_after_expected, &regs_after[self.dst_reg]);
+        // 65-bit sum, split into the wrapped result and the carry out
+        let wide = dst_before.value().get_lower_64() as u128 + imm_u64 as u128;
+        let result = load_bounded(ctx, gate, wide & u64::MAX as u128, 64);
+        let carry = load_bounded(ctx, gate, wide >> 64, 1);
+
+        // Constrain: dst_before + imm = carry * 2^64 + result
+        let sum = gate.add(ctx, dst_before, QuantumCell::Constant(F::from(imm_u64)));
+        let recomposed = gate.mul_add(ctx, carry, QuantumCell::Constant(two_pow_64()), result);
+        ctx.constrain_equal(&sum, &recomposed);
+
+        ctx.constrain_equal(&result, &regs_after[self.dst_reg]);
 
         // Constrain that all other registers remain unchanged
         for i in 0..11 {
@@ -71,8 +74,12 @@ impl<F: ScalarField> BpfInstructionChip<F> for Alu64AddImmChip {
 mod tests {
     use super::*;
     use halo2_base::{
+        gates::{circuit::builder::BaseCircuitBuilder, flex_gate::GateChip},
         utils::testing::base_test,
-        halo2_proofs::halo2curves::bn256::Fr,
+        halo2_proofs::{
+            dev::MockProver,
+            halo2curves::bn256::{Fq, Fr},
+        },
     };
 
     #[test]
@@ -85,7 +92,7 @@ mod tests {
 
             // Simulate: r1 = r1 + 42
             let dst_reg = 1;
-            let imm = 42i64;
+            let imm = 42;
             let expected_dst = Fr::from(10u64) + Fr::from(42u64); // r1 was 10, now 10 + 42 = 52
 
             let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
@@ -101,29 +108,79 @@ mod tests {
         });
     }
 
+    fn run_add_imm(dst_value: u64, imm: i32, result: u64, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { dst_value } else { 100 }))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { result } else { 100 }))
+            });
+
+            let chip = Alu64AddImmChip::new(0, imm);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
     #[test]
     fn test_alu64_add_imm_negative() {
-        base_test().run_gate(|ctx, gate| {
-            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|_i| {
-                ctx.load_witness(Fr::from(100u64))
-            });
+        // r0 = 100 + (-5)
+        run_add_imm(100, -5, 95, true);
+    }
 
-            // Simulate: r0 = r0 + (-5)
-            let dst_reg = 0;
-            let imm = -5i64;
-            let imm_u64 = imm as u64;
-            let expected_dst = Fr::from(100u64) + Fr::from(imm_u64); // 100 + (-5 as u64)
+    #[test]
+    fn test_alu64_add_imm_wraps_mod_2_64() {
+        // -1 sign-extends to 0xFFFF_FFFF_FFFF_FFFF, not 0x0000_0000_FFFF_FFFF
+        run_add_imm(0, -1, u64::MAX, true);
+        run_add_imm(0, -1, 0xFFFF_FFFF, false);
+
+        // Adding past 2^64 wraps
+        run_add_imm(u64::MAX, 2, 1, true);
+        run_add_imm(u64::MAX, 1, 0, true);
+        run_add_imm(u64::MAX, 0, u64::MAX, true);
+        run_add_imm(u64::MAX, 1, u64::MAX, false);
+    }
 
+    #[test]
+    fn test_alu64_add_imm_unwrapped_sum_fails() {
+        // The field sum 100 + (2^64 - 5) is not a 64-bit value
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|_| ctx.load_witness(Fr::from(100u64)));
             let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
-                if i == dst_reg {
-                    ctx.load_witness(expected_dst)
+                if i == 0 {
+                    ctx.load_witness(Fr::from(100u64) + Fr::from(-5i64 as u64))
                 } else {
                     ctx.load_witness(Fr::from(100u64))
                 }
             });
 
-            let chip = Alu64AddImmChip::new(dst_reg, imm);
+            let chip = Alu64AddImmChip::new(0, -5);
             chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
         });
     }
+
+    /// Synthesize r1 = 10 + 42 over the scalar field `F`
+    fn run_add_imm_over<F: ScalarField>() {
+        let mut builder = BaseCircuitBuilder::<F>::new(false).use_k(10);
+        let gate = GateChip::<F>::default();
+        let ctx = builder.main(0);
+
+        let regs_before: [AssignedValue<F>; 11] =
+            std::array::from_fn(|i| ctx.load_witness(F::from(i as u64 * 10)));
+        let regs_after: [AssignedValue<F>; 11] = std::array::from_fn(|i| {
+            ctx.load_witness(F::from(if i == 1 { 52 } else { i as u64 * 10 }))
+        });
+        Alu64AddImmChip::new(1, 42).synthesize(ctx, &gate, &regs_before, &regs_after).unwrap();
+
+        builder.calculate_params(Some(9));
+        MockProver::run(10, &builder, vec![]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_alu64_add_imm_over_other_fields() {
+        run_add_imm_over::<Fr>();
+        // BN256's base field is Grumpkin's scalar field
+        run_add_imm_over::<Fq>();
+    }
 }
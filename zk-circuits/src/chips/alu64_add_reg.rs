@@ -123,4 +123,25 @@ mod tests {
             chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
         });
     }
+
+    #[test]
+    fn test_alu64_add_reg_wrong_sum() {
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(i as u64 * 10))
+            });
+
+            // Witness claims r1 = 31 after `add64 r1, r2` (10 + 20)
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                if i == 1 {
+                    ctx.load_witness(Fr::from(31u64))
+                } else {
+                    ctx.load_witness(Fr::from(i as u64 * 10))
+                }
+            });
+
+            let chip = Alu64AddRegChip::new(1, 2);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
 }
@@ -0,0 +1,112 @@
+//! ALU64_DIV_IMM instruction chip
+//!
+//! Divides a 64-bit register by an immediate (unsigned).
+//! Instruction format: dst = dst / imm
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::{load_bounded, BpfInstructionChip},
+    Result,
+};
+
+/// ALU64_DIV_IMM instruction chip (general divide)
+///
+/// Constraints:
+/// 1. dst_before = quotient * divisor + remainder, with quotient < 2^64
+/// 2. remainder < divisor (divisor - 1 - remainder fits in 64 bits)
+/// 3. dst_after = quotient
+/// 4. All other registers remain unchanged
+///
+/// For power-of-two divisors the dispatcher uses the cheaper
+/// [`Alu64RshImmChip`](crate::chips::Alu64RshImmChip) instead.
+#[derive(Debug, Clone)]
+pub struct Alu64DivImmChip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// Divisor, already extended to 64 bits as the opcode specifies
+    pub divisor: u64,
+}
+
+impl Alu64DivImmChip {
+    /// Create a new ALU64_DIV_IMM chip
+    pub fn new(dst_reg: usize, divisor: u64) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        assert!(divisor != 0, "Division by zero");
+        Self { dst_reg, divisor }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for Alu64DivImmChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        let dst_before = regs_before[self.dst_reg];
+        let dividend = dst_before.value().get_lower_64();
+
+        let quotient = load_bounded(ctx, gate, (dividend / self.divisor) as u128, 64);
+        let remainder = load_bounded(ctx, gate, (dividend % self.divisor) as u128, 64);
+
+        // Constrain: dst_before = quotient * divisor + remainder
+        let divisor = QuantumCell::Constant(F::from(self.divisor));
+        let recomposed = gate.mul_add(ctx, quotient, divisor, remainder);
+        ctx.constrain_equal(&dst_before, &recomposed);
+
+        // Constrain: remainder < divisor
+        let slack = load_bounded(ctx, gate, (self.divisor - 1 - dividend % self.divisor) as u128, 64);
+        let bound = gate.add(ctx, remainder, slack);
+        gate.assert_is_const(ctx, &bound, &F::from(self.divisor - 1));
+
+        ctx.constrain_equal(&quotient, &regs_after[self.dst_reg]);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn run_div(dst_value: u64, divisor: u64, result: u64, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { dst_value } else { i as u64 }))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { result } else { i as u64 }))
+            });
+
+            let chip = Alu64DivImmChip::new(0, divisor);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_alu64_div_imm() {
+        run_div(100, 7, 14, true);
+        run_div(u64::MAX, 3, u64::MAX / 3, true);
+    }
+
+    #[test]
+    fn test_alu64_div_imm_wrong_quotient() {
+        run_div(100, 7, 15, false);
+    }
+}
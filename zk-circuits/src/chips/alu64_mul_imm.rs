@@ -0,0 +1,113 @@
+//! ALU64_MUL_IMM instruction chip
+//!
+//! Multiplies a 64-bit register by an immediate.
+//! Instruction format: dst = dst * imm (mod 2^64)
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::{load_bounded, two_pow_64, BpfInstructionChip},
+    Result,
+};
+
+/// ALU64_MUL_IMM instruction chip (general multiply)
+///
+/// Constraints:
+/// 1. dst_before * imm = hi * 2^64 + lo, with lo and hi both < 2^64
+/// 2. dst_after = lo
+/// 3. All other registers remain unchanged
+///
+/// For power-of-two immediates the dispatcher uses the cheaper
+/// [`Alu64LshImmChip`](crate::chips::Alu64LshImmChip) instead.
+#[derive(Debug, Clone)]
+pub struct Alu64MulImmChip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// Immediate multiplier (sign-extended)
+    pub imm: i64,
+}
+
+impl Alu64MulImmChip {
+    /// Create a new ALU64_MUL_IMM chip
+    pub fn new(dst_reg: usize, imm: i64) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        Self { dst_reg, imm }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for Alu64MulImmChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        let imm_u64 = self.imm as u64;
+        let dst_before = regs_before[self.dst_reg];
+
+        // Full 128-bit product, split into 64-bit halves
+        let wide = (dst_before.value().get_lower_64() as u128) * (imm_u64 as u128);
+        let lo = load_bounded(ctx, gate, wide & u64::MAX as u128, 64);
+        let hi = load_bounded(ctx, gate, wide >> 64, 64);
+
+        // Constrain: dst_before * imm = hi * 2^64 + lo
+        let product = gate.mul(ctx, dst_before, QuantumCell::Constant(F::from(imm_u64)));
+        let recomposed = gate.mul_add(ctx, hi, QuantumCell::Constant(two_pow_64()), lo);
+        ctx.constrain_equal(&product, &recomposed);
+
+        // The result is the low 64 bits
+        ctx.constrain_equal(&lo, &regs_after[self.dst_reg]);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn run_mul(dst_value: u64, imm: i64, result: u64, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { dst_value } else { i as u64 }))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { result } else { i as u64 }))
+            });
+
+            let chip = Alu64MulImmChip::new(0, imm);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_alu64_mul_imm_wraps() {
+        let value = u64::MAX - 6;
+        run_mul(value, 7, value.wrapping_mul(7), true);
+    }
+
+    #[test]
+    fn test_alu64_mul_imm_negative() {
+        run_mul(5, -3, 5u64.wrapping_mul(-3i64 as u64), true);
+    }
+
+    #[test]
+    fn test_alu64_mul_imm_wrong_result() {
+        run_mul(5, 7, 36, false);
+    }
+}
@@ -0,0 +1,184 @@
+//! ALU64 shift-by-immediate instruction chips
+//!
+//! Logical left/right shifts of a 64-bit register.
+//! Instruction formats: dst = dst << imm, dst = dst >> imm (mod 2^64)
+//!
+//! The dispatcher also uses these for multiply/divide by a power of two:
+//! splitting at a known bit position needs far fewer range-check bits than
+//! a general 128-bit product or a quotient/remainder pair.
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::{load_bounded, two_pow_64, BpfInstructionChip},
+    Result,
+};
+
+/// ALU64_LSH_IMM instruction chip
+///
+/// Constraints:
+/// 1. dst_before * 2^shift = hi * 2^64 + lo, with lo < 2^64 and hi < 2^shift
+/// 2. dst_after = lo
+/// 3. All other registers remain unchanged
+#[derive(Debug, Clone)]
+pub struct Alu64LshImmChip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// Shift amount (0-63)
+    pub shift: u32,
+}
+
+impl Alu64LshImmChip {
+    /// Create a new ALU64_LSH_IMM chip
+    pub fn new(dst_reg: usize, shift: u32) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        assert!(shift < 64, "Invalid shift amount");
+        Self { dst_reg, shift }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for Alu64LshImmChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        let dst_before = regs_before[self.dst_reg];
+
+        // Bits shifted past bit 63 land in `hi` and are dropped
+        let wide = (dst_before.value().get_lower_64() as u128) << self.shift;
+        let lo = load_bounded(ctx, gate, wide & u64::MAX as u128, 64);
+        let hi = load_bounded(ctx, gate, wide >> 64, self.shift as usize);
+
+        // Constrain: dst_before * 2^shift = hi * 2^64 + lo
+        let factor = QuantumCell::Constant(F::from(1u64 << self.shift));
+        let shifted = gate.mul(ctx, dst_before, factor);
+        let recomposed = gate.mul_add(ctx, hi, QuantumCell::Constant(two_pow_64()), lo);
+        ctx.constrain_equal(&shifted, &recomposed);
+
+        ctx.constrain_equal(&lo, &regs_after[self.dst_reg]);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ALU64_RSH_IMM instruction chip
+///
+/// Constraints:
+/// 1. dst_before = quotient * 2^shift + remainder, with
+///    quotient < 2^(64 - shift) and remainder < 2^shift
+/// 2. dst_after = quotient
+/// 3. All other registers remain unchanged
+#[derive(Debug, Clone)]
+pub struct Alu64RshImmChip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// Shift amount (0-63)
+    pub shift: u32,
+}
+
+impl Alu64RshImmChip {
+    /// Create a new ALU64_RSH_IMM chip
+    pub fn new(dst_reg: usize, shift: u32) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        assert!(shift < 64, "Invalid shift amount");
+        Self { dst_reg, shift }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for Alu64RshImmChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        let dst_before = regs_before[self.dst_reg];
+        let value = dst_before.value().get_lower_64();
+
+        let quotient = load_bounded(ctx, gate, (value >> self.shift) as u128, 64 - self.shift as usize);
+        let remainder = load_bounded(
+            ctx,
+            gate,
+            (value & ((1u64 << self.shift) - 1)) as u128,
+            self.shift as usize,
+        );
+
+        // Constrain: dst_before = quotient * 2^shift + remainder
+        let factor = QuantumCell::Constant(F::from(1u64 << self.shift));
+        let recomposed = gate.mul_add(ctx, quotient, factor, remainder);
+        ctx.constrain_equal(&dst_before, &recomposed);
+
+        ctx.constrain_equal(&quotient, &regs_after[self.dst_reg]);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn run_shift(chip: impl BpfInstructionChip<Fr>, dst_value: u64, result: u64, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { dst_value } else { i as u64 }))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { result } else { i as u64 }))
+            });
+
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_alu64_lsh_imm_drops_high_bits() {
+        let value = 0xf000_0000_0000_0001u64;
+        run_shift(Alu64LshImmChip::new(0, 4), value, value << 4, true);
+    }
+
+    #[test]
+    fn test_alu64_lsh_imm_zero_shift() {
+        run_shift(Alu64LshImmChip::new(0, 0), 42, 42, true);
+    }
+
+    #[test]
+    fn test_alu64_lsh_imm_wrong_result() {
+        run_shift(Alu64LshImmChip::new(0, 4), 3, (3 << 4) | 1, false);
+    }
+
+    #[test]
+    fn test_alu64_rsh_imm() {
+        run_shift(Alu64RshImmChip::new(0, 3), 1001, 1001 >> 3, true);
+    }
+
+    #[test]
+    fn test_alu64_rsh_imm_wrong_result() {
+        run_shift(Alu64RshImmChip::new(0, 3), 1001, 126, false);
+    }
+}
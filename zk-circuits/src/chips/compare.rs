@@ -0,0 +1,104 @@
+//! Unsigned 64-bit comparison gadget
+//!
+//! Used by [`ReturnRangeChip`](crate::chips::ReturnRangeChip) to bound the
+//! return value. There are no conditional jump chips yet; when they land
+//! they should compare through here too, so every comparison range-checks
+//! its operands the same way.
+//!
+//! The gadget takes a [`GateInstructions`], not a `RangeChip`: synthesis
+//! threads only a gate through the chips (see
+//! [`CounterCircuit::synthesize`](crate::CounterCircuit::synthesize)), and
+//! like the other chips it range-checks by bit decomposition. A lookup-based
+//! `RangeChip` would be cheaper per comparison, but its lookups only count
+//! if it is the builder's own range chip, so switching means passing that
+//! through synthesis for every chip.
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::chips::two_pow_64;
+
+/// Compare two unsigned 64-bit values
+///
+/// Returns boolean `(lt, eq, gt)` selectors for `a < b`, `a == b` and
+/// `a > b`; exactly one of them is set. `<=` and `>=` are `lt + eq` and
+/// `gt + eq`.
+///
+/// Constraints:
+/// 1. `a` and `b` fit in 64 bits
+/// 2. `a - b + 2^64` fits in 65 bits; its top bit is `a >= b`
+/// 3. `eq` is `a == b`, and `gt = (a >= b) - eq`
+///
+/// Without (1) the field difference could wrap and the top bit would
+/// say nothing about the integer ordering, so the operands are always
+/// range-checked here rather than trusted from the caller.
+pub fn compare_u64<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: AssignedValue<F>,
+    b: AssignedValue<F>,
+) -> (AssignedValue<F>, AssignedValue<F>, AssignedValue<F>) {
+    let _a_bits = gate.num_to_bits(ctx, a, 64);
+    let _b_bits = gate.num_to_bits(ctx, b, 64);
+
+    // a - b + 2^64 is in [1, 2^65) and at least 2^64 exactly when a >= b
+    let shifted = gate.add(ctx, a, QuantumCell::Constant(two_pow_64::<F>()));
+    let shifted = gate.sub(ctx, shifted, b);
+    let ge = gate.num_to_bits(ctx, shifted, 65)[64];
+
+    let lt = gate.not(ctx, ge);
+    let eq = gate.is_equal(ctx, a, b);
+    let gt = gate.sub(ctx, ge, eq);
+
+    (lt, eq, gt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    /// `(lt, eq, gt)` of comparing `a` with `b`, as booleans
+    fn compare(a: u64, b: u64) -> (bool, bool, bool) {
+        base_test().run_gate(|ctx, gate| {
+            let a = ctx.load_witness(Fr::from(a));
+            let b = ctx.load_witness(Fr::from(b));
+            let (lt, eq, gt) = compare_u64(ctx, gate, a, b);
+            let flag = |cell: AssignedValue<Fr>| *cell.value() == Fr::from(1u64);
+            (flag(lt), flag(eq), flag(gt))
+        })
+    }
+
+    #[test]
+    fn test_compare_orderings_at_boundaries() {
+        let max = u64::MAX;
+        let cases = [
+            (0, 0, (false, true, false)),
+            (0, 1, (true, false, false)),
+            (1, 0, (false, false, true)),
+            (max, max, (false, true, false)),
+            (max - 1, max, (true, false, false)),
+            (max, max - 1, (false, false, true)),
+            (0, max, (true, false, false)),
+            (max, 0, (false, false, true)),
+            (1 << 63, (1 << 63) - 1, (false, false, true)),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(compare(a, b), expected, "compare({}, {})", a, b);
+        }
+    }
+
+    #[test]
+    fn test_compare_rejects_operand_wider_than_64_bits() {
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let a = ctx.load_witness(two_pow_64::<Fr>());
+            let b = ctx.load_witness(Fr::from(1u64));
+            compare_u64(ctx, gate, a, b);
+        });
+    }
+}
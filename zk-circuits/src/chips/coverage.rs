@@ -0,0 +1,122 @@
+//! Chip test registry
+//!
+//! Lists the chip tests that exercise each opcode, so that every opcode the
+//! dispatcher claims to support is held to at least one witness the chip
+//! accepts and one it rejects. Register new chip tests here alongside the
+//! opcodes they cover.
+
+use bpf_tracer::decode::opcodes;
+
+/// Outcome a registered chip test checks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Expect {
+    /// A correct witness satisfies the chip
+    Satisfied,
+    /// A wrong witness is rejected by the chip
+    Rejected,
+}
+
+/// A chip test and the opcodes it covers
+#[derive(Debug)]
+pub(crate) struct ChipTest {
+    /// Opcodes dispatched to the chip under test
+    pub opcodes: &'static [u8],
+    /// Test path relative to `chips`
+    pub test: &'static str,
+    /// What the test asserts
+    pub expect: Expect,
+}
+
+const fn satisfied(opcodes: &'static [u8], test: &'static str) -> ChipTest {
+    ChipTest { opcodes, test, expect: Expect::Satisfied }
+}
+
+const fn rejected(opcodes: &'static [u8], test: &'static str) -> ChipTest {
+    ChipTest { opcodes, test, expect: Expect::Rejected }
+}
+
+const MUL_IMM: &[u8] = &[opcodes::MUL64_IMM, opcodes::LMUL64_IMM];
+const DIV_IMM: &[u8] = &[opcodes::DIV64_IMM, opcodes::UDIV64_IMM];
+const LOAD: &[u8] = &[opcodes::LD_DW_REG, opcodes::LD_8B_REG];
+const STORE: &[u8] = &[opcodes::ST_DW_REG, opcodes::ST_8B_REG];
+
+/// Every registered chip test
+pub(crate) const CHIP_TESTS: &[ChipTest] = &[
+    satisfied(&[opcodes::ADD64_IMM], "alu64_add_imm::tests::test_alu64_add_imm_chip"),
+    rejected(&[opcodes::ADD64_IMM], "alu64_add_imm::tests::test_alu64_add_imm_unwrapped_sum_fails"),
+    satisfied(&[opcodes::ADD64_REG], "alu64_add_reg::tests::test_alu64_add_reg_chip"),
+    rejected(&[opcodes::ADD64_REG], "alu64_add_reg::tests::test_alu64_add_reg_wrong_sum"),
+    satisfied(&[opcodes::MOV64_IMM], "alu64_mov_imm::tests::test_alu64_mov_imm_chip"),
+    rejected(&[opcodes::MOV64_IMM], "alu64_mov_imm::tests::test_alu64_mov_imm_wrong_value"),
+    satisfied(&[opcodes::MOV32_IMM], "imm64::tests::test_mov32_imm_zero_extends"),
+    rejected(&[opcodes::MOV32_IMM], "imm64::tests::test_mov32_imm_sign_extended_fails"),
+    satisfied(&[opcodes::HOR64_IMM], "imm64::tests::test_hor64_chip"),
+    rejected(&[opcodes::HOR64_IMM], "imm64::tests::test_hor64_chip_wrong_result"),
+    satisfied(&[opcodes::EXIT], "exit::tests::test_exit_chip"),
+    rejected(&[opcodes::EXIT], "exit::tests::test_exit_chip_changed_register_fails"),
+    satisfied(&[opcodes::LSH64_IMM], "alu64_shift_imm::tests::test_alu64_lsh_imm_drops_high_bits"),
+    rejected(&[opcodes::LSH64_IMM], "alu64_shift_imm::tests::test_alu64_lsh_imm_wrong_result"),
+    satisfied(&[opcodes::RSH64_IMM], "alu64_shift_imm::tests::test_alu64_rsh_imm"),
+    rejected(&[opcodes::RSH64_IMM], "alu64_shift_imm::tests::test_alu64_rsh_imm_wrong_result"),
+    satisfied(MUL_IMM, "alu64_mul_imm::tests::test_alu64_mul_imm_wraps"),
+    rejected(MUL_IMM, "alu64_mul_imm::tests::test_alu64_mul_imm_wrong_result"),
+    satisfied(DIV_IMM, "alu64_div_imm::tests::test_alu64_div_imm"),
+    rejected(DIV_IMM, "alu64_div_imm::tests::test_alu64_div_imm_wrong_quotient"),
+    satisfied(
+        &[opcodes::UDIV64_REG, opcodes::UREM64_REG, opcodes::UREM64_IMM],
+        "pqr::tests::test_pqr_quotient_and_remainder",
+    ),
+    rejected(&[opcodes::UDIV64_REG], "pqr::tests::test_pqr_zero_divisor_register_fails"),
+    rejected(&[opcodes::UREM64_REG], "pqr::tests::test_pqr_wrong_remainder"),
+    rejected(&[opcodes::UREM64_IMM], "pqr::tests::test_pqr_imm_wrong_remainder"),
+    satisfied(LOAD, "memory::tests::test_ldw_chip"),
+    rejected(LOAD, "memory::tests::test_ldw_chip_value_exceeds_1_byte"),
+    satisfied(STORE, "memory::tests::test_stw_chip"),
+    rejected(STORE, "memory::tests::test_stw_frame_pointer_below_stack_fails"),
+];
+
+mod tests {
+    use super::*;
+    use crate::supported_opcodes;
+
+    #[test]
+    fn test_supported_opcodes_have_positive_and_negative_tests() {
+        for opcode in supported_opcodes() {
+            for expect in [Expect::Satisfied, Expect::Rejected] {
+                assert!(
+                    CHIP_TESTS
+                        .iter()
+                        .any(|t| t.expect == expect && t.opcodes.contains(&opcode)),
+                    "opcode 0x{:02x} has no registered {:?} chip test",
+                    opcode,
+                    expect
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_registered_tests_exist() {
+        let sources = [
+            ("alu64_add_imm", include_str!("alu64_add_imm.rs")),
+            ("alu64_add_reg", include_str!("alu64_add_reg.rs")),
+            ("alu64_div_imm", include_str!("alu64_div_imm.rs")),
+            ("alu64_mov_imm", include_str!("alu64_mov_imm.rs")),
+            ("alu64_mul_imm", include_str!("alu64_mul_imm.rs")),
+            ("alu64_shift_imm", include_str!("alu64_shift_imm.rs")),
+            ("exit", include_str!("exit.rs")),
+            ("imm64", include_str!("imm64.rs")),
+            ("memory", include_str!("memory.rs")),
+            ("pqr", include_str!("pqr.rs")),
+        ];
+
+        for test in CHIP_TESTS {
+            let (module, name) = test.test.split_once("::tests::").unwrap();
+            let (_, source) = sources
+                .iter()
+                .find(|(m, _)| *m == module)
+                .unwrap_or_else(|| panic!("{} is in an unknown chip module", test.test));
+            assert!(source.contains(&format!("fn {}()", name)), "{} does not exist", test.test);
+        }
+    }
+}
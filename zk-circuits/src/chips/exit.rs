@@ -8,7 +8,10 @@ use halo2_base::{
     utils::ScalarField,
     AssignedValue, Context,
 };
-use crate::{chips::BpfInstructionChip, Result};
+use crate::{
+    chips::{compare_u64, sign_extend_imm, BpfInstructionChip},
+    Result,
+};
 
 /// EXIT instruction chip
 ///
@@ -57,6 +60,92 @@ impl<F: ScalarField> BpfInstructionChip<F> for ExitChip {
     }
 }
 
+/// Return-a-constant chip for `mov64 r0, imm; exit`
+///
+/// Covers both instructions as one step: `exit` leaves the registers as the
+/// `mov` set them, so the state between the two needs no witness.
+///
+/// Constraints:
+/// 1. r0_after = imm, sign-extended from 32 to 64 bits
+/// 2. All other registers remain unchanged
+#[derive(Debug, Clone)]
+pub struct ReturnChip {
+    /// 32-bit immediate moved into r0
+    pub imm: i32,
+}
+
+impl ReturnChip {
+    /// Create a new return-a-constant chip
+    pub fn new(imm: i32) -> Self {
+        Self { imm }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for ReturnChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        gate.assert_is_const(ctx, &regs_after[0], &F::from(sign_extend_imm(self.imm)));
+
+        for i in 1..11 {
+            ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Return-code range chip
+///
+/// Proves the value in r0 at `exit` lies in `[min, max]`, for programs
+/// whose return codes carry meaning: `[0, 0]` proves the program
+/// succeeded, a wider range that it returned a known error class.
+///
+/// Constraints:
+/// 1. r0 fits in 64 bits
+/// 2. min <= r0 <= max (see [`compare_u64`])
+///
+/// The bounds are circuit constants, so they are fixed by the verifying
+/// key rather than passed as instance values.
+#[derive(Debug, Clone)]
+pub struct ReturnRangeChip {
+    /// Smallest accepted return value
+    pub min: u64,
+    /// Largest accepted return value
+    pub max: u64,
+}
+
+impl ReturnRangeChip {
+    /// Create a chip accepting return values in `[min, max]`
+    pub fn new(min: u64, max: u64) -> Self {
+        assert!(min <= max, "Empty return range [{}, {}]", min, max);
+        Self { min, max }
+    }
+
+    /// Constrain the return value cell `r0` to the range
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        r0: AssignedValue<F>,
+    ) -> Result<()> {
+        let min = ctx.load_constant(F::from(self.min));
+        let max = ctx.load_constant(F::from(self.max));
+
+        // Constrain: !(r0 < min) and !(r0 > max)
+        let (below, _, _) = compare_u64(ctx, gate, r0, min);
+        gate.assert_is_const(ctx, &below, &F::ZERO);
+        let (_, _, above) = compare_u64(ctx, gate, r0, max);
+        gate.assert_is_const(ctx, &above, &F::ZERO);
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,4 +179,59 @@ mod tests {
             chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
         });
     }
+
+    #[test]
+    fn test_exit_chip_changed_register_fails() {
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|i| ctx.load_witness(Fr::from(i as u64)));
+            // EXIT must not touch r3
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 3 { 4 } else { i as u64 }))
+            });
+
+            ExitChip::new().synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_return_chip_binds_r0() {
+        for (r0, expect_satisfied) in [(42u64, true), (41, false)] {
+            base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+                let regs_before: [AssignedValue<Fr>; 11] =
+                    std::array::from_fn(|i| ctx.load_witness(Fr::from(i as u64)));
+                let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                    ctx.load_witness(Fr::from(if i == 0 { r0 } else { i as u64 }))
+                });
+
+                ReturnChip::new(42).synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+            });
+        }
+    }
+
+    fn run_return_range(r0: u64, min: u64, max: u64, expect_satisfied: bool) {
+        let chip = ReturnRangeChip::new(min, max);
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let r0 = ctx.load_witness(Fr::from(r0));
+            chip.synthesize(ctx, gate, r0).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_return_range_success_code() {
+        run_return_range(0, 0, 0, true);
+    }
+
+    #[test]
+    fn test_return_range_rejects_error_code() {
+        run_return_range(5, 0, 0, false);
+    }
+
+    #[test]
+    fn test_return_range_bounds_are_inclusive() {
+        run_return_range(1, 1, 10, true);
+        run_return_range(10, 1, 10, true);
+        run_return_range(11, 1, 10, false);
+        run_return_range(u64::MAX, 0, u64::MAX, true);
+    }
 }
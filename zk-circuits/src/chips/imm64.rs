@@ -0,0 +1,190 @@
+//! 64-bit immediate instruction chips
+//!
+//! From SBPFv2 on, `lddw` is gone and a 64-bit constant is built in two
+//! steps: `mov32` sets the low half, then `hor64` ORs in the high half.
+//! Instruction formats: dst = (u32) imm, dst = dst | (imm << 32)
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::{load_bounded, BpfInstructionChip},
+    Result,
+};
+
+/// MOV32_IMM instruction chip
+///
+/// Constraints:
+/// 1. dst_after = imm, zero-extended from 32 to 64 bits
+/// 2. All other registers remain unchanged
+#[derive(Debug, Clone)]
+pub struct Mov32ImmChip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// 32-bit immediate, zero-extended to 64 bits when moved
+    pub imm: i32,
+}
+
+impl Mov32ImmChip {
+    /// Create a new MOV32_IMM chip
+    pub fn new(dst_reg: usize, imm: i32) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        Self { dst_reg, imm }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for Mov32ImmChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        // Constrain: dst_after = imm, zero-extended to 64 bits
+        let imm_f = F::from(self.imm as u32 as u64);
+        gate.assert_is_const(ctx, &regs_after[self.dst_reg], &imm_f);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// HOR64_IMM instruction chip
+///
+/// Constraints:
+/// 1. dst_before = high * 2^32 + low, with high and low 32-bit values and
+///    high decomposed into bits
+/// 2. dst_after = dst_before + (imm - (high & imm)) * 2^32, where
+///    high & imm sums the bits of high that are also set in imm; this is
+///    dst_before with the high half replaced by high | imm
+/// 3. All other registers remain unchanged
+#[derive(Debug, Clone)]
+pub struct Hor64Chip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// 32-bit immediate ORed into the high half of the register
+    pub imm: i32,
+}
+
+impl Hor64Chip {
+    /// Create a new HOR64_IMM chip
+    pub fn new(dst_reg: usize, imm: i32) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        Self { dst_reg, imm }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for Hor64Chip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        let imm = self.imm as u32;
+        let dst_before = regs_before[self.dst_reg];
+        let value = dst_before.value().get_lower_64();
+
+        // Split the register into its halves, keeping the high half's bits
+        let low = load_bounded(ctx, gate, (value & u32::MAX as u64) as u128, 32);
+        let high = ctx.load_witness(F::from(value >> 32));
+        let high_bits = gate.num_to_bits(ctx, high, 32);
+
+        // Constrain: dst_before = high * 2^32 + low
+        let two_pow_32 = QuantumCell::Constant(F::from(1u64 << 32));
+        let recomposed = gate.mul_add(ctx, high, two_pow_32, low);
+        ctx.constrain_equal(&dst_before, &recomposed);
+
+        // high & imm: the bits of high at the positions set in imm
+        let masked = gate.inner_product(
+            ctx,
+            high_bits,
+            (0..32).map(|i| QuantumCell::Constant(F::from((imm & (1 << i)) as u64))),
+        );
+
+        // Constrain: dst_after = dst_before + (imm - (high & imm)) * 2^32
+        let added = gate.sub(ctx, QuantumCell::Constant(F::from(imm as u64)), masked);
+        let result = gate.mul_add(ctx, added, two_pow_32, dst_before);
+        ctx.constrain_equal(&result, &regs_after[self.dst_reg]);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{halo2_proofs::halo2curves::bn256::Fr, utils::testing::base_test};
+
+    fn run_hor64(dst_value: u64, imm: i32, result: u64, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { dst_value } else { 100 }))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 0 { result } else { 100 }))
+            });
+
+            Hor64Chip::new(0, imm).synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_hor64_chip() {
+        run_hor64(0x9abc_def0, 0x1234_5678, 0x1234_5678_9abc_def0, true);
+        // Bits already set in the high half stay set
+        run_hor64(0x0f00_0000_0000_0001, 0x00f0_0000, 0x0ff0_0000_0000_0001, true);
+        // A negative immediate still only touches the high half
+        run_hor64(7, -1, 0xffff_ffff_0000_0007, true);
+    }
+
+    #[test]
+    fn test_hor64_chip_wrong_result() {
+        // Adding instead of ORing double-counts the shared bit
+        run_hor64(0x1_0000_0000, 1, 0x2_0000_0000, false);
+        run_hor64(0x9abc_def0, 0x1234_5678, 0x1234_5678_0000_0000, false);
+    }
+
+    #[test]
+    fn test_mov32_imm_zero_extends() {
+        base_test().run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|_| ctx.load_witness(Fr::from(5u64)));
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 2 { 0xffff_ffff } else { 5 }))
+            });
+
+            Mov32ImmChip::new(2, -1).synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_mov32_imm_sign_extended_fails() {
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let regs: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|_| ctx.load_witness(Fr::from(5u64)));
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i == 2 { u64::MAX } else { 5 }))
+            });
+
+            Mov32ImmChip::new(2, -1).synthesize(ctx, gate, &regs, &regs_after).unwrap();
+        });
+    }
+}
@@ -0,0 +1,139 @@
+//! Memory copy chip (sol_memcpy_)
+//!
+//! Constrains a bounded-length copy between two memory windows.
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::Result;
+
+/// Bounded-length memcpy chip
+///
+/// Proves `dst[i] = src[i]` for every `i < len`, where `len` is witnessed
+/// and at most `max_len`. The source and destination windows are always
+/// `max_len` bytes, so the circuit shape does not depend on the copied
+/// length; bytes past `len` are unconstrained.
+///
+/// Constraints:
+/// 1. Every window byte fits in 8 bits
+/// 2. Copy flags are boolean and non-increasing (a prefix of ones)
+/// 3. The flags sum to `len`, which bounds `len` by `max_len`
+/// 4. flag[i] * (dst[i] - src[i]) = 0
+///
+/// There is no memory table yet, so the windows are taken from the caller
+/// (e.g. the memory snapshot around the `sol_memcpy_` call) rather than
+/// looked up by address.
+#[derive(Debug, Clone)]
+pub struct MemcpyChip {
+    /// Largest copy length the chip can prove
+    pub max_len: usize,
+}
+
+impl MemcpyChip {
+    /// Create a chip for copies of up to `max_len` bytes
+    pub fn new(max_len: usize) -> Self {
+        assert!(max_len > 0, "Maximum copy length must be non-zero");
+        Self { max_len }
+    }
+
+    /// Synthesize the constraints for one copy of `len` bytes
+    ///
+    /// `src` and `dst` are the `max_len`-byte windows starting at the
+    /// source and destination addresses; `dst` is read after the copy.
+    /// Returns the assigned length cell.
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        src: &[u8],
+        dst: &[u8],
+        len: usize,
+    ) -> Result<AssignedValue<F>> {
+        anyhow::ensure!(
+            src.len() == self.max_len && dst.len() == self.max_len,
+            "Copy windows must be {} bytes, got {} source and {} destination",
+            self.max_len,
+            src.len(),
+            dst.len()
+        );
+        anyhow::ensure!(
+            len <= self.max_len,
+            "Copy length {} exceeds the maximum of {}",
+            len,
+            self.max_len
+        );
+
+        let len_cell = ctx.load_witness(F::from(len as u64));
+        let mut flag_sum = ctx.load_constant(F::ZERO);
+        let mut prev_flag: Option<AssignedValue<F>> = None;
+
+        for (i, (&src_byte, &dst_byte)) in src.iter().zip(dst).enumerate() {
+            let src_cell = ctx.load_witness(F::from(src_byte as u64));
+            let dst_cell = ctx.load_witness(F::from(dst_byte as u64));
+            let _src_bits = gate.num_to_bits(ctx, src_cell, 8);
+            let _dst_bits = gate.num_to_bits(ctx, dst_cell, 8);
+
+            let flag = ctx.load_witness(F::from((i < len) as u64));
+            gate.assert_bit(ctx, flag);
+
+            // A flag may only be set if the previous one is
+            if let Some(prev) = prev_flag {
+                let not_prev = gate.not(ctx, prev);
+                let violation = gate.mul(ctx, flag, not_prev);
+                gate.assert_is_const(ctx, &violation, &F::ZERO);
+            }
+
+            // Constrain: flag * (dst - src) = 0
+            let diff = gate.sub(ctx, dst_cell, src_cell);
+            let masked = gate.mul(ctx, flag, diff);
+            gate.assert_is_const(ctx, &masked, &F::ZERO);
+
+            flag_sum = gate.add(ctx, flag_sum, QuantumCell::Existing(flag));
+            prev_flag = Some(flag);
+        }
+
+        ctx.constrain_equal(&flag_sum, &len_cell);
+
+        Ok(len_cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn run_memcpy(src: &[u8], dst: &[u8], len: usize, expect_satisfied: bool) {
+        let chip = MemcpyChip::new(src.len());
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            chip.synthesize::<Fr>(ctx, gate, src, dst, len).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_memcpy_16_bytes() {
+        let src: Vec<u8> = (0..16).map(|i| i * 7 + 3).collect();
+        run_memcpy(&src, &src, 16, true);
+    }
+
+    #[test]
+    fn test_memcpy_ignores_bytes_past_length() {
+        let src = [1u8; 16];
+        let mut dst = src;
+        dst[12..].fill(0xff);
+        run_memcpy(&src, &dst, 12, true);
+    }
+
+    #[test]
+    fn test_memcpy_differing_byte_fails() {
+        let src: Vec<u8> = (0..16).map(|i| i * 7 + 3).collect();
+        let mut dst = src.clone();
+        dst[9] ^= 1;
+        run_memcpy(&src, &dst, 16, false);
+    }
+}
@@ -7,7 +7,108 @@ use halo2_base::{
     utils::ScalarField,
     AssignedValue, Context, QuantumCell,
 };
-use crate::{chips::BpfInstructionChip, Result};
+use bpf_tracer::memory::{stack_frame_size, MM_STACK_START, REGION_BITS};
+use crate::{
+    chips::BpfInstructionChip,
+    witness::{MemoryOpType, MemoryOpWitness},
+    Result,
+};
+
+/// Frame pointer register; accesses based on it must hit the stack
+const FRAME_POINTER_REG: usize = 10;
+
+/// Compute `base + offset` with the offset sign-extended
+///
+/// Negative offsets (e.g. `[r10 - 8]`) subtract from the base instead of
+/// adding their 64-bit two's complement.
+fn effective_address<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    base: AssignedValue<F>,
+    offset: i16,
+) -> AssignedValue<F> {
+    let magnitude = QuantumCell::Constant(F::from(offset.unsigned_abs() as u64));
+    if offset < 0 {
+        gate.sub(ctx, base, magnitude)
+    } else {
+        gate.add(ctx, base, magnitude)
+    }
+}
+
+/// Constrain an address to lie in the stack region
+///
+/// Regions are 2^32 aligned, so this checks `address - MM_STACK_START`
+/// fits in 32 bits.
+fn constrain_stack_address<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    address: AssignedValue<F>,
+) {
+    let stack_offset = gate.sub(ctx, address, QuantumCell::Constant(F::from(MM_STACK_START)));
+    let _bits = gate.num_to_bits(ctx, stack_offset, REGION_BITS as usize);
+}
+
+/// Fail unless `[r10 + offset]`, `width` bytes wide, lies in the current frame
+///
+/// The frame is the `stack_frame_size` bytes below the frame pointer. The
+/// offset is part of the instruction, so this needs no constraint: a
+/// function cannot address past its own frame through r10, and reaches
+/// other frames only through a pointer.
+fn ensure_in_frame(offset: i16, width: usize) -> Result<()> {
+    let offset = offset as i64;
+    anyhow::ensure!(
+        offset + width as i64 <= 0 && -offset <= stack_frame_size() as i64,
+        "[r10{:+}] ({} bytes) is outside the current stack frame",
+        offset,
+        width
+    );
+    Ok(())
+}
+
+/// Constrain an address to lie above the read-only program region
+///
+/// Bytecode and read-only data occupy the regions below the stack, and
+/// nothing there may be written. Checks `address - MM_STACK_START` fits in
+/// 64 bits, i.e. the address is at least `MM_STACK_START`.
+fn constrain_writable_address<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    address: AssignedValue<F>,
+) {
+    let writable_offset = gate.sub(ctx, address, QuantumCell::Constant(F::from(MM_STACK_START)));
+    let _bits = gate.num_to_bits(ctx, writable_offset, 64);
+}
+
+/// Cells a witnessed memory operation is bound to
+#[derive(Debug, Clone, Copy)]
+pub struct AssignedMemoryOp<F: ScalarField> {
+    /// Effective address
+    pub address: AssignedValue<F>,
+    /// Value loaded or stored
+    pub value: AssignedValue<F>,
+}
+
+/// Bind a witnessed memory operation to the step that performed it
+///
+/// Constrains the operation's address to `base + offset` and its value to
+/// the register cell it was loaded into or stored from.
+fn bind_memory_op<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    op: &MemoryOpWitness,
+    base: AssignedValue<F>,
+    offset: i16,
+    value: AssignedValue<F>,
+) -> AssignedMemoryOp<F> {
+    let address = effective_address(ctx, gate, base, offset);
+    let op_address = ctx.load_witness(F::from(op.address));
+    ctx.constrain_equal(&op_address, &address);
+
+    let op_value = ctx.load_witness(F::from(op.value));
+    ctx.constrain_equal(&op_value, &value);
+
+    AssignedMemoryOp { address: op_address, value: op_value }
+}
 
 /// LDW (Load Word) instruction chip
 ///
@@ -15,14 +116,17 @@ use crate::{chips::BpfInstructionChip, Result};
 /// Instruction format: dst = *(u64*)(src + offset)
 ///
 /// Constraints:
-/// 1. address = src + offset
-/// 2. dst_after = memory[address]
+/// 1. address = src + offset (offset sign-extended; in the stack region if src is r10)
+/// 2. dst_after < 2^(8 * width)
 /// 3. All other registers remain unchanged
 ///
-/// Note: In this MVP, we don't implement full memory consistency.
-/// We just verify the address calculation and that the destination
-/// register is updated. Full memory checking would require memory
-/// trace verification.
+/// Loads relative to r10 must lie in the current stack frame, the
+/// `stack_frame_size` bytes below it; synthesis fails otherwise.
+///
+/// That `dst_after = memory[address]` is not checked by the chip alone:
+/// [`Self::synthesize_memory_op`] binds the address and `dst_after` to the
+/// step's memory operation, which the
+/// [memory table](crate::memory_table) checks against earlier accesses.
 #[derive(Debug, Clone)]
 pub struct LdwChip {
     /// Destination register index (0-10)
@@ -31,16 +135,45 @@ pub struct LdwChip {
     pub src_reg: usize,
     /// Offset from base address
     pub offset: i16,
-    /// The value loaded from memory (witness)
-    pub loaded_value: u64,
+    /// Load width in bytes (1, 2, 4 or 8)
+    pub width: usize,
 }
 
 impl LdwChip {
-    /// Create a new LDW chip
-    pub fn new(dst_reg: usize, src_reg: usize, offset: i16, loaded_value: u64) -> Self {
+    /// Create a new LDW chip for an 8-byte load
+    pub fn new(dst_reg: usize, src_reg: usize, offset: i16) -> Self {
         assert!(dst_reg < 11, "Invalid destination register index");
         assert!(src_reg < 11, "Invalid source register index");
-        Self { dst_reg, src_reg, offset, loaded_value }
+        Self { dst_reg, src_reg, offset, width: 8 }
+    }
+
+    /// Set the load width in bytes
+    pub fn with_width(mut self, width: usize) -> Self {
+        assert!(matches!(width, 1 | 2 | 4 | 8), "Invalid load width");
+        self.width = width;
+        self
+    }
+
+    /// Constrain the witnessed memory operation of this load
+    ///
+    /// Returns the cells it is bound to. Fails if `op` is not a load of
+    /// this width.
+    pub fn synthesize_memory_op<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        op: &MemoryOpWitness,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<AssignedMemoryOp<F>> {
+        anyhow::ensure!(
+            op.op_type == MemoryOpType::Load && op.size as usize == self.width,
+            "Memory operation at pc {} is not a {}-byte load",
+            op.pc,
+            self.width
+        );
+        let base = regs_before[self.src_reg];
+        Ok(bind_memory_op(ctx, gate, op, base, self.offset, regs_after[self.dst_reg]))
     }
 }
 
@@ -53,19 +186,17 @@ impl<F: ScalarField> BpfInstructionChip<F> for LdwChip {
         regs_after: &[AssignedValue<F>; 11],
     ) -> Result<()> {
         // Calculate address = src + offset
-        let src = regs_before[self.src_reg];
-        let offset_u64 = self.offset as u64;
-        let _address = gate.add(ctx, src, QuantumCell::Constant(F::from(offset_u64)));
-
-        // In a full implementation, we would:
-        // 1. Verify address is valid
-        // 2. Lookup the value in a memory trace
-        // 3. Constrain dst = memory[address]
-        //
-        // For MVP, we just constrain that dst_after = loaded_value
-        let loaded_value_f = F::from(self.loaded_value);
-        let loaded_value_cell = ctx.load_witness(loaded_value_f);
-        ctx.constrain_equal(&loaded_value_cell, &regs_after[self.dst_reg]);
+        let address = effective_address(ctx, gate, regs_before[self.src_reg], self.offset);
+
+        // Frame-pointer relative loads read the stack
+        if self.src_reg == FRAME_POINTER_REG {
+            ensure_in_frame(self.offset, self.width)?;
+            constrain_stack_address(ctx, gate, address);
+        }
+
+        // Constrain the loaded value to fit the load width: the bits must
+        // recompose to the value
+        let _bits = gate.num_to_bits(ctx, regs_after[self.dst_reg], 8 * self.width);
 
         // Constrain that all other registers remain unchanged
         for i in 0..11 {
@@ -84,13 +215,17 @@ impl<F: ScalarField> BpfInstructionChip<F> for LdwChip {
 /// Instruction format: *(u64*)(dst + offset) = src
 ///
 /// Constraints:
-/// 1. address = dst + offset
-/// 2. memory[address] = src
-/// 3. All registers remain unchanged (STW doesn't modify registers)
+/// 1. address = dst + offset (offset sign-extended; in the stack region if dst is r10)
+/// 2. address is not in the read-only program region
+/// 3. memory[address] = src
+/// 4. All registers remain unchanged (STW doesn't modify registers)
 ///
-/// Note: In this MVP, we don't implement full memory consistency.
-/// We just verify the address calculation. Full memory checking
-/// would require memory trace verification.
+/// Stores relative to r10 must lie in the current stack frame, as for
+/// [`LdwChip`]; synthesis fails otherwise.
+///
+/// The write itself is recorded by [`Self::synthesize_memory_op`], which
+/// binds the step's memory operation to the address and `src`; later
+/// loads are checked against it by the [memory table](crate::memory_table).
 #[derive(Debug, Clone)]
 pub struct StwChip {
     /// Destination register index (base address, 0-10)
@@ -108,6 +243,26 @@ impl StwChip {
         assert!(src_reg < 11, "Invalid source register index");
         Self { dst_reg, src_reg, offset }
     }
+
+    /// Constrain the witnessed memory operation of this store
+    ///
+    /// Returns the cells it is bound to. Fails if `op` is not an 8-byte
+    /// store.
+    pub fn synthesize_memory_op<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        op: &MemoryOpWitness,
+        regs_before: &[AssignedValue<F>; 11],
+    ) -> Result<AssignedMemoryOp<F>> {
+        anyhow::ensure!(
+            op.op_type == MemoryOpType::Store && op.size == 8,
+            "Memory operation at pc {} is not an 8-byte store",
+            op.pc
+        );
+        let base = regs_before[self.dst_reg];
+        Ok(bind_memory_op(ctx, gate, op, base, self.offset, regs_before[self.src_reg]))
+    }
 }
 
 impl<F: ScalarField> BpfInstructionChip<F> for StwChip {
@@ -119,19 +274,16 @@ impl<F: ScalarField> BpfInstructionChip<F> for StwChip {
         regs_after: &[AssignedValue<F>; 11],
     ) -> Result<()> {
         // Calculate address = dst + offset
-        let dst = regs_before[self.dst_reg];
-        let offset_u64 = self.offset as u64;
-        let _address = gate.add(ctx, dst, QuantumCell::Constant(F::from(offset_u64)));
-
-        // Get the value to store
-        let _src_value = regs_before[self.src_reg];
+        let address = effective_address(ctx, gate, regs_before[self.dst_reg], self.offset);
 
-        // In a full implementation, we would:
-        // 1. Verify address is valid
-        // 2. Record the memory write in a memory trace
-        // 3. Constrain memory[address] = src
-        //
-        // For MVP, we just constrain that registers don't change
+        // Frame-pointer relative stores write the stack; no store may
+        // write the program region
+        if self.dst_reg == FRAME_POINTER_REG {
+            ensure_in_frame(self.offset, 8)?;
+            constrain_stack_address(ctx, gate, address);
+        } else {
+            constrain_writable_address(ctx, gate, address);
+        }
 
         // STW doesn't modify any registers
         for i in 0..11 {
@@ -145,6 +297,7 @@ impl<F: ScalarField> BpfInstructionChip<F> for StwChip {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bpf_tracer::memory::MM_HEAP_START;
     use halo2_base::{
         utils::testing::base_test,
         halo2_proofs::halo2curves::bn256::Fr,
@@ -180,20 +333,94 @@ mod tests {
                 }
             });
 
-            let chip = LdwChip::new(dst_reg, src_reg, offset, loaded_value);
+            let chip = LdwChip::new(dst_reg, src_reg, offset);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    /// Run a load of `loaded_value` into r0 with the given width
+    fn run_ldw_with_width(loaded_value: u64, width: usize, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|_| {
+                ctx.load_witness(Fr::from(0u64))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                if i == 0 {
+                    ctx.load_witness(Fr::from(loaded_value))
+                } else {
+                    ctx.load_witness(Fr::from(0u64))
+                }
+            });
+
+            let chip = LdwChip::new(0, 1, 0).with_width(width);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_ldw_chip_max_u64_fits_8_bytes() {
+        run_ldw_with_width(u64::MAX, 8, true);
+    }
+
+    #[test]
+    fn test_ldw_chip_value_exceeds_1_byte() {
+        run_ldw_with_width(256, 1, false);
+    }
+
+    /// Run `stxdw [r10 + offset], r1` with r10 at `frame_pointer`
+    fn run_stack_store(frame_pointer: u64, offset: i16, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs: [u64; 11] = std::array::from_fn(|i| match i {
+                1 => 99,
+                10 => frame_pointer,
+                _ => 0,
+            });
+            let regs_before: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|i| ctx.load_witness(Fr::from(regs[i])));
+            let regs_after: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|i| ctx.load_witness(Fr::from(regs[i])));
+
+            let chip = StwChip::new(10, 1, offset);
             chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
         });
     }
 
+    #[test]
+    fn test_stw_frame_pointer_negative_offset() {
+        run_stack_store(MM_STACK_START + 0x1000, -8, true);
+    }
+
+    #[test]
+    fn test_stw_frame_pointer_below_stack_fails() {
+        // r10 - 8 falls below the start of the stack region
+        run_stack_store(MM_STACK_START, -8, false);
+    }
+
+    #[test]
+    fn test_stw_outside_frame_rejected() {
+        base_test().run_gate(|ctx, gate| {
+            let regs: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|_| ctx.load_witness(Fr::from(MM_STACK_START + 0x2000)));
+
+            // Above the frame pointer
+            let chip = StwChip::new(10, 1, 8);
+            assert!(chip.synthesize(ctx, gate, &regs, &regs).is_err());
+
+            // Straddling the frame pointer
+            let chip = StwChip::new(10, 1, -4);
+            assert!(chip.synthesize(ctx, gate, &regs, &regs).is_err());
+        });
+    }
+
     #[test]
     fn test_stw_chip() {
         base_test().run_gate(|ctx, gate| {
             // Create test register states
-            // r1 = base address = 2000
+            // r1 = base address = MM_HEAP_START + 2000
             // r2 = value to store = 99
             let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
                 if i == 1 {
-                    ctx.load_witness(Fr::from(2000u64))
+                    ctx.load_witness(Fr::from(MM_HEAP_START + 2000))
                 } else if i == 2 {
                     ctx.load_witness(Fr::from(99u64))
                 } else {
@@ -202,7 +429,7 @@ mod tests {
             });
 
             // STW: *(r1 + 16) = r2
-            // Store value from r2 to memory address 2016
+            // Store value from r2 to memory address MM_HEAP_START + 2016
             let dst_reg = 1;
             let src_reg = 2;
             let offset = 16i16;
@@ -210,7 +437,7 @@ mod tests {
             // STW doesn't modify registers
             let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
                 if i == 1 {
-                    ctx.load_witness(Fr::from(2000u64))
+                    ctx.load_witness(Fr::from(MM_HEAP_START + 2000))
                 } else if i == 2 {
                     ctx.load_witness(Fr::from(99u64))
                 } else {
@@ -222,4 +449,23 @@ mod tests {
             chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
         });
     }
+
+    #[test]
+    fn test_stw_into_program_region_fails() {
+        // *(r1 + 0) = r2 with r1 = MM_RODATA_START
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let regs: [u64; 11] = std::array::from_fn(|i| match i {
+                1 => 1 << REGION_BITS,
+                2 => 42,
+                _ => 0,
+            });
+            let regs_before: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|i| ctx.load_witness(Fr::from(regs[i])));
+            let regs_after: [AssignedValue<Fr>; 11] =
+                std::array::from_fn(|i| ctx.load_witness(Fr::from(regs[i])));
+
+            let chip = StwChip::new(1, 2, 0);
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
 }
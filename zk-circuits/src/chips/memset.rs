@@ -0,0 +1,134 @@
+//! Memory fill chip (sol_memset_)
+//!
+//! Constrains a bounded-length fill of a memory window with one byte.
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::Result;
+
+/// Bounded-length memset chip
+///
+/// Proves `dst[i] = fill` for every `i < len`, where `len` is witnessed
+/// and at most `max_len`. The destination window is always `max_len`
+/// bytes, so the circuit shape does not depend on the filled length;
+/// bytes past `len` are unconstrained.
+///
+/// Constraints:
+/// 1. Every window byte and the fill byte fit in 8 bits
+/// 2. Fill flags are boolean and non-increasing (a prefix of ones)
+/// 3. The flags sum to `len`, which bounds `len` by `max_len`
+/// 4. flag[i] * (dst[i] - fill) = 0
+///
+/// As with [`MemcpyChip`](super::MemcpyChip), there is no memory table
+/// yet, so the window is taken from the caller (e.g. the memory snapshot
+/// after the `sol_memset_` call) rather than looked up by address.
+#[derive(Debug, Clone)]
+pub struct MemsetChip {
+    /// Largest fill length the chip can prove
+    pub max_len: usize,
+}
+
+impl MemsetChip {
+    /// Create a chip for fills of up to `max_len` bytes
+    pub fn new(max_len: usize) -> Self {
+        assert!(max_len > 0, "Maximum fill length must be non-zero");
+        Self { max_len }
+    }
+
+    /// Synthesize the constraints for one fill of `len` bytes with `fill`
+    ///
+    /// `dst` is the `max_len`-byte window starting at the destination
+    /// address, read after the fill. Returns the assigned length cell.
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        dst: &[u8],
+        fill: u8,
+        len: usize,
+    ) -> Result<AssignedValue<F>> {
+        anyhow::ensure!(
+            dst.len() == self.max_len,
+            "Fill window must be {} bytes, got {}",
+            self.max_len,
+            dst.len()
+        );
+        anyhow::ensure!(
+            len <= self.max_len,
+            "Fill length {} exceeds the maximum of {}",
+            len,
+            self.max_len
+        );
+
+        let len_cell = ctx.load_witness(F::from(len as u64));
+        let fill_cell = ctx.load_witness(F::from(fill as u64));
+        let _fill_bits = gate.num_to_bits(ctx, fill_cell, 8);
+        let mut flag_sum = ctx.load_constant(F::ZERO);
+        let mut prev_flag: Option<AssignedValue<F>> = None;
+
+        for (i, &dst_byte) in dst.iter().enumerate() {
+            let dst_cell = ctx.load_witness(F::from(dst_byte as u64));
+            let _dst_bits = gate.num_to_bits(ctx, dst_cell, 8);
+
+            let flag = ctx.load_witness(F::from((i < len) as u64));
+            gate.assert_bit(ctx, flag);
+
+            // A flag may only be set if the previous one is
+            if let Some(prev) = prev_flag {
+                let not_prev = gate.not(ctx, prev);
+                let violation = gate.mul(ctx, flag, not_prev);
+                gate.assert_is_const(ctx, &violation, &F::ZERO);
+            }
+
+            // Constrain: flag * (dst - fill) = 0
+            let diff = gate.sub(ctx, dst_cell, fill_cell);
+            let masked = gate.mul(ctx, flag, diff);
+            gate.assert_is_const(ctx, &masked, &F::ZERO);
+
+            flag_sum = gate.add(ctx, flag_sum, QuantumCell::Existing(flag));
+            prev_flag = Some(flag);
+        }
+
+        ctx.constrain_equal(&flag_sum, &len_cell);
+
+        Ok(len_cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn run_memset(dst: &[u8], fill: u8, len: usize, expect_satisfied: bool) {
+        let chip = MemsetChip::new(dst.len());
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            chip.synthesize::<Fr>(ctx, gate, dst, fill, len).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_memset_32_bytes_to_zero() {
+        run_memset(&[0u8; 32], 0, 32, true);
+    }
+
+    #[test]
+    fn test_memset_ignores_bytes_past_length() {
+        let mut dst = [0xaau8; 16];
+        dst[10..].fill(0x11);
+        run_memset(&dst, 0xaa, 10, true);
+    }
+
+    #[test]
+    fn test_memset_unfilled_byte_fails() {
+        let mut dst = [0u8; 32];
+        dst[17] = 1;
+        run_memset(&dst, 0, 32, false);
+    }
+}
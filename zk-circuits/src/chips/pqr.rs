@@ -0,0 +1,189 @@
+//! PQR (product/quotient/remainder) instruction chip
+//!
+//! Constrains the unsigned 64-bit division family of the sBPF PQR
+//! instruction class (SIMD-0174, enabled from SBPFv2).
+//! Instruction formats: dst = dst / divisor, dst = dst % divisor
+
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::{load_bounded, BpfInstructionChip},
+    Result,
+};
+
+/// Where a PQR instruction takes its divisor from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PqrDivisor {
+    /// Immediate, already extended to 64 bits as the opcode specifies
+    Imm(u64),
+    /// Source register index (0-10)
+    Reg(usize),
+}
+
+/// Which division output a PQR instruction writes to dst
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PqrOutput {
+    /// UDIV: the quotient
+    Quotient,
+    /// UREM: the remainder
+    Remainder,
+}
+
+/// PQR division chip
+///
+/// Quotient and remainder are constrained together whichever one the
+/// instruction keeps, so `udiv` and `urem` share one set of constraints.
+///
+/// Constraints:
+/// 1. dst_before = quotient * divisor + remainder, with quotient < 2^64
+/// 2. remainder < divisor (divisor - 1 - remainder fits in 64 bits), which
+///    also rules out a zero divisor
+/// 3. dst_after = quotient or remainder, per [`PqrOutput`]
+/// 4. All other registers remain unchanged
+#[derive(Debug, Clone)]
+pub struct PqrChip {
+    /// Destination register index (0-10)
+    pub dst_reg: usize,
+    /// Divisor operand
+    pub divisor: PqrDivisor,
+    /// Output written to dst
+    pub output: PqrOutput,
+}
+
+impl PqrChip {
+    /// Create a new PQR division chip
+    pub fn new(dst_reg: usize, divisor: PqrDivisor, output: PqrOutput) -> Self {
+        assert!(dst_reg < 11, "Invalid register index");
+        match divisor {
+            PqrDivisor::Imm(imm) => assert!(imm != 0, "Division by zero"),
+            PqrDivisor::Reg(src_reg) => assert!(src_reg < 11, "Invalid register index"),
+        }
+        Self { dst_reg, divisor, output }
+    }
+}
+
+impl<F: ScalarField> BpfInstructionChip<F> for PqrChip {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        let dst_before = regs_before[self.dst_reg];
+        let dividend = dst_before.value().get_lower_64();
+        let (divisor_value, divisor) = match self.divisor {
+            PqrDivisor::Imm(imm) => (imm, QuantumCell::Constant(F::from(imm))),
+            PqrDivisor::Reg(src_reg) => {
+                let src = regs_before[src_reg];
+                (src.value().get_lower_64(), QuantumCell::Existing(src))
+            }
+        };
+
+        // A zero divisor has no valid witness; the VM faults before tracing it
+        let (q, r) = match divisor_value {
+            0 => (0, 0),
+            d => (dividend / d, dividend % d),
+        };
+        let quotient = load_bounded(ctx, gate, q as u128, 64);
+        let remainder = load_bounded(ctx, gate, r as u128, 64);
+
+        // Constrain: dst_before = quotient * divisor + remainder
+        let recomposed = gate.mul_add(ctx, quotient, divisor, remainder);
+        ctx.constrain_equal(&dst_before, &recomposed);
+
+        // Constrain: remainder + slack = divisor - 1, with slack < 2^64
+        let slack = load_bounded(
+            ctx,
+            gate,
+            divisor_value.wrapping_sub(1).wrapping_sub(r) as u128,
+            64,
+        );
+        let bound = gate.add(ctx, remainder, slack);
+        match self.divisor {
+            PqrDivisor::Imm(imm) => gate.assert_is_const(ctx, &bound, &F::from(imm - 1)),
+            PqrDivisor::Reg(src_reg) => {
+                let divisor_minus_one =
+                    gate.sub(ctx, regs_before[src_reg], QuantumCell::Constant(F::ONE));
+                ctx.constrain_equal(&bound, &divisor_minus_one);
+            }
+        }
+
+        let result = match self.output {
+            PqrOutput::Quotient => quotient,
+            PqrOutput::Remainder => remainder,
+        };
+        ctx.constrain_equal(&result, &regs_after[self.dst_reg]);
+
+        // Constrain that all other registers remain unchanged
+        for i in 0..11 {
+            if i != self.dst_reg {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn run_pqr(chip: PqrChip, regs: [u64; 2], result: u64, expect_satisfied: bool) {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let regs_before: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                ctx.load_witness(Fr::from(if i < 2 { regs[i] } else { i as u64 }))
+            });
+            let regs_after: [AssignedValue<Fr>; 11] = std::array::from_fn(|i| {
+                let value = match i {
+                    0 => result,
+                    1 => regs[1],
+                    _ => i as u64,
+                };
+                ctx.load_witness(Fr::from(value))
+            });
+
+            chip.synthesize(ctx, gate, &regs_before, &regs_after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_pqr_quotient_and_remainder() {
+        let urem = PqrChip::new(0, PqrDivisor::Reg(1), PqrOutput::Remainder);
+        run_pqr(urem, [100, 7], 2, true);
+
+        let udiv = PqrChip::new(0, PqrDivisor::Reg(1), PqrOutput::Quotient);
+        run_pqr(udiv, [100, 7], 14, true);
+
+        let urem_imm = PqrChip::new(0, PqrDivisor::Imm(10), PqrOutput::Remainder);
+        run_pqr(urem_imm, [u64::MAX, 0], u64::MAX % 10, true);
+    }
+
+    #[test]
+    fn test_pqr_wrong_remainder() {
+        // 100 = 13 * 7 + 9, but 9 is not below the divisor
+        let urem = PqrChip::new(0, PqrDivisor::Reg(1), PqrOutput::Remainder);
+        run_pqr(urem, [100, 7], 9, false);
+    }
+
+    #[test]
+    fn test_pqr_imm_wrong_remainder() {
+        // 105 = 9 * 10 + 15, but 15 is not below the divisor
+        let urem_imm = PqrChip::new(0, PqrDivisor::Imm(10), PqrOutput::Remainder);
+        run_pqr(urem_imm, [105, 0], 15, false);
+    }
+
+    #[test]
+    fn test_pqr_zero_divisor_register_fails() {
+        let udiv = PqrChip::new(0, PqrDivisor::Reg(1), PqrOutput::Quotient);
+        run_pqr(udiv, [100, 0], 0, false);
+    }
+}
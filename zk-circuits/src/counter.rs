@@ -2,13 +2,102 @@
 //!
 //! ZK circuit that proves correct execution of a counter increment program.
 
-use bpf_tracer::{ExecutionTrace, InstructionTrace, RegisterState};
+use std::{ops::Range, sync::Arc};
+
+use bpf_tracer::{
+    decode::{opcodes, INSN_SIZE},
+    memory::call_frame_stride,
+    DecodedInsn, ExecutionTrace, InstructionTrace, OpcodeClass, SBPFVersion,
+};
 use halo2_base::{
     gates::GateInstructions,
     utils::ScalarField,
-    AssignedValue, Context,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::ReturnRangeChip,
+    dispatch::{word_opcode, InstructionChip},
+    memory_table::{constrain_memory_table, sorted_order, InitialMemory},
+    registry::ChipRegistry,
+    witness::{MemoryOpWitness, Witness},
+    Result,
 };
-use crate::Result;
+
+/// Where a circuit reads its private witness from
+enum CircuitSource {
+    /// Full execution trace
+    Trace(ExecutionTrace),
+    /// Witness register vectors and program counters
+    Witness(Witness),
+}
+
+/// What a circuit requires of the end of its trace
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CircuitMode {
+    /// The trace is a whole execution: the last executed instruction must
+    /// be `exit`
+    FullProgram,
+    /// The trace is a window or truncated prefix: only the state transition
+    /// from the initial to the final registers is proven
+    ///
+    /// The default, since chunked proving splits one execution into windows
+    /// that mostly do not end in `exit`.
+    #[default]
+    Partial,
+}
+
+/// What [`CounterCircuit::synthesize`] assigned
+#[derive(Debug, Clone)]
+pub struct SynthesisOutput<F: ScalarField> {
+    /// Final register cells (r0-r10)
+    pub final_registers: [AssignedValue<F>; 11],
+    /// Number of executed instructions
+    ///
+    /// Summed in circuit over the steps whose opcode is not padding
+    /// [`opcodes::NOP`]; a fused `mov64 r0, imm; exit` counts as two.
+    pub instruction_count: AssignedValue<F>,
+    /// PCs of executed instructions that were left unconstrained
+    ///
+    /// Always empty unless [`CounterCircuit::with_allow_unconstrained`] is
+    /// set. If not empty, the proof does not cover these instructions: they
+    /// have no chip, or are loads reading memory the circuit has no initial
+    /// value for (see [`InitialMemory::Entry`]).
+    pub unconstrained_pcs: Vec<u64>,
+    /// Advice cells each step assigned, in execution order
+    pub step_regions: Vec<StepRegion>,
+}
+
+/// Advice cells assigned for one step of [`CounterCircuit::synthesize`]
+///
+/// Lets a constraint failure at a cell offset be traced back to the
+/// instruction and register behind it (see [`crate::debug::diagnose`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepRegion {
+    /// Index in the trace of the step's first instruction
+    pub index: usize,
+    /// PC of that instruction
+    pub pc: u64,
+    /// Offsets in the context's advice of every cell the step assigned
+    pub cells: Range<usize>,
+    /// Offset of the witnessed r0 after the step; r1-r10 follow it
+    pub registers_after: usize,
+}
+
+impl StepRegion {
+    /// Register whose after-step value is witnessed at `offset`, if any
+    pub fn register_at(&self, offset: usize) -> Option<usize> {
+        offset.checked_sub(self.registers_after).filter(|&register| register < 11)
+    }
+}
+
+/// One instruction step as read by synthesis
+struct Step<'a> {
+    pc: u64,
+    instruction_bytes: &'a [u8],
+    registers_after: &'a [u64],
+    /// PC a `call imm` into the program jumps to
+    call_target: Option<u64>,
+}
 
 /// Counter circuit with public inputs for initial and final state
 ///
@@ -17,26 +106,65 @@ use crate::Result;
 ///
 /// For recursive proving, this circuit supports fixed-size chunks with padding.
 ///
+/// The executed sequence is accounted for step by step:
+/// - the i-th executed step carries index `i`, and the count of steps
+///   proven equals the public instruction count;
+/// - each step's instruction is the program's instruction at its PC, looked
+///   up in the program text (see [`Self::program_words`]);
+/// - consecutive steps are chained by PC: a non-branching instruction
+///   continues at the next PC, `ja` at its target, and a conditional jump
+///   at one of the two, a call into the program at the function it calls,
+///   and the `exit` returning from it right after the call.
+///
+/// A call opens a frame: it keeps r0-r9 and moves the frame pointer r10 to
+/// the callee's stack frame, and the matching `exit` restores r6-r10 to
+/// what they were at the call. Which `exit` returns from which call is
+/// fixed by the trace, like the rest of the circuit's shape. Loads and
+/// stores relative to r10 must stay within its frame.
+///
+/// Every load must read what the last store to its address wrote; the
+/// [memory table](crate::memory_table) checks this across all steps. A
+/// load with no store before it reads zero in the stack and heap of a
+/// [`CircuitMode::FullProgram`] circuit, which starts where the VM starts
+/// a program; a window's memory is part of its witnessed starting state.
+///
 /// Public Inputs:
 /// - Initial register state (r0-r10)
 /// - Final register state (r0-r10)
+/// - Executed instruction count (see [`SynthesisOutput::instruction_count`])
 ///
 /// Private Witness:
-/// - Full execution trace of the counter program (padded to chunk_size if needed)
+/// - Full execution trace of the counter program (padded to chunk_size if needed),
+///   or the equivalent [`Witness`]
 pub struct CounterCircuit {
-    /// Execution trace (private witness)
-    trace: ExecutionTrace,
+    /// Execution trace or witness (private witness)
+    source: CircuitSource,
     /// Maximum instructions (for chunked proving with padding)
     /// If None, uses actual trace length (legacy mode)
     chunk_size: Option<usize>,
+    /// Number of executed (non-padding) instructions
+    executed_instructions: usize,
+    /// Whether the trace must end in `exit`
+    mode: CircuitMode,
+    /// Whether instructions without a chip are let through unconstrained
+    allow_unconstrained: bool,
+    /// Range the return value in r0 must lie in, if any
+    return_range: Option<ReturnRangeChip>,
+    /// User-registered chips, consulted before the built-in ones
+    chip_registry: Option<Arc<ChipRegistry>>,
 }
 
 impl CounterCircuit {
     /// Create a new counter circuit from an execution trace (legacy mode)
     pub fn from_trace(trace: ExecutionTrace) -> Self {
         Self {
-            trace,
+            executed_instructions: trace.instruction_count(),
+            source: CircuitSource::Trace(trace),
             chunk_size: None,
+            mode: CircuitMode::default(),
+            allow_unconstrained: false,
+            return_range: None,
+            chip_registry: None,
         }
     }
 
@@ -45,13 +173,126 @@ impl CounterCircuit {
     /// The trace will be padded to `chunk_size` with NOP instructions if needed.
     /// This ensures the circuit has fixed shape for recursive proving.
     pub fn from_trace_chunked(trace: ExecutionTrace, chunk_size: usize) -> Self {
+        let executed_instructions = trace.instruction_count().min(chunk_size);
         let padded_trace = Self::pad_trace(trace, chunk_size);
         Self {
-            trace: padded_trace,
+            source: CircuitSource::Trace(padded_trace),
             chunk_size: Some(chunk_size),
+            executed_instructions,
+            mode: CircuitMode::default(),
+            allow_unconstrained: false,
+            return_range: None,
+            chip_registry: None,
+        }
+    }
+
+    /// Create a new counter circuit from a witness (legacy mode)
+    ///
+    /// Synthesis reads the witness's register states, program counters and
+    /// instruction bytes, so the execution trace need not be kept around
+    /// through proving. The circuit is the same one [`Self::from_trace`]
+    /// builds from the originating trace.
+    pub fn from_witness(witness: Witness) -> Self {
+        Self {
+            executed_instructions: witness.instruction_count(),
+            source: CircuitSource::Witness(witness),
+            chunk_size: None,
+            mode: CircuitMode::default(),
+            allow_unconstrained: false,
+            return_range: None,
+            chip_registry: None,
+        }
+    }
+
+    /// Create a new counter circuit from a witness for chunked proving
+    ///
+    /// The witness is padded to `chunk_size` with NOP steps, matching
+    /// [`Self::from_trace_chunked`].
+    pub fn from_witness_chunked(witness: Witness, chunk_size: usize) -> Self {
+        let executed_instructions = witness.instruction_count().min(chunk_size);
+        let padded_witness = Self::pad_witness(witness, chunk_size);
+        Self {
+            source: CircuitSource::Witness(padded_witness),
+            chunk_size: Some(chunk_size),
+            executed_instructions,
+            mode: CircuitMode::default(),
+            allow_unconstrained: false,
+            return_range: None,
+            chip_registry: None,
+        }
+    }
+
+    /// Set what the circuit requires of the end of the trace
+    pub fn with_mode(mut self, mode: CircuitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// What the circuit requires of the end of the trace
+    pub fn mode(&self) -> CircuitMode {
+        self.mode
+    }
+
+    /// Let instructions without a chip through unconstrained
+    ///
+    /// By default synthesis fails on an executed instruction no chip
+    /// supports. With this set, such an instruction's register transition
+    /// is witnessed but not constrained, and its PC is reported in
+    /// [`SynthesisOutput::unconstrained_pcs`].
+    ///
+    /// In [`CircuitMode::FullProgram`], loads reading program or input
+    /// memory nothing was stored to are let through and reported the same
+    /// way; otherwise they fail synthesis.
+    ///
+    /// **This weakens soundness**: a proof of such a circuit says nothing
+    /// about the skipped instructions. It is meant for bringing up new
+    /// programs before every opcode they use has a chip.
+    pub fn with_allow_unconstrained(mut self, allow_unconstrained: bool) -> Self {
+        self.allow_unconstrained = allow_unconstrained;
+        self
+    }
+
+    /// Prove opcodes registered in `registry` with the registered chips
+    ///
+    /// Registered opcodes take precedence over the built-in chips (see
+    /// [`InstructionChip::select_with`]).
+    pub fn with_chip_registry(mut self, registry: Arc<ChipRegistry>) -> Self {
+        self.chip_registry = Some(registry);
+        self
+    }
+
+    /// Require the return value (r0 at `exit`) to lie in `[min, max]`
+    ///
+    /// Only valid in [`CircuitMode::FullProgram`], where the trace ends in
+    /// `exit`; synthesis fails otherwise. See [`ReturnRangeChip`].
+    ///
+    /// # Panics
+    /// If `min > max`.
+    pub fn with_return_range(mut self, min: u64, max: u64) -> Self {
+        self.return_range = Some(ReturnRangeChip::new(min, max));
+        self
+    }
+
+    /// The (padded) execution trace this circuit proves
+    ///
+    /// Returns `None` for circuits built from a witness.
+    pub fn trace(&self) -> Option<&ExecutionTrace> {
+        match &self.source {
+            CircuitSource::Trace(trace) => Some(trace),
+            CircuitSource::Witness(_) => None,
         }
     }
 
+    /// Chunk size the trace was padded to, if any
+    pub fn chunk_size(&self) -> Option<usize> {
+        self.chunk_size
+    }
+
+    /// Number of executed instructions, excluding padding
+    pub fn executed_instructions(&self) -> usize {
+        self.executed_instructions
+    }
+
     /// Pad a trace to the specified chunk size with NOP instructions
     ///
     /// NOP instructions maintain register state (registers_after == registers_before)
@@ -78,6 +319,8 @@ impl CounterCircuit {
                 instruction_bytes: vec![0x00; 8], // NOP opcode (0x00 in sBPF)
                 registers_before: last_regs.clone(),
                 registers_after: last_regs.clone(), // NOP: no state change
+                call_target: None,
+                text_offset: None,
             };
             trace.instructions.push(nop);
         }
@@ -86,52 +329,408 @@ impl CounterCircuit {
         trace
     }
 
+    /// Pad a witness to the specified chunk size with NOP steps
+    ///
+    /// Mirrors [`Self::pad_trace`]: NOP steps are at pc 0 with zeroed
+    /// instruction bytes and repeat the last register state.
+    fn pad_witness(mut witness: Witness, chunk_size: usize) -> Witness {
+        let current_len = witness.instruction_count();
+
+        if current_len >= chunk_size {
+            witness.program_counters.truncate(chunk_size);
+            witness.instruction_bytes.truncate(chunk_size);
+            witness.instruction_register_states.truncate(chunk_size);
+            witness.call_targets.truncate(chunk_size);
+            witness.memory_ops = witness.derive_memory_ops();
+            return witness;
+        }
+
+        let last_regs = witness
+            .instruction_register_states
+            .last()
+            .unwrap_or(&witness.initial_registers)
+            .clone();
+
+        for _ in current_len..chunk_size {
+            witness.program_counters.push(0);
+            witness.instruction_bytes.push(vec![0x00; 8]);
+            witness.instruction_register_states.push(last_regs.clone());
+        }
+
+        witness
+    }
+
+    /// Initial register state (r0-r10, plus PC for traces)
+    fn initial_registers(&self) -> &[u64] {
+        match &self.source {
+            CircuitSource::Trace(trace) => &trace.initial_registers.regs,
+            CircuitSource::Witness(witness) => &witness.initial_registers,
+        }
+    }
+
+    /// Final register state (r0-r10, plus PC for traces)
+    fn final_registers(&self) -> &[u64] {
+        match &self.source {
+            CircuitSource::Trace(trace) => &trace.final_registers.regs,
+            CircuitSource::Witness(witness) => &witness.final_registers,
+        }
+    }
+
+    /// sBPF version the instructions were traced under
+    fn sbpf_version(&self) -> SBPFVersion {
+        match &self.source {
+            CircuitSource::Trace(trace) => trace.meta.version(),
+            CircuitSource::Witness(witness) => witness.version(),
+        }
+    }
+
+    /// Memory operations of the load and store steps, in execution order
+    fn memory_ops(&self) -> Result<Vec<MemoryOpWitness>> {
+        match &self.source {
+            CircuitSource::Trace(trace) => Ok(Witness::from_trace(trace)?.memory_ops),
+            CircuitSource::Witness(witness) => Ok(witness.memory_ops.clone()),
+        }
+    }
+
+    /// Program the executed instructions are looked up in, one word per slot
+    ///
+    /// Words are the little-endian instruction bytes, read from the trace's
+    /// program text. The words are wired into the circuit, so circuits of
+    /// different programs need different keys. A trace built by hand
+    /// carries no program text; its executed steps then spell out the
+    /// program, with unvisited slots left zero.
+    pub fn program_words(&self) -> Result<Vec<u64>> {
+        let text = match &self.source {
+            CircuitSource::Trace(trace) => &trace.program_text,
+            CircuitSource::Witness(witness) => &witness.program_text,
+        };
+        if !text.is_empty() {
+            return Ok(text.chunks_exact(INSN_SIZE).map(instruction_word).collect());
+        }
+
+        let mut words = Vec::new();
+        for step in self.steps()?.iter().take(self.executed_instructions) {
+            let slot = step.pc as usize;
+            if words.len() <= slot {
+                words.resize(slot + 1, 0);
+            }
+            let word = instruction_word(step.instruction_bytes);
+            anyhow::ensure!(
+                words[slot] == 0 || words[slot] == word,
+                "Steps at pc {} execute different instructions",
+                step.pc
+            );
+            words[slot] = word;
+        }
+        Ok(words)
+    }
+
+    /// Order the memory table checks this circuit's memory operations in
+    ///
+    /// Indices into the operations in execution order, sorted by address
+    /// and then by time (see [`crate::memory_table`]). The order is wired
+    /// into the circuit, so circuits whose orders differ need different keys.
+    pub fn memory_order(&self) -> Result<Vec<usize>> {
+        Ok(sorted_order(&self.memory_ops()?))
+    }
+
+    /// Instruction steps in execution order
+    fn steps(&self) -> Result<Vec<Step<'_>>> {
+        match &self.source {
+            CircuitSource::Trace(trace) => Ok(trace
+                .instructions
+                .iter()
+                .map(|instr| Step {
+                    pc: instr.pc,
+                    instruction_bytes: trace.instruction_bytes(instr),
+                    registers_after: &instr.registers_after.regs,
+                    call_target: instr.call_target,
+                })
+                .collect()),
+            CircuitSource::Witness(witness) => {
+                witness.validate_lengths()?;
+                Ok(witness
+                    .program_counters
+                    .iter()
+                    .zip(&witness.instruction_bytes)
+                    .zip(&witness.instruction_register_states)
+                    .enumerate()
+                    .map(|(index, ((&pc, bytes), regs))| Step {
+                        pc,
+                        instruction_bytes: bytes,
+                        registers_after: regs,
+                        call_target: witness.call_targets.get(index).copied().flatten(),
+                    })
+                    .collect())
+            }
+        }
+    }
+
     /// Synthesize the circuit constraints
     ///
     /// This method builds the complete constraint system proving
     /// correct execution of the counter program.
     ///
     /// This is intended to be called from within a circuit builder context.
+    /// Returns the final register cells (r0-r10), so the caller can expose
+    /// the return value in r0 as a public output.
+    ///
+    /// Fails if an executed instruction has no chip, unless
+    /// [`Self::with_allow_unconstrained`] is set, and if a witnessed value
+    /// does not fit in `F` (see [`Witness::assert_fits_field`]). A
+    /// full-program circuit also fails if its trace ends inside a call.
     pub fn synthesize<F: ScalarField>(
         &self,
         ctx: &mut Context<F>,
         gate: &impl GateInstructions<F>,
-    ) -> Result<()> {
+    ) -> Result<SynthesisOutput<F>> {
+        anyhow::ensure!(
+            self.mode == CircuitMode::Partial || self.executed_instructions > 0,
+            "A full-program circuit needs at least one executed instruction"
+        );
+
+        // Values of a field of 64 bits or fewer could wrap when loaded
+        if F::NUM_BITS <= 64 {
+            match &self.source {
+                CircuitSource::Trace(trace) => {
+                    Witness::from_trace(trace)?.assert_fits_field::<F>()?
+                }
+                CircuitSource::Witness(witness) => witness.assert_fits_field::<F>()?,
+            }
+        }
+
         // Load initial register state as witnesses
-        let mut current_regs = self.load_register_state(ctx, &self.trace.initial_registers);
+        let mut current_regs = self.load_register_state(ctx, self.initial_registers())?;
+
+        // Where the next executed step may be, if the previous step decides it
+        let mut successor: Option<Successor<F>> = None;
+        // Frames of the calls not yet returned from, innermost last
+        let mut frames: Vec<Frame<F>> = Vec::new();
+        let mut unconstrained_pcs = Vec::new();
+        let mut step_regions = Vec::new();
+        let mut instruction_count = ctx.load_constant(F::ZERO);
+        // Instructions the executed steps account for, fixed by the shape
+        let mut executed_steps = ctx.load_constant(F::ZERO);
+        let program = self.program_words()?;
+
+        let steps = self.steps()?;
+        let memory_ops = self.memory_ops()?;
+        let mut pending_memory_ops = memory_ops.iter();
+        // Cells each memory operation is bound to, for the memory table
+        let mut memory_cells = Vec::with_capacity(memory_ops.len());
+        let version = self.sbpf_version();
+        let mut index = 0;
+        while index < steps.len() {
+            let step = &steps[index];
+            let insn = DecodedInsn::decode(step.instruction_bytes)?;
+
+            // `mov64 r0, imm; exit` ending the execution is proven as one
+            // step, skipping the register state between the two
+            let mut fused_exit = None;
+            if index + 2 == self.executed_instructions && frames.is_empty() {
+                let exit_step = &steps[index + 1];
+                let exit = DecodedInsn::decode(exit_step.instruction_bytes)?;
+                if let Some(chip) = InstructionChip::select_return(&insn, &exit) {
+                    fused_exit = Some((exit_step, chip));
+                }
+            }
+            let last_step = fused_exit.as_ref().map_or(step, |(exit_step, ..)| *exit_step);
+
+            // A call into the program opens a frame, and the `exit` after
+            // it returns from the innermost one
+            let frame_move = match insn.class_for(version) {
+                _ if index >= self.executed_instructions => None,
+                OpcodeClass::Call => step.call_target.map(FrameMove::Call),
+                OpcodeClass::Exit => frames.pop().map(FrameMove::Return),
+                _ => None,
+            };
 
-        // Iterate through each instruction in the trace
-        for instr_trace in &self.trace.instructions {
             // Load the "after" register state for this instruction
-            let next_regs = self.load_register_state(ctx, &instr_trace.registers_after);
-
-            // TODO: In a full implementation, we would:
-            // 1. Decode the instruction bytes to determine instruction type
-            // 2. Instantiate the appropriate chip (ALU64_ADD_IMM, etc.)
-            // 3. Call chip.synthesize() to verify the instruction
-            //
-            // For this MVP skeleton, we just constrain that registers transition correctly
-            // (This would be replaced with actual instruction chip dispatch)
-
-            // For now, we just verify the transition happens
-            // In practice, each instruction chip would constrain this
-            for i in 0..11 {
-                // This is a placeholder - real implementation would use instruction chips
-                // to properly constrain the state transition
-                let _ = gate.add(ctx, current_regs[i], next_regs[i]);
+            let region_start = ctx.advice.len();
+            let next_regs = self.load_register_state(ctx, last_step.registers_after)?;
+
+            let chip = match &fused_exit {
+                Some((.., chip)) => Some(chip.clone()),
+                None => match &self.chip_registry {
+                    Some(registry) => InstructionChip::select_with(&insn, version, registry),
+                    None => InstructionChip::select_for(&insn, version),
+                },
+            };
+
+            // Sequence check: the step's PC is where the previous step
+            // continues, and the program's word at that PC is the step's
+            // instruction, so a step cannot be dropped, reordered or swapped
+            // for another instruction. Padding steps are exempt.
+            let mut step_opcode = None;
+            if index < self.executed_instructions {
+                let pc = ctx.load_witness(F::from(step.pc));
+                if let Some(successor) = successor.take() {
+                    successor.constrain(ctx, gate, pc);
+                }
+                let word = constrain_in_program(ctx, gate, &program, pc, step.instruction_bytes);
+                step_opcode = Some(word_opcode(ctx, gate, word));
+                successor = match &frame_move {
+                    Some(FrameMove::Call(target)) => {
+                        frames.push(Frame {
+                            return_pc: pc_offset(ctx, gate, pc, 1),
+                            saved: std::array::from_fn(|i| current_regs[6 + i]),
+                        });
+                        Some(Successor::At(ctx.load_constant(F::from(*target))))
+                    }
+                    Some(FrameMove::Return(frame)) => Some(Successor::At(frame.return_pc)),
+                    None => Successor::of(ctx, gate, &insn, version, pc),
+                };
+
+                // The fused `exit` directly follows the `mov`
+                if let Some((exit_step, _)) = &fused_exit {
+                    let exit_pc = ctx.load_witness(F::from(exit_step.pc));
+                    let expected = gate.add(ctx, pc, QuantumCell::Constant(F::ONE));
+                    ctx.constrain_equal(&exit_pc, &expected);
+                    let exit_word = constrain_in_program(
+                        ctx,
+                        gate,
+                        &program,
+                        exit_pc,
+                        exit_step.instruction_bytes,
+                    );
+
+                    let opcode = word_opcode(ctx, gate, exit_word);
+                    gate.assert_is_const(ctx, &opcode, &F::from(opcodes::EXIT as u64));
+                }
+
+                let steps = if fused_exit.is_some() { 2u64 } else { 1 };
+                executed_steps =
+                    gate.add(ctx, executed_steps, QuantumCell::Constant(F::from(steps)));
             }
+            // Padding is not in the program, so its opcode is witnessed
+            let opcode = match step_opcode {
+                Some(opcode) => opcode,
+                None => ctx.load_witness(F::from(insn.opcode as u64)),
+            };
+
+            // A whole program ends in `exit` (already checked if fused)
+            if self.mode == CircuitMode::FullProgram
+                && fused_exit.is_none()
+                && index + 1 == self.executed_instructions
+            {
+                gate.assert_is_const(ctx, &opcode, &F::from(opcodes::EXIT as u64));
+            }
+
+            // Each load or store binds the next witnessed memory operation
+            if let Some(chip) = chip.as_ref().filter(|chip| chip.accesses_memory()) {
+                let op =
+                    pending_memory_ops.next().filter(|op| op.pc == step.pc).ok_or_else(|| {
+                        anyhow::anyhow!("No memory operation witnessed for pc {}", step.pc)
+                    })?;
+                let cells = chip.synthesize_memory_op(ctx, gate, op, &current_regs, &next_regs)?;
+                memory_cells.push(cells);
+            }
+
+            match (frame_move, chip) {
+                (Some(frame_move), _) => {
+                    frame_move.constrain(ctx, gate, version, &current_regs, &next_regs);
+                    instruction_count =
+                        gate.add(ctx, instruction_count, QuantumCell::Constant(F::ONE));
+                }
+                (None, Some(chip)) => {
+                    chip.synthesize(ctx, gate, opcode, &current_regs, &next_regs)?;
+                    // Padding is the only step with a zero opcode
+                    let is_padding = gate.is_zero(ctx, opcode);
+                    let executed = gate.not(ctx, is_padding);
+                    let steps = if fused_exit.is_some() { 2u64 } else { 1 };
+                    instruction_count = gate.mul_add(
+                        ctx,
+                        executed,
+                        QuantumCell::Constant(F::from(steps)),
+                        instruction_count,
+                    );
+                }
+                (None, None) => {
+                    anyhow::ensure!(
+                        self.allow_unconstrained,
+                        "No chip for opcode 0x{:02x} at pc {}",
+                        insn.opcode,
+                        step.pc
+                    );
+                    // The transition is witnessed but left unconstrained
+                    tracing::debug!(
+                        "No chip for opcode 0x{:02x} at pc {}, transition unconstrained",
+                        insn.opcode,
+                        step.pc
+                    );
+                    unconstrained_pcs.push(step.pc);
+                    for i in 0..11 {
+                        let _ = gate.add(ctx, current_regs[i], next_regs[i]);
+                    }
+                    // Padding always has a chip, so this step was executed
+                    instruction_count =
+                        gate.add(ctx, instruction_count, QuantumCell::Constant(F::ONE));
+                }
+            }
+
+            step_regions.push(StepRegion {
+                index,
+                pc: step.pc,
+                cells: region_start..ctx.advice.len(),
+                registers_after: region_start,
+            });
 
             // Update current state for next iteration
             current_regs = next_regs;
+            index += if fused_exit.is_some() { 2 } else { 1 };
         }
 
+        // Every executed step was counted, so none was passed off as padding
+        ctx.constrain_equal(&executed_steps, &instruction_count);
+
+        anyhow::ensure!(
+            self.mode == CircuitMode::Partial || frames.is_empty(),
+            "A full-program circuit ends inside {} call(s)",
+            frames.len()
+        );
+
+        anyhow::ensure!(
+            pending_memory_ops.next().is_none(),
+            "Witness has memory operations that no load or store performed"
+        );
+        let initial_memory = match self.mode {
+            CircuitMode::FullProgram => {
+                InitialMemory::Entry { allow_uninitialized: self.allow_unconstrained }
+            }
+            CircuitMode::Partial => InitialMemory::Witnessed,
+        };
+        let uninitialized_pcs =
+            constrain_memory_table(ctx, gate, &memory_ops, &memory_cells, initial_memory)?;
+        unconstrained_pcs.extend(uninitialized_pcs);
+
         // Verify final register state matches trace
-        let final_regs = self.load_register_state(ctx, &self.trace.final_registers);
+        let final_regs = self.load_register_state(ctx, self.final_registers())?;
         for i in 0..11 {
             ctx.constrain_equal(&current_regs[i], &final_regs[i]);
         }
 
-        Ok(())
+        if let Some(return_range) = &self.return_range {
+            anyhow::ensure!(
+                self.mode == CircuitMode::FullProgram,
+                "A return range needs a full-program circuit"
+            );
+            return_range.synthesize(ctx, gate, final_regs[0])?;
+        }
+
+        if !unconstrained_pcs.is_empty() {
+            tracing::warn!(
+                "UNSOUND: {} instruction(s) left unconstrained, the proof does not cover pcs {:?}",
+                unconstrained_pcs.len(),
+                unconstrained_pcs
+            );
+        }
+
+        Ok(SynthesisOutput {
+            final_registers: final_regs,
+            instruction_count,
+            unconstrained_pcs,
+            step_regions,
+        })
     }
 
     /// Get the number of constraints in this circuit
@@ -140,24 +739,188 @@ impl CounterCircuit {
     pub fn num_constraints(&self) -> usize {
         // Rough estimate: each instruction needs ~50 constraints
         // (register checks, arithmetic operations, etc.)
-        self.trace.instruction_count() * 50
+        let instruction_count = match &self.source {
+            CircuitSource::Trace(trace) => trace.instruction_count(),
+            CircuitSource::Witness(witness) => witness.instruction_count(),
+        };
+        instruction_count * 50
     }
 
-    /// Helper to load a RegisterState as assigned values
+    /// Helper to load r0-r10 of a register state as assigned values
     fn load_register_state<F: ScalarField>(
         &self,
         ctx: &mut Context<F>,
-        regs: &RegisterState,
-    ) -> [AssignedValue<F>; 11] {
-        std::array::from_fn(|i| ctx.load_witness(F::from(regs.regs[i])))
+        regs: &[u64],
+    ) -> Result<[AssignedValue<F>; 11]> {
+        anyhow::ensure!(
+            regs.len() >= 11,
+            "Malformed register state: expected at least 11 registers (r0-r10), got {}",
+            regs.len()
+        );
+        Ok(std::array::from_fn(|i| ctx.load_witness(F::from(regs[i]))))
+    }
+}
+
+/// A call into the program that has not returned yet
+struct Frame<F: ScalarField> {
+    /// PC right after the call, where its `exit` continues
+    return_pc: AssignedValue<F>,
+    /// r6-r10 at the call, which its `exit` restores
+    saved: [AssignedValue<F>; 5],
+}
+
+/// How a step moves between call frames
+enum FrameMove<F: ScalarField> {
+    /// A call into the program, to this PC
+    Call(u64),
+    /// The `exit` returning from this frame
+    Return(Frame<F>),
+}
+
+impl<F: ScalarField> FrameMove<F> {
+    /// Constrain the registers across the call or return
+    fn constrain(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        version: SBPFVersion,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) {
+        match self {
+            // r0-r9 are kept, r10 moves to the callee's frame
+            Self::Call(_) => {
+                for i in 0..10 {
+                    ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+                }
+                let stride = QuantumCell::Constant(F::from(call_frame_stride(version)));
+                let frame_pointer = gate.add(ctx, regs_before[10], stride);
+                ctx.constrain_equal(&regs_after[10], &frame_pointer);
+            }
+            // r0-r5 are what the callee left, r6-r10 what the caller had
+            Self::Return(frame) => {
+                for i in 0..6 {
+                    ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+                }
+                for (saved, after) in frame.saved.iter().zip(&regs_after[6..]) {
+                    ctx.constrain_equal(saved, after);
+                }
+            }
+        }
+    }
+}
+
+/// Where execution may continue after an executed step
+enum Successor<F: ScalarField> {
+    /// Exactly this PC
+    At(AssignedValue<F>),
+    /// Either PC: a conditional jump not taken or taken
+    Either(AssignedValue<F>, AssignedValue<F>),
+}
+
+impl<F: ScalarField> Successor<F> {
+    /// Successor of `insn` executed at `pc`, if its instruction fixes one
+    ///
+    /// Calls and exits continue where the call stack says (see
+    /// [`FrameMove`]), so they return `None` here, as do syscalls, `callx`
+    /// and opcodes outside every class.
+    fn of(
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        insn: &DecodedInsn,
+        version: SBPFVersion,
+        pc: AssignedValue<F>,
+    ) -> Option<Self> {
+        let target = 1 + insn.off as i64;
+        match insn.class_for(version) {
+            OpcodeClass::Alu64
+            | OpcodeClass::Alu32
+            | OpcodeClass::Pqr
+            | OpcodeClass::Load
+            | OpcodeClass::Store
+            | OpcodeClass::ByteSwap => Some(Self::At(pc_offset(ctx, gate, pc, 1))),
+            // `lddw` takes two instruction slots
+            OpcodeClass::LoadImm64 => Some(Self::At(pc_offset(ctx, gate, pc, 2))),
+            OpcodeClass::Jump if insn.opcode == opcodes::JA => {
+                Some(Self::At(pc_offset(ctx, gate, pc, target)))
+            }
+            OpcodeClass::Jump => Some(Self::Either(
+                pc_offset(ctx, gate, pc, 1),
+                pc_offset(ctx, gate, pc, target),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Constrain the next step's PC to this successor
+    fn constrain(
+        self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        pc: AssignedValue<F>,
+    ) {
+        match self {
+            Self::At(expected) => ctx.constrain_equal(&pc, &expected),
+            Self::Either(first, second) => {
+                let from_first = gate.sub(ctx, pc, first);
+                let from_second = gate.sub(ctx, pc, second);
+                let product = gate.mul(ctx, from_first, from_second);
+                gate.assert_is_const(ctx, &product, &F::ZERO);
+            }
+        }
     }
 }
 
+/// `pc + delta`, for a possibly negative jump offset
+fn pc_offset<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    pc: AssignedValue<F>,
+    delta: i64,
+) -> AssignedValue<F> {
+    let magnitude = QuantumCell::Constant(F::from(delta.unsigned_abs()));
+    if delta < 0 {
+        gate.sub(ctx, pc, magnitude)
+    } else {
+        gate.add(ctx, pc, magnitude)
+    }
+}
+
+/// Constrain the program's word at `pc` to equal `instruction_bytes`,
+/// returning the selected word
+///
+/// Selects the word with a one-hot indicator over the whole program, so a
+/// step costs cells linear in the program length. A PC past the end selects
+/// zero, which no instruction encodes to.
+fn constrain_in_program<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    program: &[u64],
+    pc: AssignedValue<F>,
+    instruction_bytes: &[u8],
+) -> AssignedValue<F> {
+    let words = program.iter().map(|&word| QuantumCell::Constant(F::from(word)));
+    let word = gate.select_from_idx(ctx, words, pc);
+    gate.assert_is_const(ctx, &word, &F::from(instruction_word(instruction_bytes)));
+    word
+}
+
+/// An instruction's bytes as one little-endian word
+fn instruction_word(instruction_bytes: &[u8]) -> u64 {
+    let mut bytes = [0u8; INSN_SIZE];
+    let len = instruction_bytes.len().min(INSN_SIZE);
+    bytes[..len].copy_from_slice(&instruction_bytes[..len]);
+    u64::from_le_bytes(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bpf_tracer::InstructionTrace;
-    use halo2_base::utils::testing::base_test;
+    use bpf_tracer::RegisterState;
+    use halo2_base::{
+        halo2_proofs::halo2curves::bn256::Fr,
+        utils::testing::base_test,
+    };
 
     #[test]
     fn test_counter_circuit_creation() {
@@ -178,6 +941,8 @@ mod tests {
             instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
             registers_before: initial_regs.clone(),
             registers_after: after_regs,
+            call_target: None,
+            text_offset: None,
         };
 
         let trace = ExecutionTrace {
@@ -185,6 +950,7 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: final_regs,
+            ..Default::default()
         };
 
         let circuit = CounterCircuit::from_trace(trace);
@@ -207,13 +973,17 @@ mod tests {
             instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
             registers_before: initial_regs.clone(),
             registers_after: after_instr1.clone(),
+            call_target: None,
+            text_offset: None,
         };
 
         let instr2 = InstructionTrace {
-            pc: 8,
+            pc: 1,
             instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
             registers_before: after_instr1,
             registers_after: after_instr2.clone(),
+            call_target: None,
+            text_offset: None,
         };
 
         let trace = ExecutionTrace {
@@ -221,13 +991,14 @@ mod tests {
             account_states: vec![],
             initial_registers: initial_regs,
             final_registers: after_instr2,
+            ..Default::default()
         };
 
         // Create chunked circuit with size 5 (will pad with 3 NOPs)
         let circuit = CounterCircuit::from_trace_chunked(trace, 5);
 
         // Verify padding
-        assert_eq!(circuit.trace.instructions.len(), 5);
+        assert_eq!(circuit.trace().unwrap().instructions.len(), 5);
 
         // Test synthesis
         base_test().run_gate(|ctx, gate| {
@@ -240,9 +1011,9 @@ mod tests {
         let trace = ExecutionTrace::new();
         let circuit = CounterCircuit::from_trace_chunked(trace, 10);
 
-        assert_eq!(circuit.trace.instructions.len(), 10);
+        assert_eq!(circuit.trace().unwrap().instructions.len(), 10);
         // All instructions should be NOPs with same register state
-        for instr in &circuit.trace.instructions {
+        for instr in &circuit.trace().unwrap().instructions {
             assert_eq!(instr.instruction_bytes, vec![0x00; 8]);
             // Verify register state unchanged (NOP behavior)
             for i in 0..11 {
@@ -253,4 +1024,477 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_witness_circuit_matches_trace_circuit() {
+        let initial_regs = RegisterState::from_regs([0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let after_regs = RegisterState::from_regs([0, 52, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8]);
+
+        let trace = ExecutionTrace {
+            instructions: vec![InstructionTrace {
+                pc: 0,
+                instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
+                registers_before: initial_regs.clone(),
+                registers_after: after_regs.clone(),
+                call_target: None,
+                text_offset: None,
+            }],
+            account_states: vec![],
+            initial_registers: initial_regs,
+            final_registers: after_regs,
+            ..Default::default()
+        };
+        let witness = Witness::from_trace(&trace).unwrap();
+
+        let from_trace = CounterCircuit::from_trace_chunked(trace, 4);
+        let from_witness = CounterCircuit::from_witness_chunked(witness, 4);
+        assert!(from_witness.trace().is_none());
+        assert_eq!(from_witness.executed_instructions(), 1);
+        assert_eq!(from_witness.num_constraints(), from_trace.num_constraints());
+
+        let trace_cells = base_test().run_gate(|ctx, gate| {
+            from_trace.synthesize(ctx, gate).unwrap();
+            ctx.advice.len()
+        });
+        let witness_cells = base_test().run_gate(|ctx, gate| {
+            from_witness.synthesize(ctx, gate).unwrap();
+            ctx.advice.len()
+        });
+        assert_eq!(witness_cells, trace_cells);
+    }
+
+    #[test]
+    fn test_malformed_witness_rejected() {
+        let mut witness = Witness::from_trace(&ExecutionTrace::new()).unwrap();
+        witness.final_registers.truncate(5);
+
+        let circuit = CounterCircuit::from_witness(witness);
+        base_test().run_gate(|ctx, gate| {
+            let err = circuit.synthesize(ctx, gate).unwrap_err();
+            assert!(err.to_string().contains("got 5"), "Unexpected error: {}", err);
+        });
+    }
+
+    /// mov64 r0, 10; mov64 r1, 20; mov64 r1, 20; add64 r0, r1; exit
+    ///
+    /// The repeated `mov64 r1, 20` leaves the registers unchanged, so
+    /// dropping it keeps the register chain consistent.
+    fn repeated_mov_witness() -> Witness {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        let trace = bpf_tracer::trace_program(bytecode).unwrap();
+        Witness::from_trace(&trace).unwrap()
+    }
+
+    #[test]
+    fn test_full_instruction_sequence_satisfied() {
+        let circuit = CounterCircuit::from_witness_chunked(repeated_mov_witness(), 8);
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_partial_trace_without_exit() {
+        // The first three instructions of the repeated-mov program
+        let mut witness = repeated_mov_witness();
+        witness.program_counters.truncate(3);
+        witness.instruction_bytes.truncate(3);
+        witness.instruction_register_states.truncate(3);
+        witness.final_registers = witness.instruction_register_states[2].clone();
+
+        let partial = CounterCircuit::from_witness_chunked(witness.clone(), 8)
+            .with_mode(CircuitMode::Partial);
+        base_test().run_gate(|ctx, gate| {
+            partial.synthesize(ctx, gate).unwrap();
+        });
+
+        let full = CounterCircuit::from_witness_chunked(witness, 8)
+            .with_mode(CircuitMode::FullProgram);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            full.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_full_program_ending_in_exit() {
+        let circuit = CounterCircuit::from_witness_chunked(repeated_mov_witness(), 8)
+            .with_mode(CircuitMode::FullProgram);
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_return_range_on_full_program() {
+        // The repeated-mov program returns 30
+        let in_range = CounterCircuit::from_witness_chunked(repeated_mov_witness(), 8)
+            .with_mode(CircuitMode::FullProgram)
+            .with_return_range(0, 30);
+        base_test().run_gate(|ctx, gate| {
+            in_range.synthesize(ctx, gate).unwrap();
+        });
+
+        let success_only = CounterCircuit::from_witness_chunked(repeated_mov_witness(), 8)
+            .with_mode(CircuitMode::FullProgram)
+            .with_return_range(0, 0);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            success_only.synthesize(ctx, gate).unwrap();
+        });
+
+        let partial = CounterCircuit::from_witness_chunked(repeated_mov_witness(), 8)
+            .with_return_range(0, 30);
+        base_test().run_gate(|ctx, gate| {
+            assert!(partial.synthesize(ctx, gate).is_err());
+        });
+    }
+
+    /// Advice cells and final r0 of the chunked circuit for `bytecode`
+    fn synthesize_program(bytecode: &[u8]) -> (usize, Fr) {
+        let trace = bpf_tracer::trace_program(bytecode).unwrap();
+        let circuit =
+            CounterCircuit::from_trace_chunked(trace, 4).with_mode(CircuitMode::FullProgram);
+        base_test().run_gate(|ctx, gate| {
+            let output = circuit.synthesize(ctx, gate).unwrap();
+            (ctx.advice.len(), *output.final_registers[0].value())
+        })
+    }
+
+    #[test]
+    fn test_return_constant_uses_fused_step() {
+        #[rustfmt::skip]
+        let return_r0: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r0, 42
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        // Same shape, but the move into r1 is not a return
+        #[rustfmt::skip]
+        let move_r1: &[u8] = &[
+            0xb7, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r1, 42
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+
+        let (fused_cells, r0) = synthesize_program(return_r0);
+        let (general_cells, _) = synthesize_program(move_r1);
+
+        assert_eq!(r0, Fr::from(42u64));
+        assert!(
+            fused_cells < general_cells,
+            "fused return used {} cells, general path {}",
+            fused_cells,
+            general_cells
+        );
+    }
+
+    #[test]
+    fn test_mov32_hor64_builds_64_bit_constant() {
+        let bytecode = bpf_tracer::InsnBuilder::program([
+            bpf_tracer::InsnBuilder::mov32_imm(0, 0x9abc_def0_u32 as i32),
+            bpf_tracer::InsnBuilder::hor64_imm(0, 0x1234_5678),
+            bpf_tracer::InsnBuilder::exit(),
+        ]);
+
+        let (_, r0) = synthesize_program(&bytecode);
+        assert_eq!(r0, Fr::from(0x1234_5678_9abc_def0u64));
+    }
+
+    #[test]
+    fn test_u64_max_register_wraps_to_zero() {
+        let max = bpf_tracer::InsnBuilder::program([
+            bpf_tracer::InsnBuilder::mov64_imm(0, -1),
+            bpf_tracer::InsnBuilder::exit(),
+        ]);
+        assert_eq!(synthesize_program(&max).1, Fr::from(u64::MAX));
+
+        let wrapped = bpf_tracer::InsnBuilder::program([
+            bpf_tracer::InsnBuilder::mov64_imm(0, -1),
+            bpf_tracer::InsnBuilder::add64_imm(0, 1),
+            bpf_tracer::InsnBuilder::exit(),
+        ]);
+        assert_eq!(synthesize_program(&wrapped).1, Fr::from(0u64));
+    }
+
+    #[test]
+    fn test_instruction_count_excludes_padding() {
+        #[rustfmt::skip]
+        let bytecode: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        let trace = bpf_tracer::trace_program(bytecode).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace, 8);
+        assert_eq!(circuit.trace().unwrap().instructions.len(), 8);
+
+        let count = base_test().run_gate(|ctx, gate| {
+            *circuit.synthesize(ctx, gate).unwrap().instruction_count.value()
+        });
+        assert_eq!(count, Fr::from(4u64));
+
+        // A fused return counts both of its instructions
+        #[rustfmt::skip]
+        let return_r0: &[u8] = &[
+            0xb7, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,  // mov64 r0, 42
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        let trace = bpf_tracer::trace_program(return_r0).unwrap();
+        let circuit = CounterCircuit::from_trace_chunked(trace, 8);
+        let count = base_test().run_gate(|ctx, gate| {
+            *circuit.synthesize(ctx, gate).unwrap().instruction_count.value()
+        });
+        assert_eq!(count, Fr::from(2u64));
+    }
+
+    #[test]
+    fn test_memory_ops_bound_to_steps() {
+        use bpf_tracer::InsnBuilder;
+
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 42),
+            InsnBuilder::stxdw(10, -8, 1),
+            InsnBuilder::ldxdw(0, 10, -8),
+            InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+        let witness = Witness::from_trace(&trace).unwrap();
+        assert_eq!(witness.memory_ops.len(), 2);
+
+        let circuit = CounterCircuit::from_witness_chunked(witness.clone(), 8);
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+
+        // A stored value that is not the source register
+        let mut wrong_value = witness.clone();
+        wrong_value.memory_ops[0].value = 43;
+        let circuit = CounterCircuit::from_witness_chunked(wrong_value, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+
+        // A load without its memory operation
+        let mut missing = witness;
+        missing.memory_ops.pop();
+        let circuit = CounterCircuit::from_witness_chunked(missing, 8);
+        base_test().run_gate(|ctx, gate| {
+            let err = circuit.synthesize(ctx, gate).unwrap_err();
+            assert!(err.to_string().contains("No memory operation"), "Unexpected error: {}", err);
+        });
+    }
+
+    /// Store to the stack, call a function, then load the value back
+    fn stack_across_call_witness() -> Witness {
+        use bpf_tracer::InsnBuilder;
+
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 42),
+            InsnBuilder::stxdw(10, -8, 1),
+            InsnBuilder::call(5),
+            InsnBuilder::ldxdw(0, 10, -8),
+            InsnBuilder::exit(),
+            // Callee clobbers a scratch register, not the caller's stack
+            InsnBuilder::mov64_imm(1, 7),
+            InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+        let pcs: Vec<u64> = trace.instructions.iter().map(|instr| instr.pc).collect();
+        assert_eq!(pcs, vec![0, 1, 2, 5, 6, 3, 4]);
+        assert_eq!(trace.final_registers.regs[0], 42);
+        Witness::from_trace(&trace).unwrap()
+    }
+
+    #[test]
+    fn test_stack_value_survives_call() {
+        let witness = stack_across_call_witness();
+        assert_eq!(witness.memory_ops.len(), 2);
+        assert_eq!(witness.memory_ops[0].address, witness.memory_ops[1].address);
+
+        let circuit =
+            CounterCircuit::from_witness_chunked(witness, 8).with_mode(CircuitMode::FullProgram);
+        let unconstrained_pcs = base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap().unconstrained_pcs
+        });
+        assert!(unconstrained_pcs.is_empty());
+    }
+
+    #[test]
+    fn test_return_restores_saved_registers() {
+        // The callee's `exit` claims to hand back a different r6
+        let mut clobbered = stack_across_call_witness();
+        for regs in &mut clobbered.instruction_register_states[4..] {
+            regs[6] = 7;
+        }
+        clobbered.final_registers[6] = 7;
+        let circuit = CounterCircuit::from_witness_chunked(clobbered, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_stack_value_changed_across_call_fails() {
+        let mut witness = stack_across_call_witness();
+
+        // Claim the load after the call read 43, consistently through the
+        // rest of the witness, so only the read-after-write check fails
+        witness.memory_ops[1].value = 43;
+        for regs in &mut witness.instruction_register_states[5..] {
+            regs[0] = 43;
+        }
+        witness.final_registers[0] = 43;
+
+        let circuit = CounterCircuit::from_witness_chunked(witness, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_reload_into_other_register_must_match() {
+        use bpf_tracer::InsnBuilder;
+
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(1, 42),
+            InsnBuilder::stxdw(10, -8, 1),
+            InsnBuilder::ldxdw(2, 10, -8),
+            InsnBuilder::exit(),
+        ]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+        let witness = Witness::from_trace(&trace).unwrap();
+        assert_eq!(witness.final_registers[2], witness.final_registers[1]);
+
+        let circuit = CounterCircuit::from_witness_chunked(witness.clone(), 8);
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+
+        // r2 != r1: the load claims a value nothing stored at [r10-8]
+        let mut tampered = witness;
+        tampered.memory_ops[1].value = 43;
+        for regs in &mut tampered.instruction_register_states[2..] {
+            regs[2] = 43;
+        }
+        tampered.final_registers[2] = 43;
+
+        let circuit = CounterCircuit::from_witness_chunked(tampered, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_dropped_middle_instruction_fails() {
+        let mut witness = repeated_mov_witness();
+        witness.program_counters.remove(2);
+        witness.instruction_bytes.remove(2);
+        witness.instruction_register_states.remove(2);
+
+        let circuit = CounterCircuit::from_witness_chunked(witness, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_instruction_outside_program_fails() {
+        // The witness claims `add64 r0, r1` at pc 3, the program disagrees
+        let mut witness = repeated_mov_witness();
+        witness.program_text[3 * INSN_SIZE + 1] = 0x20;
+
+        let circuit = CounterCircuit::from_witness_chunked(witness, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_jump_fixes_next_pc() {
+        use bpf_tracer::InsnBuilder;
+
+        // ja +1; exit; exit: the jump lands on the second exit
+        let bytecode =
+            InsnBuilder::program([InsnBuilder::ja(1), InsnBuilder::exit(), InsnBuilder::exit()]);
+        let trace = bpf_tracer::trace_program(&bytecode).unwrap();
+        let witness = Witness::from_trace(&trace).unwrap();
+        assert_eq!(witness.program_counters, vec![0, 2]);
+
+        // `ja` has no chip yet, but where it continues is still constrained
+        let circuit = CounterCircuit::from_witness_chunked(witness.clone(), 4)
+            .with_allow_unconstrained(true);
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+
+        // The exit at pc 1 is in the program, but the jump skips it
+        let mut fallen_through = witness;
+        fallen_through.program_counters[1] = 1;
+        let circuit = CounterCircuit::from_witness_chunked(fallen_through, 4)
+            .with_allow_unconstrained(true);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_executed_step_passed_off_as_padding_fails() {
+        // The last executed step claims to be padding with unchanged registers
+        let mut witness = repeated_mov_witness();
+        let last = witness.instruction_count() - 1;
+        witness.instruction_bytes[last] = vec![0x00; 8];
+        witness.program_text[last * INSN_SIZE..(last + 1) * INSN_SIZE].fill(0);
+
+        let circuit = CounterCircuit::from_witness_chunked(witness, 8);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_allow_unconstrained_reports_skipped_pcs() {
+        let initial_regs = RegisterState::from_regs([10, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let after_mul = RegisterState::from_regs([30, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let after_add = RegisterState::from_regs([72, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        let trace = ExecutionTrace {
+            instructions: vec![
+                InstructionTrace {
+                    pc: 0,
+                    instruction_bytes: vec![0x2f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // MUL64_REG r0, r1
+                    registers_before: initial_regs.clone(),
+                    registers_after: after_mul.clone(),
+                    call_target: None,
+                    text_offset: None,
+                },
+                InstructionTrace {
+                    pc: 1,
+                    instruction_bytes: vec![0x07, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r0, 42
+                    registers_before: after_mul,
+                    registers_after: after_add.clone(),
+                    call_target: None,
+                    text_offset: None,
+                },
+            ],
+            initial_registers: initial_regs,
+            final_registers: after_add,
+            ..Default::default()
+        };
+
+        let strict = CounterCircuit::from_trace_chunked(trace.clone(), 4);
+        base_test().run_gate(|ctx, gate| {
+            let err = strict.synthesize(ctx, gate).unwrap_err();
+            assert!(err.to_string().contains("opcode 0x2f at pc 0"), "Unexpected error: {}", err);
+        });
+
+        let lenient = CounterCircuit::from_trace_chunked(trace, 4).with_allow_unconstrained(true);
+        let unconstrained_pcs = base_test().run_gate(|ctx, gate| {
+            lenient.synthesize(ctx, gate).unwrap().unconstrained_pcs
+        });
+        assert_eq!(unconstrained_pcs, vec![0]);
+    }
 }
@@ -0,0 +1,211 @@
+//! Constraint failure diagnosis
+//!
+//! MockProver reports a failed copy constraint as two column and row
+//! locations, which say little about the trace behind them. [`diagnose`]
+//! re-checks the constraints of a [`CounterCircuit`] against its own
+//! assignments and maps each failure, through the [`StepRegion`]s synthesis
+//! recorded, to the instruction and register whose witness the circuit
+//! disagrees with.
+
+use std::fmt;
+
+use halo2_base::{
+    gates::{circuit::builder::BaseCircuitBuilder, flex_gate::GateChip},
+    halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr},
+    utils::ScalarField,
+    Context,
+};
+use crate::{
+    counter::{CounterCircuit, StepRegion},
+    Result,
+};
+
+/// A constraint the assignments of a circuit do not satisfy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch<F: ScalarField> {
+    /// Step the witnessed cell was assigned in, if any
+    pub step: Option<StepRegion>,
+    /// Register whose after-step value the witnessed cell holds, if any
+    pub register: Option<usize>,
+    /// Offset in the advice of the witnessed cell
+    pub witness_cell: usize,
+    /// Value the witness assigned
+    pub actual: F,
+    /// Offset of the cell holding the value the circuit computed, or `None`
+    /// if it is a constant or the output of a gate
+    pub circuit_cell: Option<usize>,
+    /// Value the circuit computed
+    pub expected: F,
+}
+
+impl<F: ScalarField> fmt::Display for Mismatch<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.step {
+            Some(step) => write!(f, "instruction {} (pc {}): ", step.index, step.pc)?,
+            None => write!(f, "outside any step: ")?,
+        }
+        match self.register {
+            Some(register) => write!(f, "r{}", register)?,
+            None => write!(f, "cell {}", self.witness_cell)?,
+        }
+        write!(
+            f,
+            " witnessed {}, but the circuit computed {}",
+            format_value(&self.actual),
+            format_value(&self.expected)
+        )?;
+        match self.circuit_cell {
+            Some(cell) => write!(f, " (cells {} and {})", self.witness_cell, cell),
+            None => write!(f, " (cell {})", self.witness_cell),
+        }
+    }
+}
+
+/// Why MockProver rejected a circuit
+#[derive(Debug, Clone)]
+pub struct Diagnosis<F: ScalarField> {
+    /// Failures as MockProver reports them
+    pub failures: Vec<String>,
+    /// Unsatisfied constraints in assignment order, mapped to steps
+    pub mismatches: Vec<Mismatch<F>>,
+}
+
+impl<F: ScalarField> fmt::Display for Diagnosis<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} constraint failure(s):", self.failures.len())?;
+        for mismatch in &self.mismatches {
+            writeln!(f, "  {}", mismatch)?;
+        }
+        for failure in &self.failures {
+            writeln!(f, "  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run MockProver on `circuit` and explain any failure
+///
+/// Returns `None` if the circuit is satisfied. Otherwise the diagnosis
+/// lists each unsatisfied copy constraint, constant and gate, naming the
+/// instruction and register where the witness and the circuit disagree.
+/// Fails if the circuit cannot be synthesized.
+pub fn diagnose(circuit: &CounterCircuit, k: u32) -> Result<Option<Diagnosis<Fr>>> {
+    let mut builder = BaseCircuitBuilder::<Fr>::new(false).use_k(k as usize);
+    let gate = GateChip::<Fr>::default();
+    let ctx = builder.main(0);
+    let output = circuit.synthesize(ctx, &gate)?;
+    let mismatches = find_mismatches(ctx, &output.step_regions);
+
+    builder.calculate_params(Some(9));
+    let prover = MockProver::run(k, &builder, vec![])
+        .map_err(|e| anyhow::anyhow!("MockProver failed to run: {:?}", e))?;
+    match prover.verify() {
+        Ok(()) => Ok(None),
+        Err(failures) => Ok(Some(Diagnosis {
+            failures: failures.iter().map(|failure| failure.to_string()).collect(),
+            mismatches,
+        })),
+    }
+}
+
+/// Check every constraint of `ctx` against its assigned values
+fn find_mismatches<F: ScalarField>(
+    ctx: &Context<F>,
+    regions: &[StepRegion],
+) -> Vec<Mismatch<F>> {
+    let value = |offset: usize| ctx.advice[offset].evaluate();
+    let region_of = |offset: usize| regions.iter().find(|region| region.cells.contains(&offset));
+    let register_of = |offset: usize| region_of(offset)?.register_at(offset);
+    let mismatch = |witness_cell: usize, circuit_cell: Option<usize>, expected: F| Mismatch {
+        step: region_of(witness_cell).cloned(),
+        register: register_of(witness_cell),
+        witness_cell,
+        actual: value(witness_cell),
+        circuit_cell,
+        expected,
+    };
+
+    let mut mismatches = Vec::new();
+    let copy_manager = ctx.copy_manager.lock().unwrap();
+    let own = |context_id: usize| context_id == ctx.context_id();
+    let equalities = copy_manager.advice_equalities.iter();
+    for (a, b) in equalities.filter(|(a, b)| own(a.context_id) && own(b.context_id)) {
+        if value(a.offset) == value(b.offset) {
+            continue;
+        }
+        // Blame the later register cell: the state the step claims to leave.
+        // Without one, the earlier cell was witnessed and the later computed.
+        let (witness, computed) = match (register_of(a.offset), register_of(b.offset)) {
+            (Some(_), None) => (a.offset, b.offset),
+            (None, Some(_)) => (b.offset, a.offset),
+            (Some(_), Some(_)) => (a.offset.max(b.offset), a.offset.min(b.offset)),
+            (None, None) => (a.offset.min(b.offset), a.offset.max(b.offset)),
+        };
+        mismatches.push(mismatch(witness, Some(computed), value(computed)));
+    }
+    let constants = copy_manager.constant_equalities.iter();
+    for (constant, cell) in constants.filter(|(_, cell)| own(cell.context_id)) {
+        if value(cell.offset) != *constant {
+            mismatches.push(mismatch(cell.offset, None, *constant));
+        }
+    }
+    drop(copy_manager);
+
+    // A gate at row i enforces a + b * c = d over the four cells from i
+    for (row, _) in ctx.selector.iter().enumerate().filter(|(_, &enabled)| enabled) {
+        let expected = value(row) + value(row + 1) * value(row + 2);
+        if value(row + 3) != expected {
+            mismatches.push(mismatch(row + 3, None, expected));
+        }
+    }
+    mismatches.sort_by_key(|mismatch| mismatch.witness_cell);
+    mismatches
+}
+
+/// Values below 2^64 in decimal, anything else as a field element
+fn format_value<F: ScalarField>(value: &F) -> String {
+    let low = value.get_lower_64();
+    if F::from(low) == *value {
+        low.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bpf_tracer::InsnBuilder;
+
+    fn add_trace() -> bpf_tracer::ExecutionTrace {
+        let bytecode = InsnBuilder::program([
+            InsnBuilder::mov64_imm(0, 2),
+            InsnBuilder::add64_imm(0, 3),
+            InsnBuilder::exit(),
+        ]);
+        bpf_tracer::trace_program(&bytecode).unwrap()
+    }
+
+    #[test]
+    fn test_diagnose_corrupted_add() {
+        let circuit = CounterCircuit::from_trace(add_trace());
+        assert!(diagnose(&circuit, 10).unwrap().is_none());
+
+        let circuit = CounterCircuit::from_trace(add_trace().corrupt(1, 0, 99));
+        let diagnosis = diagnose(&circuit, 10).unwrap().expect("corrupted trace is rejected");
+        assert!(!diagnosis.failures.is_empty());
+
+        let first = &diagnosis.mismatches[0];
+        assert_eq!(first.step.as_ref().map(|step| step.index), Some(1));
+        assert_eq!(first.register, Some(0));
+        assert_eq!(first.actual, Fr::from(99u64));
+        assert_eq!(first.expected, Fr::from(5u64));
+
+        let report = diagnosis.to_string();
+        assert!(
+            report.contains("instruction 1 (pc 1): r0 witnessed 99, but the circuit computed 5"),
+            "Unexpected diagnosis: {}",
+            report
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! Instruction dispatch
+//!
+//! Decodes each traced instruction and selects the chip that constrains it.
+
+use std::sync::Arc;
+
+use bpf_tracer::{
+    decode::{opcodes, INSN_SIZE},
+    DecodedInsn, SBPFVersion, SBPF_VERSION,
+};
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::{
+        sign_extend_imm, Alu64AddImmChip, Alu64AddRegChip, Alu64DivImmChip, Alu64LshImmChip,
+        Alu64MovImmChip, Alu64MulImmChip, Alu64RshImmChip, AssignedMemoryOp, BpfInstructionChip,
+        ExitChip, Hor64Chip, LdwChip, Mov32ImmChip, PqrChip, PqrDivisor, PqrOutput, ReturnChip,
+        StwChip,
+    },
+    registry::{synthesize_registered, ChipRegistry, RegisteredChip},
+    witness::MemoryOpWitness,
+    Result,
+};
+
+/// The chip selected for a single traced instruction
+#[derive(Debug, Clone)]
+pub enum InstructionChip {
+    /// Padding instruction (registers unchanged)
+    Nop,
+    /// dst += imm
+    AddImm(Alu64AddImmChip),
+    /// dst += src
+    AddReg(Alu64AddRegChip),
+    /// dst = imm
+    MovImm(Alu64MovImmChip),
+    /// dst = (u32) imm
+    Mov32Imm(Mov32ImmChip),
+    /// dst |= imm << 32
+    Hor64(Hor64Chip),
+    /// dst <<= shift, with the opcode it was encoded as
+    ///
+    /// Also selected for `mul dst, imm` when `imm` is a power of two.
+    Lsh(u8, Alu64LshImmChip),
+    /// dst >>= shift, with the opcode it was encoded as
+    ///
+    /// Also selected for `div dst, imm` when `imm` is a power of two.
+    Rsh(u8, Alu64RshImmChip),
+    /// dst *= imm, with the opcode it was encoded as
+    MulImm(u8, Alu64MulImmChip),
+    /// dst /= imm, with the opcode it was encoded as
+    DivImm(u8, Alu64DivImmChip),
+    /// Unsigned PQR-class division or remainder, with the opcode it was encoded as
+    Pqr(u8, PqrChip),
+    /// dst = *(u64 *)(src + off), with the opcode it was encoded as
+    Ldw(u8, LdwChip),
+    /// *(u64 *)(dst + off) = src, with the opcode it was encoded as
+    Stw(u8, StwChip),
+    /// Program exit
+    Exit(ExitChip),
+    /// `mov64 r0, imm` followed by `exit`, proven as one step
+    ///
+    /// Only selected through [`Self::select_return`].
+    Return(ReturnChip),
+    /// Chip from a [`ChipRegistry`], with the opcode it was registered for
+    Registered(u8, Arc<dyn RegisteredChip>),
+}
+
+impl InstructionChip {
+    /// Select the chip for an instruction traced as [`SBPF_VERSION`]
+    ///
+    /// See [`Self::select_for`].
+    pub fn select(insn: &DecodedInsn) -> Option<Self> {
+        Self::select_for(insn, SBPF_VERSION)
+    }
+
+    /// Select the chip for an instruction traced under `version`
+    ///
+    /// Depends only on the instruction and the version, never on witnessed
+    /// values. The version decides what an opcode means: under SBPFv2,
+    /// 0x27 and 0x37 are byte and halfword stores, not `mul64`/`div64`,
+    /// and only then do `hor64` and the PQR class exist.
+    /// Returns `None` if the opcode has no chip yet or a register index is
+    /// out of range.
+    pub fn select_for(insn: &DecodedInsn, version: SBPFVersion) -> Option<Self> {
+        let dst = insn.dst as usize;
+        let src = insn.src as usize;
+        if dst > 10 || src > 10 {
+            return None;
+        }
+
+        let chip = match insn.opcode {
+            opcodes::NOP => Self::Nop,
+            opcodes::ADD64_IMM => Self::AddImm(Alu64AddImmChip::new(dst, insn.imm)),
+            opcodes::ADD64_REG => Self::AddReg(Alu64AddRegChip::new(dst, src)),
+            opcodes::MOV64_IMM => Self::MovImm(Alu64MovImmChip::new(dst, insn.imm)),
+            opcodes::MOV32_IMM => Self::Mov32Imm(Mov32ImmChip::new(dst, insn.imm)),
+            opcodes::HOR64_IMM if version.disable_lddw() => {
+                Self::Hor64(Hor64Chip::new(dst, insn.imm))
+            }
+            opcodes::LSH64_IMM => {
+                Self::Lsh(insn.opcode, Alu64LshImmChip::new(dst, shift_amount(insn.imm)?))
+            }
+            opcodes::RSH64_IMM => {
+                Self::Rsh(insn.opcode, Alu64RshImmChip::new(dst, shift_amount(insn.imm)?))
+            }
+            op if op == opcodes::mul64_imm_for(version) => {
+                match power_of_two_shift(sign_extend_imm(insn.imm)) {
+                    Some(shift) => Self::Lsh(insn.opcode, Alu64LshImmChip::new(dst, shift)),
+                    None => Self::MulImm(insn.opcode, Alu64MulImmChip::new(dst, insn.imm.into())),
+                }
+            }
+            op if op == opcodes::div64_imm_for(version) => {
+                // UDIV zero-extends the 32-bit immediate, DIV sign-extends it
+                let divisor = if insn.opcode == opcodes::UDIV64_IMM {
+                    insn.imm as u32 as u64
+                } else {
+                    sign_extend_imm(insn.imm)
+                };
+                if divisor == 0 {
+                    return None;
+                }
+                match power_of_two_shift(divisor) {
+                    Some(shift) => Self::Rsh(insn.opcode, Alu64RshImmChip::new(dst, shift)),
+                    None => Self::DivImm(insn.opcode, Alu64DivImmChip::new(dst, divisor)),
+                }
+            }
+            opcodes::UDIV64_REG if version.enable_pqr() => Self::Pqr(
+                insn.opcode,
+                PqrChip::new(dst, PqrDivisor::Reg(src), PqrOutput::Quotient),
+            ),
+            opcodes::UREM64_REG if version.enable_pqr() => Self::Pqr(
+                insn.opcode,
+                PqrChip::new(dst, PqrDivisor::Reg(src), PqrOutput::Remainder),
+            ),
+            opcodes::UREM64_IMM if version.enable_pqr() => {
+                let divisor = insn.imm as u32 as u64;
+                if divisor == 0 {
+                    return None;
+                }
+                Self::Pqr(
+                    insn.opcode,
+                    PqrChip::new(dst, PqrDivisor::Imm(divisor), PqrOutput::Remainder),
+                )
+            }
+            op if op == opcodes::ldxdw_for(version) => {
+                Self::Ldw(insn.opcode, LdwChip::new(dst, src, insn.off))
+            }
+            op if op == opcodes::stxdw_for(version) => {
+                Self::Stw(insn.opcode, StwChip::new(dst, src, insn.off))
+            }
+            opcodes::EXIT => Self::Exit(ExitChip::new()),
+            _ => return None,
+        };
+
+        Some(chip)
+    }
+
+    /// Select the chip for a traced instruction, preferring `registry`
+    ///
+    /// An opcode with a registered chip uses it, even if a built-in chip
+    /// exists; any other opcode falls back to [`Self::select_for`].
+    pub fn select_with(
+        insn: &DecodedInsn,
+        version: SBPFVersion,
+        registry: &ChipRegistry,
+    ) -> Option<Self> {
+        match registry.select(insn) {
+            Some(chip) => Some(Self::Registered(insn.opcode, chip)),
+            None => Self::select_for(insn, version),
+        }
+    }
+
+    /// Select the fused chip for `mov64 r0, imm` directly followed by `exit`
+    ///
+    /// Returns `None` unless `mov` and `exit` are exactly that pair. The
+    /// caller is responsible for the two being the last executed steps.
+    pub fn select_return(mov: &DecodedInsn, exit: &DecodedInsn) -> Option<Self> {
+        (mov.opcode == opcodes::MOV64_IMM && mov.dst == 0 && exit.opcode == opcodes::EXIT)
+            .then(|| Self::Return(ReturnChip::new(mov.imm)))
+    }
+
+    /// The opcode this chip constrains
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Self::Nop => opcodes::NOP,
+            Self::AddImm(_) => opcodes::ADD64_IMM,
+            Self::AddReg(_) => opcodes::ADD64_REG,
+            Self::MovImm(_) | Self::Return(_) => opcodes::MOV64_IMM,
+            Self::Mov32Imm(_) => opcodes::MOV32_IMM,
+            Self::Hor64(_) => opcodes::HOR64_IMM,
+            Self::Lsh(opcode, _)
+            | Self::Rsh(opcode, _)
+            | Self::MulImm(opcode, _)
+            | Self::DivImm(opcode, _)
+            | Self::Pqr(opcode, _)
+            | Self::Ldw(opcode, _)
+            | Self::Stw(opcode, _)
+            | Self::Registered(opcode, _) => *opcode,
+            Self::Exit(_) => opcodes::EXIT,
+        }
+    }
+
+    /// Whether this chip loads or stores memory
+    pub fn accesses_memory(&self) -> bool {
+        matches!(self, Self::Ldw(..) | Self::Stw(..))
+    }
+
+    /// Constrain the witnessed memory operation of a load or store
+    ///
+    /// Binds `op`'s address and value to the step's register cells (see
+    /// [`LdwChip::synthesize_memory_op`]) and returns them. Fails for chips
+    /// that do not access memory.
+    pub fn synthesize_memory_op<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        op: &MemoryOpWitness,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<AssignedMemoryOp<F>> {
+        match self {
+            Self::Ldw(_, chip) => chip.synthesize_memory_op(ctx, gate, op, regs_before, regs_after),
+            Self::Stw(_, chip) => chip.synthesize_memory_op(ctx, gate, op, regs_before),
+            _ => anyhow::bail!("Opcode 0x{:02x} does not access memory", self.opcode()),
+        }
+    }
+
+    /// Synthesize the constraints for this instruction
+    ///
+    /// `opcode` is the cell holding the step's opcode byte, split off the
+    /// instruction word the step executes (see [`word_opcode`]). It is
+    /// constrained to equal [`Self::opcode`], so the off-circuit dispatch
+    /// decision cannot disagree with the instruction in the circuit.
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        opcode: AssignedValue<F>,
+        regs_before: &[AssignedValue<F>; 11],
+        regs_after: &[AssignedValue<F>; 11],
+    ) -> Result<()> {
+        gate.assert_is_const(ctx, &opcode, &F::from(self.opcode() as u64));
+
+        match self {
+            Self::Nop => {
+                for i in 0..11 {
+                    ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+                }
+                Ok(())
+            }
+            Self::AddImm(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::AddReg(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::MovImm(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Mov32Imm(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Hor64(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Lsh(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Rsh(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::MulImm(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::DivImm(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Pqr(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Ldw(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Stw(_, chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Exit(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Return(chip) => chip.synthesize(ctx, gate, regs_before, regs_after),
+            Self::Registered(_, chip) => {
+                synthesize_registered(chip.as_ref(), ctx, regs_before, regs_after)
+            }
+        }
+    }
+}
+
+/// Opcodes with a chip under every sBPF version
+///
+/// Padding [`opcodes::NOP`] is left out: it is not an sBPF instruction.
+const COMMON_OPCODES: &[u8] = &[
+    opcodes::ADD64_IMM,
+    opcodes::ADD64_REG,
+    opcodes::MOV64_IMM,
+    opcodes::MOV32_IMM,
+    opcodes::LSH64_IMM,
+    opcodes::RSH64_IMM,
+    opcodes::EXIT,
+];
+
+/// Opcodes the circuit can currently prove under [`SBPF_VERSION`]
+pub fn supported_opcodes() -> Vec<u8> {
+    supported_opcodes_for(SBPF_VERSION)
+}
+
+/// Opcodes [`InstructionChip::select_for`] has a chip for under `version`
+///
+/// `mul64`/`div64` by immediate and the 64-bit load and store are proven
+/// only in the encoding `version` gives them. `hor64` and the PQR
+/// instructions only exist from the version that introduces them.
+pub fn supported_opcodes_for(version: SBPFVersion) -> Vec<u8> {
+    let mut supported = COMMON_OPCODES.to_vec();
+    supported.extend([
+        opcodes::mul64_imm_for(version),
+        opcodes::div64_imm_for(version),
+        opcodes::ldxdw_for(version),
+        opcodes::stxdw_for(version),
+    ]);
+    if version.disable_lddw() {
+        supported.push(opcodes::HOR64_IMM);
+    }
+    if version.enable_pqr() {
+        supported.extend([opcodes::UDIV64_REG, opcodes::UREM64_IMM, opcodes::UREM64_REG]);
+    }
+    supported
+}
+
+/// Check that every instruction in raw bytecode has a chip under
+/// [`SBPF_VERSION`]
+///
+/// See [`is_provable_for`].
+pub fn is_provable(bytecode: &[u8]) -> std::result::Result<(), Vec<u8>> {
+    is_provable_for(bytecode, SBPF_VERSION)
+}
+
+/// Check that every instruction in raw bytecode has a chip under `version`
+///
+/// Returns the unsupported opcodes in order of first appearance. An
+/// instruction whose operands no chip accepts (e.g. a shift by 64 or more)
+/// counts as unsupported too. A trailing partial instruction is ignored;
+/// the VM rejects such bytecode anyway.
+pub fn is_provable_for(
+    bytecode: &[u8],
+    version: SBPFVersion,
+) -> std::result::Result<(), Vec<u8>> {
+    let mut unsupported = Vec::new();
+    for bytes in bytecode.chunks_exact(INSN_SIZE) {
+        let Ok(insn) = DecodedInsn::decode(bytes) else { continue };
+        let supported = InstructionChip::select_for(&insn, version).is_some();
+        if !supported && !unsupported.contains(&insn.opcode) {
+            unsupported.push(insn.opcode);
+        }
+    }
+
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(unsupported)
+    }
+}
+
+/// Shift amount of a shift-by-immediate, or `None` if it is out of range
+fn shift_amount(imm: i32) -> Option<u32> {
+    (0..64).contains(&imm).then_some(imm as u32)
+}
+
+/// If `value` is a power of two, the shift that multiplies or divides by it
+fn power_of_two_shift(value: u64) -> Option<u32> {
+    value.is_power_of_two().then(|| value.trailing_zeros())
+}
+
+/// Split the opcode byte off an instruction word
+///
+/// `word` holds the instruction's 8 bytes as one little-endian number, so
+/// the opcode is its low byte. The byte and the 56 bits above it are
+/// range-checked and constrained to recompose to `word`: the returned cell
+/// is the opcode of exactly that word.
+pub fn word_opcode<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    word: AssignedValue<F>,
+) -> AssignedValue<F> {
+    let value = word.value().get_lower_64();
+    let opcode = ctx.load_witness(F::from(value & 0xff));
+    let rest = ctx.load_witness(F::from(value >> 8));
+    let _bits = gate.num_to_bits(ctx, opcode, 8);
+    let _bits = gate.num_to_bits(ctx, rest, 56);
+    let recomposed = gate.mul_add(ctx, rest, QuantumCell::Constant(F::from(256u64)), opcode);
+    ctx.constrain_equal(&recomposed, &word);
+    opcode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bpf_tracer::{InsnBuilder, InstructionTrace, RegisterState};
+    use halo2_base::{
+        utils::testing::base_test,
+        halo2_proofs::halo2curves::bn256::Fr,
+    };
+
+    fn add_imm_trace() -> InstructionTrace {
+        InstructionTrace {
+            pc: 0,
+            instruction_bytes: vec![0x07, 0x01, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00], // ADD_IMM r1, 42
+            registers_before: RegisterState::from_regs([0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            registers_after: RegisterState::from_regs([0, 52, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8]),
+            call_target: None,
+            text_offset: None,
+        }
+    }
+
+    fn load_regs(ctx: &mut Context<Fr>, regs: &RegisterState) -> [AssignedValue<Fr>; 11] {
+        std::array::from_fn(|i| ctx.load_witness(Fr::from(regs.regs[i])))
+    }
+
+    /// Opcode cell of an instruction, split off its word as a step does
+    fn load_opcode(
+        ctx: &mut Context<Fr>,
+        gate: &impl GateInstructions<Fr>,
+        instruction_bytes: &[u8],
+    ) -> AssignedValue<Fr> {
+        let word = u64::from_le_bytes(instruction_bytes[..INSN_SIZE].try_into().unwrap());
+        let word = ctx.load_witness(Fr::from(word));
+        word_opcode(ctx, gate, word)
+    }
+
+    #[test]
+    fn test_select_chip_by_opcode() {
+        let instr = add_imm_trace();
+        let insn = DecodedInsn::decode(&instr.instruction_bytes).unwrap();
+
+        let chip = InstructionChip::select(&insn).unwrap();
+        assert!(matches!(chip, InstructionChip::AddImm(_)));
+        assert_eq!(chip.opcode(), opcodes::ADD64_IMM);
+    }
+
+    #[test]
+    fn test_select_unsupported_opcode() {
+        let mut instr = add_imm_trace();
+        instr.instruction_bytes[0] = 0x2f; // MUL64_REG
+
+        let insn = DecodedInsn::decode(&instr.instruction_bytes).unwrap();
+        assert!(InstructionChip::select(&insn).is_none());
+    }
+
+    #[test]
+    fn test_select_follows_sbpf_version() {
+        let insn = |opcode| DecodedInsn { opcode, dst: 1, src: 2, off: 0, imm: 3 };
+
+        // SBPFv2 moved the memory classes: 0x27/0x37 are byte and halfword
+        // stores, and the PQR class took over multiply/divide by immediate
+        for opcode in [opcodes::MUL64_IMM, opcodes::DIV64_IMM] {
+            assert!(InstructionChip::select_for(&insn(opcode), SBPFVersion::V2).is_none());
+        }
+        for (version, ldxdw, stxdw, mul, div) in [
+            (
+                SBPFVersion::V0,
+                opcodes::LD_DW_REG,
+                opcodes::ST_DW_REG,
+                opcodes::MUL64_IMM,
+                opcodes::DIV64_IMM,
+            ),
+            (
+                SBPFVersion::V2,
+                opcodes::LD_8B_REG,
+                opcodes::ST_8B_REG,
+                opcodes::LMUL64_IMM,
+                opcodes::UDIV64_IMM,
+            ),
+        ] {
+            let select = |opcode| InstructionChip::select_for(&insn(opcode), version);
+            assert!(matches!(select(ldxdw), Some(InstructionChip::Ldw(..))));
+            assert!(matches!(select(stxdw), Some(InstructionChip::Stw(..))));
+            assert!(matches!(select(mul), Some(InstructionChip::MulImm(..))));
+            assert!(matches!(select(div), Some(InstructionChip::DivImm(..))));
+            assert_eq!(select(ldxdw).unwrap().opcode(), ldxdw);
+        }
+        // The V0 encodings of the 64-bit load and store mean nothing to V2
+        for opcode in [opcodes::LD_DW_REG, opcodes::ST_DW_REG] {
+            assert!(InstructionChip::select_for(&insn(opcode), SBPFVersion::V2).is_none());
+        }
+    }
+
+    fn mul_imm_trace(opcode: u8, imm: i32) -> (DecodedInsn, InstructionTrace) {
+        let mut instruction_bytes = vec![opcode, 0x00, 0x00, 0x00];
+        instruction_bytes.extend_from_slice(&imm.to_le_bytes());
+        let before = 5u64;
+        let after = before.wrapping_mul(imm as i64 as u64);
+        let instr = InstructionTrace {
+            pc: 0,
+            instruction_bytes,
+            registers_before: RegisterState::from_regs([before, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            registers_after: RegisterState::from_regs([after, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8]),
+            call_target: None,
+            text_offset: None,
+        };
+        (DecodedInsn::decode(&instr.instruction_bytes).unwrap(), instr)
+    }
+
+    #[test]
+    fn test_power_of_two_immediates_use_shifts() {
+        let (insn, _) = mul_imm_trace(opcodes::mul64_imm(), 8);
+        let chip = InstructionChip::select(&insn).unwrap();
+        assert!(matches!(chip, InstructionChip::Lsh(_, Alu64LshImmChip { shift: 3, .. })));
+        assert_eq!(chip.opcode(), opcodes::mul64_imm());
+
+        let (insn, _) = mul_imm_trace(opcodes::mul64_imm(), 7);
+        let chip = InstructionChip::select(&insn).unwrap();
+        assert!(matches!(chip, InstructionChip::MulImm(..)));
+
+        let (insn, _) = mul_imm_trace(opcodes::div64_imm(), 16);
+        let chip = InstructionChip::select(&insn).unwrap();
+        assert!(matches!(chip, InstructionChip::Rsh(_, Alu64RshImmChip { shift: 4, .. })));
+        assert_eq!(chip.opcode(), opcodes::div64_imm());
+
+        let (insn, _) = mul_imm_trace(opcodes::div64_imm(), 0);
+        assert!(InstructionChip::select(&insn).is_none());
+    }
+
+    #[test]
+    fn test_mul_by_power_of_two_is_cheaper() {
+        let (insn, instr) = mul_imm_trace(opcodes::mul64_imm(), 8);
+        assert_eq!(instr.registers_after.regs[0], 40);
+        let shift_chip = InstructionChip::select(&insn).unwrap();
+        let general_chip = InstructionChip::MulImm(insn.opcode, Alu64MulImmChip::new(0, 8));
+
+        // Both paths accept the same result; the shift path needs fewer cells
+        let mut cells = Vec::new();
+        for chip in [shift_chip, general_chip] {
+            let used = base_test().run_gate(|ctx, gate| {
+                let before = load_regs(ctx, &instr.registers_before);
+                let after = load_regs(ctx, &instr.registers_after);
+                let opcode = load_opcode(ctx, gate, &instr.instruction_bytes);
+                let start = ctx.advice.len();
+                chip.synthesize(ctx, gate, opcode, &before, &after).unwrap();
+                ctx.advice.len() - start
+            });
+            cells.push(used);
+        }
+        assert!(cells[0] < cells[1], "shift used {} cells, multiply {}", cells[0], cells[1]);
+    }
+
+    #[test]
+    fn test_dispatched_opcode_matches() {
+        let instr = add_imm_trace();
+        let insn = DecodedInsn::decode(&instr.instruction_bytes).unwrap();
+        let chip = InstructionChip::select(&insn).unwrap();
+
+        base_test().run_gate(|ctx, gate| {
+            let before = load_regs(ctx, &instr.registers_before);
+            let after = load_regs(ctx, &instr.registers_after);
+            let opcode = load_opcode(ctx, gate, &instr.instruction_bytes);
+            assert_eq!(opcode.value(), &Fr::from(insn.opcode as u64));
+            chip.synthesize(ctx, gate, opcode, &before, &after).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_supported_opcodes_select_a_chip() {
+        for version in [SBPFVersion::V0, SBPFVersion::V2] {
+            for opcode in supported_opcodes_for(version) {
+                let insn = DecodedInsn { opcode, dst: 0, src: 1, off: 0, imm: 3 };
+                assert!(
+                    InstructionChip::select_for(&insn, version).is_some(),
+                    "opcode 0x{:02x} is listed but has no chip under {:?}",
+                    opcode,
+                    version
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_provable() {
+        #[rustfmt::skip]
+        let arithmetic = [
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0xb7, 0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,  // mov64 r1, 20
+            0x0f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // add64 r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        assert_eq!(is_provable(&arithmetic), Ok(()));
+
+        #[rustfmt::skip]
+        let with_unsupported = [
+            0xb7, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,  // mov64 r0, 10
+            0x2f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // mul64 r0, r1
+            0x2f, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // mul64 r0, r1
+            0x15, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,  // jeq r0, 0, +1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        assert_eq!(is_provable(&with_unsupported), Err(vec![0x2f, 0x15]));
+
+        #[rustfmt::skip]
+        let byte_store = [
+            0x27, 0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,  // V0 mul64 r1, 2 / V2 stb [r1], 2
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // exit
+        ];
+        assert_eq!(is_provable_for(&byte_store, SBPFVersion::V0), Ok(()));
+        assert_eq!(is_provable_for(&byte_store, SBPFVersion::V2), Err(vec![0x27]));
+    }
+
+    #[test]
+    fn test_v2_only_opcodes_not_provable_under_v0() {
+        let v2_only = [
+            opcodes::HOR64_IMM,
+            opcodes::UDIV64_REG,
+            opcodes::UREM64_IMM,
+            opcodes::UREM64_REG,
+        ];
+        let bytecode = InsnBuilder::program(
+            v2_only
+                .map(|opcode| InsnBuilder::insn(opcode, 0, 1, 0, 3))
+                .into_iter()
+                .chain([InsnBuilder::exit()]),
+        );
+
+        assert_eq!(is_provable_for(&bytecode, SBPFVersion::V2), Ok(()));
+        assert_eq!(is_provable_for(&bytecode, SBPFVersion::V0), Err(v2_only.to_vec()));
+
+        let v0 = supported_opcodes_for(SBPFVersion::V0);
+        for opcode in v2_only {
+            assert!(!v0.contains(&opcode), "opcode 0x{:02x} listed under V0", opcode);
+        }
+    }
+
+    #[test]
+    fn test_altered_opcode_byte_fails() {
+        let instr = add_imm_trace();
+        let insn = DecodedInsn::decode(&instr.instruction_bytes).unwrap();
+        let chip = InstructionChip::select(&insn).unwrap();
+
+        // The step executes ADD64_REG while the ADD64_IMM chip processed it
+        let mut altered = instr.instruction_bytes.clone();
+        altered[0] = opcodes::ADD64_REG;
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            let before = load_regs(ctx, &instr.registers_before);
+            let after = load_regs(ctx, &instr.registers_after);
+            let opcode = load_opcode(ctx, gate, &altered);
+            chip.synthesize(ctx, gate, opcode, &before, &after).unwrap();
+        });
+    }
+}
@@ -0,0 +1,211 @@
+//! Keccak-256 Gadget
+//!
+//! Computes keccak256 over assigned bytes with halo2-base gates, one cell per
+//! state bit. Every bit is a boolean cell, so the step functions are plain
+//! arithmetic:
+//!
+//! - xor: `a + b - 2ab`
+//! - chi: `a xor ((1 - b) * c)`
+//! - rho and pi only move cells, and iota flips the bits set in the round
+//!   constant.
+//!
+//! Lanes are little-endian, as in the spec: bit `z` of lane `i` is bit
+//! `z % 8` of byte `8i + z / 8`. The number of absorbed blocks follows from
+//! the input length, so the length is part of the circuit shape.
+
+use halo2_base::{
+    gates::GateInstructions, utils::ScalarField, AssignedValue, Context, QuantumCell,
+};
+
+/// Bytes absorbed per permutation (1088-bit rate of keccak256)
+pub const RATE_BYTES: usize = 136;
+
+/// Lanes absorbed per permutation
+const RATE_LANES: usize = RATE_BYTES / 8;
+
+/// Rounds of keccak-f[1600]
+const ROUNDS: usize = 24;
+
+/// Iota round constants
+const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rho rotation of lane `x + 5y`
+const ROTATIONS: [usize; 25] = [
+    0, 1, 62, 28, 27, //
+    36, 44, 6, 55, 20, //
+    3, 10, 43, 25, 39, //
+    41, 45, 15, 21, 8, //
+    18, 2, 61, 56, 14,
+];
+
+/// A lane as 64 boolean cells, least significant bit first
+type Lane<F> = [AssignedValue<F>; 64];
+
+/// keccak256 of `bytes`
+///
+/// `bytes` must already be constrained to 8 bits each; they are decomposed
+/// again here, which also enforces it. Returns the 32 digest bytes.
+pub fn keccak256<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    bytes: &[AssignedValue<F>],
+) -> [AssignedValue<F>; 32] {
+    let mut bits: Vec<AssignedValue<F>> = bytes
+        .iter()
+        .flat_map(|&byte| gate.num_to_bits(ctx, byte, 8))
+        .collect();
+
+    // pad10*1 with the keccak domain bit: 0x01, zeros, then 0x80
+    let padded_len = (bytes.len() / RATE_BYTES + 1) * RATE_BYTES;
+    let mut padding = vec![0u8; padded_len - bytes.len()];
+    padding[0] |= 0x01;
+    *padding.last_mut().unwrap() |= 0x80;
+    for byte in padding {
+        for i in 0..8 {
+            bits.push(ctx.load_constant(F::from(((byte >> i) & 1) as u64)));
+        }
+    }
+
+    let zero = ctx.load_constant(F::ZERO);
+    let mut state: [Lane<F>; 25] = [[zero; 64]; 25];
+    for block in bits.chunks(RATE_BYTES * 8) {
+        for (lane, block_lane) in state.iter_mut().take(RATE_LANES).zip(block.chunks(64)) {
+            for (bit, &input) in lane.iter_mut().zip(block_lane) {
+                *bit = xor(ctx, gate, *bit, input);
+            }
+        }
+        keccak_f(ctx, gate, &mut state);
+    }
+
+    std::array::from_fn(|i| {
+        let lane = &state[i / 8];
+        let byte_bits = lane[8 * (i % 8)..8 * (i % 8) + 8].iter().copied();
+        let weights = (0..8).map(|k| QuantumCell::Constant(F::from(1u64 << k)));
+        gate.inner_product(ctx, byte_bits, weights)
+    })
+}
+
+/// keccak-f[1600] over the state, lanes indexed `x + 5y`
+fn keccak_f<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    state: &mut [Lane<F>; 25],
+) {
+    for round_constant in ROUND_CONSTANTS {
+        // Theta: xor each bit with the parities of the neighbouring columns
+        let parity: [Lane<F>; 5] = std::array::from_fn(|x| {
+            std::array::from_fn(|z| {
+                (1..5).fold(state[x][z], |acc, y| xor(ctx, gate, acc, state[x + 5 * y][z]))
+            })
+        });
+        for x in 0..5 {
+            let d: Lane<F> = std::array::from_fn(|z| {
+                let left = parity[(x + 4) % 5][z];
+                let right = parity[(x + 1) % 5][(z + 63) % 64];
+                xor(ctx, gate, left, right)
+            });
+            for y in 0..5 {
+                for z in 0..64 {
+                    state[x + 5 * y][z] = xor(ctx, gate, state[x + 5 * y][z], d[z]);
+                }
+            }
+        }
+
+        // Rho and pi: B[y][2x + 3y] = rot(A[x][y], r[x][y])
+        let mut moved = *state;
+        for x in 0..5 {
+            for y in 0..5 {
+                let lane = &state[x + 5 * y];
+                let rotation = ROTATIONS[x + 5 * y];
+                moved[y + 5 * ((2 * x + 3 * y) % 5)] =
+                    std::array::from_fn(|z| lane[(z + 64 - rotation) % 64]);
+            }
+        }
+
+        // Chi: A[x][y] = B[x][y] xor (not B[x+1][y] and B[x+2][y])
+        for y in 0..5 {
+            for x in 0..5 {
+                for z in 0..64 {
+                    let b = moved[(x + 1) % 5 + 5 * y][z];
+                    let c = moved[(x + 2) % 5 + 5 * y][z];
+                    let not_b_and_c = gate.sub_mul(ctx, c, b, c);
+                    state[x + 5 * y][z] = xor(ctx, gate, moved[x + 5 * y][z], not_b_and_c);
+                }
+            }
+        }
+
+        // Iota
+        for z in 0..64 {
+            if (round_constant >> z) & 1 == 1 {
+                state[0][z] = gate.not(ctx, state[0][z]);
+            }
+        }
+    }
+}
+
+/// `a xor b` for boolean cells, as `b + a(1 - 2b)`
+fn xor<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: AssignedValue<F>,
+    b: AssignedValue<F>,
+) -> AssignedValue<F> {
+    let one_minus_2b =
+        gate.mul_add(ctx, b, QuantumCell::Constant(-F::from(2)), QuantumCell::Constant(F::ONE));
+    gate.mul_add(ctx, a, one_minus_2b, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::{halo2_proofs::halo2curves::bn256::Fr, utils::testing::base_test};
+    use sha3::{Digest, Keccak256};
+
+    fn assert_digest(input: &[u8]) {
+        let expected: [u8; 32] = Keccak256::digest(input).into();
+        base_test().k(20).run_gate(|ctx, gate| {
+            let bytes: Vec<_> =
+                input.iter().map(|&byte| ctx.load_witness(Fr::from(byte as u64))).collect();
+            let digest = keccak256(ctx, gate, &bytes);
+            let computed: Vec<u8> =
+                digest.iter().map(|cell| cell.value().get_lower_64() as u8).collect();
+            assert_eq!(computed, expected);
+        });
+    }
+
+    #[test]
+    fn test_keccak256_of_empty_input() {
+        assert_digest(b"");
+    }
+
+    #[test]
+    fn test_keccak256_across_blocks() {
+        // Exactly one rate block, so the padding fills a second one
+        assert_digest(&[0xab; RATE_BYTES]);
+    }
+}
@@ -4,10 +4,29 @@
 //! Solana BPF programs. It defines instruction chips for a minimal BPF subset
 //! and provides a circuit for proving counter program execution.
 
+pub mod account_data;
 pub mod chips;
 pub mod counter;
+pub mod debug;
+pub mod dispatch;
+pub mod keccak;
+pub mod memory_table;
+pub mod preimage;
+pub mod registry;
+pub mod witness;
 
-pub use counter::CounterCircuit;
+pub use account_data::{AccountDataChip, AccountDataEqualityCircuit, DataCommitment};
+pub use counter::{CircuitMode, CounterCircuit, StepRegion, SynthesisOutput};
+pub use debug::{diagnose, Diagnosis, Mismatch};
+pub use dispatch::{
+    is_provable, is_provable_for, supported_opcodes, supported_opcodes_for, word_opcode,
+    InstructionChip,
+};
+pub use preimage::PreimageCircuit;
+pub use registry::{ChipFactory, ChipRegistry, RegisteredChip};
+pub use witness::{
+    field_elements_to_pubkey, pubkey_to_field_elements, MemoryOpType, MemoryOpWitness, Witness,
+};
 
 /// Result type for ZK circuit operations
 pub type Result<T> = anyhow::Result<T>;
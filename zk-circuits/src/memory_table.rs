@@ -0,0 +1,328 @@
+//! Memory Table
+//!
+//! Checks loads against the memory operations before them. The load and
+//! store steps bind each witnessed operation to its address and register
+//! value in execution order (see [`AssignedMemoryOp`]); the table then
+//! walks those same cells sorted by address and, per address, by time (the
+//! operation's position in execution order), and constrains each adjacent
+//! pair:
+//!
+//! - addresses are equal, or the later one is at least 8 bytes above the
+//!   earlier one, so the order really is sorted and accesses never
+//!   partially overlap;
+//! - within one address, time increases;
+//! - a load reads the value of the operation before it at the same address.
+//!
+//! A load with no operation before it at its address reads the memory the
+//! table starts from (see [`InitialMemory`]). For a whole program that is
+//! the memory the VM starts it with: the stack and heap are zeroed, so such
+//! a load reads zero. The program and input regions hold data the circuit
+//! has no commitment to, so loads reading them first are rejected unless
+//! explicitly let through. For a window of an execution, the memory it
+//! starts from is part of its witnessed starting state, like its initial
+//! registers.
+//!
+//! The sorted order is wiring, not witness: which cells are compared is
+//! fixed when the keys are generated, so a prover cannot reorder the table.
+//! Traces of one program whose accesses sort differently are different
+//! circuits, and [`CounterCircuit::memory_order`] is part of the circuit id
+//! for that reason.
+//!
+//! [`CounterCircuit::memory_order`]: crate::CounterCircuit::memory_order
+
+use bpf_tracer::memory::{MemoryRegionKind, MM_HEAP_START, MM_STACK_START, REGION_BITS};
+use halo2_base::{
+    gates::GateInstructions, utils::ScalarField, AssignedValue, Context, QuantumCell,
+};
+use crate::{
+    chips::AssignedMemoryOp,
+    witness::{MemoryOpType, MemoryOpWitness},
+    Result,
+};
+
+/// Width in bytes of every access the table covers (`ldxdw`/`stxdw`)
+const ACCESS_SIZE: u64 = 8;
+
+/// What a load reads when no earlier operation in the table shares its address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialMemory {
+    /// Whatever the witness says: the memory is part of the starting state
+    /// the table is proven from
+    Witnessed,
+    /// The memory the VM starts a program with: zero in the stack and heap
+    ///
+    /// Loads reading the program or input region first are rejected,
+    /// unless `allow_uninitialized` is set; they are then left
+    /// unconstrained and reported.
+    Entry {
+        /// Let first loads outside the stack and heap through unconstrained
+        allow_uninitialized: bool,
+    },
+}
+
+/// Order the memory table visits `ops` in: by address, then by time
+///
+/// `ops` are in execution order, so an operation's index is its time.
+/// Returns the indices into `ops`.
+pub fn sorted_order(ops: &[MemoryOpWitness]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..ops.len()).collect();
+    order.sort_by_key(|&index| (ops[index].address, index));
+    order
+}
+
+/// Constrain every load to read the value last stored at its address
+///
+/// `cells[i]` are the cells `ops[i]` was bound to by its step. A load with
+/// no earlier operation at its address reads `initial` memory. Returns the
+/// PCs of loads left unconstrained because they read memory the circuit
+/// cannot initialize (see [`InitialMemory::Entry`]). Fails if the two
+/// slices differ in length, an access is not 8 bytes wide, or such a load
+/// is not allowed.
+pub fn constrain_memory_table<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    ops: &[MemoryOpWitness],
+    cells: &[AssignedMemoryOp<F>],
+    initial: InitialMemory,
+) -> Result<Vec<u64>> {
+    anyhow::ensure!(
+        ops.len() == cells.len(),
+        "{} memory operations but {} bound to steps",
+        ops.len(),
+        cells.len()
+    );
+    if let Some(op) = ops.iter().find(|op| op.size as u64 != ACCESS_SIZE) {
+        anyhow::bail!("Memory operation at pc {} is {} bytes wide", op.pc, op.size);
+    }
+
+    let order = sorted_order(ops);
+    let mut uninitialized_pcs = Vec::new();
+    for (position, &current) in order.iter().enumerate() {
+        let cur_cells = &cells[current];
+        let previous = position.checked_sub(1).map(|previous| order[previous]);
+
+        // Whether the access before this one in the table has its address
+        let same =
+            previous.map(|previous| constrain_pair(ctx, gate, ops, cells, previous, current));
+
+        let InitialMemory::Entry { allow_uninitialized } = initial else {
+            continue;
+        };
+        if ops[current].op_type != MemoryOpType::Load {
+            continue;
+        }
+
+        // A load reading initial memory reads zero in the stack and heap
+        let first = match same {
+            Some(same) => gate.not(ctx, same),
+            None => ctx.load_constant(F::ONE),
+        };
+        let zeroed = in_zeroed_region(ctx, gate, cur_cells.address);
+        let first_zeroed = gate.mul(ctx, first, zeroed);
+        let read = gate.mul(ctx, first_zeroed, cur_cells.value);
+        gate.assert_is_const(ctx, &read, &F::ZERO);
+
+        // and nothing anywhere else, unless that is let through
+        let is_first = previous.map_or(true, |previous| {
+            ops[previous].address != ops[current].address
+        });
+        let region = MemoryRegionKind::of(ops[current].address);
+        if is_first && !matches!(region, MemoryRegionKind::Stack | MemoryRegionKind::Heap) {
+            anyhow::ensure!(
+                allow_uninitialized,
+                "Load at pc {} reads {:?} memory the circuit has no initial value for",
+                ops[current].pc,
+                region
+            );
+            uninitialized_pcs.push(ops[current].pc);
+        }
+        if !allow_uninitialized {
+            let elsewhere = gate.not(ctx, zeroed);
+            let first_elsewhere = gate.mul(ctx, first, elsewhere);
+            gate.assert_is_const(ctx, &first_elsewhere, &F::ZERO);
+        }
+    }
+
+    Ok(uninitialized_pcs)
+}
+
+/// Constrain the accesses at `previous` and `current`, adjacent in the table
+///
+/// Returns whether they share an address.
+fn constrain_pair<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    ops: &[MemoryOpWitness],
+    cells: &[AssignedMemoryOp<F>],
+    previous: usize,
+    current: usize,
+) -> AssignedValue<F> {
+    let (prev_cells, cur_cells) = (&cells[previous], &cells[current]);
+    let same = gate.is_equal(ctx, cur_cells.address, prev_cells.address);
+
+    // A new address starts at least one access past the previous one
+    let step = gate.sub(ctx, cur_cells.address, prev_cells.address);
+    let gap = gate.sub(ctx, step, QuantumCell::Constant(F::from(ACCESS_SIZE)));
+    let new_address_gap = gate.sub_mul(ctx, gap, same, gap);
+    let _bits = gate.num_to_bits(ctx, new_address_gap, 64);
+
+    // Going back in time is only allowed when moving to a new address
+    if current < previous {
+        gate.assert_is_const(ctx, &same, &F::ZERO);
+    }
+
+    // A load repeats the value of the access before it at its address
+    if ops[current].op_type == MemoryOpType::Load {
+        let change = gate.sub(ctx, cur_cells.value, prev_cells.value);
+        let same_address_change = gate.mul(ctx, same, change);
+        gate.assert_is_const(ctx, &same_address_change, &F::ZERO);
+    }
+
+    same
+}
+
+/// Whether `address` lies in the stack or heap, the regions that start zeroed
+///
+/// Splits the address into its region (the upper 32 bits) and the offset
+/// within it, range-checking both.
+fn in_zeroed_region<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    address: AssignedValue<F>,
+) -> AssignedValue<F> {
+    let value = address.value().get_lower_64();
+    let region = ctx.load_witness(F::from(value >> REGION_BITS));
+    let offset = ctx.load_witness(F::from(value & ((1u64 << REGION_BITS) - 1)));
+    let _bits = gate.num_to_bits(ctx, region, 64 - REGION_BITS as usize);
+    let _bits = gate.num_to_bits(ctx, offset, REGION_BITS as usize);
+    let scale = QuantumCell::Constant(F::from(1u64 << REGION_BITS));
+    let recomposed = gate.mul_add(ctx, region, scale, offset);
+    ctx.constrain_equal(&recomposed, &address);
+
+    let stack = QuantumCell::Constant(F::from(MM_STACK_START >> REGION_BITS));
+    let heap = QuantumCell::Constant(F::from(MM_HEAP_START >> REGION_BITS));
+    let in_stack = gate.is_equal(ctx, region, stack);
+    let in_heap = gate.is_equal(ctx, region, heap);
+    gate.add(ctx, in_stack, in_heap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bpf_tracer::memory::MM_INPUT_START;
+    use halo2_base::{halo2_proofs::halo2curves::bn256::Fr, utils::testing::base_test};
+
+    fn op(op_type: MemoryOpType, address: u64, value: u64) -> MemoryOpWitness {
+        MemoryOpWitness { address, value, size: 8, op_type, pc: 0 }
+    }
+
+    /// Bind `ops` to fresh cells and run the table over them
+    fn run_table(ops: &[MemoryOpWitness], expect_satisfied: bool) {
+        run_table_from(ops, InitialMemory::Witnessed, expect_satisfied);
+    }
+
+    /// Like [`run_table`], starting from `initial` memory
+    ///
+    /// Returns the PCs the table left unconstrained.
+    fn run_table_from(
+        ops: &[MemoryOpWitness],
+        initial: InitialMemory,
+        expect_satisfied: bool,
+    ) -> Vec<u64> {
+        base_test().expect_satisfied(expect_satisfied).run_gate(|ctx, gate| {
+            let cells: Vec<_> = ops
+                .iter()
+                .map(|op| AssignedMemoryOp {
+                    address: ctx.load_witness(Fr::from(op.address)),
+                    value: ctx.load_witness(Fr::from(op.value)),
+                })
+                .collect();
+            constrain_memory_table(ctx, gate, ops, &cells, initial).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_sorted_order_by_address_then_time() {
+        let ops = [
+            op(MemoryOpType::Store, 16, 1),
+            op(MemoryOpType::Store, 8, 2),
+            op(MemoryOpType::Load, 16, 1),
+            op(MemoryOpType::Load, 8, 2),
+        ];
+        assert_eq!(sorted_order(&ops), vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn test_loads_read_last_store() {
+        let ops = [
+            op(MemoryOpType::Store, 16, 1),
+            op(MemoryOpType::Store, 8, 2),
+            op(MemoryOpType::Store, 16, 3),
+            op(MemoryOpType::Load, 16, 3),
+            op(MemoryOpType::Load, 8, 2),
+            op(MemoryOpType::Load, 8, 2),
+        ];
+        run_table(&ops, true);
+    }
+
+    #[test]
+    fn test_load_of_overwritten_value_fails() {
+        let ops = [
+            op(MemoryOpType::Store, 16, 1),
+            op(MemoryOpType::Store, 16, 3),
+            op(MemoryOpType::Load, 16, 1),
+        ];
+        run_table(&ops, false);
+    }
+
+    #[test]
+    fn test_partially_overlapping_accesses_fail() {
+        let ops = [op(MemoryOpType::Store, 16, 1), op(MemoryOpType::Load, 20, 0)];
+        run_table(&ops, false);
+    }
+
+    #[test]
+    fn test_mismatched_cells_rejected() {
+        base_test().run_gate(|ctx, gate| {
+            let ops = [op(MemoryOpType::Load, 8, 0)];
+            let initial = InitialMemory::Witnessed;
+            assert!(constrain_memory_table::<Fr>(ctx, gate, &ops, &[], initial).is_err());
+        });
+    }
+
+    #[test]
+    fn test_first_stack_load_reads_zero() {
+        let entry = InitialMemory::Entry { allow_uninitialized: false };
+        let slot = MM_STACK_START + 0xff8;
+
+        let ops = [op(MemoryOpType::Load, slot, 0), op(MemoryOpType::Store, slot, 7)];
+        assert!(run_table_from(&ops, entry, true).is_empty());
+
+        // Memory never stored to holds nothing else
+        let ops = [op(MemoryOpType::Load, slot, 7)];
+        run_table_from(&ops, entry, false);
+
+        // A window starts from whatever memory it is witnessed with
+        run_table_from(&ops, InitialMemory::Witnessed, true);
+    }
+
+    #[test]
+    fn test_first_input_load_needs_allowing() {
+        let mut load = op(MemoryOpType::Load, MM_INPUT_START + 8, 42);
+        load.pc = 3;
+        let ops = [load];
+
+        base_test().run_gate(|ctx, gate| {
+            let cells = [AssignedMemoryOp {
+                address: ctx.load_witness(Fr::from(ops[0].address)),
+                value: ctx.load_witness(Fr::from(ops[0].value)),
+            }];
+            let entry = InitialMemory::Entry { allow_uninitialized: false };
+            let err = constrain_memory_table(ctx, gate, &ops, &cells, entry).unwrap_err();
+            assert!(err.to_string().contains("pc 3"), "Unexpected error: {}", err);
+        });
+
+        let allowed = InitialMemory::Entry { allow_uninitialized: true };
+        assert_eq!(run_table_from(&ops, allowed, true), vec![3]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Preimage Circuit
+//!
+//! Circuit for proving a program computed `keccak256(preimage) == digest`
+//! through the `sol_keccak256` syscall, without revealing the preimage.
+//!
+//! The preimage bytes are hashed in-circuit with the [`keccak`] gadget and
+//! the resulting digest bytes are constrained to the public digest. The
+//! preimage length fixes how many blocks are absorbed, so it is public by
+//! way of the circuit shape; the bytes themselves stay private.
+//!
+//! [`keccak`]: crate::keccak
+
+use bpf_tracer::{ExecutionTrace, HashCall};
+use halo2_base::{
+    gates::GateInstructions,
+    utils::ScalarField,
+    AssignedValue, Context,
+};
+use sha3::{Digest, Keccak256};
+use crate::{keccak, Result};
+
+/// Syscall whose calls this circuit proves
+pub const KECCAK_SYSCALL: &str = "sol_keccak256";
+
+/// Keccak-256 preimage circuit
+///
+/// Public Inputs:
+/// - Digest (32 bytes)
+///
+/// Private Witness:
+/// - Preimage bytes hashed by the program
+#[derive(Debug, Clone)]
+pub struct PreimageCircuit {
+    /// Bytes hashed by the program (private witness)
+    preimage: Vec<u8>,
+    /// Digest the program computed (public)
+    digest: [u8; 32],
+}
+
+impl PreimageCircuit {
+    /// Create a preimage circuit for a known preimage and digest
+    ///
+    /// Fails if `keccak256(preimage) != digest`.
+    pub fn new(preimage: Vec<u8>, digest: [u8; 32]) -> Result<Self> {
+        let computed: [u8; 32] = Keccak256::digest(&preimage).into();
+        anyhow::ensure!(
+            computed == digest,
+            "Preimage does not hash to the digest: expected {}, got {}",
+            hex_digest(&digest),
+            hex_digest(&computed)
+        );
+        Ok(Self { preimage, digest })
+    }
+
+    /// Create a preimage circuit from the `index`-th keccak call in a trace
+    pub fn from_trace(trace: &ExecutionTrace, index: usize) -> Result<Self> {
+        let call = trace
+            .hash_calls
+            .iter()
+            .filter(|call| call.syscall == KECCAK_SYSCALL)
+            .nth(index)
+            .ok_or_else(|| anyhow::anyhow!("Trace has no {} call #{}", KECCAK_SYSCALL, index))?;
+        Self::from_hash_call(call)
+    }
+
+    /// Create a preimage circuit from a recorded hash call
+    pub fn from_hash_call(call: &HashCall) -> Result<Self> {
+        anyhow::ensure!(
+            call.syscall == KECCAK_SYSCALL,
+            "Expected a {} call, got {}",
+            KECCAK_SYSCALL,
+            call.syscall
+        );
+        Self::new(call.preimage.clone(), call.digest)
+    }
+
+    /// The public digest
+    pub fn digest(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    /// Synthesize the circuit constraints
+    ///
+    /// Witnesses the preimage bytes, hashes them in-circuit and constrains
+    /// the digest to the public value. Returns the assigned digest bytes.
+    pub fn synthesize<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+    ) -> Result<[AssignedValue<F>; 32]> {
+        // Preimage bytes; the keccak gadget range-checks them to 8 bits
+        let preimage: Vec<_> = self
+            .preimage
+            .iter()
+            .map(|&byte| ctx.load_witness(F::from(byte as u64)))
+            .collect();
+
+        // Digest bytes pinned to the public digest
+        let digest = keccak::keccak256(ctx, gate, &preimage);
+        for (cell, &byte) in digest.iter().zip(&self.digest) {
+            gate.assert_is_const(ctx, cell, &F::from(byte as u64));
+        }
+
+        Ok(digest)
+    }
+}
+
+/// Hex-encode a digest for error messages
+fn hex_digest(digest: &[u8; 32]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::utils::testing::base_test;
+
+    /// keccak256("hello world")
+    const HELLO_WORLD_DIGEST: &str =
+        "47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad";
+
+    fn fixed_digest() -> [u8; 32] {
+        std::array::from_fn(|i| {
+            u8::from_str_radix(&HELLO_WORLD_DIGEST[2 * i..2 * i + 2], 16).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_preimage_circuit_for_fixed_digest() {
+        let mut trace = ExecutionTrace::new();
+        trace.hash_calls.push(HashCall {
+            syscall: KECCAK_SYSCALL.to_string(),
+            preimage: b"hello world".to_vec(),
+            digest: fixed_digest(),
+        });
+
+        let circuit = PreimageCircuit::from_trace(&trace, 0).unwrap();
+        assert_eq!(circuit.digest(), fixed_digest());
+
+        base_test().k(20).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_other_preimage_fails_constraints() {
+        // Bypass the native check in `new` to reach the in-circuit one
+        let circuit = PreimageCircuit { preimage: b"hello there".to_vec(), digest: fixed_digest() };
+
+        base_test().k(20).expect_satisfied(false).run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_wrong_preimage_rejected() {
+        let err = PreimageCircuit::new(b"hello there".to_vec(), fixed_digest()).unwrap_err();
+        assert!(err.to_string().contains("does not hash to the digest"));
+    }
+}
@@ -0,0 +1,215 @@
+//! User-registered instruction chips
+//!
+//! A patched runtime may execute opcodes the built-in chips know nothing
+//! about. A [`ChipRegistry`] maps such opcodes to chips supplied by the
+//! user; [`crate::InstructionChip::select_with`] consults it before the
+//! built-in dispatch, so a registered opcode also overrides a built-in chip.
+
+use std::{any::Any, collections::BTreeMap, fmt, sync::Arc};
+
+use bpf_tracer::DecodedInsn;
+use halo2_base::{
+    gates::flex_gate::GateChip,
+    halo2_proofs::halo2curves::bn256::Fr,
+    utils::ScalarField,
+    AssignedValue, Context,
+};
+use crate::{chips::BpfInstructionChip, Result};
+
+/// Object-safe form of [`BpfInstructionChip`] for registered chips
+///
+/// [`BpfInstructionChip::synthesize`] is generic over the gate, so chips
+/// cannot be boxed as that trait. Every `BpfInstructionChip<Fr>` that is
+/// `Send + Sync` implements this one, so a factory can return
+/// `Box::new(chip)` for any such chip.
+pub trait RegisteredChip: Send + Sync {
+    /// Constrain `regs_after` to be the result of the instruction on `regs_before`
+    fn synthesize(
+        &self,
+        ctx: &mut Context<Fr>,
+        gate: &GateChip<Fr>,
+        regs_before: &[AssignedValue<Fr>; 11],
+        regs_after: &[AssignedValue<Fr>; 11],
+    ) -> Result<()>;
+}
+
+impl<T: BpfInstructionChip<Fr> + Send + Sync> RegisteredChip for T {
+    fn synthesize(
+        &self,
+        ctx: &mut Context<Fr>,
+        gate: &GateChip<Fr>,
+        regs_before: &[AssignedValue<Fr>; 11],
+        regs_after: &[AssignedValue<Fr>; 11],
+    ) -> Result<()> {
+        BpfInstructionChip::synthesize(self, ctx, gate, regs_before, regs_after)
+    }
+}
+
+impl fmt::Debug for dyn RegisteredChip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RegisteredChip")
+    }
+}
+
+/// Builds the chip for one decoded instruction
+pub type ChipFactory = Box<dyn Fn(&DecodedInsn) -> Box<dyn RegisteredChip> + Send + Sync>;
+
+/// Chips for user-defined opcodes
+///
+/// Registered chips prove over BN254's scalar field only; synthesizing a
+/// step that uses one over another field fails. A registered chip is
+/// assumed to fall through to the next PC and not to access memory.
+#[derive(Default)]
+pub struct ChipRegistry {
+    factories: BTreeMap<u8, ChipFactory>,
+}
+
+impl ChipRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prove instructions with `opcode` using the chip `factory` builds
+    ///
+    /// Replaces any factory registered for `opcode` before.
+    pub fn with_chip<G>(mut self, opcode: u8, factory: G) -> Self
+    where
+        G: Fn(&DecodedInsn) -> Box<dyn RegisteredChip> + Send + Sync + 'static,
+    {
+        self.factories.insert(opcode, Box::new(factory));
+        self
+    }
+
+    /// Whether a chip is registered for `opcode`
+    pub fn contains(&self, opcode: u8) -> bool {
+        self.factories.contains_key(&opcode)
+    }
+
+    /// Registered opcodes, in ascending order
+    pub fn opcodes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.factories.keys().copied()
+    }
+
+    /// The registered chip for `insn`, if its opcode has one
+    pub fn select(&self, insn: &DecodedInsn) -> Option<Arc<dyn RegisteredChip>> {
+        self.factories.get(&insn.opcode).map(|factory| Arc::from(factory(insn)))
+    }
+}
+
+impl fmt::Debug for ChipRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opcodes: Vec<String> = self.opcodes().map(|op| format!("0x{:02x}", op)).collect();
+        f.debug_struct("ChipRegistry").field("opcodes", &opcodes).finish()
+    }
+}
+
+/// Synthesize a registered chip over the circuit's field
+///
+/// Fails unless `F` is BN254's scalar field, the only one registered
+/// chips are written for.
+pub(crate) fn synthesize_registered<F: ScalarField>(
+    chip: &dyn RegisteredChip,
+    ctx: &mut Context<F>,
+    regs_before: &[AssignedValue<F>; 11],
+    regs_after: &[AssignedValue<F>; 11],
+) -> Result<()> {
+    let ctx = (ctx as &mut dyn Any).downcast_mut::<Context<Fr>>().ok_or_else(wrong_field)?;
+    let (before, after) = (fr_registers(regs_before)?, fr_registers(regs_after)?);
+    chip.synthesize(ctx, &GateChip::default(), before, after)
+}
+
+/// `regs` as BN254 register cells, if `F` is BN254's scalar field
+fn fr_registers<F: ScalarField>(
+    regs: &[AssignedValue<F>; 11],
+) -> Result<&[AssignedValue<Fr>; 11]> {
+    (regs as &dyn Any).downcast_ref().ok_or_else(wrong_field)
+}
+
+fn wrong_field() -> anyhow::Error {
+    anyhow::anyhow!("Registered chips only prove over BN254's scalar field")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CounterCircuit;
+    use bpf_tracer::{ExecutionTrace, InsnBuilder, InstructionTrace, RegisterState};
+    use halo2_base::{gates::GateInstructions, utils::testing::base_test};
+
+    /// Opcode of a made-up `dbl64 dst` instruction: dst = 2 * dst
+    const DBL64: u8 = 0xe7;
+
+    /// dst = 2 * dst, without a range check on the result
+    struct DoubleChip {
+        dst: usize,
+    }
+
+    impl<F: ScalarField> BpfInstructionChip<F> for DoubleChip {
+        fn synthesize(
+            &self,
+            ctx: &mut Context<F>,
+            gate: &impl GateInstructions<F>,
+            regs_before: &[AssignedValue<F>; 11],
+            regs_after: &[AssignedValue<F>; 11],
+        ) -> Result<()> {
+            let doubled = gate.add(ctx, regs_before[self.dst], regs_before[self.dst]);
+            ctx.constrain_equal(&doubled, &regs_after[self.dst]);
+            for i in (0..11).filter(|&i| i != self.dst) {
+                ctx.constrain_equal(&regs_before[i], &regs_after[i]);
+            }
+            Ok(())
+        }
+    }
+
+    /// `dbl64 r1; exit` with r1 = 5 before, claiming `result` after
+    fn double_trace(result: u64) -> ExecutionTrace {
+        let before = RegisterState::from_regs([0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let after = RegisterState::from_regs([0, result, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let step = |pc, bytes: [u8; 8], before: &RegisterState, after: &RegisterState| {
+            InstructionTrace {
+                pc,
+                instruction_bytes: bytes.to_vec(),
+                registers_before: before.clone(),
+                registers_after: after.clone(),
+                call_target: None,
+                text_offset: None,
+            }
+        };
+        ExecutionTrace {
+            instructions: vec![
+                step(0, InsnBuilder::insn(DBL64, 1, 0, 0, 0), &before, &after),
+                step(1, InsnBuilder::exit(), &after, &after),
+            ],
+            initial_registers: before,
+            final_registers: after,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registered_chip_proves_custom_opcode() {
+        let registry = Arc::new(ChipRegistry::new().with_chip(DBL64, |insn: &DecodedInsn| {
+            Box::new(DoubleChip { dst: insn.dst as usize }) as Box<dyn RegisteredChip>
+        }));
+        assert!(registry.contains(DBL64));
+
+        let circuit =
+            CounterCircuit::from_trace(double_trace(10)).with_chip_registry(registry.clone());
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize(ctx, gate).unwrap();
+        });
+
+        let wrong = CounterCircuit::from_trace(double_trace(11)).with_chip_registry(registry);
+        base_test().expect_satisfied(false).run_gate(|ctx, gate| {
+            wrong.synthesize(ctx, gate).unwrap();
+        });
+
+        // Without the registry the opcode has no chip
+        let unregistered = CounterCircuit::from_trace(double_trace(10));
+        base_test().run_gate(|ctx, gate| {
+            let err = unregistered.synthesize(ctx, gate).unwrap_err();
+            assert!(err.to_string().contains("0xe7"), "Unexpected error: {}", err);
+        });
+    }
+}
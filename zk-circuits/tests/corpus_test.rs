@@ -0,0 +1,100 @@
+//! Fixture corpus regression test
+//!
+//! Every program in `tests/corpus/` is traced, checked against the expected
+//! r0 in `manifest.json`, turned into a witness and run through the mock
+//! prover, so a regression in the tracer, witness or circuit shows up for
+//! whichever fixture it breaks.
+
+use bpf_tracer::trace_program;
+use halo2_base::{
+    halo2_proofs::halo2curves::bn256::Fr,
+    utils::testing::base_test,
+};
+use serde::Deserialize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use zk_circuits::{CircuitMode, CounterCircuit, Witness};
+
+/// The corpus manifest
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    fixtures: Vec<Fixture>,
+}
+
+/// One corpus program and what running it must produce
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    name: String,
+    /// Raw sBPF bytecode file, relative to the corpus directory
+    program: String,
+    expected_r0: u64,
+    /// PCs of executed instructions no chip constrains yet
+    ///
+    /// A fixture with jumps can only be proven unconstrained until jump
+    /// chips land; listing the PCs keeps the gap visible.
+    #[serde(default)]
+    unconstrained_pcs: Vec<u64>,
+}
+
+fn corpus_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+/// Run one fixture through every stage, naming the stage that failed
+fn run_fixture(fixture: &Fixture) -> Result<(), String> {
+    let bytecode = std::fs::read(corpus_dir().join(&fixture.program))
+        .map_err(|e| format!("load: {}", e))?;
+
+    let trace = trace_program(&bytecode).map_err(|e| format!("trace: {}", e))?;
+    trace.check_consistency().map_err(|e| format!("trace consistency: {}", e))?;
+    trace
+        .assert_register(0, fixture.expected_r0)
+        .map_err(|e| format!("final registers: {}", e))?;
+
+    let witness = Witness::from_trace(&trace).map_err(|e| format!("witness: {}", e))?;
+
+    let circuit = CounterCircuit::from_witness_chunked(witness, trace.instruction_count())
+        .with_mode(CircuitMode::FullProgram)
+        .with_allow_unconstrained(!fixture.unconstrained_pcs.is_empty());
+    // The mock prover panics on unsatisfied constraints
+    let synthesized = panic::catch_unwind(AssertUnwindSafe(|| {
+        base_test().run_gate(|ctx, gate| {
+            circuit.synthesize::<Fr>(ctx, gate).map(|output| output.unconstrained_pcs)
+        })
+    }))
+    .map_err(|_| "mock prover: constraints not satisfied".to_string())?;
+    let unconstrained_pcs = synthesized.map_err(|e| format!("synthesis: {}", e))?;
+    if unconstrained_pcs != fixture.unconstrained_pcs {
+        return Err(format!(
+            "synthesis: unconstrained pcs {:?}, manifest expects {:?}",
+            unconstrained_pcs, fixture.unconstrained_pcs
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_corpus() {
+    let manifest = std::fs::read_to_string(corpus_dir().join("manifest.json"))
+        .expect("Corpus manifest should be readable");
+    let manifest: Manifest =
+        serde_json::from_str(&manifest).expect("Corpus manifest should be valid");
+    assert!(!manifest.fixtures.is_empty(), "Corpus manifest lists no fixtures");
+
+    let failures: Vec<String> = manifest
+        .fixtures
+        .iter()
+        .filter_map(|fixture| {
+            run_fixture(fixture).err().map(|e| format!("{}: {}", fixture.name, e))
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} corpus fixtures failed:\n  {}",
+        failures.len(),
+        manifest.fixtures.len(),
+        failures.join("\n  ")
+    );
+}